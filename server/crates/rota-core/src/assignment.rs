@@ -0,0 +1,319 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::availability::Availability;
+use crate::profile::{ContractType, Ineligibility, StaffProfile};
+use crate::shift::Shift;
+
+// Why assigning someone to a shift, or letting them take leave, is a problem.
+// Clients match on these, so they are part of the API and must not be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictCode {
+    // Already working a shift that overlaps this one
+    DoubleBooked,
+    OnLeave,
+    // Said they can't work then
+    Unavailable,
+    NoProfile,
+    NotStarted,
+    Left,
+    MissingSkill,
+    MissingQualification,
+    ExpiredQualification,
+    ExceedsContractedHours,
+    ExceedsMaximumHours,
+    // Leave falls in a blackout period
+    Blackout,
+    // Leave would put more of a team off at once than its limit allows
+    TooManyOff,
+}
+
+// One problem with an assignment, with a message a scheduler can act on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub code: ConflictCode,
+    pub message: String,
+}
+
+impl Conflict {
+    pub fn new(code: ConflictCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Ineligibility> for Conflict {
+    fn from(reason: Ineligibility) -> Self {
+        match reason {
+            Ineligibility::NoProfile => {
+                Conflict::new(ConflictCode::NoProfile, "No employment record to schedule against")
+            }
+            Ineligibility::NotStarted(date) => {
+                Conflict::new(ConflictCode::NotStarted, format!("Doesn't start until {}", date))
+            }
+            Ineligibility::Left(date) => Conflict::new(ConflictCode::Left, format!("Left on {}", date)),
+            Ineligibility::MissingSkill(skill) => {
+                Conflict::new(ConflictCode::MissingSkill, format!("Lacks the {} skill", skill))
+            }
+            Ineligibility::MissingQualification(name) => {
+                Conflict::new(ConflictCode::MissingQualification, format!("Doesn't hold {}", name))
+            }
+            Ineligibility::ExpiredQualification(name) => {
+                Conflict::new(ConflictCode::ExpiredQualification, format!("{} has expired", name))
+            }
+        }
+    }
+}
+
+// A period of approved leave, in whole days
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovedLeave {
+    pub id: i64,
+    pub leave_type: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+// Everything known about the person when assigning them to `shift`
+#[derive(Debug, Clone)]
+pub struct AssignmentCheck<'a> {
+    pub shift: &'a Shift,
+    // `None` when they have no employment record
+    pub profile: Option<&'a StaffProfile>,
+    // Their other shifts from the start of the shift's week to its end
+    pub assigned: &'a [Shift],
+    // Their approved leave over the days the shift is worked
+    pub leave: &'a [ApprovedLeave],
+    // What they said about when they can work, if anything
+    pub availability: Option<&'a Availability>,
+    // Most paid hours anyone may work in a week, whatever their contract
+    pub max_weekly_hours: f64,
+}
+
+// The Monday-to-Monday week a shift starts in, as instants, using the
+// shift's own time zone
+pub fn week_of(shift: &Shift) -> (DateTime<Utc>, DateTime<Utc>) {
+    let date = shift.local_start().date();
+    let monday = date - Duration::days(i64::from(date.weekday().num_days_from_monday()));
+    let midnight = |day: NaiveDate| {
+        let local = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+        // Midnight can be skipped by a clock change; the day then starts at
+        // the first instant after it
+        shift
+            .tz()
+            .from_local_datetime(&local)
+            .earliest()
+            .unwrap_or_else(|| shift.tz().from_utc_datetime(&local))
+            .with_timezone(&Utc)
+    };
+
+    (midnight(monday), midnight(monday + Duration::days(7)))
+}
+
+// Every reason the assignment is a problem; empty when it isn't
+pub fn check_assignment(check: &AssignmentCheck) -> Vec<Conflict> {
+    let shift = check.shift;
+    let mut conflicts = Vec::new();
+
+    for other in check.assigned.iter().filter(|s| s.id != shift.id && s.overlaps(shift)) {
+        conflicts.push(Conflict::new(
+            ConflictCode::DoubleBooked,
+            format!("Already working shift {} from {} to {}", other.id, other.starts_at, other.ends_at),
+        ));
+    }
+
+    let (first, last) = shift.local_dates();
+    for leave in check.leave.iter().filter(|l| l.start_date <= last && first <= l.end_date) {
+        conflicts.push(Conflict::new(
+            ConflictCode::OnLeave,
+            format!("On {} leave from {} to {}", leave.leave_type, leave.start_date, leave.end_date),
+        ));
+    }
+
+    let unavailable = check
+        .availability
+        .and_then(|availability| availability.unavailable_during(shift.local_start(), shift.local_end()));
+    if let Some(window) = unavailable {
+        conflicts.push(Conflict::new(
+            ConflictCode::Unavailable,
+            format!("Unavailable from {} to {}", window.start, window.end),
+        ));
+    }
+
+    match check.profile {
+        Some(profile) => conflicts.extend(profile.eligibility(&shift.requirements()).into_iter().map(Conflict::from)),
+        None => conflicts.push(Ineligibility::NoProfile.into()),
+    }
+
+    let (week_start, week_end) = week_of(shift);
+    let worked: i64 = check
+        .assigned
+        .iter()
+        .filter(|s| s.id != shift.id && week_start <= s.starts_at && s.starts_at < week_end)
+        .map(Shift::paid_minutes)
+        .sum();
+    let hours = (worked + shift.paid_minutes()) as f64 / 60.0;
+
+    // Annualised hours are an average, so one long week isn't over contract
+    let contracted = check
+        .profile
+        .filter(|p| p.contract_type != ContractType::Annualised)
+        .and_then(|p| p.weekly_hours);
+    if let Some(contracted) = contracted.filter(|c| hours > *c) {
+        conflicts.push(Conflict::new(
+            ConflictCode::ExceedsContractedHours,
+            format!("{:.1} hours that week against {:.1} contracted", hours, contracted),
+        ));
+    }
+    if hours > check.max_weekly_hours {
+        conflicts.push(Conflict::new(
+            ConflictCode::ExceedsMaximumHours,
+            format!("{:.1} hours that week against a maximum of {:.1}", hours, check.max_weekly_hours),
+        ));
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::availability::{AvailabilityKind, WeeklyWindow};
+    use crate::shift::{local_to_utc, parse_time_zone, NewShift};
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn shift(id: i64, start: &str, hours: i64) -> Shift {
+        let local = chrono::NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M").unwrap();
+        let starts_at = local_to_utc(local, parse_time_zone("Europe/London").unwrap()).unwrap();
+        NewShift {
+            rota_id: None,
+            team_id: Some(1),
+            location: "Ward 3".to_string(),
+            position: "Nurse".to_string(),
+            starts_at,
+            ends_at: starts_at + Duration::hours(hours),
+            time_zone: "Europe/London".to_string(),
+            unpaid_break_minutes: 0,
+            required_headcount: 1,
+            notes: String::new(),
+            required_skills: vec!["ICU".to_string()],
+            required_qualifications: Vec::new(),
+        }
+        .into_shift(id, Utc::now())
+    }
+
+    fn profile() -> StaffProfile {
+        StaffProfile {
+            user_id: 1,
+            contract_type: ContractType::PartTime,
+            weekly_hours: Some(24.0),
+            pay_grade: None,
+            start_date: date("2024-01-01"),
+            end_date: None,
+            home_team_id: Some(1),
+            skills: vec!["icu".to_string()],
+            qualifications: Vec::new(),
+        }
+    }
+
+    fn codes(conflicts: Vec<Conflict>) -> Vec<ConflictCode> {
+        conflicts.into_iter().map(|c| c.code).collect()
+    }
+
+    #[test]
+    fn a_clear_assignment_has_no_conflicts() {
+        let profile = profile();
+        let monday = shift(1, "2025-06-02 08:00", 12);
+        let friday = shift(2, "2025-06-06 08:00", 12);
+        let check = AssignmentCheck {
+            shift: &monday,
+            profile: Some(&profile),
+            assigned: std::slice::from_ref(&friday),
+            leave: &[],
+            availability: None,
+            max_weekly_hours: 48.0,
+        };
+
+        assert!(check_assignment(&check).is_empty());
+        assert_eq!(week_of(&friday), week_of(&monday));
+    }
+
+    #[test]
+    fn every_conflict_is_reported() {
+        let profile = StaffProfile { skills: Vec::new(), ..profile() };
+        let night = shift(1, "2025-06-06 20:00", 12);
+        let assigned = vec![
+            shift(2, "2025-06-02 08:00", 12),
+            shift(3, "2025-06-04 08:00", 12),
+            // Overlaps the night shift's last hours
+            shift(4, "2025-06-07 07:00", 4),
+            // Next week, so its hours don't count
+            shift(5, "2025-06-09 08:00", 12),
+        ];
+        let leave = [ApprovedLeave {
+            id: 1,
+            leave_type: "annual".to_string(),
+            start_date: date("2025-06-07"),
+            end_date: date("2025-06-08"),
+        }];
+        let availability = Availability {
+            weekly: vec![WeeklyWindow {
+                weekday: chrono::Weekday::Sat,
+                start_time: "06:00".parse().unwrap(),
+                end_time: "12:00".parse().unwrap(),
+                kind: AvailabilityKind::Unavailable,
+            }],
+            exceptions: Vec::new(),
+        };
+        let check = AssignmentCheck {
+            shift: &night,
+            profile: Some(&profile),
+            assigned: &assigned,
+            leave: &leave,
+            availability: Some(&availability),
+            max_weekly_hours: 38.0,
+        };
+
+        assert_eq!(
+            codes(check_assignment(&check)),
+            vec![
+                ConflictCode::DoubleBooked,
+                ConflictCode::OnLeave,
+                ConflictCode::Unavailable,
+                ConflictCode::MissingSkill,
+                ConflictCode::ExceedsContractedHours,
+                ConflictCode::ExceedsMaximumHours,
+            ]
+        );
+
+        let annualised = StaffProfile { contract_type: ContractType::Annualised, ..profile.clone() };
+        let check = AssignmentCheck {
+            profile: Some(&annualised),
+            assigned: &assigned[..2],
+            leave: &[],
+            availability: None,
+            ..check
+        };
+        assert_eq!(codes(check_assignment(&check)), vec![ConflictCode::MissingSkill]);
+
+        let check = AssignmentCheck { profile: None, ..check };
+        assert_eq!(codes(check_assignment(&check)), vec![ConflictCode::NoProfile]);
+    }
+
+    #[test]
+    fn weeks_follow_the_shift_time_zone() {
+        // In summer the week ends at local midnight, an hour before UTC midnight
+        let sunday = shift(1, "2025-03-30 23:30", 8);
+        let (start, end) = week_of(&sunday);
+
+        assert_eq!(start.to_rfc3339(), "2025-03-24T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2025-03-30T23:00:00+00:00");
+        assert!(sunday.starts_at < end);
+    }
+}
@@ -0,0 +1,290 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+
+// Most weekly windows one person can have
+pub const MAX_WEEKLY_WINDOWS: usize = 50;
+
+// What someone has said about a stretch of time. Time nobody has said
+// anything about is neither available nor unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AvailabilityKind {
+    Available,
+    // Available, and would rather work then
+    Preferred,
+    Unavailable,
+}
+
+impl Display for AvailabilityKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AvailabilityKind::Available => write!(f, "available"),
+            AvailabilityKind::Preferred => write!(f, "preferred"),
+            AvailabilityKind::Unavailable => write!(f, "unavailable"),
+        }
+    }
+}
+
+impl FromStr for AvailabilityKind {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "available" => Ok(AvailabilityKind::Available),
+            "preferred" => Ok(AvailabilityKind::Preferred),
+            "unavailable" => Ok(AvailabilityKind::Unavailable),
+            other => Err(ValidationError(format!("Unknown availability: {}", other))),
+        }
+    }
+}
+
+// A window that repeats every week. Times are wall-clock times wherever the
+// shift is; an end at or before the start finishes the next day, so
+// 00:00 to 00:00 is the whole day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeeklyWindow {
+    pub weekday: Weekday,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub kind: AvailabilityKind,
+}
+
+// A change to one date, such as a holiday or a day someone can come in
+// after all. A date's exceptions replace its weekly windows. Without times
+// the exception covers the whole day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityException {
+    pub id: i64,
+    pub user_id: i64,
+    pub date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub kind: AvailabilityKind,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A stretch of one day's availability in local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayWindow {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub kind: AvailabilityKind,
+    // Whether it came from an exception rather than the weekly windows
+    pub exception: bool,
+}
+
+// Wall-clock span from `start` to `end` on `date`, running past midnight
+// when `end` isn't after `start`
+fn span(date: NaiveDate, start: NaiveTime, end: NaiveTime) -> (NaiveDateTime, NaiveDateTime) {
+    let start_at = date.and_time(start);
+    let mut end_at = date.and_time(end);
+    if end_at <= start_at {
+        end_at += Duration::days(1);
+    }
+    (start_at, end_at)
+}
+
+// Check a week of windows: there can't be too many, and none may overlap,
+// including one running past Sunday midnight into Monday's
+pub fn validate_weekly(windows: &[WeeklyWindow]) -> Result<(), ValidationError> {
+    if windows.len() > MAX_WEEKLY_WINDOWS {
+        return Err(ValidationError(format!("At most {} weekly windows are allowed", MAX_WEEKLY_WINDOWS)));
+    }
+
+    // Lay the windows out on a week starting Monday 1 January 2024
+    let monday = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap_or_default();
+    let week = Duration::days(7);
+    let spans: Vec<_> = windows
+        .iter()
+        .map(|w| {
+            let date = monday + Duration::days(i64::from(w.weekday.num_days_from_monday()));
+            (w, span(date, w.start_time, w.end_time))
+        })
+        .collect();
+
+    for (i, (first, (a_start, a_end))) in spans.iter().enumerate() {
+        for (second, (b_start, b_end)) in &spans[i + 1..] {
+            let overlaps = [-week, Duration::zero(), week]
+                .iter()
+                .any(|shift| *a_start < *b_end + *shift && *b_start + *shift < *a_end);
+            if overlaps {
+                return Err(ValidationError(format!(
+                    "Windows on {} from {} and {} from {} overlap",
+                    first.weekday, first.start_time, second.weekday, second.start_time
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Check an exception's times, both or neither, and that it doesn't overlap
+// the other exceptions already on its date
+pub fn validate_exception(
+    exception: &AvailabilityException,
+    existing: &[AvailabilityException],
+) -> Result<(), ValidationError> {
+    if exception.start_time.is_some() != exception.end_time.is_some() {
+        return Err(ValidationError::new("Give both a start and end time, or neither for the whole day"));
+    }
+
+    let on_date = Availability { weekly: Vec::new(), exceptions: vec![exception.clone()] };
+    let new = on_date.windows_on(exception.date)[0];
+    let others = Availability { weekly: Vec::new(), exceptions: existing.to_vec() };
+    if others.windows_on(exception.date).iter().any(|w| w.start < new.end && new.start < w.end) {
+        return Err(ValidationError(format!("Overlaps another exception on {}", exception.date)));
+    }
+
+    Ok(())
+}
+
+// Everything one person has said about when they can work
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Availability {
+    pub weekly: Vec<WeeklyWindow>,
+    pub exceptions: Vec<AvailabilityException>,
+}
+
+impl Availability {
+    // Windows starting on `date`, by start time: its exceptions if it has
+    // any, otherwise the weekly windows for that day of the week
+    pub fn windows_on(&self, date: NaiveDate) -> Vec<DayWindow> {
+        let exceptions: Vec<_> = self.exceptions.iter().filter(|e| e.date == date).collect();
+        let mut windows: Vec<DayWindow> = if exceptions.is_empty() {
+            self.weekly
+                .iter()
+                .filter(|w| w.weekday == date.weekday())
+                .map(|w| {
+                    let (start, end) = span(date, w.start_time, w.end_time);
+                    DayWindow { start, end, kind: w.kind, exception: false }
+                })
+                .collect()
+        } else {
+            exceptions
+                .iter()
+                .map(|e| {
+                    let (start, end) = match (e.start_time, e.end_time) {
+                        (Some(start), Some(end)) => span(date, start, end),
+                        _ => span(date, NaiveTime::MIN, NaiveTime::MIN),
+                    };
+                    DayWindow { start, end, kind: e.kind, exception: true }
+                })
+                .collect()
+        };
+        windows.sort_by_key(|w| w.start);
+
+        windows
+    }
+
+    // The first unavailable window sharing any time with `start` to `end`,
+    // both local. Windows from the day before can run into the period.
+    pub fn unavailable_during(&self, start: NaiveDateTime, end: NaiveDateTime) -> Option<DayWindow> {
+        let mut date = start.date() - Duration::days(1);
+        while date <= end.date() {
+            let found = self
+                .windows_on(date)
+                .into_iter()
+                .find(|w| w.kind == AvailabilityKind::Unavailable && w.start < end && start < w.end);
+            if found.is_some() {
+                return found;
+            }
+            date += Duration::days(1);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveTime {
+        text.parse().unwrap()
+    }
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn window(weekday: Weekday, start: &str, end: &str, kind: AvailabilityKind) -> WeeklyWindow {
+        WeeklyWindow { weekday, start_time: time(start), end_time: time(end), kind }
+    }
+
+    #[test]
+    fn weekly_windows_cannot_overlap() {
+        let evenings = [
+            window(Weekday::Mon, "18:00", "22:00", AvailabilityKind::Preferred),
+            window(Weekday::Tue, "18:00", "22:00", AvailabilityKind::Preferred),
+        ];
+        assert_eq!(validate_weekly(&evenings), Ok(()));
+
+        let clash = [
+            window(Weekday::Mon, "18:00", "22:00", AvailabilityKind::Preferred),
+            window(Weekday::Mon, "21:00", "23:00", AvailabilityKind::Unavailable),
+        ];
+        assert!(validate_weekly(&clash).is_err());
+
+        // Sunday night runs into Monday morning
+        let wrapped = [
+            window(Weekday::Sun, "20:00", "08:00", AvailabilityKind::Unavailable),
+            window(Weekday::Mon, "07:00", "12:00", AvailabilityKind::Available),
+        ];
+        assert!(validate_weekly(&wrapped).is_err());
+    }
+
+    fn exception(date: &str, times: Option<(&str, &str)>, kind: AvailabilityKind) -> AvailabilityException {
+        AvailabilityException {
+            id: 1,
+            user_id: 1,
+            date: date.parse().unwrap(),
+            start_time: times.map(|(start, _)| time(start)),
+            end_time: times.map(|(_, end)| time(end)),
+            kind,
+            note: String::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn exceptions_on_a_date_cannot_overlap() {
+        let morning = exception("2025-06-09", Some(("08:00", "12:00")), AvailabilityKind::Unavailable);
+        let afternoon = exception("2025-06-09", Some(("12:00", "18:00")), AvailabilityKind::Preferred);
+        let all_day = exception("2025-06-09", None, AvailabilityKind::Unavailable);
+
+        assert_eq!(validate_exception(&afternoon, std::slice::from_ref(&morning)), Ok(()));
+        assert!(validate_exception(&all_day, &[morning]).is_err());
+        let half_given = AvailabilityException { end_time: None, ..afternoon };
+        assert!(validate_exception(&half_given, &[]).is_err());
+    }
+
+    #[test]
+    fn exceptions_replace_the_weekly_windows_for_their_date() {
+        let availability = Availability {
+            weekly: vec![
+                window(Weekday::Mon, "00:00", "00:00", AvailabilityKind::Unavailable),
+                window(Weekday::Tue, "09:00", "17:00", AvailabilityKind::Available),
+            ],
+            exceptions: vec![exception("2025-06-09", Some(("12:00", "20:00")), AvailabilityKind::Available)],
+        };
+
+        let monday = availability.windows_on("2025-06-02".parse().unwrap());
+        assert_eq!(monday.len(), 1);
+        assert_eq!(monday[0].end, local("2025-06-03 00:00"));
+        let covered = availability.windows_on("2025-06-09".parse().unwrap());
+        assert_eq!(covered[0].kind, AvailabilityKind::Available);
+        assert!(covered[0].exception);
+
+        assert!(availability.unavailable_during(local("2025-06-02 08:00"), local("2025-06-02 16:00")).is_some());
+        assert!(availability.unavailable_during(local("2025-06-09 12:00"), local("2025-06-09 20:00")).is_none());
+        // A Sunday night into Monday is caught by Monday's window
+        assert!(availability.unavailable_during(local("2025-06-01 20:00"), local("2025-06-02 08:00")).is_some());
+        assert!(availability.unavailable_during(local("2025-06-03 09:00"), local("2025-06-03 17:00")).is_none());
+    }
+}
@@ -0,0 +1,536 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::assignment::{ApprovedLeave, Conflict};
+use crate::error::ValidationError;
+
+// Longest single leave request, in days
+pub const MAX_LEAVE_DAYS: i64 = 366;
+
+// The kinds of absence staff can request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaveType {
+    Annual,
+    Study,
+    Sickness,
+    Compassionate,
+    Parental,
+    Unpaid,
+    // Time off in lieu, paid from the TOIL ledger rather than an entitlement
+    Toil,
+    Other,
+}
+
+impl Display for LeaveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeaveType::Annual => write!(f, "annual"),
+            LeaveType::Study => write!(f, "study"),
+            LeaveType::Sickness => write!(f, "sickness"),
+            LeaveType::Compassionate => write!(f, "compassionate"),
+            LeaveType::Parental => write!(f, "parental"),
+            LeaveType::Unpaid => write!(f, "unpaid"),
+            LeaveType::Toil => write!(f, "toil"),
+            LeaveType::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl FromStr for LeaveType {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "annual" => Ok(LeaveType::Annual),
+            "study" => Ok(LeaveType::Study),
+            "sickness" => Ok(LeaveType::Sickness),
+            "compassionate" => Ok(LeaveType::Compassionate),
+            "parental" => Ok(LeaveType::Parental),
+            "unpaid" => Ok(LeaveType::Unpaid),
+            "toil" => Ok(LeaveType::Toil),
+            "other" => Ok(LeaveType::Other),
+            other => Err(ValidationError(format!("Unknown leave type: {}", other))),
+        }
+    }
+}
+
+impl LeaveType {
+    // Leave booked ahead, which blackouts and limits apply to; sickness and
+    // the like can't wait for a quieter week
+    pub fn is_planned(self) -> bool {
+        matches!(
+            self,
+            LeaveType::Annual | LeaveType::Study | LeaveType::Unpaid | LeaveType::Toil | LeaveType::Other
+        )
+    }
+}
+
+// Where a leave request is in its approval workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaveStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Cancelled,
+}
+
+impl LeaveStatus {
+    // Pending and approved leave hold the days; the rest are finished with
+    pub fn is_active(self) -> bool {
+        matches!(self, LeaveStatus::Pending | LeaveStatus::Approved)
+    }
+}
+
+impl Display for LeaveStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeaveStatus::Pending => write!(f, "pending"),
+            LeaveStatus::Approved => write!(f, "approved"),
+            LeaveStatus::Rejected => write!(f, "rejected"),
+            LeaveStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl FromStr for LeaveStatus {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(LeaveStatus::Pending),
+            "approved" => Ok(LeaveStatus::Approved),
+            "rejected" => Ok(LeaveStatus::Rejected),
+            "cancelled" => Ok(LeaveStatus::Cancelled),
+            other => Err(ValidationError(format!("Unknown leave status: {}", other))),
+        }
+    }
+}
+
+// A decision on a leave request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaveAction {
+    Approve,
+    Reject,
+    // Withdraw a pending request or give back approved leave
+    Cancel,
+}
+
+impl LeaveAction {
+    // The status a request in `from` moves to
+    pub fn apply(self, from: LeaveStatus) -> Result<LeaveStatus, ValidationError> {
+        match (self, from) {
+            (LeaveAction::Approve, LeaveStatus::Pending) => Ok(LeaveStatus::Approved),
+            (LeaveAction::Reject, LeaveStatus::Pending) => Ok(LeaveStatus::Rejected),
+            (LeaveAction::Cancel, LeaveStatus::Pending | LeaveStatus::Approved) => Ok(LeaveStatus::Cancelled),
+            (LeaveAction::Approve | LeaveAction::Reject, _) => {
+                Err(ValidationError(format!("Leave request is already {}", from)))
+            }
+            (LeaveAction::Cancel, _) => Err(ValidationError(format!("Leave request is {}", from))),
+        }
+    }
+}
+
+// Working days from `start` to `end` inclusive, Monday to Friday, less half a
+// day for each half day at either end
+pub fn leave_days(start: NaiveDate, end: NaiveDate, half_day_start: bool, half_day_end: bool) -> f64 {
+    let is_working = |date: NaiveDate| !matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+    let whole = start.iter_days().take_while(|d| *d <= end).filter(|d| is_working(*d)).count() as f64;
+    let halves = [(half_day_start, start), (half_day_end, end)]
+        .iter()
+        .filter(|(half, date)| *half && is_working(*date))
+        .count() as f64;
+
+    if start == end {
+        whole - halves.min(1.0) * 0.5
+    } else {
+        whole - halves * 0.5
+    }
+}
+
+// When leave years start. Leave year 2025 starts on the first of
+// `start_month` in 2025.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaveYear {
+    pub start_month: u32,
+}
+
+impl LeaveYear {
+    // The leave year `date` falls in
+    pub fn of(&self, date: NaiveDate) -> i32 {
+        if date.month() >= self.start_month {
+            date.year()
+        } else {
+            date.year() - 1
+        }
+    }
+
+    // First and last day of leave year `year`
+    pub fn bounds(&self, year: i32) -> (NaiveDate, NaiveDate) {
+        let start = NaiveDate::from_ymd_opt(year, self.start_month, 1).unwrap_or_default();
+        let next = NaiveDate::from_ymd_opt(year + 1, self.start_month, 1).unwrap_or_default();
+        (start, next - Duration::days(1))
+    }
+}
+
+// A leave request and where it has got to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaveRequest {
+    pub id: i64,
+    pub user_id: i64,
+    pub leave_type: LeaveType,
+    pub start_date: NaiveDate,
+    // Last day of leave, inclusive
+    pub end_date: NaiveDate,
+    // The first day is only half taken
+    pub half_day_start: bool,
+    // The last day is only half taken
+    pub half_day_end: bool,
+    // Working days taken, counted when the request was made
+    pub days: f64,
+    // For sickness leave this is the sickness note, which is encrypted at rest
+    pub reason: String,
+    pub status: LeaveStatus,
+    // Who should decide it, from the team hierarchy; `None` leaves it to admins
+    pub approver_id: Option<i64>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_note: String,
+    // Set when blackouts or limits were overridden to let the leave through,
+    // with the conflicts that were overridden
+    pub overridden_by: Option<String>,
+    pub override_reason: Option<String>,
+    pub overridden: Vec<Conflict>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl LeaveRequest {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // Whether the request shares any day with `start` to `end`
+    pub fn overlaps(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.start_date <= end && start <= self.end_date
+    }
+
+    // Take `action` on behalf of `actor` at `now`, if the current status allows it
+    pub fn transition(
+        &mut self,
+        action: LeaveAction,
+        actor: &str,
+        note: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), ValidationError> {
+        self.status = action.apply(self.status)?;
+        self.decided_by = Some(actor.to_string());
+        self.decided_at = Some(now);
+        self.decision_note = note.trim().to_string();
+        Ok(())
+    }
+
+    // The request as it blocks assignments; half days block the whole day
+    pub fn approved_leave(&self) -> ApprovedLeave {
+        ApprovedLeave {
+            id: self.id,
+            leave_type: self.leave_type.to_string(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+        }
+    }
+}
+
+// Everything needed to submit a leave request
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewLeaveRequest {
+    pub user_id: i64,
+    pub leave_type: LeaveType,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub half_day_start: bool,
+    pub half_day_end: bool,
+    pub reason: String,
+    pub approver_id: Option<i64>,
+    pub overridden_by: Option<String>,
+    pub override_reason: Option<String>,
+    pub overridden: Vec<Conflict>,
+}
+
+impl NewLeaveRequest {
+    pub fn days(&self) -> f64 {
+        leave_days(self.start_date, self.end_date, self.half_day_start, self.half_day_end)
+    }
+
+    pub fn into_request(self, id: i64, now: DateTime<Utc>) -> LeaveRequest {
+        LeaveRequest {
+            id,
+            user_id: self.user_id,
+            leave_type: self.leave_type,
+            start_date: self.start_date,
+            end_date: self.end_date,
+            half_day_start: self.half_day_start,
+            half_day_end: self.half_day_end,
+            days: self.days(),
+            reason: self.reason,
+            status: LeaveStatus::Pending,
+            approver_id: self.approver_id,
+            decided_by: None,
+            decided_at: None,
+            decision_note: String::new(),
+            overridden_by: self.overridden_by,
+            override_reason: self.override_reason,
+            overridden: self.overridden,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+// Check a request's dates: in order, within one leave year, at most
+// `MAX_LEAVE_DAYS` long and taking at least half a working day
+pub fn validate_leave(request: &NewLeaveRequest, year: LeaveYear) -> Result<(), ValidationError> {
+    if request.end_date < request.start_date {
+        return Err(ValidationError::new("Leave can't end before it starts"));
+    }
+    if (request.end_date - request.start_date).num_days() >= MAX_LEAVE_DAYS {
+        return Err(ValidationError(format!("Leave can't be longer than {} days", MAX_LEAVE_DAYS)));
+    }
+    if year.of(request.start_date) != year.of(request.end_date) {
+        return Err(ValidationError::new("Leave can't span two leave years; make a request for each"));
+    }
+    if request.start_date == request.end_date && request.half_day_start && request.half_day_end {
+        return Err(ValidationError::new("A single day can only be one half day"));
+    }
+    if request.days() <= 0.0 {
+        return Err(ValidationError::new("Leave must include at least one working day"));
+    }
+    if request.reason.chars().count() > 500 {
+        return Err(ValidationError::new("Reason must be at most 500 characters"));
+    }
+
+    Ok(())
+}
+
+// How many days of one type of leave someone gets in a leave year. `days` is
+// the full-time, full-year figure; `carried_over` is what came across unused
+// from the year before.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaveEntitlement {
+    pub user_id: i64,
+    pub year: i32,
+    pub leave_type: LeaveType,
+    pub days: f64,
+    pub carried_over: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Check the figures on an entitlement
+pub fn validate_entitlement(entitlement: &LeaveEntitlement) -> Result<(), ValidationError> {
+    let is_halves = |days: f64| (days * 2.0).fract() == 0.0;
+    if !(0.0..=366.0).contains(&entitlement.days) || !is_halves(entitlement.days) {
+        return Err(ValidationError::new("Days must be whole or half days from 0 to 366"));
+    }
+    if !(0.0..=366.0).contains(&entitlement.carried_over) || !is_halves(entitlement.carried_over) {
+        return Err(ValidationError::new("Carried over days must be whole or half days from 0 to 366"));
+    }
+
+    Ok(())
+}
+
+// A full-time, full-year entitlement scaled by contracted hours against
+// `full_time_hours` and by the share of the leave year `year` the person is
+// employed, rounded up to the next half day. Unknown hours count as full time.
+pub fn pro_rata(
+    days: f64,
+    weekly_hours: Option<f64>,
+    full_time_hours: f64,
+    employment: Option<(NaiveDate, Option<NaiveDate>)>,
+    year: (NaiveDate, NaiveDate),
+) -> f64 {
+    let hours = match weekly_hours {
+        Some(hours) if full_time_hours > 0.0 => (hours / full_time_hours).min(1.0),
+        _ => 1.0,
+    };
+    let employed = match employment {
+        Some((start, end)) => {
+            let (year_start, year_end) = year;
+            let from = start.max(year_start);
+            let to = end.map_or(year_end, |end| end.min(year_end));
+            let year_days = (year_end - year_start).num_days() + 1;
+            ((to - from).num_days() + 1).max(0) as f64 / year_days as f64
+        }
+        None => 1.0,
+    };
+
+    (days * hours * employed * 2.0).ceil() / 2.0
+}
+
+// Where someone stands with one type of leave in one leave year
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaveBalance {
+    pub leave_type: LeaveType,
+    pub year: i32,
+    // The full-time, full-year figure
+    pub entitlement: f64,
+    // The entitlement once pro-rated
+    pub pro_rated: f64,
+    pub carried_over: f64,
+    // Approved leave in the year
+    pub booked: f64,
+    // Leave waiting for a decision
+    pub pending: f64,
+    // What's left once booked leave is taken off
+    pub remaining: f64,
+}
+
+impl LeaveBalance {
+    // Balance against `entitlement` given the person's requests; only
+    // requests of the entitlement's type starting in `bounds` count
+    pub fn new(
+        entitlement: &LeaveEntitlement,
+        pro_rated: f64,
+        bounds: (NaiveDate, NaiveDate),
+        requests: &[LeaveRequest],
+    ) -> Self {
+        let counted = |status: LeaveStatus| {
+            requests
+                .iter()
+                .filter(|r| {
+                    r.leave_type == entitlement.leave_type
+                        && r.status == status
+                        && !r.is_deleted()
+                        && bounds.0 <= r.start_date
+                        && r.start_date <= bounds.1
+                })
+                .map(|r| r.days)
+                .sum::<f64>()
+        };
+        let booked = counted(LeaveStatus::Approved);
+
+        Self {
+            leave_type: entitlement.leave_type,
+            year: entitlement.year,
+            entitlement: entitlement.days,
+            pro_rated,
+            carried_over: entitlement.carried_over,
+            booked,
+            pending: counted(LeaveStatus::Pending),
+            remaining: pro_rated + entitlement.carried_over - booked,
+        }
+    }
+
+    // What can still be asked for, once pending requests are allowed for
+    pub fn available(&self) -> f64 {
+        self.remaining - self.pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn request(start: &str, end: &str, half_day_start: bool, half_day_end: bool) -> NewLeaveRequest {
+        NewLeaveRequest {
+            user_id: 1,
+            leave_type: LeaveType::Annual,
+            start_date: date(start),
+            end_date: date(end),
+            half_day_start,
+            half_day_end,
+            reason: String::new(),
+            approver_id: None,
+            overridden_by: None,
+            override_reason: None,
+            overridden: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn leave_days_skip_weekends_and_count_half_days() {
+        // Monday 2 June to Sunday 15 June 2025
+        assert_eq!(leave_days(date("2025-06-02"), date("2025-06-15"), false, false), 10.0);
+        assert_eq!(leave_days(date("2025-06-02"), date("2025-06-06"), true, true), 4.0);
+        assert_eq!(leave_days(date("2025-06-04"), date("2025-06-04"), true, false), 0.5);
+        // A half day on a Saturday takes nothing off
+        assert_eq!(leave_days(date("2025-06-06"), date("2025-06-07"), false, true), 1.0);
+    }
+
+    #[test]
+    fn requests_must_stay_in_one_leave_year_and_take_a_working_day() {
+        let april = LeaveYear { start_month: 4 };
+
+        assert_eq!(validate_leave(&request("2025-06-02", "2025-06-06", false, false), april), Ok(()));
+        assert!(validate_leave(&request("2025-03-28", "2025-04-02", false, false), april).is_err());
+        assert!(validate_leave(&request("2025-06-07", "2025-06-08", false, false), april).is_err());
+        assert!(validate_leave(&request("2025-06-04", "2025-06-04", true, true), april).is_err());
+        assert!(validate_leave(&request("2025-06-06", "2025-06-02", false, false), april).is_err());
+        assert_eq!(april.of(date("2026-03-31")), 2025);
+        assert_eq!(april.bounds(2025), (date("2025-04-01"), date("2026-03-31")));
+    }
+
+    #[test]
+    fn entitlements_pro_rate_by_hours_and_time_employed() {
+        let year = (date("2025-01-01"), date("2025-12-31"));
+
+        assert_eq!(pro_rata(27.0, None, 37.5, None, year), 27.0);
+        // 22.5 of 37.5 hours is 16.2 days, rounded up to the half day
+        assert_eq!(pro_rata(27.0, Some(22.5), 37.5, None, year), 16.5);
+        assert_eq!(pro_rata(27.0, Some(45.0), 37.5, None, year), 27.0);
+        // Joining on 2 July leaves 183 of 365 days
+        assert_eq!(pro_rata(27.0, None, 37.5, Some((date("2025-07-02"), None)), year), 14.0);
+        assert_eq!(pro_rata(27.0, None, 37.5, Some((date("2024-01-01"), Some(date("2024-12-31")))), year), 0.0);
+    }
+
+    #[test]
+    fn balances_count_approved_and_pending_leave_of_their_type() {
+        let now = Utc::now();
+        let entitlement = LeaveEntitlement {
+            user_id: 1,
+            year: 2025,
+            leave_type: LeaveType::Annual,
+            days: 27.0,
+            carried_over: 2.0,
+            updated_at: now,
+        };
+        let mut approved = request("2025-06-02", "2025-06-06", false, false).into_request(1, now);
+        approved.status = LeaveStatus::Approved;
+        let pending = request("2025-07-07", "2025-07-07", true, false).into_request(2, now);
+        let study = NewLeaveRequest { leave_type: LeaveType::Study, ..request("2025-08-04", "2025-08-04", false, false) };
+        let mut cancelled = request("2025-09-01", "2025-09-05", false, false).into_request(4, now);
+        cancelled.transition(LeaveAction::Cancel, "1", "", now).unwrap();
+        let requests = [approved, pending, study.into_request(3, now), cancelled];
+
+        let balance = LeaveBalance::new(&entitlement, 27.0, (date("2025-01-01"), date("2025-12-31")), &requests);
+
+        assert_eq!(balance.booked, 5.0);
+        assert_eq!(balance.pending, 0.5);
+        assert_eq!(balance.remaining, 24.0);
+        assert_eq!(balance.available(), 23.5);
+    }
+
+    #[test]
+    fn decided_requests_cannot_be_decided_again() {
+        let now = Utc::now();
+        let mut leave = request("2025-06-02", "2025-06-06", false, false).into_request(1, now);
+
+        assert!(leave.transition(LeaveAction::Approve, "7", "Enjoy", now).is_ok());
+        assert_eq!(leave.status, LeaveStatus::Approved);
+        assert_eq!(leave.decided_by.as_deref(), Some("7"));
+        assert!(leave.transition(LeaveAction::Reject, "7", "", now).is_err());
+        assert!(leave.transition(LeaveAction::Cancel, "1", "", now).is_ok());
+        assert!(leave.transition(LeaveAction::Cancel, "1", "", now).is_err());
+    }
+}
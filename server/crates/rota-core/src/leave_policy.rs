@@ -0,0 +1,247 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::assignment::{ApprovedLeave, Conflict, ConflictCode};
+use crate::error::ValidationError;
+use crate::leave::MAX_LEAVE_DAYS;
+
+// Days when leave can't be taken, such as Christmas or an audit. A blackout
+// covers a team and the teams under it, or anyone rostered at a location.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blackout {
+    pub id: i64,
+    pub team_id: Option<i64>,
+    pub location: Option<String>,
+    pub start_date: NaiveDate,
+    // Last day of the blackout, inclusive
+    pub end_date: NaiveDate,
+    pub reason: String,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Blackout {
+    // The days from `start` to `end` that fall in the blackout, if any
+    pub fn overlap(&self, start: NaiveDate, end: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let from = start.max(self.start_date);
+        let to = end.min(self.end_date);
+        (from <= to).then_some((from, to))
+    }
+
+    // The conflict for leave from `start` to `end`, if it falls in the blackout
+    pub fn conflict(&self, start: NaiveDate, end: NaiveDate) -> Option<Conflict> {
+        self.overlap(start, end).map(|(from, to)| {
+            let days = if from == to { from.to_string() } else { format!("{} to {}", from, to) };
+            Conflict::new(ConflictCode::Blackout, format!("{} is blacked out: {}", days, self.reason))
+        })
+    }
+}
+
+// Everything needed to create a blackout
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewBlackout {
+    pub team_id: Option<i64>,
+    pub location: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub reason: String,
+    pub created_by: Option<String>,
+}
+
+impl NewBlackout {
+    pub fn into_blackout(self, id: i64, created_at: DateTime<Utc>) -> Blackout {
+        Blackout {
+            id,
+            team_id: self.team_id,
+            location: self.location,
+            start_date: self.start_date,
+            end_date: self.end_date,
+            reason: self.reason,
+            created_by: self.created_by,
+            created_at,
+        }
+    }
+}
+
+// Check a blackout covers someone, has dates in order and says why
+pub fn validate_blackout(blackout: &NewBlackout) -> Result<(), ValidationError> {
+    if blackout.team_id.is_none() && blackout.location.as_deref().is_none_or(str::is_empty) {
+        return Err(ValidationError::new("A blackout needs a team or a location"));
+    }
+    if blackout.end_date < blackout.start_date {
+        return Err(ValidationError::new("A blackout can't end before it starts"));
+    }
+    if (blackout.end_date - blackout.start_date).num_days() >= MAX_LEAVE_DAYS {
+        return Err(ValidationError(format!("A blackout can't be longer than {} days", MAX_LEAVE_DAYS)));
+    }
+    if blackout.reason.is_empty() {
+        return Err(ValidationError::new("A blackout needs a reason"));
+    }
+    if blackout.reason.chars().count() > 500 {
+        return Err(ValidationError::new("Reason must be at most 500 characters"));
+    }
+
+    Ok(())
+}
+
+// How many of a team's members may be off at once: everyone on the team, or
+// only those with `skill`. With both caps set the lower one applies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaveLimit {
+    pub id: i64,
+    pub team_id: i64,
+    pub skill: Option<String>,
+    pub max_off: Option<u32>,
+    // Share of the members, rounded down
+    pub max_percent: Option<u32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LeaveLimit {
+    // Whether someone with `skills` counts towards the limit
+    pub fn applies_to(&self, skills: &[String]) -> bool {
+        self.skill.as_ref().is_none_or(|skill| skills.iter().any(|s| s.eq_ignore_ascii_case(skill)))
+    }
+
+    // Most people allowed off at once out of `members`
+    pub fn allowed(&self, members: usize) -> usize {
+        let by_count = self.max_off.map_or(usize::MAX, |max| max as usize);
+        let by_share = self.max_percent.map_or(usize::MAX, |percent| members * percent as usize / 100);
+        by_count.min(by_share)
+    }
+
+    // The conflict for one more of `members` being off from `start` to `end`
+    // when `others` are already off, naming the busiest day
+    pub fn conflict(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        members: usize,
+        others: &[ApprovedLeave],
+    ) -> Option<Conflict> {
+        let allowed = self.allowed(members);
+        let (day, off) = start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .map(|day| (day, 1 + others.iter().filter(|l| l.start_date <= day && day <= l.end_date).count()))
+            .max_by_key(|(day, off)| (*off, std::cmp::Reverse(*day)))?;
+        if off <= allowed {
+            return None;
+        }
+
+        let who = self.skill.as_ref().map_or("the team".to_string(), |skill| format!("{} staff", skill));
+        Some(Conflict::new(
+            ConflictCode::TooManyOff,
+            format!("{} of {} would be off on {}; at most {} may be", off, who, day, allowed),
+        ))
+    }
+}
+
+// Everything needed to create a limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewLeaveLimit {
+    pub team_id: i64,
+    pub skill: Option<String>,
+    pub max_off: Option<u32>,
+    pub max_percent: Option<u32>,
+}
+
+impl NewLeaveLimit {
+    pub fn into_limit(self, id: i64, created_at: DateTime<Utc>) -> LeaveLimit {
+        LeaveLimit {
+            id,
+            team_id: self.team_id,
+            skill: self.skill,
+            max_off: self.max_off,
+            max_percent: self.max_percent,
+            created_at,
+        }
+    }
+}
+
+// Check a limit caps something, and that a share is a percentage
+pub fn validate_limit(limit: &NewLeaveLimit) -> Result<(), ValidationError> {
+    if limit.max_off.is_none() && limit.max_percent.is_none() {
+        return Err(ValidationError::new("A limit needs max_off, max_percent or both"));
+    }
+    if limit.max_percent.is_some_and(|percent| percent > 100) {
+        return Err(ValidationError::new("max_percent must be from 0 to 100"));
+    }
+    if limit.skill.as_deref().is_some_and(|skill| skill.is_empty() || skill.chars().count() > 100) {
+        return Err(ValidationError::new("Skill must be 1 to 100 characters"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn leave(id: i64, start: &str, end: &str) -> ApprovedLeave {
+        ApprovedLeave {
+            id,
+            leave_type: "annual".to_string(),
+            start_date: date(start),
+            end_date: date(end),
+        }
+    }
+
+    fn limit(skill: Option<&str>, max_off: Option<u32>, max_percent: Option<u32>) -> LeaveLimit {
+        NewLeaveLimit { team_id: 1, skill: skill.map(str::to_string), max_off, max_percent }.into_limit(1, Utc::now())
+    }
+
+    #[test]
+    fn blackouts_conflict_with_leave_sharing_a_day() {
+        let christmas = NewBlackout {
+            team_id: Some(1),
+            location: None,
+            start_date: date("2025-12-20"),
+            end_date: date("2026-01-02"),
+            reason: "Christmas cover".to_string(),
+            created_by: None,
+        };
+        let blackout = christmas.clone().into_blackout(1, Utc::now());
+
+        assert_eq!(validate_blackout(&christmas), Ok(()));
+        assert!(validate_blackout(&NewBlackout { team_id: None, ..christmas.clone() }).is_err());
+        assert!(validate_blackout(&NewBlackout { reason: String::new(), ..christmas }).is_err());
+        let overlap = blackout.overlap(date("2025-12-15"), date("2025-12-22"));
+        assert_eq!(overlap, Some((date("2025-12-20"), date("2025-12-22"))));
+        assert_eq!(blackout.conflict(date("2025-12-01"), date("2025-12-19")), None);
+        let conflict = blackout.conflict(date("2026-01-02"), date("2026-01-09")).unwrap();
+        assert_eq!(conflict.code, ConflictCode::Blackout);
+        assert_eq!(conflict.message, "2026-01-02 is blacked out: Christmas cover");
+    }
+
+    #[test]
+    fn limits_take_the_lower_cap_and_count_the_busiest_day() {
+        // 25% of 10 rounds down to 2
+        assert_eq!(limit(None, Some(3), Some(25)).allowed(10), 2);
+        assert_eq!(limit(None, Some(1), None).allowed(10), 1);
+        assert!(limit(Some("Charge nurse"), Some(1), None).applies_to(&["charge NURSE".to_string()]));
+        assert!(!limit(Some("Charge nurse"), Some(1), None).applies_to(&[]));
+
+        let others = [leave(1, "2025-06-02", "2025-06-04"), leave(2, "2025-06-04", "2025-06-06")];
+        let two_off = limit(None, Some(2), None);
+        assert_eq!(two_off.conflict(date("2025-06-02"), date("2025-06-03"), 10, &others), None);
+        let conflict = two_off.conflict(date("2025-06-03"), date("2025-06-05"), 10, &others).unwrap();
+        assert_eq!(conflict.code, ConflictCode::TooManyOff);
+        assert_eq!(conflict.message, "3 of the team would be off on 2025-06-04; at most 2 may be");
+    }
+
+    #[test]
+    fn limits_must_cap_something() {
+        let limit = NewLeaveLimit { team_id: 1, skill: None, max_off: None, max_percent: None };
+
+        assert!(validate_limit(&limit).is_err());
+        assert!(validate_limit(&NewLeaveLimit { max_percent: Some(101), ..limit.clone() }).is_err());
+        let blank_skill = NewLeaveLimit { skill: Some(String::new()), max_off: Some(1), ..limit.clone() };
+        assert!(validate_limit(&blank_skill).is_err());
+        assert_eq!(validate_limit(&NewLeaveLimit { max_off: Some(0), ..limit }), Ok(()));
+    }
+}
@@ -0,0 +1,21 @@
+//! Domain types and rules for rota management.
+//!
+//! This crate deliberately has no knowledge of HTTP or databases so the
+//! scheduling logic can be embedded in other tools. The `rota-server` crate
+//! layers persistence and the REST API on top of it.
+
+pub mod assignment;
+pub mod availability;
+pub mod error;
+pub mod leave;
+pub mod leave_policy;
+pub mod pattern;
+pub mod profile;
+pub mod recurrence;
+pub mod rota;
+pub mod shift;
+pub mod template;
+pub mod toil;
+pub mod user;
+
+pub use error::ValidationError;
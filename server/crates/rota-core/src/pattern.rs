@@ -0,0 +1,274 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ValidationError;
+
+// Longest cycle a pattern can have, in days
+pub const MAX_CYCLE_DAYS: usize = 56;
+
+// Well-known rotating patterns. Each is a cycle of slots that index into the
+// templates it is built from, with `None` for days off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternPreset {
+    // Four days on, four off, on one template
+    FourOnFourOff,
+    // 2-2-3 over a fortnight on one template: two on, two off, three on, then
+    // the reverse
+    Panama,
+    // Two earlies, two lates and two nights, then two days off, on three
+    // templates in that order
+    Continental,
+}
+
+impl PatternPreset {
+    fn slots(self) -> &'static [Option<usize>] {
+        const ON: Option<usize> = Some(0);
+        const OFF: Option<usize> = None;
+        match self {
+            PatternPreset::FourOnFourOff => &[ON, ON, ON, ON, OFF, OFF, OFF, OFF],
+            PatternPreset::Panama => &[ON, ON, OFF, OFF, ON, ON, ON, OFF, OFF, ON, ON, OFF, OFF, OFF],
+            PatternPreset::Continental => &[Some(0), Some(0), Some(1), Some(1), Some(2), Some(2), OFF, OFF],
+        }
+    }
+
+    // How many templates the preset is built from
+    pub fn templates_needed(self) -> usize {
+        self.slots().iter().flatten().max().map_or(0, |slot| slot + 1)
+    }
+
+    // The cycle this preset makes from `templates`, in slot order
+    pub fn cycle(self, templates: &[i64]) -> Result<Vec<Option<i64>>, ValidationError> {
+        if templates.len() != self.templates_needed() {
+            return Err(ValidationError(format!(
+                "This pattern needs {} template(s), not {}",
+                self.templates_needed(),
+                templates.len()
+            )));
+        }
+
+        Ok(self.slots().iter().map(|slot| slot.map(|i| templates[i])).collect())
+    }
+}
+
+// A cycle of shift templates that a team works through over and over. Day 0
+// of the cycle falls on `anchor_date` for someone at offset 0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub id: i64,
+    pub team_id: i64,
+    pub name: String,
+    pub anchor_date: NaiveDate,
+    // The template worked on each day of the cycle, or `None` for a day off
+    pub cycle: Vec<Option<i64>>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// Everything needed to create a pattern, or replace one's details
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPattern {
+    pub team_id: i64,
+    pub name: String,
+    pub anchor_date: NaiveDate,
+    pub cycle: Vec<Option<i64>>,
+}
+
+impl NewPattern {
+    pub fn into_pattern(self, id: i64, now: DateTime<Utc>) -> Pattern {
+        Pattern {
+            id,
+            team_id: self.team_id,
+            name: self.name,
+            anchor_date: self.anchor_date,
+            cycle: self.cycle,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+// Check a pattern before it is saved
+pub fn validate_pattern(pattern: &NewPattern) -> Result<(), ValidationError> {
+    if pattern.name.trim().is_empty() {
+        return Err(ValidationError::new("Pattern name cannot be empty"));
+    }
+    if pattern.cycle.is_empty() || pattern.cycle.len() > MAX_CYCLE_DAYS {
+        return Err(ValidationError(format!("A cycle must be 1 to {} days long", MAX_CYCLE_DAYS)));
+    }
+    if pattern.cycle.iter().all(Option::is_none) {
+        return Err(ValidationError::new("A cycle needs at least one working day"));
+    }
+
+    Ok(())
+}
+
+// Someone working a pattern: on the anchor date they are on day `offset` of
+// the cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternMember {
+    pub user_id: i64,
+    pub offset: i32,
+}
+
+// A change to what the cycle says for one person on one day, such as a swap
+// or a day off. Overrides are kept apart from the cycle so they survive the
+// pattern being changed and projected again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternOverride {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    // The template worked instead, or `None` for a day off
+    pub template_id: Option<i64>,
+    pub reason: String,
+}
+
+// One working day that a pattern gives someone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectedDay {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    pub template_id: i64,
+    // Day of the cycle the person is on; still reported when overridden
+    pub cycle_day: usize,
+    pub overridden: bool,
+}
+
+impl Pattern {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // The day of the cycle someone at `offset` is on at `date`
+    pub fn cycle_day(&self, offset: i32, date: NaiveDate) -> usize {
+        let days = (date - self.anchor_date).num_days() + i64::from(offset);
+        days.rem_euclid(self.cycle.len() as i64) as usize
+    }
+
+    // Check a member's offset falls within the cycle
+    pub fn validate_member(&self, member: &PatternMember) -> Result<(), ValidationError> {
+        if member.offset < 0 || member.offset as usize >= self.cycle.len() {
+            return Err(ValidationError(format!(
+                "Offset for user {} must be from 0 to {}",
+                member.user_id,
+                self.cycle.len() - 1
+            )));
+        }
+        Ok(())
+    }
+
+    // Every working day the pattern gives its members from `from` to `to`
+    // inclusive, by date then member, with overrides applied
+    pub fn project(
+        &self,
+        members: &[PatternMember],
+        overrides: &[PatternOverride],
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<ProjectedDay> {
+        let mut days = Vec::new();
+        let mut date = from;
+
+        while date <= to {
+            for member in members {
+                let cycle_day = self.cycle_day(member.offset, date);
+                let manual = overrides.iter().find(|o| o.user_id == member.user_id && o.date == date);
+                let template_id = match manual {
+                    Some(manual) => manual.template_id,
+                    None => self.cycle[cycle_day],
+                };
+                if let Some(template_id) = template_id {
+                    days.push(ProjectedDay {
+                        user_id: member.user_id,
+                        date,
+                        template_id,
+                        cycle_day,
+                        overridden: manual.is_some(),
+                    });
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        days
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn pattern(cycle: Vec<Option<i64>>) -> Pattern {
+        NewPattern {
+            team_id: 1,
+            name: "Control room".to_string(),
+            anchor_date: date("2025-06-02"),
+            cycle,
+        }
+        .into_pattern(1, Utc::now())
+    }
+
+    fn worked(days: &[ProjectedDay], user_id: i64) -> Vec<String> {
+        days.iter().filter(|d| d.user_id == user_id).map(|d| d.date.format("%d").to_string()).collect()
+    }
+
+    #[test]
+    fn presets_fill_their_slots_with_templates() {
+        assert_eq!(PatternPreset::Continental.templates_needed(), 3);
+        assert_eq!(
+            PatternPreset::Continental.cycle(&[7, 8, 9]).unwrap(),
+            vec![Some(7), Some(7), Some(8), Some(8), Some(9), Some(9), None, None]
+        );
+        assert_eq!(PatternPreset::Panama.cycle(&[7]).unwrap().iter().flatten().count(), 7);
+        assert!(PatternPreset::FourOnFourOff.cycle(&[7, 8]).is_err());
+    }
+
+    #[test]
+    fn offsets_stagger_members_through_the_cycle() {
+        let pattern = pattern(PatternPreset::FourOnFourOff.cycle(&[7]).unwrap());
+        let members = [
+            PatternMember { user_id: 1, offset: 0 },
+            PatternMember { user_id: 2, offset: 4 },
+        ];
+
+        let days = pattern.project(&members, &[], date("2025-06-02"), date("2025-06-11"));
+
+        assert_eq!(worked(&days, 1), vec!["02", "03", "04", "05", "10", "11"]);
+        assert_eq!(worked(&days, 2), vec!["06", "07", "08", "09"]);
+        // Dates before the anchor carry on the same cycle backwards
+        assert_eq!(pattern.cycle_day(0, date("2025-06-01")), 7);
+        assert!(pattern.validate_member(&PatternMember { user_id: 3, offset: 8 }).is_err());
+    }
+
+    #[test]
+    fn overrides_replace_what_the_cycle_says() {
+        let pattern = pattern(vec![Some(7), None]);
+        let members = [PatternMember { user_id: 1, offset: 0 }];
+        let overrides = [
+            PatternOverride { user_id: 1, date: date("2025-06-02"), template_id: None, reason: "Training".into() },
+            PatternOverride { user_id: 1, date: date("2025-06-03"), template_id: Some(9), reason: String::new() },
+        ];
+
+        let days = pattern.project(&members, &overrides, date("2025-06-02"), date("2025-06-04"));
+
+        assert_eq!(
+            days.iter().map(|d| (d.date.to_string(), d.template_id, d.overridden)).collect::<Vec<_>>(),
+            vec![("2025-06-03".to_string(), 9, true), ("2025-06-04".to_string(), 7, false)]
+        );
+        assert!(validate_pattern(&NewPattern {
+            team_id: 1,
+            name: "Idle".into(),
+            anchor_date: date("2025-06-02"),
+            cycle: vec![None, None]
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+use crate::user::validate_contracted_hours;
+
+// The kind of employment contract a member of staff is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractType {
+    FullTime,
+    PartTime,
+    // No guaranteed hours
+    ZeroHours,
+    // Hours agreed per year; the weekly figure is the average
+    Annualised,
+}
+
+impl Display for ContractType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContractType::FullTime => write!(f, "full_time"),
+            ContractType::PartTime => write!(f, "part_time"),
+            ContractType::ZeroHours => write!(f, "zero_hours"),
+            ContractType::Annualised => write!(f, "annualised"),
+        }
+    }
+}
+
+impl FromStr for ContractType {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full_time" => Ok(ContractType::FullTime),
+            "part_time" => Ok(ContractType::PartTime),
+            "zero_hours" => Ok(ContractType::ZeroHours),
+            "annualised" => Ok(ContractType::Annualised),
+            other => Err(ValidationError(format!("Unknown contract type: {}", other))),
+        }
+    }
+}
+
+// A certificate or licence held by a member of staff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Qualification {
+    pub name: String,
+    pub issued_on: NaiveDate,
+    // `None` for qualifications that never lapse
+    pub expires_on: Option<NaiveDate>,
+}
+
+impl Qualification {
+    // Whether the qualification can be relied on for work on `date`
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        self.issued_on <= date && self.expires_on.is_none_or(|expiry| date <= expiry)
+    }
+}
+
+// Everything about a member of staff that decides when and where they can work
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaffProfile {
+    pub user_id: i64,
+    pub contract_type: ContractType,
+    pub weekly_hours: Option<f64>,
+    pub pay_grade: Option<String>,
+    pub start_date: NaiveDate,
+    // Last day of employment, for leavers
+    pub end_date: Option<NaiveDate>,
+    pub home_team_id: Option<i64>,
+    pub skills: Vec<String>,
+    pub qualifications: Vec<Qualification>,
+}
+
+// What a shift asks of whoever works it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShiftRequirements {
+    pub date: NaiveDate,
+    pub skills: Vec<String>,
+    pub qualifications: Vec<String>,
+}
+
+// Why someone can't work a shift
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum Ineligibility {
+    // There is no employment record to schedule against
+    NoProfile,
+    NotStarted(NaiveDate),
+    Left(NaiveDate),
+    MissingSkill(String),
+    MissingQualification(String),
+    ExpiredQualification(String),
+}
+
+impl StaffProfile {
+    // Whether the member of staff is employed on `date`
+    pub fn is_employed_on(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && self.end_date.is_none_or(|end| date <= end)
+    }
+
+    // Every reason the member of staff can't work a shift with these
+    // requirements; empty when they can. Names compare ignoring case.
+    pub fn eligibility(&self, requirements: &ShiftRequirements) -> Vec<Ineligibility> {
+        let mut reasons = Vec::new();
+        let date = requirements.date;
+
+        if date < self.start_date {
+            reasons.push(Ineligibility::NotStarted(self.start_date));
+        }
+        if let Some(end) = self.end_date.filter(|end| date > *end) {
+            reasons.push(Ineligibility::Left(end));
+        }
+
+        for skill in &requirements.skills {
+            if !self.skills.iter().any(|s| s.eq_ignore_ascii_case(skill)) {
+                reasons.push(Ineligibility::MissingSkill(skill.clone()));
+            }
+        }
+
+        for name in &requirements.qualifications {
+            let held: Vec<_> = self
+                .qualifications
+                .iter()
+                .filter(|q| q.name.eq_ignore_ascii_case(name))
+                .collect();
+            if held.is_empty() {
+                reasons.push(Ineligibility::MissingQualification(name.clone()));
+            } else if !held.iter().any(|q| q.is_valid_on(date)) {
+                reasons.push(Ineligibility::ExpiredQualification(name.clone()));
+            }
+        }
+
+        reasons
+    }
+}
+
+// Check a profile before it is saved
+pub fn validate_profile(profile: &StaffProfile) -> Result<(), ValidationError> {
+    if profile.end_date.is_some_and(|end| end < profile.start_date) {
+        return Err(ValidationError::new("End date cannot be before the start date"));
+    }
+
+    match (profile.contract_type, profile.weekly_hours) {
+        (ContractType::ZeroHours, Some(hours)) if hours > 0.0 => {
+            return Err(ValidationError::new("Zero-hours contracts have no weekly hours"));
+        }
+        (ContractType::ZeroHours, _) => {}
+        (_, None) => {
+            return Err(ValidationError::new(format!(
+                "Weekly hours are required for {} contracts",
+                profile.contract_type
+            )));
+        }
+        (_, Some(hours)) => validate_contracted_hours(hours)?,
+    }
+
+    if profile.pay_grade.as_deref().is_some_and(|grade| grade.trim().is_empty()) {
+        return Err(ValidationError::new("Pay grade cannot be blank"));
+    }
+
+    for (i, qualification) in profile.qualifications.iter().enumerate() {
+        if qualification.name.trim().is_empty() {
+            return Err(ValidationError::new("Qualification name cannot be empty"));
+        }
+        if qualification.expires_on.is_some_and(|expiry| expiry < qualification.issued_on) {
+            return Err(ValidationError(format!(
+                "{} expires before it was issued",
+                qualification.name
+            )));
+        }
+        if profile.qualifications[..i]
+            .iter()
+            .any(|q| q.name.trim().eq_ignore_ascii_case(qualification.name.trim()))
+        {
+            return Err(ValidationError(format!(
+                "Qualification {} is listed twice",
+                qualification.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn profile() -> StaffProfile {
+        StaffProfile {
+            user_id: 1,
+            contract_type: ContractType::PartTime,
+            weekly_hours: Some(22.5),
+            pay_grade: Some("Band 5".to_string()),
+            start_date: date("2024-01-01"),
+            end_date: Some(date("2026-12-31")),
+            home_team_id: Some(1),
+            skills: vec!["ICU".to_string()],
+            qualifications: vec![Qualification {
+                name: "BLS".to_string(),
+                issued_on: date("2024-01-01"),
+                expires_on: Some(date("2025-12-31")),
+            }],
+        }
+    }
+
+    #[test]
+    fn contract_type_round_trips_through_its_string_form() {
+        for contract in [
+            ContractType::FullTime,
+            ContractType::PartTime,
+            ContractType::ZeroHours,
+            ContractType::Annualised,
+        ] {
+            assert_eq!(contract.to_string().parse::<ContractType>(), Ok(contract));
+            assert_eq!(
+                serde_json::to_value(contract).unwrap(),
+                serde_json::Value::String(contract.to_string())
+            );
+        }
+        assert!("casual".parse::<ContractType>().is_err());
+    }
+
+    #[test]
+    fn eligibility_explains_every_shortfall() {
+        let profile = profile();
+        let requirements = |on: &str| ShiftRequirements {
+            date: date(on),
+            skills: vec!["icu".to_string(), "Triage".to_string()],
+            qualifications: vec!["bls".to_string(), "ALS".to_string()],
+        };
+
+        assert_eq!(
+            profile.eligibility(&requirements("2025-06-01")),
+            vec![
+                Ineligibility::MissingSkill("Triage".to_string()),
+                Ineligibility::MissingQualification("ALS".to_string()),
+            ]
+        );
+        assert_eq!(
+            profile.eligibility(&requirements("2027-01-01")),
+            vec![
+                Ineligibility::Left(date("2026-12-31")),
+                Ineligibility::MissingSkill("Triage".to_string()),
+                Ineligibility::ExpiredQualification("bls".to_string()),
+                Ineligibility::MissingQualification("ALS".to_string()),
+            ]
+        );
+
+        let easy = ShiftRequirements { date: date("2024-01-01"), ..Default::default() };
+        assert!(profile.eligibility(&easy).is_empty());
+        assert!(profile.is_employed_on(date("2026-12-31")));
+        assert!(!profile.is_employed_on(date("2023-12-31")));
+    }
+
+    #[test]
+    fn profile_validation() {
+        assert_eq!(validate_profile(&profile()), Ok(()));
+
+        let ends_early = StaffProfile { end_date: Some(date("2023-01-01")), ..profile() };
+        assert!(validate_profile(&ends_early).is_err());
+
+        let no_hours = StaffProfile { weekly_hours: None, ..profile() };
+        assert!(validate_profile(&no_hours).is_err());
+
+        let zero_hours = StaffProfile {
+            contract_type: ContractType::ZeroHours,
+            weekly_hours: None,
+            ..profile()
+        };
+        assert_eq!(validate_profile(&zero_hours), Ok(()));
+        let zero_with_hours = StaffProfile { weekly_hours: Some(10.0), ..zero_hours };
+        assert!(validate_profile(&zero_with_hours).is_err());
+
+        let mut twice = profile();
+        twice.qualifications.push(Qualification { name: "bls ".to_string(), ..twice.qualifications[0].clone() });
+        assert!(validate_profile(&twice).is_err());
+
+        let mut backwards = profile();
+        backwards.qualifications[0].expires_on = Some(date("2023-01-01"));
+        assert!(validate_profile(&backwards).is_err());
+    }
+}
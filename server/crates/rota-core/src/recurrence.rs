@@ -0,0 +1,286 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+
+// How often a rule repeats. Only the frequencies rotas need are supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+}
+
+// The subset of an RFC 5545 RRULE that shift patterns use: `FREQ` (DAILY or
+// WEEKLY), `INTERVAL`, `BYDAY` (plain weekdays), `COUNT` and `UNTIL` (a date).
+// Weeks start on Monday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    // Days of the week to repeat on; empty means the start date's weekday for
+    // weekly rules and every day for daily ones
+    pub by_day: Vec<Weekday>,
+    pub count: Option<u32>,
+    // Last date an occurrence can fall on, inclusive
+    pub until: Option<NaiveDate>,
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn parse_weekday(code: &str) -> Result<Weekday, ValidationError> {
+    WEEKDAYS
+        .iter()
+        .find(|(name, _)| *name == code)
+        .map(|(_, day)| *day)
+        .ok_or_else(|| ValidationError(format!("Unsupported BYDAY value: {}", code)))
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    WEEKDAYS[day.num_days_from_monday() as usize].0
+}
+
+// `UNTIL` is a date (`20250630`) or a UTC date-time (`20250630T235959Z`), of
+// which only the date is used
+fn parse_until(value: &str) -> Result<NaiveDate, ValidationError> {
+    let date = value.split('T').next().unwrap_or(value);
+    NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| ValidationError(format!("Invalid UNTIL: {}", value)))
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let text = text.strip_prefix("RRULE:").unwrap_or(text);
+        let mut frequency = None;
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Weekly,
+            interval: 1,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| ValidationError(format!("Invalid rule part: {}", part)))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        other => return Err(ValidationError(format!("Unsupported FREQ: {}", other))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| ValidationError(format!("Invalid INTERVAL: {}", value)))?
+                }
+                "BYDAY" => {
+                    for code in value.split(',') {
+                        let day = parse_weekday(&code.trim().to_ascii_uppercase())?;
+                        if !rule.by_day.contains(&day) {
+                            rule.by_day.push(day);
+                        }
+                    }
+                    rule.by_day.sort_by_key(|day| day.num_days_from_monday());
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| ValidationError(format!("Invalid COUNT: {}", value)))?,
+                    )
+                }
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                other => return Err(ValidationError(format!("Unsupported rule part: {}", other))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| ValidationError::new("Rule needs a FREQ"))?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(ValidationError::new("A rule can't have both COUNT and UNTIL"));
+        }
+
+        Ok(rule)
+    }
+}
+
+// The canonical form, which is what gets stored
+impl Display for RecurrenceRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter().map(|day| weekday_code(*day)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for RecurrenceRule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RecurrenceRule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(|err: ValidationError| serde::de::Error::custom(err.0))
+    }
+}
+
+// A rule anchored to the date it starts on, with dates to leave out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub rule: RecurrenceRule,
+    pub starts_on: NaiveDate,
+    // Dates the rule would produce that should be skipped, such as bank
+    // holidays. They still count towards `COUNT`.
+    pub exceptions: Vec<NaiveDate>,
+}
+
+impl Recurrence {
+    fn matches(&self, date: NaiveDate) -> bool {
+        let rule = &self.rule;
+        let on_day = |day: Weekday| {
+            if rule.by_day.is_empty() {
+                rule.frequency == Frequency::Daily || day == self.starts_on.weekday()
+            } else {
+                rule.by_day.contains(&day)
+            }
+        };
+        let interval = i64::from(rule.interval);
+
+        match rule.frequency {
+            Frequency::Daily => (date - self.starts_on).num_days() % interval == 0 && on_day(date.weekday()),
+            Frequency::Weekly => {
+                let monday = |d: NaiveDate| d - Duration::days(i64::from(d.weekday().num_days_from_monday()));
+                let weeks = (monday(date) - monday(self.starts_on)).num_weeks();
+                weeks % interval == 0 && on_day(date.weekday())
+            }
+        }
+    }
+
+    // Dates from `from` to `to` inclusive that the recurrence falls on
+    pub fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let last = self.rule.until.map_or(to, |until| until.min(to));
+        let mut dates = Vec::new();
+        let mut seen = 0;
+        let mut date = self.starts_on;
+
+        while date <= last {
+            if self.matches(date) {
+                seen += 1;
+                if self.rule.count.is_some_and(|count| seen > count) {
+                    break;
+                }
+                if date >= from && !self.exceptions.contains(&date) {
+                    dates.push(date);
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        dates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn recurrence(rule: &str, starts_on: &str) -> Recurrence {
+        Recurrence {
+            rule: rule.parse().unwrap(),
+            starts_on: date(starts_on),
+            exceptions: Vec::new(),
+        }
+    }
+
+    fn strings(dates: Vec<NaiveDate>) -> Vec<String> {
+        dates.into_iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn rules_parse_to_a_canonical_form() {
+        let rule: RecurrenceRule = "RRULE:freq=weekly;byday=FR,MO,MO;INTERVAL=2;UNTIL=20250630T235959Z".parse().unwrap();
+
+        assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;UNTIL=20250630");
+        assert_eq!(rule.to_string().parse::<RecurrenceRule>(), Ok(rule));
+        assert!("FREQ=MONTHLY".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=WEEKLY;BYDAY=1MO".parse::<RecurrenceRule>().is_err());
+        assert!("INTERVAL=2".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=DAILY;COUNT=3;UNTIL=20250630".parse::<RecurrenceRule>().is_err());
+    }
+
+    #[test]
+    fn weekly_rules_repeat_on_their_days() {
+        let mut weekdays = recurrence("FREQ=WEEKLY;BYDAY=MO,WE,FR", "2025-06-04");
+        weekdays.exceptions = vec![date("2025-06-09")];
+
+        // Occurrences before the start date don't exist, and exceptions are dropped
+        assert_eq!(
+            strings(weekdays.occurrences(date("2025-06-02"), date("2025-06-13"))),
+            vec!["2025-06-04", "2025-06-06", "2025-06-11", "2025-06-13"]
+        );
+
+        let fortnightly = recurrence("FREQ=WEEKLY;INTERVAL=2", "2025-06-03");
+        assert_eq!(
+            strings(fortnightly.occurrences(date("2025-06-01"), date("2025-06-30"))),
+            vec!["2025-06-03", "2025-06-17"]
+        );
+    }
+
+    #[test]
+    fn count_and_until_end_the_rule() {
+        let mut counted = recurrence("FREQ=DAILY;INTERVAL=2;COUNT=3", "2025-06-01");
+        counted.exceptions = vec![date("2025-06-03")];
+        // The exception still uses up one of the three
+        assert_eq!(
+            strings(counted.occurrences(date("2025-06-01"), date("2025-06-30"))),
+            vec!["2025-06-01", "2025-06-05"]
+        );
+
+        let until = recurrence("FREQ=DAILY;BYDAY=SA,SU;UNTIL=20250608", "2025-06-01");
+        assert_eq!(
+            strings(until.occurrences(date("2025-06-02"), date("2025-06-30"))),
+            vec!["2025-06-07", "2025-06-08"]
+        );
+    }
+}
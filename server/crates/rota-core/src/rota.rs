@@ -0,0 +1,267 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+use crate::user::UserRole;
+
+// How long a rota period runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotaLength {
+    Week,
+    Fortnight,
+    FourWeeks,
+}
+
+impl RotaLength {
+    pub fn days(self) -> i64 {
+        match self {
+            RotaLength::Week => 7,
+            RotaLength::Fortnight => 14,
+            RotaLength::FourWeeks => 28,
+        }
+    }
+
+    // The length of a period running from `start` to `end` inclusive
+    pub fn of_period(start: NaiveDate, end: NaiveDate) -> Option<Self> {
+        match (end - start).num_days() + 1 {
+            7 => Some(RotaLength::Week),
+            14 => Some(RotaLength::Fortnight),
+            28 => Some(RotaLength::FourWeeks),
+            _ => None,
+        }
+    }
+}
+
+// Where a rota is in its lifecycle. Staff only ever see what was last
+// published; a locked rota has gone to payroll and can't change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotaStatus {
+    Draft,
+    Published,
+    Locked,
+}
+
+impl Display for RotaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RotaStatus::Draft => write!(f, "draft"),
+            RotaStatus::Published => write!(f, "published"),
+            RotaStatus::Locked => write!(f, "locked"),
+        }
+    }
+}
+
+impl FromStr for RotaStatus {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(RotaStatus::Draft),
+            "published" => Ok(RotaStatus::Published),
+            "locked" => Ok(RotaStatus::Locked),
+            other => Err(ValidationError(format!("Unknown rota status: {}", other))),
+        }
+    }
+}
+
+// A move from one status to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotaAction {
+    // Show staff the rota as it is now. Allowed again after edits.
+    Publish,
+    // Freeze the rota once the period has gone to payroll
+    Lock,
+    // Reopen a locked rota for corrections
+    Unlock,
+}
+
+impl RotaAction {
+    // Whether someone with `role` may take this action
+    pub fn is_allowed_for(self, role: &UserRole) -> bool {
+        match self {
+            RotaAction::Publish => role.is_manager(),
+            RotaAction::Lock | RotaAction::Unlock => *role == UserRole::Admin,
+        }
+    }
+
+    // The status a rota in `from` moves to
+    pub fn apply(self, from: RotaStatus) -> Result<RotaStatus, ValidationError> {
+        match (self, from) {
+            (RotaAction::Publish, RotaStatus::Draft | RotaStatus::Published) => Ok(RotaStatus::Published),
+            (RotaAction::Lock, RotaStatus::Published) => Ok(RotaStatus::Locked),
+            (RotaAction::Unlock, RotaStatus::Locked) => Ok(RotaStatus::Published),
+            (RotaAction::Publish, RotaStatus::Locked) => {
+                Err(ValidationError::new("Locked rotas can't be published; unlock it first"))
+            }
+            (RotaAction::Lock, RotaStatus::Draft) => Err(ValidationError::new("Only published rotas can be locked")),
+            (RotaAction::Lock, RotaStatus::Locked) => Err(ValidationError::new("Rota is already locked")),
+            (RotaAction::Unlock, _) => Err(ValidationError::new("Rota isn't locked")),
+        }
+    }
+}
+
+// The shifts of one team over a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rota {
+    pub id: i64,
+    pub team_id: i64,
+    pub period_start: NaiveDate,
+    // Last day of the period, inclusive
+    pub period_end: NaiveDate,
+    pub status: RotaStatus,
+    // How many times the rota has been published; 0 for a draft
+    pub revision: i64,
+    pub published_at: Option<DateTime<Utc>>,
+    pub published_by: Option<String>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub locked_by: Option<String>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Rota {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn length(&self) -> Option<RotaLength> {
+        RotaLength::of_period(self.period_start, self.period_end)
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.period_start <= date && date <= self.period_end
+    }
+
+    // Whether the rota shares any day with the period from `start` to `end`
+    pub fn overlaps(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.period_start <= end && start <= self.period_end
+    }
+
+    // Shifts can be added, changed and assigned until the rota is locked
+    pub fn is_editable(&self) -> bool {
+        self.status != RotaStatus::Locked
+    }
+
+    // Take `action` on behalf of `actor` at `now`, if the current status allows it
+    pub fn transition(
+        &mut self,
+        action: RotaAction,
+        actor: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), ValidationError> {
+        self.status = action.apply(self.status)?;
+        match action {
+            RotaAction::Publish => {
+                self.revision += 1;
+                self.published_at = Some(now);
+                self.published_by = Some(actor.to_string());
+            }
+            RotaAction::Lock => {
+                self.locked_at = Some(now);
+                self.locked_by = Some(actor.to_string());
+            }
+            RotaAction::Unlock => {
+                self.locked_at = None;
+                self.locked_by = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Everything needed to create a rota
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewRota {
+    pub team_id: i64,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+}
+
+impl NewRota {
+    pub fn new(team_id: i64, period_start: NaiveDate, length: RotaLength) -> Self {
+        Self {
+            team_id,
+            period_start,
+            period_end: period_start + Duration::days(length.days() - 1),
+        }
+    }
+
+    pub fn into_rota(self, id: i64, now: DateTime<Utc>) -> Rota {
+        Rota {
+            id,
+            team_id: self.team_id,
+            period_start: self.period_start,
+            period_end: self.period_end,
+            status: RotaStatus::Draft,
+            revision: 0,
+            published_at: None,
+            published_by: None,
+            locked_at: None,
+            locked_by: None,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rota(length: RotaLength) -> Rota {
+        NewRota::new(1, "2025-01-27".parse().unwrap(), length).into_rota(1, Utc::now())
+    }
+
+    #[test]
+    fn periods_cover_whole_weeks() {
+        let four_weeks = rota(RotaLength::FourWeeks);
+
+        assert_eq!(four_weeks.period_end.to_string(), "2025-02-23");
+        assert_eq!(four_weeks.length(), Some(RotaLength::FourWeeks));
+        assert!(four_weeks.contains("2025-02-23".parse().unwrap()));
+        assert!(!four_weeks.contains("2025-02-24".parse().unwrap()));
+        assert!(four_weeks.overlaps("2025-02-23".parse().unwrap(), "2025-03-01".parse().unwrap()));
+        assert_eq!(rota(RotaLength::Fortnight).period_end.to_string(), "2025-02-09");
+    }
+
+    #[test]
+    fn lifecycle_moves_forward_and_checks_roles() {
+        let mut rota = rota(RotaLength::Week);
+        let now = Utc::now();
+
+        assert!(rota.transition(RotaAction::Lock, "1", now).is_err());
+        rota.transition(RotaAction::Publish, "1", now).unwrap();
+        rota.transition(RotaAction::Publish, "1", now).unwrap();
+        assert_eq!((rota.status, rota.revision), (RotaStatus::Published, 2));
+
+        rota.transition(RotaAction::Lock, "2", now).unwrap();
+        assert!(!rota.is_editable());
+        assert_eq!(rota.locked_by.as_deref(), Some("2"));
+        assert!(rota.transition(RotaAction::Publish, "1", now).is_err());
+        rota.transition(RotaAction::Unlock, "2", now).unwrap();
+        assert_eq!(rota.status, RotaStatus::Published);
+        assert!(rota.locked_at.is_none());
+
+        assert!(RotaAction::Publish.is_allowed_for(&UserRole::Manager));
+        assert!(!RotaAction::Publish.is_allowed_for(&UserRole::User));
+        assert!(!RotaAction::Lock.is_allowed_for(&UserRole::Manager));
+        assert!(RotaAction::Unlock.is_allowed_for(&UserRole::Admin));
+    }
+
+    #[test]
+    fn status_round_trips_through_its_string_form() {
+        for status in [RotaStatus::Draft, RotaStatus::Published, RotaStatus::Locked] {
+            assert_eq!(status.to_string().parse::<RotaStatus>(), Ok(status));
+        }
+        assert!("archived".parse::<RotaStatus>().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ValidationError;
+use crate::profile::ShiftRequirements;
+
+// Longest shift that can be scheduled, breaks included
+pub const MAX_SHIFT_HOURS: i64 = 16;
+
+// A period of work at one location, to be filled by `required_headcount` people.
+// Times are stored in UTC; `time_zone` is where the shift happens and decides
+// its local times.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shift {
+    pub id: i64,
+    pub rota_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub location: String,
+    // The role worked on the shift, e.g. "Charge nurse"
+    pub position: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub time_zone: String,
+    pub unpaid_break_minutes: i32,
+    pub required_headcount: i32,
+    pub notes: String,
+    // Skills and qualifications everyone working the shift must have
+    pub required_skills: Vec<String>,
+    pub required_qualifications: Vec<String>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// Everything needed to create a shift
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewShift {
+    pub rota_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub location: String,
+    pub position: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub time_zone: String,
+    pub unpaid_break_minutes: i32,
+    pub required_headcount: i32,
+    pub notes: String,
+    pub required_skills: Vec<String>,
+    pub required_qualifications: Vec<String>,
+}
+
+impl NewShift {
+    pub fn into_shift(self, id: i64, now: DateTime<Utc>) -> Shift {
+        Shift {
+            id,
+            rota_id: self.rota_id,
+            team_id: self.team_id,
+            location: self.location,
+            position: self.position,
+            starts_at: self.starts_at,
+            ends_at: self.ends_at,
+            time_zone: self.time_zone,
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            notes: self.notes,
+            required_skills: self.required_skills,
+            required_qualifications: self.required_qualifications,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+impl Shift {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn length(&self) -> Duration {
+        self.ends_at - self.starts_at
+    }
+
+    // Minutes worked and paid for, excluding the unpaid break
+    pub fn paid_minutes(&self) -> i64 {
+        self.length().num_minutes() - i64::from(self.unpaid_break_minutes)
+    }
+
+    // The shift's time zone; stored zones are validated, so UTC is only a fallback
+    pub fn tz(&self) -> Tz {
+        self.time_zone.parse().unwrap_or(Tz::UTC)
+    }
+
+    pub fn local_start(&self) -> NaiveDateTime {
+        self.starts_at.with_timezone(&self.tz()).naive_local()
+    }
+
+    pub fn local_end(&self) -> NaiveDateTime {
+        self.ends_at.with_timezone(&self.tz()).naive_local()
+    }
+
+    // Whether the shift runs past local midnight. One ending exactly at
+    // midnight isn't overnight.
+    pub fn is_overnight(&self) -> bool {
+        let (first, last) = self.local_dates();
+        first != last
+    }
+
+    // Whether the two shifts share any time; back-to-back shifts don't
+    pub fn overlaps(&self, other: &Shift) -> bool {
+        self.starts_at < other.ends_at && other.starts_at < self.ends_at
+    }
+
+    // Local dates the shift is worked on: one, or two for an overnight shift
+    pub fn local_dates(&self) -> (NaiveDate, NaiveDate) {
+        let last_minute = self.ends_at - Duration::minutes(1);
+        (self.local_start().date(), last_minute.with_timezone(&self.tz()).date_naive())
+    }
+
+    // What the shift asks of whoever works it, judged on the day it starts
+    pub fn requirements(&self) -> ShiftRequirements {
+        ShiftRequirements {
+            date: self.local_start().date(),
+            skills: self.required_skills.clone(),
+            qualifications: self.required_qualifications.clone(),
+        }
+    }
+
+    // The same shift `days` later, at the same local times. Its length
+    // changes when only one of the two falls across a clock change, and it
+    // can't move onto a start or end skipped by the clocks going forward.
+    pub fn moved_by(&self, days: i64) -> Result<NewShift, ValidationError> {
+        let tz = self.tz();
+        let offset = Duration::days(days);
+
+        Ok(NewShift {
+            rota_id: self.rota_id,
+            team_id: self.team_id,
+            location: self.location.clone(),
+            position: self.position.clone(),
+            starts_at: local_to_utc(self.local_start() + offset, tz)?,
+            ends_at: local_to_utc(self.local_end() + offset, tz)?,
+            time_zone: self.time_zone.clone(),
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            notes: self.notes.clone(),
+            required_skills: self.required_skills.clone(),
+            required_qualifications: self.required_qualifications.clone(),
+        })
+    }
+}
+
+// Parse an IANA time zone name such as `Europe/London`
+pub fn parse_time_zone(name: &str) -> Result<Tz, ValidationError> {
+    name.parse()
+        .map_err(|_| ValidationError(format!("Unknown time zone: {}", name)))
+}
+
+// The instant a wall-clock time in `tz` refers to. Times skipped when the
+// clocks go forward don't exist; times repeated when they go back mean the
+// first occurrence.
+pub fn local_to_utc(local: NaiveDateTime, tz: Tz) -> Result<DateTime<Utc>, ValidationError> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => Ok(time.with_timezone(&Utc)),
+        LocalResult::None => Err(ValidationError(format!(
+            "{} does not exist in {} because the clocks go forward",
+            local, tz
+        ))),
+    }
+}
+
+// Check a shift's times and numbers before it is saved
+pub fn validate_shift(shift: &NewShift) -> Result<(), ValidationError> {
+    parse_time_zone(&shift.time_zone)?;
+
+    if shift.ends_at <= shift.starts_at {
+        return Err(ValidationError::new("Shift must end after it starts"));
+    }
+    let length = shift.ends_at - shift.starts_at;
+    if length > Duration::hours(MAX_SHIFT_HOURS) {
+        return Err(ValidationError(format!(
+            "Shifts can be at most {} hours long",
+            MAX_SHIFT_HOURS
+        )));
+    }
+
+    if shift.unpaid_break_minutes < 0 {
+        return Err(ValidationError::new("Unpaid break cannot be negative"));
+    }
+    if i64::from(shift.unpaid_break_minutes) >= length.num_minutes() {
+        return Err(ValidationError::new("Unpaid break must be shorter than the shift"));
+    }
+
+    if shift.required_headcount < 1 {
+        return Err(ValidationError::new("A shift needs at least one person"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn new_shift(start: &str, end: &str, tz: &str) -> NewShift {
+        let zone = parse_time_zone(tz).unwrap();
+        NewShift {
+            rota_id: None,
+            team_id: Some(1),
+            location: "Ward 3".to_string(),
+            position: "Nurse".to_string(),
+            starts_at: local_to_utc(local(start), zone).unwrap(),
+            ends_at: local_to_utc(local(end), zone).unwrap(),
+            time_zone: tz.to_string(),
+            unpaid_break_minutes: 30,
+            required_headcount: 2,
+            notes: String::new(),
+            required_skills: vec!["ICU".to_string()],
+            required_qualifications: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn local_times_follow_daylight_saving() {
+        let london = parse_time_zone("Europe/London").unwrap();
+
+        // 01:30 is skipped on the last Sunday of March, and repeated in October
+        assert!(local_to_utc(local("2025-03-30 01:30"), london).is_err());
+        assert_eq!(
+            local_to_utc(local("2025-10-26 01:30"), london).unwrap().to_rfc3339(),
+            "2025-10-26T00:30:00+00:00"
+        );
+
+        // A night shift over the change is an hour shorter in spring
+        let night = new_shift("2025-03-29 20:00", "2025-03-30 08:00", "Europe/London").into_shift(1, Utc::now());
+        assert_eq!(night.length(), Duration::hours(11));
+        assert_eq!(night.paid_minutes(), 11 * 60 - 30);
+        assert_eq!(night.local_end(), local("2025-03-30 08:00"));
+        assert!(night.is_overnight());
+
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn moved_shifts_keep_their_local_times() {
+        let night = new_shift("2025-10-18 20:00", "2025-10-19 08:00", "Europe/London").into_shift(1, Utc::now());
+        let early = new_shift("2025-03-23 01:30", "2025-03-23 09:00", "Europe/London").into_shift(2, Utc::now());
+
+        // A week on, the clocks go back overnight, so the night is 13 hours
+        let moved = night.moved_by(7).unwrap();
+        assert_eq!(moved.starts_at.to_rfc3339(), "2025-10-25T19:00:00+00:00");
+        assert_eq!(moved.ends_at.to_rfc3339(), "2025-10-26T08:00:00+00:00");
+        // Across the end of the month and out of summer time
+        let moved = night.moved_by(14).unwrap();
+        assert_eq!(moved.starts_at.to_rfc3339(), "2025-11-01T20:00:00+00:00");
+        assert!(early.moved_by(7).is_err());
+    }
+
+    #[test]
+    fn overnight_and_overlap() {
+        let day = new_shift("2025-06-02 08:00", "2025-06-02 20:00", "UTC").into_shift(1, Utc::now());
+        let to_midnight = new_shift("2025-06-02 16:00", "2025-06-03 00:00", "UTC").into_shift(2, Utc::now());
+        let next = new_shift("2025-06-02 20:00", "2025-06-03 08:00", "UTC").into_shift(3, Utc::now());
+
+        assert!(!day.is_overnight());
+        assert!(!to_midnight.is_overnight());
+        assert!(next.is_overnight());
+        assert!(day.overlaps(&to_midnight));
+        assert!(!day.overlaps(&next));
+    }
+
+    #[test]
+    fn shift_validation() {
+        assert_eq!(validate_shift(&new_shift("2025-06-02 08:00", "2025-06-02 20:00", "UTC")), Ok(()));
+
+        let backwards = new_shift("2025-06-02 20:00", "2025-06-02 08:00", "UTC");
+        assert!(validate_shift(&backwards).is_err());
+
+        let too_long = new_shift("2025-06-02 06:00", "2025-06-02 22:30", "UTC");
+        assert!(validate_shift(&too_long).is_err());
+
+        let all_break = NewShift {
+            unpaid_break_minutes: 60,
+            ..new_shift("2025-06-02 08:00", "2025-06-02 09:00", "UTC")
+        };
+        assert!(validate_shift(&all_break).is_err());
+
+        let nobody = NewShift {
+            required_headcount: 0,
+            ..new_shift("2025-06-02 08:00", "2025-06-02 20:00", "UTC")
+        };
+        assert!(validate_shift(&nobody).is_err());
+
+        let nowhere = NewShift {
+            time_zone: "Nowhere".to_string(),
+            ..new_shift("2025-06-02 08:00", "2025-06-02 20:00", "UTC")
+        };
+        assert!(validate_shift(&nowhere).is_err());
+    }
+}
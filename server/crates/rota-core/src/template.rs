@@ -0,0 +1,185 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ValidationError;
+use crate::shift::{local_to_utc, parse_time_zone, NewShift, MAX_SHIFT_HOURS};
+
+// A reusable shape of shift, such as "Early" or "Night", that recurrence
+// rules stamp out onto dates. Times are wall-clock times in `time_zone`; an
+// end at or before the start finishes the next day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShiftTemplate {
+    pub id: i64,
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub location: String,
+    // The role worked on the shift
+    pub position: String,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub time_zone: String,
+    pub unpaid_break_minutes: i32,
+    pub required_headcount: i32,
+    // `#rrggbb`, for showing the shifts on a calendar
+    pub colour: String,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// Everything needed to create a template, or replace one's details
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewShiftTemplate {
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub location: String,
+    pub position: String,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub time_zone: String,
+    pub unpaid_break_minutes: i32,
+    pub required_headcount: i32,
+    pub colour: String,
+}
+
+impl NewShiftTemplate {
+    pub fn into_template(self, id: i64, now: DateTime<Utc>) -> ShiftTemplate {
+        ShiftTemplate {
+            id,
+            team_id: self.team_id,
+            name: self.name,
+            location: self.location,
+            position: self.position,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            time_zone: self.time_zone,
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            colour: self.colour,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+// Wall-clock length of a shift from `start` to `end`, running past midnight
+// when `end` isn't after `start`
+fn wall_clock_length(start: NaiveTime, end: NaiveTime) -> Duration {
+    let length = end - start;
+    if length > Duration::zero() {
+        length
+    } else {
+        length + Duration::days(1)
+    }
+}
+
+// Whether `colour` is written `#rrggbb`
+pub fn is_hex_colour(colour: &str) -> bool {
+    colour.len() == 7 && colour.starts_with('#') && colour[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Check a template before it is saved, with the same limits as a shift
+pub fn validate_template(template: &NewShiftTemplate) -> Result<(), ValidationError> {
+    if template.name.trim().is_empty() {
+        return Err(ValidationError::new("Template name cannot be empty"));
+    }
+    if !is_hex_colour(&template.colour) {
+        return Err(ValidationError::new("Colour must be written #rrggbb"));
+    }
+    parse_time_zone(&template.time_zone)?;
+
+    let length = wall_clock_length(template.start_time, template.end_time);
+    if length > Duration::hours(MAX_SHIFT_HOURS) {
+        return Err(ValidationError(format!("Shifts can be at most {} hours long", MAX_SHIFT_HOURS)));
+    }
+    if template.unpaid_break_minutes < 0 {
+        return Err(ValidationError::new("Unpaid break cannot be negative"));
+    }
+    if i64::from(template.unpaid_break_minutes) >= length.num_minutes() {
+        return Err(ValidationError::new("Unpaid break must be shorter than the shift"));
+    }
+    if template.required_headcount < 1 {
+        return Err(ValidationError::new("A shift needs at least one person"));
+    }
+
+    Ok(())
+}
+
+impl ShiftTemplate {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // The shift this template describes starting on `date`. Fails when the
+    // start or end falls in the hour skipped by the clocks going forward.
+    pub fn shift_on(&self, date: NaiveDate) -> Result<NewShift, ValidationError> {
+        let tz = parse_time_zone(&self.time_zone)?;
+        let start = date.and_time(self.start_time);
+        let end = start + wall_clock_length(self.start_time, self.end_time);
+
+        Ok(NewShift {
+            rota_id: None,
+            team_id: self.team_id,
+            location: self.location.clone(),
+            position: self.position.clone(),
+            starts_at: local_to_utc(start, tz)?,
+            ends_at: local_to_utc(end, tz)?,
+            time_zone: self.time_zone.clone(),
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            notes: String::new(),
+            required_skills: Vec::new(),
+            required_qualifications: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shift::validate_shift;
+
+    fn night() -> NewShiftTemplate {
+        NewShiftTemplate {
+            team_id: Some(1),
+            name: "Night".to_string(),
+            location: "Ward 3".to_string(),
+            position: "Nurse".to_string(),
+            start_time: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            time_zone: "Europe/London".to_string(),
+            unpaid_break_minutes: 60,
+            required_headcount: 2,
+            colour: "#1f3a93".to_string(),
+        }
+    }
+
+    #[test]
+    fn templates_are_checked_like_shifts() {
+        assert_eq!(validate_template(&night()), Ok(()));
+        assert!(validate_template(&NewShiftTemplate { colour: "navy".to_string(), ..night() }).is_err());
+        assert!(validate_template(&NewShiftTemplate { name: " ".to_string(), ..night() }).is_err());
+        assert!(validate_template(&NewShiftTemplate { unpaid_break_minutes: 720, ..night() }).is_err());
+        // 06:00 to 23:00 is 17 hours
+        let long = NewShiftTemplate {
+            start_time: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            ..night()
+        };
+        assert!(validate_template(&long).is_err());
+    }
+
+    #[test]
+    fn nights_over_a_clock_change_keep_their_wall_clock_times() {
+        let template = night().into_template(1, Utc::now());
+        let shift = template.shift_on("2025-10-25".parse().unwrap()).unwrap();
+
+        // The clocks go back overnight, so the 20:00 to 08:00 night is 13 hours
+        assert_eq!(shift.starts_at.to_rfc3339(), "2025-10-25T19:00:00+00:00");
+        assert_eq!(shift.ends_at.to_rfc3339(), "2025-10-26T08:00:00+00:00");
+        assert_eq!(validate_shift(&shift), Ok(()));
+    }
+}
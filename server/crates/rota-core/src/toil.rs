@@ -0,0 +1,557 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+
+// Most overtime one claim can be for, in hours
+pub const MAX_OVERTIME_HOURS: f64 = 24.0;
+
+// Where an overtime claim is in its approval workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OvertimeStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl Display for OvertimeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OvertimeStatus::Pending => write!(f, "pending"),
+            OvertimeStatus::Approved => write!(f, "approved"),
+            OvertimeStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl FromStr for OvertimeStatus {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(OvertimeStatus::Pending),
+            "approved" => Ok(OvertimeStatus::Approved),
+            "rejected" => Ok(OvertimeStatus::Rejected),
+            other => Err(ValidationError(format!("Unknown overtime status: {}", other))),
+        }
+    }
+}
+
+// TOIL hours earned per hour of overtime, by the day it was worked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToilMultipliers {
+    pub weekday: f64,
+    pub weekend: f64,
+}
+
+impl ToilMultipliers {
+    pub fn on(&self, date: NaiveDate) -> f64 {
+        match date.weekday() {
+            Weekday::Sat | Weekday::Sun => self.weekend,
+            _ => self.weekday,
+        }
+    }
+
+    // TOIL earned for `hours` of overtime on `date`, to the nearest minute
+    pub fn earned(&self, date: NaiveDate, hours: f64) -> f64 {
+        (hours * self.on(date) * 60.0).round() / 60.0
+    }
+}
+
+// Hours someone worked beyond their shifts, claimed for TOIL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Overtime {
+    pub id: i64,
+    pub user_id: i64,
+    // Day the overtime was worked
+    pub date: NaiveDate,
+    pub hours: f64,
+    // The shift it ran on from, if any
+    pub shift_id: Option<i64>,
+    pub reason: String,
+    pub status: OvertimeStatus,
+    // Who should decide it, from the team hierarchy; `None` leaves it to admins
+    pub approver_id: Option<i64>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_note: String,
+    // TOIL hours credited on approval
+    pub toil_hours: Option<f64>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Overtime {
+    // Approve the claim, crediting `toil_hours`, or reject it, on behalf of
+    // `actor` at `now`. Only pending claims can be decided.
+    pub fn decide(
+        &mut self,
+        toil_hours: Option<f64>,
+        actor: &str,
+        note: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), ValidationError> {
+        if self.status != OvertimeStatus::Pending {
+            return Err(ValidationError(format!("Overtime is already {}", self.status)));
+        }
+        self.status = if toil_hours.is_some() { OvertimeStatus::Approved } else { OvertimeStatus::Rejected };
+        self.toil_hours = toil_hours;
+        self.decided_by = Some(actor.to_string());
+        self.decided_at = Some(now);
+        self.decision_note = note.trim().to_string();
+        Ok(())
+    }
+}
+
+// Everything needed to claim overtime
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOvertime {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    pub hours: f64,
+    pub shift_id: Option<i64>,
+    pub reason: String,
+    pub approver_id: Option<i64>,
+}
+
+impl NewOvertime {
+    pub fn into_overtime(self, id: i64, now: DateTime<Utc>) -> Overtime {
+        Overtime {
+            id,
+            user_id: self.user_id,
+            date: self.date,
+            hours: self.hours,
+            shift_id: self.shift_id,
+            reason: self.reason,
+            status: OvertimeStatus::Pending,
+            approver_id: self.approver_id,
+            decided_by: None,
+            decided_at: None,
+            decision_note: String::new(),
+            toil_hours: None,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+// Check a claim is for worked time: in quarter hours up to
+// `MAX_OVERTIME_HOURS`, on or before `today`, with a reason
+pub fn validate_overtime(overtime: &NewOvertime, today: NaiveDate) -> Result<(), ValidationError> {
+    let quarters = overtime.hours * 4.0;
+    if !(overtime.hours > 0.0 && overtime.hours <= MAX_OVERTIME_HOURS && quarters.fract() == 0.0) {
+        return Err(ValidationError(format!(
+            "Hours must be in quarter hours, more than 0 and at most {}",
+            MAX_OVERTIME_HOURS
+        )));
+    }
+    if overtime.date > today {
+        return Err(ValidationError::new("Overtime can only be claimed once it's worked"));
+    }
+    if overtime.reason.is_empty() {
+        return Err(ValidationError::new("Overtime needs a reason"));
+    }
+    if overtime.reason.chars().count() > 500 {
+        return Err(ValidationError::new("Reason must be at most 500 characters"));
+    }
+
+    Ok(())
+}
+
+// Why a TOIL ledger entry was made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToilEntryKind {
+    // Earned from approved overtime
+    Accrual,
+    // Taken as approved TOIL leave
+    Redemption,
+    // Handed back when approved TOIL leave is cancelled
+    Refund,
+    // Set by an admin, such as an opening balance or a correction
+    Adjustment,
+}
+
+impl Display for ToilEntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToilEntryKind::Accrual => write!(f, "accrual"),
+            ToilEntryKind::Redemption => write!(f, "redemption"),
+            ToilEntryKind::Refund => write!(f, "refund"),
+            ToilEntryKind::Adjustment => write!(f, "adjustment"),
+        }
+    }
+}
+
+impl FromStr for ToilEntryKind {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accrual" => Ok(ToilEntryKind::Accrual),
+            "redemption" => Ok(ToilEntryKind::Redemption),
+            "refund" => Ok(ToilEntryKind::Refund),
+            "adjustment" => Ok(ToilEntryKind::Adjustment),
+            other => Err(ValidationError(format!("Unknown TOIL entry kind: {}", other))),
+        }
+    }
+}
+
+// One line of someone's TOIL ledger. Entries are never changed; positive
+// hours can be spent until `expires_on` inclusive, negative hours spend them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToilEntry {
+    pub id: i64,
+    pub user_id: i64,
+    pub kind: ToilEntryKind,
+    pub hours: f64,
+    // Day the hours were earned or spent
+    pub date: NaiveDate,
+    pub expires_on: Option<NaiveDate>,
+    pub overtime_id: Option<i64>,
+    pub leave_request_id: Option<i64>,
+    pub note: String,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Everything needed to add a ledger entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewToilEntry {
+    pub user_id: i64,
+    pub kind: ToilEntryKind,
+    pub hours: f64,
+    pub date: NaiveDate,
+    pub expires_on: Option<NaiveDate>,
+    pub overtime_id: Option<i64>,
+    pub leave_request_id: Option<i64>,
+    pub note: String,
+    pub created_by: Option<String>,
+}
+
+impl NewToilEntry {
+    pub fn into_entry(self, id: i64, created_at: DateTime<Utc>) -> ToilEntry {
+        ToilEntry {
+            id,
+            user_id: self.user_id,
+            kind: self.kind,
+            hours: self.hours,
+            date: self.date,
+            expires_on: self.expires_on,
+            overtime_id: self.overtime_id,
+            leave_request_id: self.leave_request_id,
+            note: self.note,
+            created_by: self.created_by,
+            created_at,
+        }
+    }
+}
+
+// Most hours one adjustment can add or take away
+pub const MAX_ADJUSTMENT_HOURS: f64 = 1000.0;
+
+// Check an admin's adjustment moves the balance by a sensible amount, says
+// why, and only lets credited hours lapse
+pub fn validate_adjustment(entry: &NewToilEntry) -> Result<(), ValidationError> {
+    if entry.hours == 0.0 || entry.hours.abs() > MAX_ADJUSTMENT_HOURS || !entry.hours.is_finite() {
+        return Err(ValidationError(format!(
+            "Hours must be non-zero and at most {} either way",
+            MAX_ADJUSTMENT_HOURS
+        )));
+    }
+    if entry.hours < 0.0 && entry.expires_on.is_some() {
+        return Err(ValidationError::new("Only hours credited can expire"));
+    }
+    if entry.expires_on.is_some_and(|expiry| expiry < entry.date) {
+        return Err(ValidationError::new("Hours can't expire before they're credited"));
+    }
+    if entry.note.is_empty() {
+        return Err(ValidationError::new("An adjustment needs a note"));
+    }
+    if entry.note.chars().count() > 500 {
+        return Err(ValidationError::new("Note must be at most 500 characters"));
+    }
+
+    Ok(())
+}
+
+// When hours earned on `date` lapse, given `expiry_days`; 0 keeps them
+pub fn toil_expiry(date: NaiveDate, expiry_days: u32) -> Option<NaiveDate> {
+    (expiry_days > 0).then(|| date + Duration::days(i64::from(expiry_days)))
+}
+
+// Hours that lapse together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToilExpiry {
+    pub date: NaiveDate,
+    pub hours: f64,
+}
+
+// Where someone stands with TOIL on a day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToilBalance {
+    // Every hour credited: accruals, refunds and positive adjustments
+    pub earned: f64,
+    // Every hour spent: redemptions and negative adjustments
+    pub used: f64,
+    // Hours that lapsed unspent
+    pub expired: f64,
+    pub available: f64,
+    // The soonest unspent hours to lapse
+    pub next_expiry: Option<ToilExpiry>,
+}
+
+// The balance on `today` from a ledger. Spending takes the hours that lapse
+// soonest first, and hours lapse the day after `expires_on`. Spending more
+// than is left runs the balance negative until more is earned.
+pub fn toil_balance(entries: &[ToilEntry], today: NaiveDate) -> ToilBalance {
+    replay(entries, today).0
+}
+
+// The hours entry `spent_id` took on `today`, with when each would have
+// lapsed, so they can be handed back as they were. Hours it overspent come
+// from whatever was earned next, and any still owed come back as `None`.
+pub fn toil_spent_by(entries: &[ToilEntry], spent_id: i64, today: NaiveDate) -> Vec<(f64, Option<NaiveDate>)> {
+    replay(entries, today)
+        .1
+        .into_iter()
+        .filter(|(id, _, _)| *id == spent_id)
+        .map(|(_, hours, expires_on)| (hours, expires_on))
+        .collect()
+}
+
+// Walks the ledger up to `today`, giving the balance and, for every spending
+// entry, the hours it took and when they would have lapsed
+fn replay(entries: &[ToilEntry], today: NaiveDate) -> (ToilBalance, Vec<(i64, f64, Option<NaiveDate>)>) {
+    let mut sorted: Vec<&ToilEntry> = entries.iter().filter(|e| e.date <= today).collect();
+    sorted.sort_by_key(|e| (e.date, e.id));
+
+    let mut balance = ToilBalance::default();
+    let mut spent = Vec::new();
+    // Unspent hours and when they lapse, soonest first with `None` last
+    let mut lots: Vec<(f64, Option<NaiveDate>)> = Vec::new();
+    // Overspent hours, oldest first, and the entry that overspent them
+    let mut owed: Vec<(i64, f64)> = Vec::new();
+    let expire = |lots: &mut Vec<(f64, Option<NaiveDate>)>, on: NaiveDate, expired: &mut f64| {
+        lots.retain(|(hours, expires_on)| {
+            let lapsed = expires_on.is_some_and(|expiry| expiry < on);
+            if lapsed {
+                *expired += hours;
+            }
+            !lapsed
+        });
+    };
+
+    for entry in sorted {
+        expire(&mut lots, entry.date, &mut balance.expired);
+        if entry.hours >= 0.0 {
+            balance.earned += entry.hours;
+            let mut left = entry.hours;
+            for debt in owed.iter_mut() {
+                let paid = debt.1.min(left);
+                if paid > 0.0 {
+                    spent.push((debt.0, paid, entry.expires_on));
+                }
+                debt.1 -= paid;
+                left -= paid;
+            }
+            owed.retain(|(_, hours)| *hours > 0.0);
+            if left > 0.0 {
+                lots.push((left, entry.expires_on));
+                lots.sort_by_key(|(_, expires_on)| (expires_on.is_none(), *expires_on));
+            }
+        } else {
+            balance.used -= entry.hours;
+            let mut due = -entry.hours;
+            for lot in lots.iter_mut() {
+                let taken = lot.0.min(due);
+                if taken > 0.0 {
+                    spent.push((entry.id, taken, lot.1));
+                }
+                lot.0 -= taken;
+                due -= taken;
+            }
+            lots.retain(|(hours, _)| *hours > 0.0);
+            if due > 0.0 {
+                owed.push((entry.id, due));
+            }
+        }
+    }
+    expire(&mut lots, today, &mut balance.expired);
+
+    for (id, hours) in &owed {
+        spent.push((*id, *hours, None));
+    }
+    balance.available = lots.iter().map(|(hours, _)| hours).sum::<f64>()
+        - owed.iter().map(|(_, hours)| hours).sum::<f64>();
+    balance.next_expiry = lots.first().and_then(|(_, expires_on)| *expires_on).map(|date| ToilExpiry {
+        date,
+        hours: lots.iter().filter(|(_, expires_on)| *expires_on == Some(date)).map(|(hours, _)| hours).sum(),
+    });
+    (balance, spent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn entry(id: i64, kind: ToilEntryKind, hours: f64, on: &str, expires_on: Option<&str>) -> ToilEntry {
+        NewToilEntry {
+            user_id: 1,
+            kind,
+            hours,
+            date: date(on),
+            expires_on: expires_on.map(date),
+            overtime_id: None,
+            leave_request_id: None,
+            note: String::new(),
+            created_by: None,
+        }
+        .into_entry(id, Utc::now())
+    }
+
+    #[test]
+    fn weekend_overtime_earns_more() {
+        let multipliers = ToilMultipliers { weekday: 1.0, weekend: 1.5 };
+
+        // Friday then Saturday
+        assert_eq!(multipliers.earned(date("2025-06-06"), 2.0), 2.0);
+        assert_eq!(multipliers.earned(date("2025-06-07"), 2.0), 3.0);
+        assert_eq!(toil_expiry(date("2025-06-07"), 90), Some(date("2025-09-05")));
+        assert_eq!(toil_expiry(date("2025-06-07"), 0), None);
+    }
+
+    #[test]
+    fn claims_are_quarter_hours_already_worked() {
+        let claim = NewOvertime {
+            user_id: 1,
+            date: date("2025-06-06"),
+            hours: 1.75,
+            shift_id: None,
+            reason: "Late handover".to_string(),
+            approver_id: None,
+        };
+        let today = date("2025-06-09");
+
+        assert_eq!(validate_overtime(&claim, today), Ok(()));
+        assert!(validate_overtime(&NewOvertime { hours: 1.1, ..claim.clone() }, today).is_err());
+        assert!(validate_overtime(&NewOvertime { hours: 0.0, ..claim.clone() }, today).is_err());
+        assert!(validate_overtime(&NewOvertime { date: date("2025-06-10"), ..claim.clone() }, today).is_err());
+        assert!(validate_overtime(&NewOvertime { reason: String::new(), ..claim }, today).is_err());
+    }
+
+    #[test]
+    fn spending_takes_the_soonest_to_lapse_and_the_rest_expires() {
+        use ToilEntryKind::*;
+        let ledger = [
+            entry(1, Accrual, 3.0, "2025-01-10", Some("2025-04-10")),
+            entry(2, Accrual, 4.0, "2025-02-01", Some("2025-05-02")),
+            entry(3, Redemption, -5.0, "2025-03-01", None),
+            entry(4, Adjustment, 1.5, "2025-03-02", None),
+        ];
+
+        // The redemption used all of January's hours and two of February's
+        let before = toil_balance(&ledger, date("2025-04-30"));
+        assert_eq!(before.available, 3.5);
+        assert_eq!(before.next_expiry, Some(ToilExpiry { date: date("2025-05-02"), hours: 2.0 }));
+
+        let after = toil_balance(&ledger, date("2025-05-03"));
+        assert_eq!(after.earned, 8.5);
+        assert_eq!(after.used, 5.0);
+        assert_eq!(after.expired, 2.0);
+        assert_eq!(after.available, 1.5);
+        assert_eq!(after.next_expiry, None);
+    }
+
+    #[test]
+    fn overspending_is_owed_from_the_next_hours_earned() {
+        use ToilEntryKind::*;
+        let ledger = [
+            entry(1, Adjustment, -2.0, "2025-01-10", None),
+            entry(2, Accrual, 3.0, "2025-02-01", Some("2025-05-02")),
+        ];
+
+        assert_eq!(toil_balance(&ledger[..1], date("2025-01-31")).available, -2.0);
+        assert_eq!(toil_balance(&ledger, date("2025-04-01")).available, 1.0);
+        // Only the hour left over can lapse
+        let balance = toil_balance(&ledger, date("2025-06-01"));
+        assert_eq!(balance.available, 0.0);
+        assert_eq!(balance.expired, 1.0);
+    }
+
+    #[test]
+    fn spending_remembers_which_hours_it_took() {
+        use ToilEntryKind::*;
+        let ledger = [
+            entry(1, Accrual, 3.0, "2025-01-10", Some("2025-04-10")),
+            entry(2, Accrual, 4.0, "2025-02-01", Some("2025-05-02")),
+            entry(3, Redemption, -9.0, "2025-03-01", None),
+            entry(4, Accrual, 1.0, "2025-03-02", Some("2025-06-01")),
+        ];
+
+        assert_eq!(
+            toil_spent_by(&ledger, 3, date("2025-03-05")),
+            vec![
+                (3.0, Some(date("2025-04-10"))),
+                (4.0, Some(date("2025-05-02"))),
+                (1.0, Some(date("2025-06-01"))),
+                (1.0, None),
+            ]
+        );
+        assert!(toil_spent_by(&ledger, 1, date("2025-03-05")).is_empty());
+    }
+
+    #[test]
+    fn adjustments_need_a_note_and_only_credits_expire() {
+        let mut adjustment = entry(1, ToilEntryKind::Adjustment, 7.5, "2025-06-02", Some("2025-08-31"));
+        let new = |entry: &ToilEntry| NewToilEntry {
+            user_id: entry.user_id,
+            kind: entry.kind,
+            hours: entry.hours,
+            date: entry.date,
+            expires_on: entry.expires_on,
+            overtime_id: None,
+            leave_request_id: None,
+            note: entry.note.clone(),
+            created_by: None,
+        };
+
+        assert!(validate_adjustment(&new(&adjustment)).is_err());
+        adjustment.note = "Opening balance".to_string();
+        assert_eq!(validate_adjustment(&new(&adjustment)), Ok(()));
+        adjustment.hours = -7.5;
+        assert!(validate_adjustment(&new(&adjustment)).is_err());
+        adjustment.expires_on = None;
+        assert_eq!(validate_adjustment(&new(&adjustment)), Ok(()));
+        adjustment.hours = 0.0;
+        assert!(validate_adjustment(&new(&adjustment)).is_err());
+    }
+
+    #[test]
+    fn only_pending_overtime_can_be_decided() {
+        let now = Utc::now();
+        let claim = NewOvertime {
+            user_id: 1,
+            date: date("2025-06-07"),
+            hours: 2.0,
+            shift_id: None,
+            reason: "Covered sickness".to_string(),
+            approver_id: Some(2),
+        };
+        let mut overtime = claim.into_overtime(1, now);
+
+        assert!(overtime.decide(Some(3.0), "2", "Thanks", now).is_ok());
+        assert_eq!(overtime.status, OvertimeStatus::Approved);
+        assert_eq!(overtime.toil_hours, Some(3.0));
+        assert!(overtime.decide(None, "2", "", now).is_err());
+    }
+}
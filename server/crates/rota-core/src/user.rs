@@ -0,0 +1,449 @@
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::ValidationError;
+
+// Shortest password accepted when one is changed
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+// User roles
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    User,
+    // Builds and publishes rotas and manages leave for their teams
+    Manager,
+    Admin,
+}
+
+impl UserRole {
+    // Whether the role can manage rotas and leave; admins can do anything a
+    // manager can
+    pub fn is_manager(&self) -> bool {
+        matches!(self, UserRole::Manager | UserRole::Admin)
+    }
+}
+
+impl Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserRole::User => write!(f, "user"),
+            UserRole::Manager => write!(f, "manager"),
+            UserRole::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(UserRole::User),
+            "manager" => Ok(UserRole::Manager),
+            "admin" => Ok(UserRole::Admin),
+            other => Err(ValidationError(format!("Unknown role: {}", other))),
+        }
+    }
+}
+
+// Someone to call if a member of staff is taken ill or injured at work
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmergencyContact {
+    pub name: String,
+    pub phone: String,
+    #[serde(default)]
+    pub relationship: String,
+}
+
+// Sensitive personal details. Stored encrypted, and never copied into
+// serialised users (and so never into audit snapshots).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersonalDetails {
+    pub phone: Option<String>,
+    pub emergency_contact: Option<EmergencyContact>,
+    pub date_of_birth: Option<NaiveDate>,
+}
+
+// Canonical form of a phone number for equality lookups: digits only, keeping
+// a leading `+`, so "+44 (0)20 7946 0000" and "+440207946 0000" compare equal
+pub fn normalise_phone(phone: &str) -> String {
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    if phone.trim_start().starts_with('+') {
+        format!("+{}", digits)
+    } else {
+        digits
+    }
+}
+
+// User model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+    #[serde(skip_serializing)] // Don't include password hash in serialized output
+    pub password_hash: String,
+    pub role: UserRole,
+    // Home team, if any
+    #[serde(default)]
+    pub team_id: Option<i64>,
+    // Skills used when matching staff to shifts, see `normalise_skills`
+    #[serde(default)]
+    pub skills: Vec<String>,
+    // Hours per week in the user's contract, if known
+    #[serde(default)]
+    pub contracted_hours: Option<f64>,
+    // Incremented on every write, for optimistic concurrency
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Set when the user is soft-deleted; deleted users are hidden by default
+    // but kept so historical rotas still resolve
+    pub deleted_at: Option<DateTime<Utc>>,
+    // Set once personal data has been scrubbed; anonymised users can't be restored
+    pub anonymised_at: Option<DateTime<Utc>>,
+    // Set while the account is deactivated: it can't log in or be rostered
+    #[serde(default)]
+    pub deactivated_at: Option<DateTime<Utc>>,
+    // Empty as read from storage; personal details are loaded and saved on
+    // their own, only where they are needed
+    #[serde(skip_serializing, default)]
+    pub personal: PersonalDetails,
+}
+
+impl User {
+    // Verify a password against the stored hash. Accounts created without a
+    // password have an empty hash and can never log in.
+    pub fn verify_password(&self, password: &str) -> bool {
+        !self.password_hash.is_empty() && verify(password, &self.password_hash).unwrap_or(false)
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // Skills are matched ignoring case
+    pub fn has_skill(&self, skill: &str) -> bool {
+        self.skills.iter().any(|s| s.eq_ignore_ascii_case(skill.trim()))
+    }
+
+    // Whether the user may log in and be assigned to shifts
+    pub fn is_active(&self) -> bool {
+        self.deactivated_at.is_none() && !self.is_deleted()
+    }
+
+    // Replace the password, hashing it with bcrypt
+    pub fn set_password(&mut self, password: &str) -> Result<(), bcrypt::BcryptError> {
+        self.password_hash = hash(password, DEFAULT_COST)?;
+        Ok(())
+    }
+
+    // Replace everything that identifies the person with placeholders derived
+    // from the id, which is all other records refer to. Irreversible.
+    pub fn anonymise(&mut self, now: DateTime<Utc>) {
+        self.username = format!("Deleted user {}", self.id);
+        self.email = format!("deleted-{}@anonymised.invalid", self.id);
+        self.password_hash = String::new();
+        self.personal = PersonalDetails::default();
+        self.anonymised_at = Some(now);
+    }
+}
+
+// A user that has not been stored yet; the repository assigns id and timestamps
+#[derive(Debug, Clone)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    pub password_hash: String,
+    pub role: UserRole,
+}
+
+impl NewUser {
+    // Create a new user, hashing the password with bcrypt if one is given
+    pub fn new(
+        username: String,
+        email: String,
+        password: Option<&str>,
+        role: UserRole,
+    ) -> Result<Self, bcrypt::BcryptError> {
+        let password_hash = match password {
+            Some(password) => hash(password, DEFAULT_COST)?,
+            None => String::new(),
+        };
+
+        Ok(Self {
+            username,
+            email,
+            password_hash,
+            role,
+        })
+    }
+
+    // Materialise the stored record once an id has been assigned
+    pub fn into_user(self, id: i64, now: DateTime<Utc>) -> User {
+        User {
+            id,
+            username: self.username,
+            email: self.email,
+            password_hash: self.password_hash,
+            role: self.role,
+            team_id: None,
+            skills: Vec::new(),
+            contracted_hours: None,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            anonymised_at: None,
+            deactivated_at: None,
+            personal: PersonalDetails::default(),
+        }
+    }
+}
+
+// Check the fields every new user must have. Messages are client-facing.
+pub fn validate_new_user(name: &str, email: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("Name cannot be empty"));
+    }
+
+    if email.trim().is_empty() {
+        return Err(ValidationError::new("Email cannot be empty"));
+    }
+
+    if !email.contains('@') {
+        return Err(ValidationError::new("Invalid email format"));
+    }
+
+    Ok(())
+}
+
+// Tidy a list of skills: trimmed, without blanks or case-insensitive
+// duplicates, and sorted so equal sets compare equal
+pub fn normalise_skills(skills: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut tidy: Vec<String> = Vec::new();
+    for skill in skills {
+        let skill = skill.trim();
+        if !skill.is_empty() && !tidy.iter().any(|s| s.eq_ignore_ascii_case(skill)) {
+            tidy.push(skill.to_string());
+        }
+    }
+    tidy.sort_by_key(|s| s.to_lowercase());
+    tidy
+}
+
+// Most hours a contract can specify: every hour of the week
+pub const MAX_CONTRACTED_HOURS: f64 = 168.0;
+
+pub fn validate_contracted_hours(hours: f64) -> Result<(), ValidationError> {
+    if !(0.0..=MAX_CONTRACTED_HOURS).contains(&hours) {
+        return Err(ValidationError::new(format!(
+            "Contracted hours must be between 0 and {}",
+            MAX_CONTRACTED_HOURS
+        )));
+    }
+
+    Ok(())
+}
+
+// Check a new password is acceptable
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ValidationError(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    Ok(())
+}
+
+// Check personal details before they are stored
+pub fn validate_personal(personal: &PersonalDetails) -> Result<(), ValidationError> {
+    let valid_phone = |phone: &str| {
+        let digits = normalise_phone(phone).trim_start_matches('+').len();
+        (7..=15).contains(&digits)
+    };
+
+    if personal.phone.as_deref().is_some_and(|phone| !valid_phone(phone)) {
+        return Err(ValidationError::new("Invalid phone number"));
+    }
+
+    if let Some(contact) = &personal.emergency_contact {
+        if contact.name.trim().is_empty() {
+            return Err(ValidationError::new("Emergency contact name cannot be empty"));
+        }
+        if !valid_phone(&contact.phone) {
+            return Err(ValidationError::new("Invalid emergency contact phone number"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_round_trips_through_its_string_form() {
+        for role in [UserRole::User, UserRole::Manager, UserRole::Admin] {
+            assert_eq!(role.to_string().parse::<UserRole>(), Ok(role));
+        }
+        assert!("superuser".parse::<UserRole>().is_err());
+    }
+
+    #[test]
+    fn password_is_hashed_and_verified() {
+        let user = NewUser::new(
+            "alice".to_string(),
+            "alice@example.com".to_string(),
+            Some("s3cret"),
+            UserRole::User,
+        )
+        .unwrap()
+        .into_user(1, Utc::now());
+
+        assert_ne!(user.password_hash, "s3cret");
+        assert!(user.verify_password("s3cret"));
+        assert!(!user.verify_password("wrong"));
+    }
+
+    #[test]
+    fn user_without_password_cannot_log_in() {
+        let user = NewUser::new(
+            "bob".to_string(),
+            "bob@example.com".to_string(),
+            None,
+            UserRole::User,
+        )
+        .unwrap()
+        .into_user(2, Utc::now());
+
+        assert!(user.password_hash.is_empty());
+        assert!(!user.verify_password(""));
+    }
+
+    #[test]
+    fn anonymised_user_keeps_only_its_id() {
+        let mut user = NewUser::new(
+            "carol".to_string(),
+            "carol@example.com".to_string(),
+            Some("s3cret"),
+            UserRole::Admin,
+        )
+        .unwrap()
+        .into_user(7, Utc::now());
+
+        user.personal.phone = Some("07700 900123".to_string());
+        user.anonymise(Utc::now());
+
+        assert_eq!(user.username, "Deleted user 7");
+        assert_eq!(user.email, "deleted-7@anonymised.invalid");
+        assert!(!user.verify_password("s3cret"));
+        assert!(user.anonymised_at.is_some());
+        assert_eq!(user.personal, PersonalDetails::default());
+    }
+
+    #[test]
+    fn phone_numbers_normalise_for_lookup() {
+        assert_eq!(normalise_phone("+44 (0)20 7946-0000"), "+4402079460000");
+        assert_eq!(normalise_phone("07700 900123"), "07700900123");
+    }
+
+    #[test]
+    fn personal_details_are_not_serialised() {
+        let mut user = NewUser::new(
+            "dan".to_string(),
+            "dan@example.com".to_string(),
+            None,
+            UserRole::User,
+        )
+        .unwrap()
+        .into_user(3, Utc::now());
+        user.personal.phone = Some("07700 900123".to_string());
+
+        let json = serde_json::to_value(&user).unwrap();
+
+        assert!(json.get("personal").is_none());
+    }
+
+    #[test]
+    fn new_user_validation() {
+        assert!(validate_new_user("Alice", "alice@example.com").is_ok());
+        assert_eq!(
+            validate_new_user("  ", "alice@example.com"),
+            Err(ValidationError::new("Name cannot be empty"))
+        );
+        assert_eq!(
+            validate_new_user("Alice", ""),
+            Err(ValidationError::new("Email cannot be empty"))
+        );
+        assert_eq!(
+            validate_new_user("Alice", "not-an-email"),
+            Err(ValidationError::new("Invalid email format"))
+        );
+    }
+
+    #[test]
+    fn personal_details_validation() {
+        let mut personal = PersonalDetails {
+            phone: Some("+44 20 7946 0000".to_string()),
+            emergency_contact: Some(EmergencyContact {
+                name: "Sam".to_string(),
+                phone: "07700 900123".to_string(),
+                relationship: "Partner".to_string(),
+            }),
+            date_of_birth: None,
+        };
+        assert!(validate_personal(&personal).is_ok());
+
+        personal.phone = Some("12".to_string());
+        assert_eq!(
+            validate_personal(&personal),
+            Err(ValidationError::new("Invalid phone number"))
+        );
+
+        personal.phone = None;
+        personal.emergency_contact.as_mut().unwrap().name = " ".to_string();
+        assert_eq!(
+            validate_personal(&personal),
+            Err(ValidationError::new("Emergency contact name cannot be empty"))
+        );
+    }
+
+    #[test]
+    fn deactivated_user_is_inactive_and_password_can_change() {
+        let mut user = NewUser::new(
+            "Eve".to_string(),
+            "eve@example.com".to_string(),
+            Some("old password"),
+            UserRole::User,
+        )
+        .unwrap()
+        .into_user(4, Utc::now());
+        assert!(user.is_active());
+
+        user.set_password("new password").unwrap();
+        user.deactivated_at = Some(Utc::now());
+
+        assert!(!user.is_active());
+        assert!(user.verify_password("new password"));
+        assert!(!user.verify_password("old password"));
+        assert!(validate_password("short").is_err());
+        assert!(validate_password("long enough").is_ok());
+    }
+
+    #[test]
+    fn skills_are_tidied() {
+        let skills = normalise_skills(
+            ["  Triage ", "ICU", "", "icu", "anaesthetics"].map(String::from),
+        );
+
+        assert_eq!(skills, vec!["anaesthetics", "ICU", "Triage"]);
+    }
+}
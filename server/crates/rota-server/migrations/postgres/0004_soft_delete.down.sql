@@ -0,0 +1,10 @@
+DROP INDEX idx_leave_requests_deleted_at;
+DROP INDEX idx_shifts_deleted_at;
+DROP INDEX idx_rotas_deleted_at;
+DROP INDEX idx_users_deleted_at;
+
+ALTER TABLE leave_requests DROP COLUMN deleted_at;
+ALTER TABLE shifts DROP COLUMN deleted_at;
+ALTER TABLE rotas DROP COLUMN deleted_at;
+ALTER TABLE users DROP COLUMN anonymised_at;
+ALTER TABLE users DROP COLUMN deleted_at;
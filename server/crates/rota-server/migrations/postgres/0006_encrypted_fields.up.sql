@@ -0,0 +1,12 @@
+-- Sensitive personal details, encrypted by the application before they reach
+-- the database (see `encryption.rs`). Every value is ciphertext stored as
+-- text, whatever its plaintext type.
+ALTER TABLE users ADD COLUMN phone TEXT;
+ALTER TABLE users ADD COLUMN emergency_contact TEXT;
+ALTER TABLE users ADD COLUMN date_of_birth TEXT;
+ALTER TABLE leave_requests ADD COLUMN sickness_note TEXT;
+
+-- Keyed hash of the normalised phone number, for equality lookups without
+-- decrypting every row
+ALTER TABLE users ADD COLUMN phone_bidx VARCHAR(64);
+CREATE INDEX idx_users_phone_bidx ON users(phone_bidx);
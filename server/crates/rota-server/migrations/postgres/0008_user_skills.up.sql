@@ -0,0 +1,12 @@
+-- Skills staff can be matched on, stored trimmed and de-duplicated
+CREATE TABLE user_skills (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    skill VARCHAR(100) NOT NULL,
+    PRIMARY KEY (user_id, skill)
+);
+
+-- Skill filters compare case-insensitively
+CREATE INDEX idx_user_skills_skill ON user_skills(LOWER(skill));
+
+-- The default order of the user list
+CREATE INDEX idx_users_created_at ON users(created_at, id);
@@ -0,0 +1,3 @@
+-- Hours per week in the user's contract; NULL when not known
+ALTER TABLE users ADD COLUMN contracted_hours DOUBLE PRECISION
+    CHECK (contracted_hours >= 0 AND contracted_hours <= 168);
@@ -0,0 +1,28 @@
+-- Employment details for staff who can be scheduled. The home team, weekly
+-- hours and skills stay on `users` and `user_skills`.
+CREATE TABLE staff_profiles (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    contract_type VARCHAR(20) NOT NULL
+        CHECK (contract_type IN ('full_time', 'part_time', 'zero_hours', 'annualised')),
+    pay_grade VARCHAR(50),
+    start_date DATE NOT NULL,
+    end_date DATE,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (end_date IS NULL OR end_date >= start_date)
+);
+
+-- Certificates and licences, checked against shift requirements
+CREATE TABLE qualifications (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES staff_profiles(user_id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    issued_on DATE NOT NULL,
+    expires_on DATE,
+    CHECK (expires_on IS NULL OR expires_on >= issued_on)
+);
+
+CREATE UNIQUE INDEX idx_qualifications_user_name ON qualifications(user_id, LOWER(name));
+-- Finding qualifications about to lapse
+CREATE INDEX idx_qualifications_expires_on ON qualifications(expires_on);
@@ -0,0 +1,5 @@
+ALTER TABLE shift_assignments DROP COLUMN conflicts;
+ALTER TABLE shift_assignments DROP COLUMN override_reason;
+ALTER TABLE shift_assignments DROP COLUMN forced;
+ALTER TABLE shift_assignments DROP COLUMN assigned_by;
+DROP TABLE shift_requirements;
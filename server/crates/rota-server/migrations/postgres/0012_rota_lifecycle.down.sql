@@ -0,0 +1,7 @@
+DROP TABLE rota_snapshots;
+
+ALTER TABLE rotas DROP COLUMN locked_by;
+ALTER TABLE rotas DROP COLUMN locked_at;
+ALTER TABLE rotas DROP COLUMN published_by;
+ALTER TABLE rotas DROP COLUMN published_at;
+ALTER TABLE rotas DROP COLUMN revision;
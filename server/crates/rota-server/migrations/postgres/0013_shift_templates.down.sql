@@ -0,0 +1,3 @@
+DROP TABLE shift_occurrences;
+DROP TABLE shift_recurrences;
+DROP TABLE shift_templates;
@@ -0,0 +1,40 @@
+-- Reusable shapes of shift. Times are wall-clock times in time_zone; an end at
+-- or before the start finishes the next day.
+CREATE TABLE shift_templates (
+    id BIGSERIAL PRIMARY KEY,
+    team_id BIGINT REFERENCES teams(id) ON DELETE SET NULL,
+    name VARCHAR(255) NOT NULL,
+    location VARCHAR(255) NOT NULL DEFAULT '',
+    position VARCHAR(255) NOT NULL DEFAULT '',
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    time_zone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    unpaid_break_minutes INTEGER NOT NULL DEFAULT 0,
+    required_headcount INTEGER NOT NULL DEFAULT 1,
+    colour VARCHAR(7) NOT NULL,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ
+);
+
+-- When a template repeats: an RRULE from starts_on, less the exception dates
+CREATE TABLE shift_recurrences (
+    id BIGSERIAL PRIMARY KEY,
+    template_id BIGINT NOT NULL REFERENCES shift_templates(id) ON DELETE CASCADE,
+    rule VARCHAR(255) NOT NULL,
+    starts_on DATE NOT NULL,
+    exceptions JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_shift_recurrences_template ON shift_recurrences(template_id);
+
+-- Which occurrence of a recurrence each generated shift is, so generating
+-- again never makes a second shift for the same day
+CREATE TABLE shift_occurrences (
+    shift_id BIGINT PRIMARY KEY REFERENCES shifts(id) ON DELETE CASCADE,
+    recurrence_id BIGINT NOT NULL REFERENCES shift_recurrences(id) ON DELETE CASCADE,
+    occurrence_date DATE NOT NULL,
+    UNIQUE (recurrence_id, occurrence_date)
+);
@@ -0,0 +1,24 @@
+-- Each user's recurring weekly availability, as a JSON list of windows with
+-- wall-clock times
+CREATE TABLE availability (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    weekly JSONB NOT NULL DEFAULT '[]',
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One-off changes to a date, which replace the weekly windows for that date.
+-- Null times cover the whole day.
+CREATE TABLE availability_exceptions (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    exception_date DATE NOT NULL,
+    start_time TIME,
+    end_time TIME,
+    kind VARCHAR(20) NOT NULL,
+    note TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_availability_exceptions_user ON availability_exceptions(user_id, exception_date);
@@ -0,0 +1,11 @@
+DROP TABLE leave_entitlements;
+DROP INDEX idx_leave_requests_approver;
+ALTER TABLE leave_requests DROP COLUMN decision_note;
+ALTER TABLE leave_requests DROP COLUMN decided_at;
+ALTER TABLE leave_requests DROP COLUMN decided_by;
+ALTER TABLE leave_requests DROP COLUMN approver_id;
+ALTER TABLE leave_requests DROP COLUMN days;
+ALTER TABLE leave_requests DROP COLUMN half_day_end;
+ALTER TABLE leave_requests DROP COLUMN half_day_start;
+ALTER TABLE teams DROP COLUMN manager_id;
+ALTER TABLE teams DROP COLUMN parent_id;
@@ -0,0 +1,7 @@
+ALTER TABLE leave_requests DROP COLUMN overridden;
+ALTER TABLE leave_requests DROP COLUMN override_reason;
+ALTER TABLE leave_requests DROP COLUMN overridden_by;
+DROP INDEX idx_leave_limits_team_skill;
+DROP TABLE leave_limits;
+DROP INDEX idx_leave_blackouts_dates;
+DROP TABLE leave_blackouts;
@@ -0,0 +1,36 @@
+-- Days leave can't be taken, for a team and the teams under it, or for
+-- anyone rostered at a location
+CREATE TABLE leave_blackouts (
+    id BIGSERIAL PRIMARY KEY,
+    team_id BIGINT REFERENCES teams(id) ON DELETE CASCADE,
+    location VARCHAR(255),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    reason TEXT NOT NULL,
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (team_id IS NOT NULL OR location IS NOT NULL),
+    CHECK (start_date <= end_date)
+);
+
+CREATE INDEX idx_leave_blackouts_dates ON leave_blackouts(start_date, end_date);
+
+-- How many of a team's members, or of those with a skill, may be off at once.
+-- An empty skill covers the whole team.
+CREATE TABLE leave_limits (
+    id BIGSERIAL PRIMARY KEY,
+    team_id BIGINT NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    skill VARCHAR(100) NOT NULL DEFAULT '',
+    max_off INTEGER CHECK (max_off >= 0),
+    max_percent INTEGER CHECK (max_percent BETWEEN 0 AND 100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (max_off IS NOT NULL OR max_percent IS NOT NULL)
+);
+
+CREATE UNIQUE INDEX idx_leave_limits_team_skill ON leave_limits(team_id, LOWER(skill));
+
+-- Leave let through despite blackouts or limits records who overrode them,
+-- why, and what was overridden
+ALTER TABLE leave_requests ADD COLUMN overridden_by VARCHAR(255);
+ALTER TABLE leave_requests ADD COLUMN override_reason TEXT;
+ALTER TABLE leave_requests ADD COLUMN overridden JSONB NOT NULL DEFAULT '[]';
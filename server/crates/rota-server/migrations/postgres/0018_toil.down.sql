@@ -0,0 +1,6 @@
+DROP INDEX idx_toil_ledger_overtime;
+DROP INDEX idx_toil_ledger_user;
+DROP TABLE toil_ledger;
+DROP INDEX idx_overtime_approver;
+DROP INDEX idx_overtime_user;
+DROP TABLE overtime;
@@ -0,0 +1,40 @@
+-- Hours worked beyond someone's shifts, claimed as time off in lieu
+CREATE TABLE overtime (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    work_date DATE NOT NULL,
+    hours DOUBLE PRECISION NOT NULL CHECK (hours > 0),
+    shift_id BIGINT REFERENCES shifts(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    approver_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    decided_by VARCHAR(255),
+    decided_at TIMESTAMPTZ,
+    decision_note TEXT NOT NULL DEFAULT '',
+    toil_hours DOUBLE PRECISION,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_overtime_user ON overtime(user_id, work_date);
+CREATE INDEX idx_overtime_approver ON overtime(approver_id, status);
+
+-- Every hour of TOIL earned, spent, refunded or adjusted. Entries are only
+-- ever added; an overtime claim is credited once.
+CREATE TABLE toil_ledger (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL,
+    hours DOUBLE PRECISION NOT NULL,
+    entry_date DATE NOT NULL,
+    expires_on DATE,
+    overtime_id BIGINT REFERENCES overtime(id) ON DELETE SET NULL,
+    leave_request_id BIGINT REFERENCES leave_requests(id) ON DELETE SET NULL,
+    note TEXT NOT NULL DEFAULT '',
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_toil_ledger_user ON toil_ledger(user_id, entry_date);
+CREATE UNIQUE INDEX idx_toil_ledger_overtime ON toil_ledger(overtime_id);
@@ -0,0 +1,2 @@
+-- Emails are matched ignoring case, so two can't differ only in case
+CREATE UNIQUE INDEX idx_users_email_lower ON users(LOWER(email));
@@ -0,0 +1,2 @@
+-- PostgreSQL stores TIMESTAMPTZ natively; kept in step with the SQLite version
+SELECT 1;
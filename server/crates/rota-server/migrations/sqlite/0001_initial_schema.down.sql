@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS audit_log;
+DROP TABLE IF EXISTS leave_requests;
+DROP TABLE IF EXISTS shift_assignments;
+DROP TABLE IF EXISTS shifts;
+DROP TABLE IF EXISTS rotas;
+DROP TABLE IF EXISTS users;
+DROP TABLE IF EXISTS teams;
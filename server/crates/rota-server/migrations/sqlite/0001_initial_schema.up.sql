@@ -0,0 +1,98 @@
+-- SQLite translation of migrations/postgres/0001_initial_schema.up.sql.
+-- Timestamps and dates are stored as ISO 8601 text, JSON as text.
+
+-- Teams group staff and own rotas
+CREATE TABLE teams (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Staff accounts. An empty password hash means the account cannot log in yet.
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    password_hash VARCHAR(255) NOT NULL DEFAULT '',
+    role VARCHAR(50) NOT NULL DEFAULT 'user',
+    team_id BIGINT REFERENCES teams(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_users_team_id ON users(team_id);
+
+-- A rota covers one team over a period and moves from draft to published to locked
+CREATE TABLE rotas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    team_id BIGINT NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (period_end >= period_start)
+);
+
+CREATE INDEX idx_rotas_team_period ON rotas(team_id, period_start);
+
+CREATE TABLE shifts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rota_id BIGINT REFERENCES rotas(id) ON DELETE SET NULL,
+    team_id BIGINT REFERENCES teams(id) ON DELETE SET NULL,
+    location VARCHAR(255) NOT NULL DEFAULT '',
+    position VARCHAR(255) NOT NULL DEFAULT '',
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    time_zone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    unpaid_break_minutes INTEGER NOT NULL DEFAULT 0,
+    required_headcount INTEGER NOT NULL DEFAULT 1,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX idx_shifts_rota_id ON shifts(rota_id);
+CREATE INDEX idx_shifts_starts_at ON shifts(starts_at);
+
+CREATE TABLE shift_assignments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    shift_id BIGINT NOT NULL REFERENCES shifts(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (shift_id, user_id)
+);
+
+CREATE INDEX idx_shift_assignments_user_id ON shift_assignments(user_id);
+
+CREATE TABLE leave_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    leave_type VARCHAR(50) NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (end_date >= start_date)
+);
+
+CREATE INDEX idx_leave_requests_user_id ON leave_requests(user_id, start_date);
+
+-- Append-only record of every write
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor VARCHAR(255),
+    request_id VARCHAR(64),
+    entity VARCHAR(64) NOT NULL,
+    entity_id VARCHAR(64) NOT NULL,
+    action VARCHAR(32) NOT NULL,
+    before TEXT,
+    after TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_audit_log_entity ON audit_log(entity, entity_id);
@@ -0,0 +1,4 @@
+ALTER TABLE leave_requests DROP COLUMN version;
+ALTER TABLE shifts DROP COLUMN version;
+ALTER TABLE rotas DROP COLUMN version;
+ALTER TABLE users DROP COLUMN version;
@@ -0,0 +1,6 @@
+-- Optimistic concurrency: every write bumps the row version, and clients send
+-- the version they read back as an If-Match header
+ALTER TABLE users ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE rotas ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE shifts ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE leave_requests ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
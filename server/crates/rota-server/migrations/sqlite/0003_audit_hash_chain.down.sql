@@ -0,0 +1,8 @@
+DROP TRIGGER audit_log_no_delete;
+DROP TRIGGER audit_log_no_update;
+
+DROP INDEX idx_audit_log_created_at;
+DROP INDEX idx_audit_log_actor;
+
+ALTER TABLE audit_log DROP COLUMN hash;
+ALTER TABLE audit_log DROP COLUMN prev_hash;
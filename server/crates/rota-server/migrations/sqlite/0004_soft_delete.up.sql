@@ -0,0 +1,14 @@
+-- Soft delete: rows are hidden by default rather than removed, so staff who
+-- appear on historical rotas keep resolving. The retention job purges or
+-- anonymises them once they are old enough.
+ALTER TABLE users ADD COLUMN deleted_at TEXT;
+ALTER TABLE users ADD COLUMN anonymised_at TEXT;
+ALTER TABLE rotas ADD COLUMN deleted_at TEXT;
+ALTER TABLE shifts ADD COLUMN deleted_at TEXT;
+ALTER TABLE leave_requests ADD COLUMN deleted_at TEXT;
+
+-- Only deleted rows are indexed; the retention job scans them by age
+CREATE INDEX idx_users_deleted_at ON users(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_rotas_deleted_at ON rotas(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_shifts_deleted_at ON shifts(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_leave_requests_deleted_at ON leave_requests(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Right-to-erasure requests. A request is raised by the user or an admin and
+-- carried out only once another admin approves it.
+CREATE TABLE erasure_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    requested_by VARCHAR(255) NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    decided_by VARCHAR(255),
+    decided_at TEXT,
+    decision_note TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one open request per user
+CREATE UNIQUE INDEX idx_erasure_requests_pending ON erasure_requests(user_id) WHERE status = 'pending';
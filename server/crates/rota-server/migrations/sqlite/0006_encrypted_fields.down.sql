@@ -0,0 +1,7 @@
+DROP INDEX idx_users_phone_bidx;
+ALTER TABLE users DROP COLUMN phone_bidx;
+
+ALTER TABLE leave_requests DROP COLUMN sickness_note;
+ALTER TABLE users DROP COLUMN date_of_birth;
+ALTER TABLE users DROP COLUMN emergency_contact;
+ALTER TABLE users DROP COLUMN phone;
@@ -0,0 +1,3 @@
+-- Deactivated users keep their account and history but can't log in, and are
+-- taken off shifts that haven't started yet
+ALTER TABLE users ADD COLUMN deactivated_at TEXT;
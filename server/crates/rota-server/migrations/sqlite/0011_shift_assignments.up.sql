@@ -0,0 +1,14 @@
+-- Skills and qualifications everyone working a shift must have
+CREATE TABLE shift_requirements (
+    shift_id INTEGER NOT NULL REFERENCES shifts(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('skill', 'qualification')),
+    name VARCHAR(100) NOT NULL,
+    PRIMARY KEY (shift_id, kind, name)
+);
+
+-- Assignments made despite conflicts record who forced them, why, and what
+-- was overridden
+ALTER TABLE shift_assignments ADD COLUMN assigned_by VARCHAR(255);
+ALTER TABLE shift_assignments ADD COLUMN forced BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE shift_assignments ADD COLUMN override_reason TEXT;
+ALTER TABLE shift_assignments ADD COLUMN conflicts TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,18 @@
+-- Who moved a rota through its lifecycle, and when
+ALTER TABLE rotas ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE rotas ADD COLUMN published_at TEXT;
+ALTER TABLE rotas ADD COLUMN published_by VARCHAR(255);
+ALTER TABLE rotas ADD COLUMN locked_at TEXT;
+ALTER TABLE rotas ADD COLUMN locked_by VARCHAR(255);
+
+-- What staff saw each time a rota was published. Managers keep editing the
+-- shifts themselves; staff only read the latest snapshot.
+CREATE TABLE rota_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rota_id INTEGER NOT NULL REFERENCES rotas(id) ON DELETE CASCADE,
+    revision BIGINT NOT NULL,
+    shifts TEXT NOT NULL,
+    published_by VARCHAR(255),
+    published_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (rota_id, revision)
+);
@@ -0,0 +1,4 @@
+DROP TABLE pattern_shifts;
+DROP TABLE pattern_overrides;
+DROP TABLE pattern_members;
+DROP TABLE shift_patterns;
@@ -0,0 +1,44 @@
+-- Rotating patterns: a cycle of shift templates, one entry per day (null for
+-- a day off), starting on anchor_date
+CREATE TABLE shift_patterns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    team_id BIGINT NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    anchor_date TEXT NOT NULL,
+    cycle TEXT NOT NULL,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TEXT
+);
+
+-- Who works each pattern, and how far into the cycle they are on the anchor date
+CREATE TABLE pattern_members (
+    pattern_id BIGINT NOT NULL REFERENCES shift_patterns(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    cycle_offset INTEGER NOT NULL,
+    PRIMARY KEY (pattern_id, user_id)
+);
+
+-- Manual changes to one person's day, kept apart from the cycle so they
+-- survive it being changed. A null template_id is a day off.
+CREATE TABLE pattern_overrides (
+    pattern_id BIGINT NOT NULL REFERENCES shift_patterns(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    override_date TEXT NOT NULL,
+    template_id BIGINT REFERENCES shift_templates(id),
+    reason TEXT NOT NULL DEFAULT '',
+    created_by VARCHAR(255),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (pattern_id, user_id, override_date)
+);
+
+-- The shift a pattern made for one template on one day, so applying the
+-- pattern again reuses it
+CREATE TABLE pattern_shifts (
+    shift_id BIGINT PRIMARY KEY REFERENCES shifts(id) ON DELETE CASCADE,
+    pattern_id BIGINT NOT NULL REFERENCES shift_patterns(id) ON DELETE CASCADE,
+    template_id BIGINT NOT NULL REFERENCES shift_templates(id),
+    shift_date TEXT NOT NULL,
+    UNIQUE (pattern_id, template_id, shift_date)
+);
@@ -0,0 +1,27 @@
+-- Teams nest under a parent team and can have a manager, who approves their
+-- members' leave
+ALTER TABLE teams ADD COLUMN parent_id BIGINT REFERENCES teams(id) ON DELETE SET NULL;
+ALTER TABLE teams ADD COLUMN manager_id BIGINT REFERENCES users(id) ON DELETE SET NULL;
+
+-- Half days, the working days taken, who should approve and how it was decided
+ALTER TABLE leave_requests ADD COLUMN half_day_start BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE leave_requests ADD COLUMN half_day_end BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE leave_requests ADD COLUMN days REAL NOT NULL DEFAULT 0;
+ALTER TABLE leave_requests ADD COLUMN approver_id BIGINT REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE leave_requests ADD COLUMN decided_by VARCHAR(255);
+ALTER TABLE leave_requests ADD COLUMN decided_at TEXT;
+ALTER TABLE leave_requests ADD COLUMN decision_note TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX idx_leave_requests_approver ON leave_requests(approver_id, status);
+
+-- Days of each type of leave someone gets per leave year, before pro-rating,
+-- and what they carried over from the year before
+CREATE TABLE leave_entitlements (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    leave_year INTEGER NOT NULL,
+    leave_type VARCHAR(50) NOT NULL,
+    days REAL NOT NULL CHECK (days >= 0),
+    carried_over REAL NOT NULL DEFAULT 0 CHECK (carried_over >= 0),
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, leave_year, leave_type)
+);
@@ -0,0 +1,40 @@
+-- Hours worked beyond someone's shifts, claimed as time off in lieu
+CREATE TABLE overtime (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    work_date TEXT NOT NULL,
+    hours REAL NOT NULL CHECK (hours > 0),
+    shift_id BIGINT REFERENCES shifts(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    approver_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    decided_by VARCHAR(255),
+    decided_at TEXT,
+    decision_note TEXT NOT NULL DEFAULT '',
+    toil_hours REAL,
+    version BIGINT NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_overtime_user ON overtime(user_id, work_date);
+CREATE INDEX idx_overtime_approver ON overtime(approver_id, status);
+
+-- Every hour of TOIL earned, spent, refunded or adjusted. Entries are only
+-- ever added; an overtime claim is credited once.
+CREATE TABLE toil_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL,
+    hours REAL NOT NULL,
+    entry_date TEXT NOT NULL,
+    expires_on TEXT,
+    overtime_id BIGINT REFERENCES overtime(id) ON DELETE SET NULL,
+    leave_request_id BIGINT REFERENCES leave_requests(id) ON DELETE SET NULL,
+    note TEXT NOT NULL DEFAULT '',
+    created_by VARCHAR(255),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_toil_ledger_user ON toil_ledger(user_id, entry_date);
+CREATE UNIQUE INDEX idx_toil_ledger_overtime ON toil_ledger(overtime_id);
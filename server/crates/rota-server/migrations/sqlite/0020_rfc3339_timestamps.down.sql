@@ -0,0 +1,2 @@
+-- Both formats read back the same, so there is nothing to undo
+SELECT 1;
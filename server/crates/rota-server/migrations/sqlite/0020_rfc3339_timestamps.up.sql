@@ -0,0 +1,5 @@
+-- The server writes timestamps as RFC 3339 ("2025-06-02T09:00:00+00:00"), but
+-- users used to take CURRENT_TIMESTAMP ("2025-06-02 09:00:00"). Rewrite those
+-- so each column holds one format and sorts and compares as text.
+UPDATE users SET created_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', created_at) WHERE created_at NOT LIKE '%T%';
+UPDATE users SET updated_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', updated_at) WHERE updated_at NOT LIKE '%T%';
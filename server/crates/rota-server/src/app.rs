@@ -0,0 +1,130 @@
+use axum::{middleware, Router};
+use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+
+use crate::{
+    auth,
+    config::Config,
+    database::{Database, Pools},
+    middleware::{request_id::request_id_middleware, transaction::transaction_middleware},
+    repo::{
+        memory::{
+            InMemoryAuditRepo, InMemoryAvailabilityRepo, InMemoryErasureRepo, InMemoryLeavePolicyRepo,
+            InMemoryLeaveRepo, InMemoryPatternRepo, InMemoryProfileRepo, InMemoryRotaRepo, InMemoryShiftRepo,
+            InMemoryTeamRepo, InMemoryTemplateRepo, InMemoryToilRepo, InMemoryUserRepo,
+        },
+        sql::{
+            SqlAuditRepo, SqlAvailabilityRepo, SqlErasureRepo, SqlLeavePolicyRepo, SqlLeaveRepo, SqlPatternRepo,
+            SqlProfileRepo, SqlRotaRepo, SqlShiftRepo, SqlTeamRepo, SqlTemplateRepo, SqlToilRepo, SqlUserRepo,
+        },
+        AuditRepo, AvailabilityRepo, ErasureRepo, LeavePolicyRepo, LeaveRepo, PatternRepo, ProfileRepo, RotaRepo,
+        ShiftRepo, TeamRepo, TemplateRepo, ToilRepo, UserRepo,
+    },
+    routes,
+};
+
+// Shared application state, handed to every handler through the `State` extractor.
+// Repositories are trait objects so the storage backend is chosen at startup.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Config,
+    // The SQL database, if any; only used for health checks outside the repositories
+    pub db: Option<Database>,
+    pub users: Arc<dyn UserRepo>,
+    pub teams: Arc<dyn TeamRepo>,
+    pub profiles: Arc<dyn ProfileRepo>,
+    pub rotas: Arc<dyn RotaRepo>,
+    pub shifts: Arc<dyn ShiftRepo>,
+    pub templates: Arc<dyn TemplateRepo>,
+    pub patterns: Arc<dyn PatternRepo>,
+    pub availability: Arc<dyn AvailabilityRepo>,
+    pub leave: Arc<dyn LeaveRepo>,
+    pub leave_policy: Arc<dyn LeavePolicyRepo>,
+    pub toil: Arc<dyn ToilRepo>,
+    pub audit: Arc<dyn AuditRepo>,
+    pub erasures: Arc<dyn ErasureRepo>,
+}
+
+impl AppState {
+    // State backed by the SQL database, whichever engine it is
+    pub fn from_database(db: Database, config: Config) -> Self {
+        match db.clone() {
+            Database::Postgres(pools) => Self::with_pools(pools, db, config),
+            Database::Sqlite(pools) => Self::with_pools(pools, db, config),
+        }
+    }
+
+    // Every SQL repository, sharing one set of pools
+    fn with_pools<DB: sqlx::Database>(pools: Pools<DB>, db: Database, config: Config) -> Self
+    where
+        SqlUserRepo<DB>: UserRepo,
+        SqlTeamRepo<DB>: TeamRepo,
+        SqlProfileRepo<DB>: ProfileRepo,
+        SqlRotaRepo<DB>: RotaRepo,
+        SqlShiftRepo<DB>: ShiftRepo,
+        SqlTemplateRepo<DB>: TemplateRepo,
+        SqlPatternRepo<DB>: PatternRepo,
+        SqlAvailabilityRepo<DB>: AvailabilityRepo,
+        SqlLeaveRepo<DB>: LeaveRepo,
+        SqlLeavePolicyRepo<DB>: LeavePolicyRepo,
+        SqlToilRepo<DB>: ToilRepo,
+        SqlAuditRepo<DB>: AuditRepo,
+        SqlErasureRepo<DB>: ErasureRepo,
+    {
+        let keyring = config.encryption.keyring.clone();
+
+        Self {
+            config,
+            db: Some(db),
+            users: Arc::new(SqlUserRepo::new(pools.clone(), keyring.clone())),
+            teams: Arc::new(SqlTeamRepo::new(pools.clone())),
+            profiles: Arc::new(SqlProfileRepo::new(pools.clone())),
+            rotas: Arc::new(SqlRotaRepo::new(pools.clone())),
+            shifts: Arc::new(SqlShiftRepo::new(pools.clone())),
+            templates: Arc::new(SqlTemplateRepo::new(pools.clone())),
+            patterns: Arc::new(SqlPatternRepo::new(pools.clone())),
+            availability: Arc::new(SqlAvailabilityRepo::new(pools.clone())),
+            leave: Arc::new(SqlLeaveRepo::new(pools.clone(), keyring)),
+            leave_policy: Arc::new(SqlLeavePolicyRepo::new(pools.clone())),
+            toil: Arc::new(SqlToilRepo::new(pools.clone())),
+            audit: Arc::new(SqlAuditRepo::new(pools.clone())),
+            erasures: Arc::new(SqlErasureRepo::new(pools)),
+        }
+    }
+
+    // State backed entirely by memory, used by tests and demos
+    pub fn in_memory(config: Config) -> Self {
+        Self {
+            config,
+            db: None,
+            users: Arc::new(InMemoryUserRepo::new()),
+            teams: Arc::new(InMemoryTeamRepo::new()),
+            profiles: Arc::new(InMemoryProfileRepo::new()),
+            rotas: Arc::new(InMemoryRotaRepo::new()),
+            shifts: Arc::new(InMemoryShiftRepo::new()),
+            templates: Arc::new(InMemoryTemplateRepo::new()),
+            patterns: Arc::new(InMemoryPatternRepo::new()),
+            availability: Arc::new(InMemoryAvailabilityRepo::new()),
+            leave: Arc::new(InMemoryLeaveRepo::new()),
+            leave_policy: Arc::new(InMemoryLeavePolicyRepo::new()),
+            toil: Arc::new(InMemoryToilRepo::new()),
+            audit: Arc::new(InMemoryAuditRepo::new()),
+            erasures: Arc::new(InMemoryErasureRepo::new()),
+        }
+    }
+}
+
+// Build the full application router. Used by `main` and by the test suite so
+// both exercise exactly the same routes and middleware stack.
+pub fn build_app(state: AppState) -> Router {
+    Router::new()
+        .merge(routes::app_routes())
+        .merge(auth::auth_routes())
+        .with_state(state)
+        // Layers run bottom-up: tracing wraps the request id middleware so the
+        // id is available to every handler, and each write's transaction ends
+        // before the response leaves
+        .layer(middleware::from_fn(transaction_middleware))
+        .layer(middleware::from_fn(request_id_middleware))
+        .layer(TraceLayer::new_for_http())
+}
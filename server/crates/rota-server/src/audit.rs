@@ -0,0 +1,237 @@
+//! Audit trail of every write.
+//!
+//! Handlers take an [`Audit`] extractor, which knows who is acting and under
+//! which request id, and call [`Audit::record`] after each successful change.
+//! Entries form a hash chain: each stores the hash of the one before it, so
+//! editing or deleting a stored row is detectable with [`verify_chain`].
+//!
+//! An entry is appended in the same transaction as the change it records:
+//! every request that can change data runs in a [`database::atomic`] block,
+//! and background jobs open their own. If the append fails the change is
+//! rolled back with it and the request fails with 500.
+//!
+//! [`database::atomic`]: crate::database::atomic
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::{
+    app::AppState,
+    auth::jwt::Claims,
+    error::AppError,
+    middleware::request_id::RequestId,
+    models::{
+        audit::{AuditEntry, NewAuditEntry},
+        user::{AuditedUser, User},
+    },
+    repo::AuditRepo,
+};
+
+// Timestamp for a new entry. PostgreSQL keeps microseconds, so anything finer
+// would be lost on the round trip and the stored hash could not be recomputed.
+pub fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+// Hash of an entry chained onto `prev_hash`. The input is canonical JSON
+// (object keys sorted), so it survives JSONB normalising the snapshots.
+pub fn entry_hash(prev_hash: &str, entry: &NewAuditEntry, created_at: DateTime<Utc>) -> String {
+    let canonical = json!({
+        "prev_hash": prev_hash,
+        "actor": entry.actor,
+        "request_id": entry.request_id,
+        "entity": entry.entity,
+        "entity_id": entry.entity_id,
+        "action": entry.action,
+        "before": entry.before,
+        "after": entry.after,
+        "created_at": created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+    });
+
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+// Seal a new entry onto the end of the chain
+pub fn seal(id: i64, prev_hash: String, entry: NewAuditEntry) -> AuditEntry {
+    let created_at = now();
+    let hash = entry_hash(&prev_hash, &entry, created_at);
+
+    AuditEntry {
+        id,
+        actor: entry.actor,
+        request_id: entry.request_id,
+        entity: entry.entity,
+        entity_id: entry.entity_id,
+        action: entry.action,
+        before: entry.before,
+        after: entry.after,
+        created_at,
+        prev_hash,
+        hash,
+    }
+}
+
+// Top-level fields that differ between two snapshots, as
+// `{ "field": { "before": .., "after": .. } }`
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Value {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let (old, new) = (before.get(key), after.get(key));
+        if old != new && !changes.contains_key(key) {
+            changes.insert(
+                key.clone(),
+                json!({
+                    "before": old.cloned().unwrap_or(Value::Null),
+                    "after": new.cloned().unwrap_or(Value::Null),
+                }),
+            );
+        }
+    }
+
+    Value::Object(changes)
+}
+
+// Outcome of checking the whole chain
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ChainReport {
+    pub valid: bool,
+    pub entries: usize,
+    // First entry whose hash or link doesn't match, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<i64>,
+}
+
+// Recompute every hash in order, oldest first
+pub fn verify_chain(entries: &[AuditEntry]) -> ChainReport {
+    let mut prev_hash = "";
+
+    for entry in entries {
+        let unsealed = NewAuditEntry {
+            actor: entry.actor.clone(),
+            request_id: entry.request_id.clone(),
+            entity: entry.entity.clone(),
+            entity_id: entry.entity_id.clone(),
+            action: entry.action.clone(),
+            before: entry.before.clone(),
+            after: entry.after.clone(),
+        };
+
+        if entry.prev_hash != prev_hash
+            || entry.hash != entry_hash(prev_hash, &unsealed, entry.created_at)
+        {
+            return ChainReport {
+                valid: false,
+                entries: entries.len(),
+                broken_at: Some(entry.id),
+            };
+        }
+        prev_hash = &entry.hash;
+    }
+
+    ChainReport {
+        valid: true,
+        entries: entries.len(),
+        broken_at: None,
+    }
+}
+
+// Records changes on behalf of the current request. The actor is taken from
+// the access token when one is present; anonymous writes are logged without one.
+#[derive(Clone)]
+pub struct Audit {
+    repo: Arc<dyn AuditRepo>,
+    actor: Option<String>,
+    request_id: Option<String>,
+}
+
+impl Audit {
+    // Records changes made by a background job rather than a request
+    pub fn system(repo: Arc<dyn AuditRepo>, actor: impl Into<String>) -> Self {
+        Self {
+            repo,
+            actor: Some(actor.into()),
+            request_id: None,
+        }
+    }
+
+    // Attribute entries to someone other than the token holder, e.g. a user
+    // who has just registered and has no token yet
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    // Append an entry for a change to `entity` `entity_id`. Snapshots are the
+    // public representation of the record, never secrets such as password
+    // hashes; users go through `record_user` instead.
+    pub async fn record<T: Serialize>(
+        &self,
+        entity: &str,
+        entity_id: impl ToString,
+        action: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<AuditEntry, AppError> {
+        let snapshot = |value: Option<&T>| {
+            value
+                .map(serde_json::to_value)
+                .transpose()
+                .map_err(|_| AppError::InternalServerError)
+        };
+
+        let entry = NewAuditEntry {
+            actor: self.actor.clone(),
+            request_id: self.request_id.clone(),
+            entity: entity.to_string(),
+            entity_id: entity_id.to_string(),
+            action: action.to_string(),
+            before: snapshot(before)?,
+            after: snapshot(after)?,
+        };
+
+        Ok(self.repo.append(entry).await?)
+    }
+
+    // Append an entry for a change to user `id`, snapshotting the user without
+    // anything that identifies them, which erasure couldn't take back out
+    pub async fn record_user(
+        &self,
+        id: i64,
+        action: &str,
+        before: Option<&User>,
+        after: Option<&User>,
+    ) -> Result<AuditEntry, AppError> {
+        let before = before.map(AuditedUser::from);
+        let after = after.map(AuditedUser::from);
+
+        self.record("user", id, action, before.as_ref(), after.as_ref()).await
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Audit {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let actor = Claims::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|claims| claims.sub);
+        let request_id = parts.extensions.get::<RequestId>().map(|id| id.0.clone());
+
+        Ok(Audit {
+            repo: state.audit.clone(),
+            actor,
+            request_id,
+        })
+    }
+}
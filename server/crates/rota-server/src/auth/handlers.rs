@@ -1,15 +1,19 @@
 use axum::{
-    extract::Json,
+    extract::{Json, State},
     http::StatusCode,
     response::IntoResponse,
 };
+use rota_core::user::validate_new_user;
 use serde::{Deserialize, Serialize};
-use tracing::error;
 
 use crate::auth::jwt::{
     validate_token, AuthError, Claims, TokenType, create_tokens,
 };
-use crate::models::user::{User, UserRole};
+use crate::app::AppState;
+use crate::audit::Audit;
+use crate::auth::password;
+use crate::models::user::{NewUser, UserRole};
+use crate::repo::RepoError;
 
 // Login request payload
 #[derive(Debug, Deserialize)]
@@ -40,28 +44,34 @@ pub struct MessageResponse {
 
 // User registration handler
 pub async fn register(
+    State(state): State<AppState>,
+    audit: Audit,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<impl IntoResponse, AuthError> {
-    // This would typically interact with a database
-    // For now, we'll simulate creating a user
-    
-    // In a real application, you would:
-    // 1. Check if user already exists
-    // 2. Hash the password (now done in User::new)
-    // 3. Store user in database
-    // 4. Generate tokens
+    validate_new_user(&payload.username, &payload.email)
+        .map_err(|err| AuthError::InvalidInput(err.0))?;
 
     // Create a new user with a hashed password
-    let user = User::new(
-        uuid::Uuid::new_v4().to_string(),
-        payload.username,
-        payload.email,
-        &payload.password,
-        UserRole::User,
-    ).map_err(|_| AuthError::TokenCreation)?; // Using TokenCreation as a general error
+    let new_user = password::blocking(move || {
+        NewUser::new(payload.username, payload.email, Some(&payload.password), UserRole::User)
+    })
+    .await
+    .map_err(|_| AuthError::Internal)?;
+
+    let user = state.users.create(new_user).await.map_err(|err| match err {
+        RepoError::Conflict(_) => AuthError::UserExists,
+        _ => AuthError::Internal,
+    })?;
+
+    // Self-registration: the new user is their own actor
+    audit
+        .with_actor(user.id.to_string())
+        .record_user(user.id, "create", None, Some(&user))
+        .await
+        .map_err(|_| AuthError::Internal)?;
 
     // Generate tokens
-    let token_response = create_tokens(&user.id, &user.role.to_string())?;
+    let token_response = create_tokens(&user.id.to_string(), &user.role.to_string())?;
 
     // Return tokens
     Ok((StatusCode::CREATED, Json(token_response)))
@@ -69,49 +79,46 @@ pub async fn register(
 
 // User login handler
 pub async fn login(
+    State(state): State<AppState>,
     Json(payload): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, AuthError> {
-    // This would typically:
-    // 1. Query the database for the user
-    // 2. Verify password hash
-    // 3. Generate tokens if valid
-    
-    // For now, simulate a stored user
-    // In a real application, you would query the database
-    let stored_user = User::new(
-        "user_123".to_string(),
-        "testuser".to_string(),
-        "test@example.com".to_string(),
-        "password", // The expected password
-        UserRole::User,
-    ).map_err(|_| AuthError::TokenCreation)?;
-    
-    // Check if email matches
-    if payload.email != stored_user.email {
-        return Err(AuthError::WrongCredentials);
-    }
-    
+    let stored_user = state.users.find_by_email(&payload.email).await
+        .map_err(|_| AuthError::Internal)?
+        .ok_or(AuthError::WrongCredentials)?;
+
     // Verify password using bcrypt
-    if !stored_user.verify_password(&payload.password) {
+    if !password::verify(&stored_user, &payload.password).await {
         return Err(AuthError::WrongCredentials);
     }
+    if !stored_user.is_active() {
+        return Err(AuthError::AccountDisabled);
+    }
 
     // Create tokens
-    let token_response = create_tokens(&stored_user.id, &stored_user.role.to_string())?;
+    let token_response = create_tokens(&stored_user.id.to_string(), &stored_user.role.to_string())?;
 
     // Return tokens
     Ok((StatusCode::OK, Json(token_response)))
 }
 
-// Token refresh handler
+// Token refresh handler. The user is looked up again so deactivation and
+// role changes take effect at the next refresh.
 pub async fn refresh_token(
+    State(state): State<AppState>,
     Json(payload): Json<RefreshTokenRequest>,
 ) -> Result<impl IntoResponse, AuthError> {
     // Validate the refresh token
     let claims = validate_token(&payload.refresh_token, Some(TokenType::Refresh))?;
-    
+    let user_id = claims.user_id().ok_or(AuthError::InvalidToken)?;
+    let user = state.users.get(user_id).await
+        .map_err(|_| AuthError::Internal)?
+        .ok_or(AuthError::InvalidToken)?;
+    if !user.is_active() {
+        return Err(AuthError::AccountDisabled);
+    }
+
     // Generate new tokens
-    let token_response = create_tokens(&claims.sub, &claims.role)?;
+    let token_response = create_tokens(&claims.sub, &user.role.to_string())?;
     
     Ok((StatusCode::OK, Json(token_response)))
 }
@@ -12,6 +12,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fmt::Display;
 
+use rota_core::user::UserRole;
+
 // JWT secret key for encoding and decoding tokens
 // In a production environment, this should be loaded from environment variables
 static JWT_SECRET: Lazy<String> = Lazy::new(|| {
@@ -32,6 +34,38 @@ pub struct Claims {
     pub token_type: String,  // Token type: "access" or "refresh"
 }
 
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    // The role the token was issued for; anything unrecognised counts as a
+    // plain user
+    pub fn role(&self) -> UserRole {
+        self.role.parse().unwrap_or(UserRole::User)
+    }
+
+    // Managers and admins can build rotas and manage shifts
+    pub fn is_manager(&self) -> bool {
+        self.role().is_manager()
+    }
+
+    // The id of the user the token was issued to
+    pub fn user_id(&self) -> Option<i64> {
+        self.sub.parse().ok()
+    }
+
+    // Users may act on their own record; admins on anyone's
+    pub fn is_self_or_admin(&self, user_id: i64) -> bool {
+        self.is_admin() || self.user_id() == Some(user_id)
+    }
+
+    // Users may act on their own record; managers and admins on anyone's
+    pub fn is_self_or_manager(&self, user_id: i64) -> bool {
+        self.is_manager() || self.user_id() == Some(user_id)
+    }
+}
+
 // Token types
 #[derive(Debug, Serialize, Deserialize)]
 pub enum TokenType {
@@ -65,16 +99,25 @@ pub enum AuthError {
     TokenCreation,
     InvalidToken,
     Expired,
+    InvalidInput(String),
+    UserExists,
+    // The account exists but has been deactivated
+    AccountDisabled,
+    Internal,
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
-            AuthError::WrongCredentials => (StatusCode::UNAUTHORIZED, "Wrong credentials"),
-            AuthError::MissingCredentials => (StatusCode::BAD_REQUEST, "Missing credentials"),
-            AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Token creation error"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AuthError::Expired => (StatusCode::UNAUTHORIZED, "Token has expired"),
+            AuthError::WrongCredentials => (StatusCode::UNAUTHORIZED, "Wrong credentials".to_string()),
+            AuthError::MissingCredentials => (StatusCode::BAD_REQUEST, "Missing credentials".to_string()),
+            AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Token creation error".to_string()),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
+            AuthError::Expired => (StatusCode::UNAUTHORIZED, "Token has expired".to_string()),
+            AuthError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
+            AuthError::UserExists => (StatusCode::CONFLICT, "User already exists".to_string()),
+            AuthError::AccountDisabled => (StatusCode::FORBIDDEN, "Account is deactivated".to_string()),
+            AuthError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()),
         };
 
         let body = Json(json!({
@@ -0,0 +1,25 @@
+pub mod handlers;
+pub mod jwt;
+pub mod password;
+
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+// Re-export commonly used functions
+pub use handlers::{login, register, refresh_token, protected, logout};
+
+use crate::app::AppState;
+
+// Authentication routes, mounted under /api
+pub fn auth_routes() -> Router<AppState> {
+    Router::new()
+        // Public authentication routes
+        .route("/api/auth/register", post(register))
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/refresh", post(refresh_token))
+        .route("/api/auth/logout", post(logout))
+        // Protected routes
+        .route("/api/protected", get(protected))
+}
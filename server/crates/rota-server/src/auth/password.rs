@@ -0,0 +1,25 @@
+//! Password hashing off the async runtime.
+//!
+//! bcrypt is deliberately slow. Run on a worker it would stall every other
+//! request scheduled there, so each call goes to the blocking pool instead.
+
+use crate::models::user::User;
+
+// Run bcrypt work on the blocking pool, resuming its panic if it has one
+pub async fn blocking<T, F>(work: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(work).await {
+        Ok(output) => output,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+// Whether `password` matches the user's stored hash
+pub async fn verify(user: &User, password: &str) -> bool {
+    let (user, password) = (user.clone(), password.to_string());
+
+    blocking(move || user.verify_password(&password)).await
+}
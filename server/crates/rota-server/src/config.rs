@@ -0,0 +1,361 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::fmt::Debug;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use rota_core::leave::LeaveYear;
+use rota_core::toil::ToilMultipliers;
+
+use crate::encryption::Keyring;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub host: IpAddr,
+    pub port: u16,
+    pub log_level: String,
+    // Apply pending migrations on startup instead of only warning about them
+    pub auto_migrate: bool,
+    pub database: DatabaseConfig,
+    pub retention: RetentionConfig,
+    pub encryption: EncryptionConfig,
+    pub scheduling: SchedulingConfig,
+    pub leave: LeaveConfig,
+    pub toil: ToilConfig,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let host = env::var("HOST")
+            .unwrap_or_else(|_| "127.0.0.1".to_string())
+            .parse()
+            .expect("HOST must be a valid IP address");
+
+        let port = env::var("PORT")
+            .unwrap_or_else(|_| "3000".to_string())
+            .parse()
+            .expect("PORT must be a valid number");
+
+        let log_level = env::var("RUST_LOG")
+            .unwrap_or_else(|_| "info".to_string());
+
+        let auto_migrate = env::var("AUTO_MIGRATE")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        Self {
+            host,
+            port,
+            log_level,
+            auto_migrate,
+            database: DatabaseConfig::from_env(),
+            retention: RetentionConfig::from_env(),
+            encryption: EncryptionConfig::from_env(),
+            scheduling: SchedulingConfig::from_env(),
+            leave: LeaveConfig::from_env(),
+            toil: ToilConfig::from_env(),
+        }
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+// Implement Default for testing purposes
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::from_str("127.0.0.1").unwrap(),
+            port: 3000,
+            log_level: "info".to_string(),
+            auto_migrate: false,
+            database: DatabaseConfig::default(),
+            retention: RetentionConfig::default(),
+            encryption: EncryptionConfig::default(),
+            scheduling: SchedulingConfig::default(),
+            leave: LeaveConfig::default(),
+            toil: ToilConfig::default(),
+        }
+    }
+}
+
+// Connection and pool settings for the database
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    // Primary database; every write goes here
+    pub url: Option<String>,
+    // Optional read replica for read-only queries such as list views and reports
+    pub replica_url: Option<String>,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    // How long a query waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    // Idle connections above `min_connections` are closed after this long
+    pub idle_timeout: Option<Duration>,
+    // Connections are recycled after this long regardless of use
+    pub max_lifetime: Option<Duration>,
+    // Server-side limit on a single statement (PostgreSQL only)
+    pub statement_timeout: Option<Duration>,
+}
+
+impl DatabaseConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            url: env::var("DATABASE_URL").ok(),
+            replica_url: env::var("DATABASE_REPLICA_URL").ok().filter(|v| !v.is_empty()),
+            max_connections: env_parse("DB_MAX_CONNECTIONS", defaults.max_connections),
+            min_connections: env_parse("DB_MIN_CONNECTIONS", defaults.min_connections),
+            acquire_timeout: Duration::from_secs(env_parse(
+                "DB_ACQUIRE_TIMEOUT_SECS",
+                defaults.acquire_timeout.as_secs(),
+            )),
+            idle_timeout: env_seconds("DB_IDLE_TIMEOUT_SECS", defaults.idle_timeout),
+            max_lifetime: env_seconds("DB_MAX_LIFETIME_SECS", defaults.max_lifetime),
+            statement_timeout: env::var("DB_STATEMENT_TIMEOUT_MS")
+                .ok()
+                .map(|v| v.parse().expect("DB_STATEMENT_TIMEOUT_MS must be a valid number"))
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+        }
+    }
+
+    // Default settings pointed at `url`
+    pub fn with_url(url: impl Into<String>) -> Self {
+        Self {
+            url: Some(url.into()),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            replica_url: None,
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(5),
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            max_lifetime: Some(Duration::from_secs(30 * 60)),
+            statement_timeout: None,
+        }
+    }
+}
+
+// How long soft-deleted records are kept before the retention job removes
+// them for good. `None` keeps them forever.
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    // Deleted users are anonymised rather than purged, since rotas refer to them
+    pub users: Option<Duration>,
+    pub rotas: Option<Duration>,
+    pub shifts: Option<Duration>,
+    pub leave_requests: Option<Duration>,
+    // How often `serve` runs the job; `None` leaves it to `rota-server retention`
+    pub interval: Option<Duration>,
+}
+
+impl RetentionConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            users: env_days("RETENTION_USERS_DAYS", defaults.users),
+            rotas: env_days("RETENTION_ROTAS_DAYS", defaults.rotas),
+            shifts: env_days("RETENTION_SHIFTS_DAYS", defaults.shifts),
+            leave_requests: env_days("RETENTION_LEAVE_DAYS", defaults.leave_requests),
+            interval: env_seconds("RETENTION_INTERVAL_SECS", defaults.interval),
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            users: None,
+            rotas: None,
+            shifts: None,
+            leave_requests: None,
+            interval: Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+}
+
+// Keys for field-level encryption, loaded from the file named by
+// ENCRYPTION_KEYS_FILE. Without one, encrypted fields can't be stored.
+#[derive(Debug, Clone, Default)]
+pub struct EncryptionConfig {
+    pub keys_file: Option<PathBuf>,
+    pub keyring: Option<Arc<Keyring>>,
+}
+
+impl EncryptionConfig {
+    pub fn from_env() -> Self {
+        let keys_file = env::var("ENCRYPTION_KEYS_FILE")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+        let keyring = keys_file.as_ref().map(|path| {
+            let keyring = Keyring::load(path)
+                .unwrap_or_else(|err| panic!("ENCRYPTION_KEYS_FILE: {}", err));
+            Arc::new(keyring)
+        });
+
+        Self { keys_file, keyring }
+    }
+
+    pub fn with_keyring(keyring: Keyring) -> Self {
+        Self {
+            keys_file: None,
+            keyring: Some(Arc::new(keyring)),
+        }
+    }
+}
+
+// Limits applied when people are assigned to shifts
+#[derive(Debug, Clone)]
+pub struct SchedulingConfig {
+    // Most paid hours anyone may work in a week, e.g. under working time rules
+    pub max_weekly_hours: f64,
+}
+
+impl SchedulingConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            max_weekly_hours: env_parse("MAX_WEEKLY_HOURS", defaults.max_weekly_hours),
+        }
+    }
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self { max_weekly_hours: 48.0 }
+    }
+}
+
+// Leave years and how entitlements are worked out
+#[derive(Debug, Clone)]
+pub struct LeaveConfig {
+    // Month leave years start in, on the first
+    pub year_start_month: u32,
+    // Weekly hours entitlements are given for; fewer hours are pro-rated
+    pub full_time_hours: f64,
+    // Most unused days that come across into the next leave year
+    pub max_carry_over_days: f64,
+}
+
+impl LeaveConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let year_start_month = env_parse("LEAVE_YEAR_START_MONTH", defaults.year_start_month);
+        if !(1..=12).contains(&year_start_month) {
+            panic!("LEAVE_YEAR_START_MONTH must be from 1 to 12");
+        }
+
+        Self {
+            year_start_month,
+            full_time_hours: env_parse("FULL_TIME_HOURS", defaults.full_time_hours),
+            max_carry_over_days: env_parse("LEAVE_CARRY_OVER_DAYS", defaults.max_carry_over_days),
+        }
+    }
+
+    pub fn year(&self) -> LeaveYear {
+        LeaveYear { start_month: self.year_start_month }
+    }
+}
+
+impl Default for LeaveConfig {
+    fn default() -> Self {
+        Self {
+            year_start_month: 1,
+            full_time_hours: 37.5,
+            max_carry_over_days: 5.0,
+        }
+    }
+}
+
+// How approved overtime turns into time off in lieu
+#[derive(Debug, Clone)]
+pub struct ToilConfig {
+    // TOIL hours earned per overtime hour worked Monday to Friday
+    pub weekday_multiplier: f64,
+    // TOIL hours earned per overtime hour worked on a Saturday or Sunday
+    pub weekend_multiplier: f64,
+    // Days after the overtime was worked that unused TOIL lapses; 0 keeps it
+    pub expiry_days: u32,
+}
+
+impl ToilConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let weekday_multiplier = env_parse("TOIL_WEEKDAY_MULTIPLIER", defaults.weekday_multiplier);
+        let weekend_multiplier = env_parse("TOIL_WEEKEND_MULTIPLIER", defaults.weekend_multiplier);
+        if !(0.0..=10.0).contains(&weekday_multiplier) || !(0.0..=10.0).contains(&weekend_multiplier) {
+            panic!("TOIL multipliers must be from 0 to 10");
+        }
+
+        Self {
+            weekday_multiplier,
+            weekend_multiplier,
+            expiry_days: env_parse("TOIL_EXPIRY_DAYS", defaults.expiry_days),
+        }
+    }
+
+    pub fn multipliers(&self) -> ToilMultipliers {
+        ToilMultipliers { weekday: self.weekday_multiplier, weekend: self.weekend_multiplier }
+    }
+}
+
+impl Default for ToilConfig {
+    fn default() -> Self {
+        Self {
+            weekday_multiplier: 1.0,
+            weekend_multiplier: 1.5,
+            expiry_days: 90,
+        }
+    }
+}
+
+// Parse an optional environment variable, panicking on malformed values
+fn env_parse<T>(name: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a valid number", name)),
+        Err(_) => default,
+    }
+}
+
+// A duration in whole seconds where 0 disables the limit
+fn env_seconds(name: &str, default: Option<Duration>) -> Option<Duration> {
+    match env::var(name) {
+        Ok(_) => match env_parse::<u64>(name, 0) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        },
+        Err(_) => default,
+    }
+}
+
+// A duration in whole days where 0 disables the limit
+fn env_days(name: &str, default: Option<Duration>) -> Option<Duration> {
+    match env::var(name) {
+        Ok(_) => match env_parse::<u64>(name, 0) {
+            0 => None,
+            days => Some(Duration::from_secs(days * 24 * 60 * 60)),
+        },
+        Err(_) => default,
+    }
+}
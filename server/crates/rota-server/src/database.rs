@@ -0,0 +1,395 @@
+use serde::Serialize;
+use sqlx::{
+    pool::{PoolConnection, PoolOptions},
+    postgres::{PgConnectOptions, PgPoolOptions},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    Connection, Pool, Postgres, Sqlite, Transaction,
+};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::config::DatabaseConfig;
+
+pub type DbPool = Pool<Postgres>;
+pub type SqlitePool = Pool<Sqlite>;
+
+// The primary pool plus an optional read replica for one engine
+#[derive(Debug)]
+pub struct Pools<DB: sqlx::Database> {
+    pub primary: Pool<DB>,
+    pub replica: Option<Pool<DB>>,
+    // Held by the `atomic` block writing to an engine that allows only one
+    // writer at a time, from its first query until it ends
+    writer: Arc<Mutex<()>>,
+}
+
+// Written by hand because the derive would require `DB: Clone`
+impl<DB: sqlx::Database> Clone for Pools<DB> {
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            replica: self.replica.clone(),
+            writer: self.writer.clone(),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> Pools<DB> {
+    pub fn new(primary: Pool<DB>) -> Self {
+        Self {
+            primary,
+            replica: None,
+            writer: Arc::new(Mutex::new(())),
+        }
+    }
+
+}
+
+impl<DB: Engine> Pools<DB> {
+    // A connection to the primary. Inside an `atomic` block this is the
+    // block's transaction, begun here by the first query that needs it.
+    pub async fn acquire(&self) -> Result<Conn<DB>, sqlx::Error> {
+        let Ok(scope) = SCOPE.try_with(Arc::clone) else {
+            return Ok(Conn::Pooled(self.primary.acquire().await?));
+        };
+
+        let mut slot = DB::slot(&scope).clone().lock_owned().await;
+        if slot.is_none() {
+            // Take the writer's turn before beginning, so a deferred SQLite
+            // transaction never has to upgrade to a write lock another holds
+            let turn = match DB::ONE_WRITER {
+                true => Some(self.writer.clone().lock_owned().await),
+                false => None,
+            };
+            *slot = Some(Open { tx: self.primary.begin().await?, _turn: turn });
+        }
+
+        Ok(Conn::Scoped(slot))
+    }
+
+    // A connection for a list query. Only inside an `on_replica` block, and
+    // outside any `atomic` one, is it taken from the read replica; everything
+    // else reads the primary.
+    pub async fn acquire_list(&self) -> Result<Conn<DB>, sqlx::Error> {
+        let lag_ok = REPLICA.try_with(|_| ()).is_ok() && SCOPE.try_with(|_| ()).is_err();
+        match &self.replica {
+            Some(replica) if lag_ok => Ok(Conn::Pooled(replica.acquire().await?)),
+            _ => self.acquire().await,
+        }
+    }
+}
+
+// An engine an `atomic` block can hold a transaction on
+pub trait Engine: sqlx::Database {
+    // Whether the engine allows only one writer at a time
+    const ONE_WRITER: bool;
+
+    fn slot(scope: &Scope) -> &Arc<Mutex<Option<Open<Self>>>>;
+}
+
+impl Engine for Postgres {
+    const ONE_WRITER: bool = false;
+
+    fn slot(scope: &Scope) -> &Arc<Mutex<Option<Open<Self>>>> {
+        &scope.postgres
+    }
+}
+
+impl Engine for Sqlite {
+    const ONE_WRITER: bool = true;
+
+    fn slot(scope: &Scope) -> &Arc<Mutex<Option<Open<Self>>>> {
+        &scope.sqlite
+    }
+}
+
+// The transaction an `atomic` block holds on one engine
+pub struct Open<DB: sqlx::Database> {
+    tx: Transaction<'static, DB>,
+    _turn: Option<OwnedMutexGuard<()>>,
+}
+
+// The transactions of one `atomic` block, at most one per engine. A block
+// works against a single database of each engine.
+#[derive(Default)]
+pub struct Scope {
+    postgres: Arc<Mutex<Option<Open<Postgres>>>>,
+    sqlite: Arc<Mutex<Option<Open<Sqlite>>>>,
+}
+
+impl Scope {
+    async fn finish(&self, commit: bool) -> Result<(), sqlx::Error> {
+        if let Some(open) = self.postgres.lock().await.take() {
+            end(open.tx, commit).await?;
+        }
+        if let Some(open) = self.sqlite.lock().await.take() {
+            end(open.tx, commit).await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn end<DB: sqlx::Database>(tx: Transaction<'static, DB>, commit: bool) -> Result<(), sqlx::Error> {
+    match commit {
+        true => tx.commit().await,
+        false => tx.rollback().await,
+    }
+}
+
+tokio::task_local! {
+    static SCOPE: Arc<Scope>;
+    static REPLICA: ();
+}
+
+// A connection handed to one repository call: the enclosing `atomic` block's
+// transaction, or a connection of its own from the pool
+pub enum Conn<DB: Engine> {
+    Pooled(PoolConnection<DB>),
+    Scoped(OwnedMutexGuard<Option<Open<DB>>>),
+}
+
+impl<DB: Engine> Deref for Conn<DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &DB::Connection {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Scoped(open) => &open.as_ref().expect("scoped transaction is open").tx,
+        }
+    }
+}
+
+impl<DB: Engine> DerefMut for Conn<DB> {
+    fn deref_mut(&mut self) -> &mut DB::Connection {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Scoped(open) => &mut open.as_mut().expect("scoped transaction is open").tx,
+        }
+    }
+}
+
+// Run `work` so that every repository call in it shares one transaction,
+// committed if it returns `Ok` and rolled back otherwise. A block inside
+// another joins the outer one, which decides for both.
+pub async fn atomic<T, E: From<sqlx::Error>>(work: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    atomic_if(work, Result::is_ok).await?
+}
+
+// As `atomic`, committing when `keep` accepts whatever `work` returned. Fails
+// only if the commit or rollback itself does.
+pub async fn atomic_if<T>(work: impl Future<Output = T>, keep: impl FnOnce(&T) -> bool) -> Result<T, sqlx::Error> {
+    if SCOPE.try_with(|_| ()).is_ok() {
+        return Ok(work.await);
+    }
+
+    let scope = Arc::new(Scope::default());
+    let output = SCOPE.scope(scope.clone(), work).await;
+    scope.finish(keep(&output)).await?;
+
+    Ok(output)
+}
+
+// Run `work` with its list queries served by the read replica, if there is
+// one. For read-only views and reports that tolerate replication lag; a
+// handler whose decisions or writes depend on what it reads must not use it.
+pub async fn on_replica<T>(work: impl Future<Output = T>) -> T {
+    REPLICA.scope((), work).await
+}
+
+// Connection pools for whichever SQL engine DATABASE_URL points at
+#[derive(Clone, Debug)]
+pub enum Database {
+    Postgres(Pools<Postgres>),
+    Sqlite(Pools<Sqlite>),
+}
+
+impl Database {
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Database::Postgres(_) => "postgres",
+            Database::Sqlite(_) => "sqlite",
+        }
+    }
+
+    // Check connectivity and collect pool statistics for monitoring
+    pub async fn health(&self) -> DatabaseHealth {
+        let (primary, replica) = match self {
+            Database::Postgres(pools) => (
+                probe(&pools.primary).await,
+                match &pools.replica {
+                    Some(replica) => Some(probe(replica).await),
+                    None => None,
+                },
+            ),
+            Database::Sqlite(pools) => (probe(&pools.primary).await, None),
+        };
+
+        DatabaseHealth {
+            healthy: primary.reachable && replica.as_ref().is_none_or(|r| r.reachable),
+            backend: self.backend_name(),
+            primary,
+            replica,
+        }
+    }
+}
+
+// Result of a database health check
+#[derive(Debug, Serialize)]
+pub struct DatabaseHealth {
+    pub healthy: bool,
+    pub backend: &'static str,
+    pub primary: PoolStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replica: Option<PoolStats>,
+}
+
+// Point-in-time statistics for one pool
+#[derive(Debug, Serialize)]
+pub struct PoolStats {
+    pub reachable: bool,
+    // Open connections, busy or idle
+    pub size: u32,
+    pub idle: usize,
+    pub in_use: usize,
+    pub max_connections: u32,
+    // Time the health probe waited for a connection, a proxy for pool contention
+    pub acquire_wait_ms: u64,
+    // Round trip of a ping once connected
+    pub ping_ms: Option<u64>,
+}
+
+async fn probe<DB: sqlx::Database>(pool: &Pool<DB>) -> PoolStats {
+    let started = Instant::now();
+    let acquired = pool.acquire().await;
+    let acquire_wait_ms = started.elapsed().as_millis() as u64;
+
+    let result = match acquired {
+        Ok(mut conn) => {
+            let started = Instant::now();
+            conn.ping().await.map(|_| started.elapsed().as_millis() as u64)
+        }
+        Err(err) => Err(err),
+    };
+    // The driver's message can name hosts and users, so it's only logged;
+    // the endpoint is unauthenticated
+    let ping_ms = match result {
+        Ok(ms) => Some(ms),
+        Err(err) => {
+            tracing::warn!(error = %err, "Database health probe failed");
+            None
+        }
+    };
+
+    // Sample after the probe connection is back in the pool
+    let size = pool.size();
+    let idle = pool.num_idle();
+
+    PoolStats {
+        reachable: ping_ms.is_some(),
+        size,
+        idle,
+        in_use: (size as usize).saturating_sub(idle),
+        max_connections: pool.options().get_max_connections(),
+        acquire_wait_ms,
+        ping_ms,
+    }
+}
+
+// Connect using DATABASE_URL and the pool settings from the environment
+pub async fn create_db_pool(config: &DatabaseConfig) -> Result<Database, sqlx::Error> {
+    if config.url.is_none() {
+        return Err(sqlx::Error::Configuration(
+            "DATABASE_URL environment variable is required".into(),
+        ));
+    }
+
+    connect(config).await
+}
+
+// Connect to the database named by `config.url`. The scheme picks the engine:
+// `postgres://` or `postgresql://` for PostgreSQL, `sqlite:` for a local file.
+pub async fn connect(config: &DatabaseConfig) -> Result<Database, sqlx::Error> {
+    let url = config.url.as_deref().unwrap_or_default();
+
+    tracing::info!("Connecting to database...");
+
+    let database = if url.starts_with("sqlite:") {
+        if config.replica_url.is_some() {
+            tracing::warn!("DATABASE_REPLICA_URL is ignored for SQLite");
+        }
+        Database::Sqlite(Pools::new(connect_sqlite(url, config).await?))
+    } else if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        let mut pools = Pools::new(connect_postgres(url, config).await?);
+        if let Some(replica_url) = &config.replica_url {
+            pools.replica = Some(connect_postgres(replica_url, config).await?);
+            tracing::info!("Read replica connected");
+        }
+        Database::Postgres(pools)
+    } else {
+        return Err(sqlx::Error::Configuration(
+            "DATABASE_URL must start with postgres://, postgresql:// or sqlite:".into(),
+        ));
+    };
+
+    tracing::info!(backend = database.backend_name(), "Database connection successful");
+
+    // Schema changes are applied by the embedded migrations in `migrate`,
+    // either via `rota-server migrate up` or AUTO_MIGRATE on startup
+
+    Ok(database)
+}
+
+// Pool options shared by both engines
+fn pool_options<DB: sqlx::Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    PoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .max_lifetime(config.max_lifetime)
+}
+
+async fn connect_postgres(url: &str, config: &DatabaseConfig) -> Result<DbPool, sqlx::Error> {
+    let mut options = PgConnectOptions::from_str(url)?;
+    if let Some(timeout) = config.statement_timeout {
+        options = options.options([("statement_timeout", timeout.as_millis().to_string())]);
+    }
+
+    // Create a connection pool
+    let pool: PgPoolOptions = pool_options(config);
+    let pool = pool.connect_with(options).await?;
+
+    // Test the connection
+    sqlx::query("SELECT 1")
+        .execute(&pool)
+        .await?;
+
+    Ok(pool)
+}
+
+async fn connect_sqlite(url: &str, config: &DatabaseConfig) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(config.acquire_timeout);
+
+    // Every connection to `sqlite::memory:` opens a separate empty database,
+    // so an in-memory pool must hold exactly one connection and never recycle it
+    let pool: SqlitePoolOptions = if url.contains(":memory:") || url.contains("mode=memory") {
+        pool_options(config)
+            .max_connections(1)
+            .min_connections(0)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        pool_options(config)
+    };
+
+    pool.connect_with(options).await
+}
@@ -0,0 +1,530 @@
+//! Field-level envelope encryption for sensitive personal data.
+//!
+//! Each value is encrypted with its own random data key (AES-256-GCM), and the
+//! data key is wrapped with a key-encryption key (KEK) read from a key file.
+//! Rotating the KEK only needs the small wrapped keys rewritten, which the
+//! `rota-server reencrypt` command does. Every value is bound to the table,
+//! column and row it was written to, so a ciphertext copied elsewhere won't
+//! decrypt. Equality lookups on encrypted fields go through blind indexes: a
+//! keyed HMAC of the normalised plaintext.
+//!
+//! The key file holds one `name = base64` pair per line:
+//!
+//! ```text
+//! # KEKs by version; the highest version encrypts new values
+//! kek.1 = 3q2+7wAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=
+//! kek.2 = ...
+//! # Key for blind indexes
+//! index = ...
+//! ```
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    AeadCore, Aes256Gcm, Key, Nonce,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use rota_core::user::{normalise_phone, EmergencyContact, PersonalDetails};
+
+use crate::database::Database;
+
+// Prefix of every ciphertext, so the format can evolve
+const FORMAT: &str = "v1";
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug)]
+pub enum KeyringError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyringError::Io(err) => write!(f, "Can't read key file: {}", err),
+            KeyringError::Invalid(msg) => write!(f, "Invalid key file: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for KeyringError {}
+
+#[derive(Debug, PartialEq)]
+pub enum CryptoError {
+    // A sensitive field was written without a key file configured
+    NotConfigured,
+    Malformed,
+    // The value was encrypted under a KEK that is no longer in the key file
+    UnknownKey(u32),
+    // Authentication failed: wrong key or tampered ciphertext
+    Decrypt,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::NotConfigured => {
+                write!(f, "Encrypted fields need ENCRYPTION_KEYS_FILE to be configured")
+            }
+            CryptoError::Malformed => write!(f, "Malformed ciphertext"),
+            CryptoError::UnknownKey(version) => write!(f, "Unknown key version {}", version),
+            CryptoError::Decrypt => write!(f, "Decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+// Key-encryption keys by version, plus the blind index key
+#[derive(Clone)]
+pub struct Keyring {
+    keks: BTreeMap<u32, [u8; 32]>,
+    index_key: [u8; 32],
+}
+
+// Never print key material
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("kek_versions", &self.keks.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Keyring {
+    pub fn load(path: &Path) -> Result<Self, KeyringError> {
+        let text = std::fs::read_to_string(path).map_err(KeyringError::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, KeyringError> {
+        let mut keks = BTreeMap::new();
+        let mut index_key = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid =
+                |msg: &str| KeyringError::Invalid(format!("line {}: {}", number + 1, msg));
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected name = key"))?;
+            let key: [u8; 32] = STANDARD
+                .decode(value.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| invalid("key must be 32 bytes of base64"))?;
+
+            match name.trim() {
+                "index" => index_key = Some(key),
+                name => {
+                    let version = name
+                        .strip_prefix("kek.")
+                        .and_then(|v| v.parse().ok())
+                        .ok_or_else(|| invalid("expected kek.<version> or index"))?;
+                    keks.insert(version, key);
+                }
+            }
+        }
+
+        if keks.is_empty() {
+            return Err(KeyringError::Invalid("no kek.<version> keys".to_string()));
+        }
+        let index_key =
+            index_key.ok_or_else(|| KeyringError::Invalid("no index key".to_string()))?;
+
+        Ok(Self { keks, index_key })
+    }
+
+    // A keyring with fresh random keys, for tests and throwaway databases
+    pub fn generate() -> Self {
+        Self {
+            keks: BTreeMap::from([(1, random_key())]),
+            index_key: random_key(),
+        }
+    }
+
+    // The KEK version used for new values
+    pub fn active_version(&self) -> u32 {
+        *self.keks.keys().next_back().expect("keyring has at least one KEK")
+    }
+
+    // Encrypt under a fresh data key wrapped with the active KEK, bound to
+    // `field`. The output is `v1.<kek version>.<wrapped data key>.<ciphertext>`.
+    pub fn encrypt(&self, plaintext: &str, field: &Field) -> String {
+        let dek = Aes256Gcm::generate_key(OsRng);
+        let payload = seal(&dek, plaintext.as_bytes(), &field.aad());
+
+        self.format(self.active_version(), &dek, &payload)
+    }
+
+    // Fails with `Decrypt` if the value was written to a different field
+    pub fn decrypt(&self, stored: &str, field: &Field) -> Result<String, CryptoError> {
+        let (version, wrapped, payload) = parse(stored)?;
+        let dek = self.unwrap_dek(version, &wrapped)?;
+        let plaintext = open(&dek, &payload, &field.aad())?;
+
+        String::from_utf8(plaintext).map_err(|_| CryptoError::Malformed)
+    }
+
+    // Re-wrap the data key under the active KEK, leaving the ciphertext alone.
+    // `None` if the value already uses the active KEK.
+    pub fn rewrap(&self, stored: &str) -> Result<Option<String>, CryptoError> {
+        let (version, wrapped, payload) = parse(stored)?;
+        if version == self.active_version() {
+            return Ok(None);
+        }
+
+        let dek = self.unwrap_dek(version, &wrapped)?;
+        Ok(Some(self.format(self.active_version(), &dek, &payload)))
+    }
+
+    // Deterministic keyed hash for equality lookups. Callers normalise first.
+    pub fn blind_index(&self, value: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.index_key)
+            .expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn format(&self, version: u32, dek: &Key<Aes256Gcm>, payload: &[u8]) -> String {
+        let kek = Key::<Aes256Gcm>::from_slice(&self.keks[&version]);
+        let wrapped = seal(kek, dek, &dek_aad(version));
+
+        format!(
+            "{}.{}.{}.{}",
+            FORMAT,
+            version,
+            URL_SAFE_NO_PAD.encode(wrapped),
+            URL_SAFE_NO_PAD.encode(payload)
+        )
+    }
+
+    fn unwrap_dek(&self, version: u32, wrapped: &[u8]) -> Result<Key<Aes256Gcm>, CryptoError> {
+        let kek = self.keks.get(&version).ok_or(CryptoError::UnknownKey(version))?;
+        let dek = open(Key::<Aes256Gcm>::from_slice(kek), wrapped, &dek_aad(version))?;
+        if dek.len() != 32 {
+            return Err(CryptoError::Malformed);
+        }
+
+        Ok(*Key::<Aes256Gcm>::from_slice(&dek))
+    }
+}
+
+// Where an encrypted value is stored; bound into its ciphertext
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub id: i64,
+}
+
+impl Field {
+    pub fn new(table: &'static str, column: &'static str, id: i64) -> Self {
+        Self { table, column, id }
+    }
+
+    fn aad(&self) -> Vec<u8> {
+        format!("rota-field:{}.{}:{}", self.table, self.column, self.id).into_bytes()
+    }
+}
+
+// A new random 256-bit key, base64 encoded for the key file
+pub fn generate_key() -> String {
+    STANDARD.encode(random_key())
+}
+
+fn random_key() -> [u8; 32] {
+    Aes256Gcm::generate_key(OsRng).into()
+}
+
+// Binds a wrapped data key to the KEK version it claims to use
+fn dek_aad(version: u32) -> Vec<u8> {
+    format!("rota-dek:{}", version).into_bytes()
+}
+
+// `nonce || ciphertext`
+fn seal(key: &Key<Aes256Gcm>, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .expect("AES-GCM encryption doesn't fail for in-memory buffers");
+
+    [nonce.as_slice(), &ciphertext].concat()
+}
+
+fn open(key: &Key<Aes256Gcm>, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    Aes256Gcm::new(key)
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+fn parse(stored: &str) -> Result<(u32, Vec<u8>, Vec<u8>), CryptoError> {
+    let mut parts = stored.split('.');
+    let (Some(FORMAT), Some(version), Some(wrapped), Some(payload), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(CryptoError::Malformed);
+    };
+
+    let version = version.parse().map_err(|_| CryptoError::Malformed)?;
+    let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| CryptoError::Malformed);
+
+    Ok((version, decode(wrapped)?, decode(payload)?))
+}
+
+// The encrypted columns for one user's personal details
+#[derive(Debug, Default)]
+pub struct SealedPersonal {
+    pub phone: Option<String>,
+    pub phone_bidx: Option<String>,
+    pub emergency_contact: Option<String>,
+    pub date_of_birth: Option<String>,
+}
+
+// Encrypt a user's personal details for storage. Fails only if there is
+// something to encrypt and no keyring to do it with.
+pub fn seal_personal(
+    keyring: Option<&Keyring>,
+    user_id: i64,
+    personal: &PersonalDetails,
+) -> Result<SealedPersonal, CryptoError> {
+    if *personal == PersonalDetails::default() {
+        return Ok(SealedPersonal::default());
+    }
+    let keyring = keyring.ok_or(CryptoError::NotConfigured)?;
+    let field = |column| Field::new("users", column, user_id);
+
+    let emergency_contact = personal
+        .emergency_contact
+        .as_ref()
+        .map(|contact| serde_json::to_string(contact).expect("contact serialises"));
+
+    Ok(SealedPersonal {
+        phone: personal.phone.as_deref().map(|p| keyring.encrypt(p, &field("phone"))),
+        phone_bidx: personal
+            .phone
+            .as_deref()
+            .map(|p| keyring.blind_index(&normalise_phone(p))),
+        emergency_contact: emergency_contact
+            .as_deref()
+            .map(|c| keyring.encrypt(c, &field("emergency_contact"))),
+        date_of_birth: personal
+            .date_of_birth
+            .map(|d| keyring.encrypt(&d.to_string(), &field("date_of_birth"))),
+    })
+}
+
+// Decrypt a user's personal details read from storage
+pub fn open_personal(
+    keyring: Option<&Keyring>,
+    user_id: i64,
+    phone: Option<String>,
+    emergency_contact: Option<String>,
+    date_of_birth: Option<String>,
+) -> Result<PersonalDetails, CryptoError> {
+    if phone.is_none() && emergency_contact.is_none() && date_of_birth.is_none() {
+        return Ok(PersonalDetails::default());
+    }
+    let keyring = keyring.ok_or(CryptoError::NotConfigured)?;
+    let decrypt = |value: Option<String>, column| {
+        value
+            .map(|v| keyring.decrypt(&v, &Field::new("users", column, user_id)))
+            .transpose()
+    };
+
+    Ok(PersonalDetails {
+        phone: decrypt(phone, "phone")?,
+        emergency_contact: decrypt(emergency_contact, "emergency_contact")?
+            .map(|json| serde_json::from_str::<EmergencyContact>(&json))
+            .transpose()
+            .map_err(|_| CryptoError::Malformed)?,
+        date_of_birth: decrypt(date_of_birth, "date_of_birth")?
+            .map(|date| date.parse())
+            .transpose()
+            .map_err(|_| CryptoError::Malformed)?,
+    })
+}
+
+// Encrypt the note given with a sickness leave request for storage
+pub fn seal_sickness_note(keyring: Option<&Keyring>, leave_id: i64, note: &str) -> Result<String, CryptoError> {
+    let keyring = keyring.ok_or(CryptoError::NotConfigured)?;
+
+    Ok(keyring.encrypt(note, &Field::new("leave_requests", "sickness_note", leave_id)))
+}
+
+// Decrypt a sickness note read from storage
+pub fn open_sickness_note(keyring: Option<&Keyring>, leave_id: i64, stored: &str) -> Result<String, CryptoError> {
+    let keyring = keyring.ok_or(CryptoError::NotConfigured)?;
+
+    keyring.decrypt(stored, &Field::new("leave_requests", "sickness_note", leave_id))
+}
+
+// Every encrypted column, as (table, column)
+pub const ENCRYPTED_FIELDS: &[(&str, &str)] = &[
+    ("users", "phone"),
+    ("users", "emergency_contact"),
+    ("users", "date_of_birth"),
+    ("leave_requests", "sickness_note"),
+];
+
+// What a re-encryption pass changed
+#[derive(Debug, Default, Serialize)]
+pub struct ReencryptReport {
+    pub active_key_version: u32,
+    // Values whose data key was re-wrapped under the active KEK
+    pub rewrapped: usize,
+    // Blind indexes recomputed because the index key changed
+    pub blind_indexes_refreshed: usize,
+    // Values left alone because they were rewritten while the pass ran,
+    // already under the active keys
+    pub skipped: usize,
+}
+
+#[derive(Debug)]
+pub enum ReencryptError {
+    Database(sqlx::Error),
+    Crypto { table: &'static str, id: i64, error: CryptoError },
+}
+
+impl fmt::Display for ReencryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReencryptError::Database(err) => write!(f, "Database error: {}", err),
+            ReencryptError::Crypto { table, id, error } => {
+                write!(f, "Can't re-encrypt {} {}: {}", table, id, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReencryptError {}
+
+impl From<sqlx::Error> for ReencryptError {
+    fn from(err: sqlx::Error) -> Self {
+        ReencryptError::Database(err)
+    }
+}
+
+// Move every encrypted value onto the active KEK and recompute blind indexes.
+// Run after adding a new `kek.N` (or replacing `index`) in the key file; old
+// KEKs can be removed from the file once this has finished. Row versions are
+// left alone since the plaintext doesn't change. The server can keep running:
+// a value it writes between being read here and being stored is kept.
+pub async fn reencrypt(db: &Database, keyring: &Keyring) -> Result<ReencryptReport, ReencryptError> {
+    let mut report = ReencryptReport {
+        active_key_version: keyring.active_version(),
+        ..ReencryptReport::default()
+    };
+
+    for &(table, column) in ENCRYPTED_FIELDS {
+        for (id, stored) in fetch_column(db, table, column).await? {
+            let rewrapped = keyring
+                .rewrap(&stored)
+                .map_err(|error| ReencryptError::Crypto { table, id, error })?;
+            if let Some(rewrapped) = rewrapped {
+                match store_column(db, table, column, id, Some(&stored), &rewrapped).await? {
+                    true => report.rewrapped += 1,
+                    false => report.skipped += 1,
+                }
+            }
+        }
+    }
+
+    let indexes = fetch_column(db, "users", "phone").await?;
+    let stored_indexes: BTreeMap<i64, String> =
+        fetch_column(db, "users", "phone_bidx").await?.into_iter().collect();
+    for (id, stored) in indexes {
+        let phone = keyring
+            .decrypt(&stored, &Field::new("users", "phone", id))
+            .map_err(|error| ReencryptError::Crypto { table: "users", id, error })?;
+        let index = keyring.blind_index(&normalise_phone(&phone));
+        let old = stored_indexes.get(&id).map(String::as_str);
+        if old != Some(index.as_str()) {
+            match store_column(db, "users", "phone_bidx", id, old, &index).await? {
+                true => report.blind_indexes_refreshed += 1,
+                false => report.skipped += 1,
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Non-null values of one column by row id
+async fn fetch_column(
+    db: &Database,
+    table: &str,
+    column: &str,
+) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let sql = format!(
+        "SELECT id, {column} FROM {table} WHERE {column} IS NOT NULL ORDER BY id",
+        column = column,
+        table = table
+    );
+
+    match db {
+        Database::Postgres(pools) => sqlx::query_as(&sql).fetch_all(&pools.primary).await,
+        Database::Sqlite(pools) => sqlx::query_as(&sql).fetch_all(&pools.primary).await,
+    }
+}
+
+// Replace one value if it still holds `old` (`None` for NULL), returning
+// whether it did. A value written since `old` was read is newer and is kept.
+pub(crate) async fn store_column(
+    db: &Database,
+    table: &str,
+    column: &str,
+    id: i64,
+    old: Option<&str>,
+    value: &str,
+) -> Result<bool, sqlx::Error> {
+    let guard = match old {
+        Some(_) => "= $3",
+        None => "IS NULL",
+    };
+    let sql = format!(
+        "UPDATE {table} SET {column} = $1 WHERE id = $2 AND {column} {guard}",
+        table = table,
+        column = column,
+        guard = guard
+    );
+
+    let updated = match db {
+        Database::Postgres(pools) => {
+            let query = sqlx::query(&sql).bind(value).bind(id);
+            let query = match old {
+                Some(old) => query.bind(old),
+                None => query,
+            };
+            query.execute(&pools.primary).await?.rows_affected()
+        }
+        Database::Sqlite(pools) => {
+            let query = sqlx::query(&sql).bind(value).bind(id);
+            let query = match old {
+                Some(old) => query.bind(old),
+                None => query,
+            };
+            query.execute(&pools.primary).await?.rows_affected()
+        }
+    };
+
+    Ok(updated > 0)
+}
@@ -0,0 +1,142 @@
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use rota_core::assignment::Conflict;
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound,
+    InternalServerError,
+    BadRequest(String),
+    Unauthorized,
+    Forbidden,
+    Conflict(String),
+    // A request was refused because of scheduling conflicts, all listed
+    Conflicts(String, Vec<Conflict>),
+    // A conditional write lost the race; carries the current representation
+    PreconditionFailed { version: i64, current: serde_json::Value },
+    // Something the request needs isn't set up on this server
+    ServiceUnavailable(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: u16,
+    // Machine-readable reasons, for errors with more than one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound => write!(f, "Resource not found"),
+            AppError::InternalServerError => write!(f, "Internal server error"),
+            AppError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
+            AppError::Unauthorized => write!(f, "Unauthorized"),
+            AppError::Forbidden => write!(f, "Forbidden"),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::Conflicts(msg, _) => write!(f, "Conflict: {}", msg),
+            AppError::PreconditionFailed { version, .. } => {
+                write!(f, "Precondition failed: current version is {}", version)
+            }
+            AppError::ServiceUnavailable(msg) => write!(f, "Service unavailable: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        // The client's copy is stale: send back what is stored now, with its ETag,
+        // so the client can merge without another round trip
+        if let AppError::PreconditionFailed { version, current } = self {
+            return (
+                StatusCode::PRECONDITION_FAILED,
+                crate::etag::etag_header(version),
+                Json(current),
+            )
+                .into_response();
+        }
+
+        let (status, error_message, conflicts) = match self {
+            AppError::NotFound => (StatusCode::NOT_FOUND, self.to_string(), Vec::new()),
+            AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string(), Vec::new()),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg, Vec::new()),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string(), Vec::new()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string(), Vec::new()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg, Vec::new()),
+            AppError::Conflicts(msg, conflicts) => (StatusCode::CONFLICT, msg, conflicts),
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg, Vec::new()),
+            AppError::PreconditionFailed { .. } => unreachable!("handled above"),
+        };
+
+        let body = Json(ErrorResponse {
+            error: error_message,
+            code: status.as_u16(),
+            conflicts,
+        });
+
+        (status, body).into_response()
+    }
+}
+
+impl AppError {
+    pub fn precondition_failed<T: Serialize>(version: i64, current: &T) -> Self {
+        match serde_json::to_value(current) {
+            Ok(current) => AppError::PreconditionFailed { version, current },
+            Err(_) => AppError::InternalServerError,
+        }
+    }
+}
+
+// Implement From<std::io::Error> for AppError
+impl From<std::io::Error> for AppError {
+    fn from(_: std::io::Error) -> Self {
+        AppError::InternalServerError
+    }
+}
+
+// Database failures are logged and surfaced as a generic 500
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        tracing::error!("Database error: {}", err);
+        AppError::InternalServerError
+    }
+}
+
+// Domain rule violations are the client's fault
+impl From<rota_core::ValidationError> for AppError {
+    fn from(err: rota_core::ValidationError) -> Self {
+        AppError::BadRequest(err.0)
+    }
+}
+
+impl From<crate::repo::RepoError> for AppError {
+    fn from(err: crate::repo::RepoError) -> Self {
+        match err {
+            crate::repo::RepoError::NotFound => AppError::NotFound,
+            crate::repo::RepoError::Conflict(msg) => AppError::Conflict(msg),
+            crate::repo::RepoError::StaleVersion => {
+                AppError::Conflict("Resource was modified by another request".to_string())
+            }
+            // Sensitive fields can't be read or written until a key file is set up
+            crate::repo::RepoError::Encryption(err @ crate::encryption::CryptoError::NotConfigured) => {
+                AppError::ServiceUnavailable(err.to_string())
+            }
+            crate::repo::RepoError::Encryption(err) => {
+                tracing::error!("Encryption error: {}", err);
+                AppError::InternalServerError
+            }
+            crate::repo::RepoError::Database(err) => err.into(),
+        }
+    }
+}
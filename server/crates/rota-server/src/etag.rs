@@ -0,0 +1,174 @@
+//! Conditional requests for versioned resources.
+//!
+//! Every mutable resource carries a row version. Responses expose it as a
+//! strong `ETag`, writes honour `If-Match` so two editors can't silently
+//! overwrite each other, and reads honour `If-None-Match` with 304.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{
+        header::{ETAG, IF_MATCH, IF_NONE_MATCH},
+        request::Parts,
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::convert::Infallible;
+
+use crate::error::AppError;
+
+// The entity tag for a given row version
+pub fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+// Parsed `If-Match` / `If-None-Match` header value
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl Condition {
+    fn parse(headers: &HeaderMap, name: axum::http::HeaderName) -> Option<Self> {
+        let values: Vec<&str> = headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+
+        let tags: Vec<String> = values
+            .iter()
+            .flat_map(|v| v.split(','))
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if tags.iter().any(|t| t == "*") {
+            Some(Condition::Any)
+        } else {
+            Some(Condition::Tags(tags))
+        }
+    }
+
+    // Strong comparison for If-Match, weak for If-None-Match (RFC 9110)
+    fn matches(&self, version: i64, weak: bool) -> bool {
+        let current = etag(version);
+        match self {
+            Condition::Any => true,
+            Condition::Tags(tags) => tags.iter().any(|tag| {
+                if weak {
+                    tag.trim_start_matches("W/") == current
+                } else {
+                    tag == &current
+                }
+            }),
+        }
+    }
+}
+
+// Extracts the optional `If-Match` precondition of a write
+#[derive(Debug, Clone)]
+pub struct IfMatch(Option<Condition>);
+
+impl IfMatch {
+    // Fail with 412 and the current representation if the client's copy is stale
+    pub fn check<T: Serialize>(&self, version: i64, current: &T) -> Result<(), AppError> {
+        match &self.0 {
+            Some(condition) if !condition.matches(version, false) => {
+                Err(AppError::precondition_failed(version, current))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(IfMatch(Condition::parse(&parts.headers, IF_MATCH)))
+    }
+}
+
+// Extracts the optional `If-None-Match` header of a read
+#[derive(Debug, Clone)]
+pub struct IfNoneMatch(Option<Condition>);
+
+impl IfNoneMatch {
+    // True if the client already holds this version
+    pub fn is_fresh(&self, version: i64) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|condition| condition.matches(version, true))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfNoneMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(IfNoneMatch(Condition::parse(&parts.headers, IF_NONE_MATCH)))
+    }
+}
+
+// A JSON body sent with the ETag of the version it represents
+pub struct Versioned<T> {
+    pub status: StatusCode,
+    pub version: i64,
+    pub body: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn ok(version: i64, body: T) -> Self {
+        Self {
+            status: StatusCode::OK,
+            version,
+            body,
+        }
+    }
+
+    pub fn created(version: i64, body: T) -> Self {
+        Self {
+            status: StatusCode::CREATED,
+            version,
+            body,
+        }
+    }
+
+    // Answer a read, replying 304 with no body if the client's copy is current
+    pub fn or_not_modified(self, if_none_match: &IfNoneMatch) -> Response
+    where
+        T: Serialize,
+    {
+        if if_none_match.is_fresh(self.version) {
+            return (StatusCode::NOT_MODIFIED, etag_header(self.version)).into_response();
+        }
+        self.into_response()
+    }
+}
+
+impl<T: Serialize> IntoResponse for Versioned<T> {
+    fn into_response(self) -> Response {
+        (self.status, etag_header(self.version), Json(self.body)).into_response()
+    }
+}
+
+pub(crate) fn etag_header(version: i64) -> [(axum::http::HeaderName, HeaderValue); 1] {
+    [(ETAG, HeaderValue::from_str(&etag(version)).expect("ETag is valid ASCII"))]
+}
@@ -0,0 +1,182 @@
+//! Subject access exports: everything held about one user, as a single JSON
+//! document or a ZIP with one file per section.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::io::Write;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    app::AppState,
+    error::AppError,
+    models::{
+        audit::{AuditEntry, AuditFilter},
+        availability::{AvailabilityException, WeeklyWindow},
+        leave::{LeaveFilter, LeaveRequest, LeaveStatus, LeaveType},
+        profile::Employment,
+        shift::Shift,
+        user::{PersonalDetails, User},
+    },
+    routes::{audit::all_audit, leave::all_leave},
+};
+
+// A shift the user was assigned to
+#[derive(Debug, Serialize)]
+pub struct ExportedShift {
+    pub id: i64,
+    pub rota_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub location: String,
+    pub position: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub time_zone: String,
+    pub unpaid_break_minutes: i32,
+    pub notes: String,
+    // Set if the shift has since been deleted
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl From<Shift> for ExportedShift {
+    fn from(shift: Shift) -> Self {
+        Self {
+            id: shift.id,
+            rota_id: shift.rota_id,
+            team_id: shift.team_id,
+            location: shift.location,
+            position: shift.position,
+            starts_at: shift.starts_at,
+            ends_at: shift.ends_at,
+            time_zone: shift.time_zone,
+            unpaid_break_minutes: shift.unpaid_break_minutes,
+            notes: shift.notes,
+            deleted_at: shift.deleted_at,
+        }
+    }
+}
+
+// A leave request the user made
+#[derive(Debug, Serialize)]
+pub struct ExportedLeave {
+    pub id: i64,
+    pub leave_type: LeaveType,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub status: LeaveStatus,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<LeaveRequest> for ExportedLeave {
+    fn from(request: LeaveRequest) -> Self {
+        Self {
+            id: request.id,
+            leave_type: request.leave_type,
+            start_date: request.start_date,
+            end_date: request.end_date,
+            status: request.status,
+            reason: request.reason,
+            created_at: request.created_at,
+        }
+    }
+}
+
+// When the user said they can and can't work
+#[derive(Debug, Serialize)]
+pub struct ExportedAvailability {
+    pub weekly: Vec<WeeklyWindow>,
+    pub exceptions: Vec<AvailabilityException>,
+}
+
+// Everything held about one user
+#[derive(Debug, Serialize)]
+pub struct ExportBundle {
+    pub generated_at: DateTime<Utc>,
+    pub profile: User,
+    // Decrypted personal details, which `profile` never serialises
+    pub personal: PersonalDetails,
+    // Contract and qualifications, if the user has a staff profile
+    pub employment: Option<Employment>,
+    pub shifts: Vec<ExportedShift>,
+    pub leave: Vec<ExportedLeave>,
+    pub availability: ExportedAvailability,
+    // Changes to the user's record and changes the user made
+    pub audit: Vec<AuditEntry>,
+}
+
+// Gather the bundle for `user`
+pub async fn collect(state: &AppState, user: User) -> Result<ExportBundle, AppError> {
+    let user_id = user.id;
+    let leave = LeaveFilter {
+        user: Some(user_id),
+        ..LeaveFilter::default()
+    };
+
+    Ok(ExportBundle {
+        generated_at: Utc::now(),
+        audit: audit_trail(state, user.id).await?,
+        personal: state.users.personal(user.id).await?,
+        employment: state.profiles.get(user.id).await?,
+        profile: user,
+        shifts: state.shifts.all_assigned_to(user_id).await?.into_iter().map(Into::into).collect(),
+        leave: all_leave(state, &leave).await?.into_iter().map(Into::into).collect(),
+        availability: ExportedAvailability {
+            weekly: state.availability.weekly(user_id).await?.map(|w| w.weekly).unwrap_or_default(),
+            exceptions: state.availability.exceptions(user_id, None, None).await?,
+        },
+    })
+}
+
+// Audit entries about the user or their profile, or made by them, oldest first
+async fn audit_trail(state: &AppState, user_id: i64) -> Result<Vec<AuditEntry>, AppError> {
+    let about = AuditFilter {
+        entity: Some("user".to_string()),
+        entity_id: Some(user_id.to_string()),
+        ..AuditFilter::default()
+    };
+    let profile = AuditFilter {
+        entity: Some("profile".to_string()),
+        entity_id: Some(user_id.to_string()),
+        ..AuditFilter::default()
+    };
+    let by = AuditFilter {
+        actor: Some(user_id.to_string()),
+        ..AuditFilter::default()
+    };
+
+    let mut entries = all_audit(state, &about).await?;
+    entries.extend(all_audit(state, &profile).await?);
+    entries.extend(all_audit(state, &by).await?);
+    entries.sort_by_key(|e| e.id);
+    entries.dedup_by_key(|e| e.id);
+
+    Ok(entries)
+}
+
+// Pack the bundle as a ZIP with one pretty-printed JSON file per section
+pub fn to_zip(bundle: &ExportBundle) -> Result<Vec<u8>, AppError> {
+    let sections = [
+        ("profile.json", serde_json::to_value(&bundle.profile)),
+        ("personal.json", serde_json::to_value(&bundle.personal)),
+        ("employment.json", serde_json::to_value(&bundle.employment)),
+        ("shifts.json", serde_json::to_value(&bundle.shifts)),
+        ("leave.json", serde_json::to_value(&bundle.leave)),
+        ("availability.json", serde_json::to_value(&bundle.availability)),
+        ("audit.json", serde_json::to_value(&bundle.audit)),
+    ];
+
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, section) in sections {
+        let json = section
+            .and_then(|value| serde_json::to_vec_pretty(&value))
+            .map_err(|_| AppError::InternalServerError)?;
+        zip.start_file(name, options)
+            .map_err(|_| AppError::InternalServerError)?;
+        zip.write_all(&json)?;
+    }
+
+    let cursor = zip.finish().map_err(|_| AppError::InternalServerError)?;
+    Ok(cursor.into_inner())
+}
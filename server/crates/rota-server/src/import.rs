@@ -0,0 +1,262 @@
+//! Bulk staff import and export as CSV.
+//!
+//! An import file starts with a header row naming its columns; `name` and
+//! `email` are required and the rest may be left out, in which case existing
+//! users keep what they have. Rows are matched to users by email. Every row is
+//! checked before anything is written, and problems are reported with the line
+//! they were found on so the file can be fixed and sent again.
+
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+use rota_core::user::{normalise_skills, validate_contracted_hours, validate_new_user};
+use serde::Serialize;
+
+use crate::{
+    error::AppError,
+    models::{
+        team::Team,
+        user::{User, UserRole, UserUpsert},
+    },
+};
+
+// Columns in the order the export writes them
+pub const COLUMNS: &[&str] = &["name", "email", "role", "team", "contracted_hours", "skills"];
+
+// Skills share one cell, separated by this
+const SKILL_SEPARATOR: char = ';';
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowAction {
+    Create,
+    Update,
+    Unchanged,
+}
+
+// What happened, or would happen, to one row
+#[derive(Debug, Serialize)]
+pub struct RowOutcome {
+    pub line: u64,
+    pub email: String,
+    pub action: RowAction,
+}
+
+// A problem with one row. `field` is absent when the row as a whole is wrong.
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    pub line: u64,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl RowError {
+    pub fn new(line: u64, field: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            field: field.map(str::to_string),
+            message: message.into(),
+        }
+    }
+}
+
+// The response to an import
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    // Whether anything was written
+    pub applied: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub rows: Vec<RowOutcome>,
+    pub errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    pub fn new(dry_run: bool, applied: bool, rows: Vec<RowOutcome>, errors: Vec<RowError>) -> Self {
+        let count = |action| rows.iter().filter(|row| row.action == action).count();
+
+        Self {
+            dry_run,
+            applied,
+            created: count(RowAction::Create),
+            updated: count(RowAction::Update),
+            unchanged: count(RowAction::Unchanged),
+            rows,
+            errors,
+        }
+    }
+}
+
+// A row that passed every check
+#[derive(Debug)]
+pub struct ParsedRow {
+    pub line: u64,
+    pub user: UserUpsert,
+}
+
+// An import file split into good rows and problems
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    pub rows: Vec<ParsedRow>,
+    pub errors: Vec<RowError>,
+}
+
+// Header text in canonical form, so `Contracted Hours` matches `contracted_hours`
+fn normalise_header(header: &str) -> String {
+    header.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+// Check the file and turn its rows into upserts. Team names are resolved
+// against `teams`. A header that can't be understood fails the whole file;
+// anything wrong with a row is reported against that row.
+pub fn parse(text: &str, teams: &[Team]) -> Result<ParsedFile, AppError> {
+    let mut reader = ReaderBuilder::new().trim(Trim::All).from_reader(text.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| AppError::BadRequest(format!("Unreadable header row: {}", err)))?
+        .iter()
+        .map(normalise_header)
+        .collect();
+    for (i, header) in headers.iter().enumerate() {
+        if !COLUMNS.contains(&header.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown column '{}'; expected {}",
+                header,
+                COLUMNS.join(", ")
+            )));
+        }
+        if headers[..i].contains(header) {
+            return Err(AppError::BadRequest(format!("Column '{}' appears twice", header)));
+        }
+    }
+    for required in ["name", "email"] {
+        if !headers.iter().any(|h| h == required) {
+            return Err(AppError::BadRequest(format!("Missing required column '{}'", required)));
+        }
+    }
+    let column = |name: &str| headers.iter().position(|h| h == name);
+
+    let mut parsed = ParsedFile::default();
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, |p| p.line());
+                parsed.errors.push(RowError::new(line, None, err.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        let cell = |name: &str| column(name).map(|i| record.get(i).unwrap_or(""));
+
+        let mut errors = Vec::new();
+        let name = cell("name").unwrap_or_default().to_string();
+        let email = cell("email").unwrap_or_default().to_string();
+        if name.is_empty() {
+            errors.push(RowError::new(line, Some("name"), "Name cannot be empty"));
+        } else if let Err(err) = validate_new_user(&name, &email) {
+            errors.push(RowError::new(line, Some("email"), err.to_string()));
+        }
+
+        // A blank role leaves the role alone, so re-importing can't demote anyone
+        let role = match cell("role").filter(|r| !r.is_empty()) {
+            Some(role) => match role.to_lowercase().parse::<UserRole>() {
+                Ok(role) => Some(role),
+                Err(err) => {
+                    errors.push(RowError::new(line, Some("role"), err.to_string()));
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let team_id = cell("team").map(|team| {
+            if team.is_empty() {
+                return None;
+            }
+            match teams.iter().find(|t| t.name.eq_ignore_ascii_case(team)) {
+                Some(t) => Some(t.id),
+                None => {
+                    errors.push(RowError::new(line, Some("team"), format!("Unknown team: {}", team)));
+                    None
+                }
+            }
+        });
+
+        let contracted_hours = cell("contracted_hours").map(|hours| {
+            if hours.is_empty() {
+                return None;
+            }
+            match hours.parse::<f64>() {
+                Ok(hours) if hours.is_finite() => match validate_contracted_hours(hours) {
+                    Ok(()) => Some(hours),
+                    Err(err) => {
+                        errors.push(RowError::new(line, Some("contracted_hours"), err.to_string()));
+                        None
+                    }
+                },
+                _ => {
+                    errors.push(RowError::new(
+                        line,
+                        Some("contracted_hours"),
+                        "Contracted hours must be a number",
+                    ));
+                    None
+                }
+            }
+        });
+
+        let skills = cell("skills")
+            .map(|skills| normalise_skills(skills.split(SKILL_SEPARATOR).map(str::to_string)));
+
+        if let Some(first) = parsed.rows.iter().find(|row| row.user.email.eq_ignore_ascii_case(&email)) {
+            errors.push(RowError::new(
+                line,
+                Some("email"),
+                format!("Email already appears on line {}", first.line),
+            ));
+        }
+
+        if errors.is_empty() {
+            parsed.rows.push(ParsedRow {
+                line,
+                user: UserUpsert { name, email, role, team_id, contracted_hours, skills },
+            });
+        } else {
+            parsed.errors.extend(errors);
+        }
+    }
+
+    Ok(parsed)
+}
+
+// Write users as CSV with the same columns the import reads
+pub fn write(users: &[User], teams: &[Team]) -> Result<String, AppError> {
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+    writer.write_record(COLUMNS).map_err(|_| AppError::InternalServerError)?;
+
+    for user in users {
+        let team = user
+            .team_id
+            .and_then(|id| teams.iter().find(|t| t.id == id))
+            .map_or("", |t| t.name.as_str());
+        let hours = user.contracted_hours.map(|h| h.to_string()).unwrap_or_default();
+        let skills = user.skills.join(&SKILL_SEPARATOR.to_string());
+        let role = user.role.to_string();
+
+        writer
+            .write_record([
+                user.username.as_str(),
+                user.email.as_str(),
+                role.as_str(),
+                team,
+                hours.as_str(),
+                skills.as_str(),
+            ])
+            .map_err(|_| AppError::InternalServerError)?;
+    }
+
+    let bytes = writer.into_inner().map_err(|_| AppError::InternalServerError)?;
+    String::from_utf8(bytes).map_err(|_| AppError::InternalServerError)
+}
@@ -0,0 +1,26 @@
+//! HTTP API for rota management.
+//!
+//! Exposes [`app::build_app`] so the router can be served by the
+//! `rota-server` binary or embedded in other tools and tests.
+
+pub mod app;
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod database;
+pub mod encryption;
+pub mod error;
+pub mod etag;
+pub mod export;
+pub mod import;
+pub mod middleware;
+pub mod migrate;
+pub mod models;
+pub mod pagination;
+pub mod patch;
+pub mod repo;
+pub mod retention;
+pub mod routes;
+
+#[cfg(test)]
+mod tests;
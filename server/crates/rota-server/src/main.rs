@@ -0,0 +1,173 @@
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use rota_server::{
+    app::{build_app, AppState},
+    config::Config,
+    database::create_db_pool,
+    audit::Audit,
+    encryption, migrate, retention,
+};
+
+#[derive(Parser)]
+#[command(name = "rota-server", about = "Rota management API server")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP server (the default)
+    Serve,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Purge or anonymise soft-deleted records past their retention period, once
+    Retention,
+    /// Re-wrap every encrypted field under the newest key in ENCRYPTION_KEYS_FILE
+    /// and refresh blind indexes
+    Reencrypt,
+    /// Print a fresh random key for the encryption key file
+    GenerateKey,
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the most recently applied migration
+    Down,
+    /// List migrations and whether each has been applied
+    Status,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Load configuration from the environment
+    let config = Config::from_env();
+
+    // Initialize tracing for logging
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(&config.log_level))
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let result = match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config).await,
+        Command::Migrate { action } => run_migrate(&config, action).await,
+        Command::Retention => run_retention(config).await,
+        Command::Reencrypt => run_reencrypt(config).await,
+        Command::GenerateKey => {
+            println!("{}", encryption::generate_key());
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            tracing::error!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn serve(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    // Connect to the database and make sure the schema is usable
+    let db = create_db_pool(&config.database).await?;
+    migrate::prepare_schema(&db, config.auto_migrate).await?;
+
+    // Build our application with routes and shared state
+    let addr = config.socket_addr();
+    let state = AppState::from_database(db, config);
+
+    if let Some(interval) = state.config.retention.interval {
+        tokio::spawn(retention::schedule(
+            state.clone(),
+            state.config.retention.clone(),
+            interval,
+        ));
+    }
+
+    let app = build_app(state);
+
+    // Start the server
+    tracing::info!("Server listening on {}", addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+async fn run_migrate(config: &Config, action: MigrateAction) -> Result<(), Box<dyn std::error::Error>> {
+    let db = create_db_pool(&config.database).await?;
+
+    match action {
+        MigrateAction::Up => {
+            migrate::up(&db).await?;
+            println!("Schema is at version {}", migrate::latest_version(migrate::migrator_for(&db)));
+        }
+        MigrateAction::Down => match migrate::down(&db).await? {
+            Some(version) => println!("Reverted migration {}", version),
+            None => println!("No migrations to revert"),
+        },
+        MigrateAction::Status => {
+            for m in migrate::status(&db).await? {
+                let state = if m.applied { "applied" } else { "pending" };
+                println!("{:>6}  {:<8}  {}", m.version, state, m.description);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_retention(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let db = create_db_pool(&config.database).await?;
+    migrate::prepare_schema(&db, config.auto_migrate).await?;
+
+    let policy = config.retention.clone();
+    let state = AppState::from_database(db, config);
+    let report = retention::run(&state, &policy).await?;
+
+    println!("Anonymised {} users", report.users_anonymised);
+    println!(
+        "Purged {} rotas, {} shifts, {} leave requests",
+        report.rotas_purged, report.shifts_purged, report.leave_requests_purged
+    );
+
+    Ok(())
+}
+
+async fn run_reencrypt(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let keyring = config
+        .encryption
+        .keyring
+        .clone()
+        .ok_or("ENCRYPTION_KEYS_FILE must be set to re-encrypt")?;
+
+    let db = create_db_pool(&config.database).await?;
+    migrate::prepare_schema(&db, config.auto_migrate).await?;
+
+    let report = encryption::reencrypt(&db, &keyring).await?;
+
+    let state = AppState::from_database(db, config);
+    Audit::system(state.audit.clone(), "system:reencrypt")
+        .record("encryption", report.active_key_version, "reencrypt", None, Some(&report))
+        .await?;
+
+    println!("Active key version {}", report.active_key_version);
+    println!(
+        "Re-wrapped {} values, refreshed {} blind indexes, skipped {} written meanwhile",
+        report.rewrapped, report.blind_indexes_refreshed, report.skipped
+    );
+
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use axum::{
     body::Body,
-    http::{Request, Response, HeaderValue},
+    http::{Request, HeaderValue},
     middleware::Next,
+    response::Response,
 };
 use uuid::Uuid;
 
 pub async fn request_id_middleware(
     mut request: Request<Body>,
-    next: Next,
-) -> Response<Body> {
+    next: Next<Body>,
+) -> Response {
     // Generate a UUID for the request
     let request_id = Uuid::new_v4().to_string();
     
@@ -0,0 +1,39 @@
+use axum::{
+    body::Body,
+    http::{Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{database, error::AppError};
+
+// Posts that never write, or that open their own transaction around the
+// write once the password hashing is done. On SQLite a transaction holds the
+// only writer's turn, so bcrypt inside one would queue every other write.
+const OUTSIDE_TRANSACTION: &[&str] = &[
+    "/api/auth/login",
+    "/api/auth/refresh",
+    "/api/auth/logout",
+    "/api/me/password",
+];
+
+// Run every request that can change data in one database transaction, so a
+// change and its audit entries are stored together or not at all. It commits
+// when the response is a success and rolls back on any error status.
+pub async fn transaction_middleware(request: Request<Body>, next: Next<Body>) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || OUTSIDE_TRANSACTION.contains(&request.uri().path())
+    {
+        return next.run(request).await;
+    }
+
+    let outcome = database::atomic_if(next.run(request), |response: &Response| {
+        !response.status().is_client_error() && !response.status().is_server_error()
+    })
+    .await;
+
+    match outcome {
+        Ok(response) => response,
+        Err(err) => AppError::from(err).into_response(),
+    }
+}
@@ -0,0 +1,181 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use std::fmt;
+
+use crate::database::Database;
+
+// Versioned schema migrations, embedded into the binary at compile time.
+// Each engine has its own SQL, but versions must stay in lockstep.
+pub static PG_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+// The migration set for the engine behind `db`
+pub fn migrator_for(db: &Database) -> &'static Migrator {
+    match db {
+        Database::Postgres(_) => &PG_MIGRATOR,
+        Database::Sqlite(_) => &SQLITE_MIGRATOR,
+    }
+}
+
+// Errors raised while inspecting or changing the schema
+#[derive(Debug)]
+pub enum SchemaError {
+    Migrate(MigrateError),
+    // The database has migrations applied that this binary doesn't know about,
+    // typically because a newer release already ran against it
+    NewerThanBinary { applied: i64, supported: i64 },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Migrate(err) => write!(f, "Migration failed: {}", err),
+            SchemaError::NewerThanBinary { applied, supported } => write!(
+                f,
+                "Database schema version {} is newer than the latest version {} supported by this binary",
+                applied, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<MigrateError> for SchemaError {
+    fn from(err: MigrateError) -> Self {
+        SchemaError::Migrate(err)
+    }
+}
+
+impl From<sqlx::Error> for SchemaError {
+    fn from(err: sqlx::Error) -> Self {
+        SchemaError::Migrate(MigrateError::Execute(err))
+    }
+}
+
+// One line of `migrate status` output
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+// Latest schema version embedded in this binary
+pub fn latest_version(migrator: &Migrator) -> i64 {
+    migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0)
+}
+
+// Refuse to run against a schema that has versions this binary can't account for
+pub fn check_compatible(migrator: &Migrator, applied: &[i64]) -> Result<(), SchemaError> {
+    let supported = latest_version(migrator);
+
+    match applied.iter().copied().max() {
+        Some(newest) if newest > supported => Err(SchemaError::NewerThanBinary {
+            applied: newest,
+            supported,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Versions currently recorded in the migrations table
+pub async fn applied_versions(db: &Database) -> Result<Vec<i64>, SchemaError> {
+    let applied = match db {
+        Database::Postgres(pools) => {
+            let mut conn = pools.primary.acquire().await?;
+            conn.ensure_migrations_table().await?;
+            conn.list_applied_migrations().await?
+        }
+        Database::Sqlite(pools) => {
+            let mut conn = pools.primary.acquire().await?;
+            conn.ensure_migrations_table().await?;
+            conn.list_applied_migrations().await?
+        }
+    };
+
+    Ok(applied.into_iter().map(|m| m.version).collect())
+}
+
+async fn run(db: &Database) -> Result<(), SchemaError> {
+    match db {
+        Database::Postgres(pools) => PG_MIGRATOR.run(&pools.primary).await?,
+        Database::Sqlite(pools) => SQLITE_MIGRATOR.run(&pools.primary).await?,
+    }
+    Ok(())
+}
+
+async fn undo(db: &Database, target: i64) -> Result<(), SchemaError> {
+    match db {
+        Database::Postgres(pools) => PG_MIGRATOR.undo(&pools.primary, target).await?,
+        Database::Sqlite(pools) => SQLITE_MIGRATOR.undo(&pools.primary, target).await?,
+    }
+    Ok(())
+}
+
+// Apply every pending migration
+pub async fn up(db: &Database) -> Result<(), SchemaError> {
+    check_compatible(migrator_for(db), &applied_versions(db).await?)?;
+    run(db).await
+}
+
+// Revert the most recently applied migration, returning its version
+pub async fn down(db: &Database) -> Result<Option<i64>, SchemaError> {
+    let applied = applied_versions(db).await?;
+    check_compatible(migrator_for(db), &applied)?;
+
+    let Some(&newest) = applied.iter().max() else {
+        return Ok(None);
+    };
+    let target = applied.iter().copied().filter(|v| *v < newest).max().unwrap_or(0);
+
+    undo(db, target).await?;
+    Ok(Some(newest))
+}
+
+// Every embedded migration, marked with whether it has been applied
+pub async fn status(db: &Database) -> Result<Vec<MigrationStatus>, SchemaError> {
+    let applied = applied_versions(db).await?;
+    Ok(status_of(migrator_for(db), &applied))
+}
+
+fn status_of(migrator: &Migrator, applied: &[i64]) -> Vec<MigrationStatus> {
+    migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            applied: applied.contains(&m.version),
+        })
+        .collect()
+}
+
+// Startup hook: verify the schema and optionally bring it up to date
+pub async fn prepare_schema(db: &Database, auto_migrate: bool) -> Result<(), SchemaError> {
+    let applied = applied_versions(db).await?;
+    check_compatible(migrator_for(db), &applied)?;
+
+    if auto_migrate {
+        tracing::info!("Applying pending database migrations");
+        return run(db).await;
+    }
+
+    let pending: Vec<_> = status_of(migrator_for(db), &applied)
+        .into_iter()
+        .filter(|m| !m.applied)
+        .map(|m| m.version)
+        .collect();
+    if !pending.is_empty() {
+        tracing::warn!(
+            ?pending,
+            "Database has pending migrations; run `rota-server migrate up` or set AUTO_MIGRATE=true"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::assignment::{ApprovedLeave, Conflict, ConflictCode};
+
+// A person working a shift. `conflicts` lists what was overridden when the
+// assignment was forced, and is empty otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub id: i64,
+    pub shift_id: i64,
+    pub user_id: i64,
+    // Subject of the token that made the assignment
+    pub assigned_by: Option<String>,
+    pub forced: bool,
+    pub override_reason: Option<String>,
+    pub conflicts: Vec<Conflict>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Everything needed to store an assignment
+#[derive(Debug, Clone)]
+pub struct NewAssignment {
+    pub shift_id: i64,
+    pub user_id: i64,
+    pub assigned_by: Option<String>,
+    pub forced: bool,
+    pub override_reason: Option<String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+// Body of `POST /api/shifts/:id/assignments`. With `force` the assignment is
+// made despite conflicts, which come back as warnings.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssignmentRequest {
+    pub user_id: i64,
+    #[serde(default)]
+    pub force: bool,
+    // Why conflicts were overridden, kept with the assignment
+    pub reason: Option<String>,
+}
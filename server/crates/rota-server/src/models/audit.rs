@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// A change about to be written to the audit log
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    // Subject of the access token that made the change, if any
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+    // Kind of record changed, e.g. "user" or "shift"
+    pub entity: String,
+    pub entity_id: String,
+    // What happened, e.g. "create", "update" or "delete"
+    pub action: String,
+    // Snapshots of the record either side of the change
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// A stored audit log entry. Entries are never updated or deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+    pub entity: String,
+    pub entity_id: String,
+    pub action: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+    // Hash of the previous entry, empty for the first one
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+// Query string filters for `GET /api/audit`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditFilter {
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub request_id: Option<String>,
+    // Inclusive lower and exclusive upper bound on `created_at`
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        fn eq(filter: &Option<String>, value: Option<&str>) -> bool {
+            filter.as_deref().is_none_or(|f| Some(f) == value)
+        }
+
+        eq(&self.entity, Some(&entry.entity))
+            && eq(&self.entity_id, Some(&entry.entity_id))
+            && eq(&self.actor, entry.actor.as_deref())
+            && eq(&self.action, Some(&entry.action))
+            && eq(&self.request_id, entry.request_id.as_deref())
+            && self.from.is_none_or(|from| entry.created_at >= from)
+            && self.to.is_none_or(|to| entry.created_at < to)
+    }
+}
+
+// Fields the audit log can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditSort {
+    Id,
+    CreatedAt,
+}
+
+impl AuditSort {
+    // Newest first; ids follow the order entries were chained in
+    pub const DEFAULT: &'static [SortKey<AuditSort>] = &[SortKey::desc(AuditSort::Id)];
+}
+
+impl SortField for AuditSort {
+    const ALL: &'static [Self] = &[Self::Id, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        self.name()
+    }
+}
+
+impl Sortable<AuditSort> for AuditEntry {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: AuditSort) -> SortValue {
+        match field {
+            AuditSort::Id => SortValue::Int(self.id),
+            AuditSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
+
+// An audit entry as returned by the API, with the fields that changed
+#[derive(Debug, Serialize)]
+pub struct AuditEntryResponse {
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+    pub changes: Value,
+}
+
+impl From<AuditEntry> for AuditEntryResponse {
+    fn from(entry: AuditEntry) -> Self {
+        let changes = crate::audit::diff(entry.before.as_ref(), entry.after.as_ref());
+        Self { entry, changes }
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::availability::{
+    Availability, AvailabilityException, AvailabilityKind, DayWindow, WeeklyWindow,
+};
+use rota_core::ValidationError;
+
+// Longest date range the team view covers, in days
+pub const MAX_TEAM_VIEW_DAYS: i64 = 62;
+
+// Someone's recurring weekly availability. Version 0 means nothing has been
+// saved yet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeeklyAvailability {
+    pub user_id: i64,
+    pub weekly: Vec<WeeklyWindow>,
+    pub version: i64,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl WeeklyAvailability {
+    // What someone who has never said anything has
+    pub fn empty(user_id: i64) -> Self {
+        Self {
+            user_id,
+            weekly: Vec::new(),
+            version: 0,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+// Body of `PUT /api/users/:id/availability`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeeklyRequest {
+    pub weekly: Vec<WeeklyWindow>,
+}
+
+// Body of `POST /api/users/:id/availability/exceptions`. Leave both times
+// out for the whole day.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExceptionRequest {
+    pub date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub kind: AvailabilityKind,
+    #[serde(default)]
+    pub note: String,
+}
+
+// Everything needed to store an exception
+#[derive(Debug, Clone)]
+pub struct NewException {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub kind: AvailabilityKind,
+    pub note: String,
+}
+
+impl NewException {
+    pub fn into_exception(self, id: i64, created_at: DateTime<Utc>) -> AvailabilityException {
+        AvailabilityException {
+            id,
+            user_id: self.user_id,
+            date: self.date,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            kind: self.kind,
+            note: self.note,
+            created_at,
+        }
+    }
+}
+
+// Query string of `GET /api/users/:id/availability/exceptions`; both dates
+// are inclusive and optional
+#[derive(Debug, Default, Deserialize)]
+pub struct ExceptionFilter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+// Query string of `GET /api/teams/:id/availability`; both dates inclusive
+#[derive(Debug, Deserialize)]
+pub struct TeamAvailabilityQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl TeamAvailabilityQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.to < self.from {
+            return Err(ValidationError::new("`to` can't be before `from`"));
+        }
+        if (self.to - self.from).num_days() >= MAX_TEAM_VIEW_DAYS {
+            return Err(ValidationError(format!("The team view covers at most {} days", MAX_TEAM_VIEW_DAYS)));
+        }
+        Ok(())
+    }
+}
+
+// One date in the team view, with the windows starting on it
+#[derive(Debug, Serialize)]
+pub struct AvailabilityDay {
+    pub date: NaiveDate,
+    pub windows: Vec<DayWindow>,
+}
+
+// One member of the team in the team view
+#[derive(Debug, Serialize)]
+pub struct MemberAvailability {
+    pub user_id: i64,
+    pub username: String,
+    pub days: Vec<AvailabilityDay>,
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use rota_core::ValidationError;
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// Where an erasure request is in its approval workflow
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErasureStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl Display for ErasureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErasureStatus::Pending => write!(f, "pending"),
+            ErasureStatus::Approved => write!(f, "approved"),
+            ErasureStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+impl FromStr for ErasureStatus {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ErasureStatus::Pending),
+            "approved" => Ok(ErasureStatus::Approved),
+            "rejected" => Ok(ErasureStatus::Rejected),
+            other => Err(ValidationError(format!("Unknown erasure status: {}", other))),
+        }
+    }
+}
+
+// A request to erase a user's personal data
+#[derive(Debug, Clone, Serialize)]
+pub struct ErasureRequest {
+    pub id: i64,
+    pub user_id: i64,
+    // Token subject of whoever raised the request
+    pub requested_by: String,
+    pub reason: String,
+    pub status: ErasureStatus,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewErasureRequest {
+    pub user_id: i64,
+    pub requested_by: String,
+    pub reason: String,
+}
+
+// Payload for raising an erasure request
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateErasureRequest {
+    #[serde(default)]
+    pub reason: String,
+}
+
+// Payload for approving or rejecting an erasure request
+#[derive(Debug, Default, Deserialize)]
+pub struct ErasureDecision {
+    #[serde(default)]
+    pub note: String,
+}
+
+// Query string for listing erasure requests
+#[derive(Debug, Default, Deserialize)]
+pub struct ErasureFilter {
+    pub status: Option<ErasureStatus>,
+}
+
+// Fields the erasure request list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErasureSort {
+    CreatedAt,
+}
+
+impl ErasureSort {
+    // Oldest first, so the queue is worked in order
+    pub const DEFAULT: &'static [SortKey<ErasureSort>] = &[SortKey::asc(ErasureSort::CreatedAt)];
+}
+
+impl SortField for ErasureSort {
+    const ALL: &'static [Self] = &[Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        self.name()
+    }
+}
+
+impl Sortable<ErasureSort> for ErasureRequest {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: ErasureSort) -> SortValue {
+        match field {
+            ErasureSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+pub use rota_core::leave::{
+    LeaveAction, LeaveBalance, LeaveEntitlement, LeaveRequest, LeaveStatus, LeaveType, LeaveYear, NewLeaveRequest,
+};
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// Body of `POST /api/leave`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaveRequestBody {
+    pub leave_type: LeaveType,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    #[serde(default)]
+    pub half_day_start: bool,
+    #[serde(default)]
+    pub half_day_end: bool,
+    #[serde(default)]
+    pub reason: String,
+    // Managers can set this, with an `override_reason`, to request leave
+    // despite blackouts and limits
+    #[serde(default)]
+    pub force: bool,
+    pub override_reason: Option<String>,
+}
+
+// Payload for approving, rejecting or cancelling a leave request. Approvers
+// can set `force`, with an `override_reason`, to approve despite blackouts
+// and limits.
+#[derive(Debug, Default, Deserialize)]
+pub struct LeaveDecision {
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub force: bool,
+    pub override_reason: Option<String>,
+}
+
+// Query string filters for `GET /api/leave`; every field is optional and the
+// dates pick requests sharing any day with them
+#[derive(Debug, Default, Deserialize)]
+pub struct LeaveFilter {
+    pub user: Option<i64>,
+    pub status: Option<LeaveStatus>,
+    pub leave_type: Option<LeaveType>,
+    pub approver: Option<i64>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl LeaveFilter {
+    pub fn matches(&self, request: &LeaveRequest) -> bool {
+        !request.is_deleted()
+            && self.user.is_none_or(|user| request.user_id == user)
+            && self.status.is_none_or(|status| request.status == status)
+            && self.leave_type.is_none_or(|leave_type| request.leave_type == leave_type)
+            && self.approver.is_none_or(|approver| request.approver_id == Some(approver))
+            && self.from.is_none_or(|from| from <= request.end_date)
+            && self.to.is_none_or(|to| request.start_date <= to)
+    }
+}
+
+// Fields the leave list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaveSort {
+    StartDate,
+    CreatedAt,
+}
+
+impl LeaveSort {
+    // By start date, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<LeaveSort>] = &[SortKey::asc(LeaveSort::StartDate)];
+}
+
+impl SortField for LeaveSort {
+    const ALL: &'static [Self] = &[Self::StartDate, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::StartDate => "start_date",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        self.name()
+    }
+}
+
+impl Sortable<LeaveSort> for LeaveRequest {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: LeaveSort) -> SortValue {
+        match field {
+            LeaveSort::StartDate => SortValue::Date(self.start_date),
+            LeaveSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
+
+// Body of `PUT /api/users/:id/leave/entitlements/:year/:leave_type`. Without
+// `carried_over`, what was left of the previous year's entitlement comes
+// across, up to `LEAVE_CARRY_OVER_DAYS`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntitlementRequest {
+    pub days: f64,
+    pub carried_over: Option<f64>,
+}
+
+// Query string of the entitlement and balance endpoints; the leave year
+// defaults to the current one
+#[derive(Debug, Default, Deserialize)]
+pub struct LeaveYearQuery {
+    pub year: Option<i32>,
+}
@@ -0,0 +1,86 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+pub use rota_core::leave_policy::{Blackout, LeaveLimit, NewBlackout, NewLeaveLimit};
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// Body of `POST /api/leave/blackouts`; give a team, a location or both
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlackoutRequest {
+    pub team_id: Option<i64>,
+    pub location: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub reason: String,
+}
+
+// Query string filters for `GET /api/leave/blackouts`; the dates pick
+// blackouts sharing any day with them
+#[derive(Debug, Default, Deserialize)]
+pub struct BlackoutFilter {
+    pub team: Option<i64>,
+    // Exact location, ignoring case
+    pub location: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl BlackoutFilter {
+    pub fn matches(&self, blackout: &Blackout) -> bool {
+        self.team.is_none_or(|team| blackout.team_id == Some(team))
+            && self.location.as_ref().is_none_or(|location| {
+                blackout.location.as_ref().is_some_and(|l| l.eq_ignore_ascii_case(location))
+            })
+            && self.from.is_none_or(|from| from <= blackout.end_date)
+            && self.to.is_none_or(|to| blackout.start_date <= to)
+    }
+}
+
+// Fields the blackout list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlackoutSort {
+    StartDate,
+}
+
+impl BlackoutSort {
+    // By start date, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<BlackoutSort>] = &[SortKey::asc(BlackoutSort::StartDate)];
+}
+
+impl SortField for BlackoutSort {
+    const ALL: &'static [Self] = &[Self::StartDate];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::StartDate => "start_date",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        self.name()
+    }
+}
+
+impl Sortable<BlackoutSort> for Blackout {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: BlackoutSort) -> SortValue {
+        match field {
+            BlackoutSort::StartDate => SortValue::Date(self.start_date),
+        }
+    }
+}
+
+// Body of `POST /api/teams/:id/leave-limits`. Leave out `skill` to cover the
+// whole team.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaveLimitRequest {
+    pub skill: Option<String>,
+    pub max_off: Option<u32>,
+    pub max_percent: Option<u32>,
+}
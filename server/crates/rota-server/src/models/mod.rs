@@ -0,0 +1,14 @@
+pub mod assignment;
+pub mod availability;
+pub mod pattern;
+pub mod audit;
+pub mod erasure;
+pub mod leave;
+pub mod leave_policy;
+pub mod profile;
+pub mod rota;
+pub mod shift;
+pub mod team;
+pub mod template;
+pub mod toil;
+pub mod user;
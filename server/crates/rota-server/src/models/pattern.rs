@@ -0,0 +1,182 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::pattern::{
+    NewPattern, Pattern, PatternMember, PatternOverride, PatternPreset, ProjectedDay,
+};
+use rota_core::ValidationError;
+
+use crate::{
+    models::assignment::Conflict,
+    pagination::{SortField, SortKey, SortValue, Sortable},
+};
+
+// Longest date range a projection can cover, in days
+pub const MAX_PROJECTION_DAYS: i64 = 366;
+
+// Body of `POST /api/patterns` and `PUT /api/patterns/:id`. The cycle is
+// given either day by day or as a preset filled with `templates`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternRequest {
+    pub team_id: i64,
+    pub name: String,
+    pub anchor_date: NaiveDate,
+    // Template id for each day of the cycle, `null` for a day off
+    pub cycle: Option<Vec<Option<i64>>>,
+    pub preset: Option<PatternPreset>,
+    #[serde(default)]
+    pub templates: Vec<i64>,
+}
+
+impl PatternRequest {
+    // Build the pattern's cycle. The result still needs validating.
+    pub fn into_new_pattern(self) -> Result<NewPattern, ValidationError> {
+        let cycle = match (self.cycle, self.preset) {
+            (Some(cycle), None) if self.templates.is_empty() => cycle,
+            (None, Some(preset)) => preset.cycle(&self.templates)?,
+            _ => return Err(ValidationError::new("Give either a cycle, or a preset and its templates")),
+        };
+
+        Ok(NewPattern {
+            team_id: self.team_id,
+            name: self.name.trim().to_string(),
+            anchor_date: self.anchor_date,
+            cycle,
+        })
+    }
+}
+
+// Query string filter for `GET /api/patterns`
+#[derive(Debug, Default, Deserialize)]
+pub struct PatternFilter {
+    pub team: Option<i64>,
+}
+
+// Fields the pattern list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternSort {
+    Name,
+    CreatedAt,
+}
+
+impl PatternSort {
+    // By name, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<PatternSort>] = &[SortKey::asc(PatternSort::Name)];
+}
+
+impl SortField for PatternSort {
+    const ALL: &'static [Self] = &[Self::Name, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "LOWER(name)",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl Sortable<PatternSort> for Pattern {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: PatternSort) -> SortValue {
+        match field {
+            PatternSort::Name => SortValue::Text(self.name.to_lowercase()),
+            PatternSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
+
+// Body of `PUT /api/patterns/:id/overrides`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverrideRequest {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    // The template worked instead, or `null` for a day off
+    pub template_id: Option<i64>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+// An override as stored, with who made it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoredOverride {
+    #[serde(flatten)]
+    pub change: PatternOverride,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Query string of `GET /api/patterns/:id/projection` and
+// `GET /api/patterns/:id/overrides`; both dates are inclusive
+#[derive(Debug, Deserialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.to < self.from {
+            return Err(ValidationError::new("`to` can't be before `from`"));
+        }
+        if (self.to - self.from).num_days() >= MAX_PROJECTION_DAYS {
+            return Err(ValidationError(format!("Ranges can cover at most {} days", MAX_PROJECTION_DAYS)));
+        }
+        Ok(())
+    }
+}
+
+// A projected working day with the times of its template on that date.
+// Times are absent when the template's start doesn't exist that day because
+// the clocks go forward.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectedShift {
+    #[serde(flatten)]
+    pub day: ProjectedDay,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+// Body of `POST /api/patterns/:id/apply`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApplyRequest {
+    pub rota_id: i64,
+}
+
+// One person's projected day in an apply report
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedDay {
+    pub user_id: i64,
+    pub date: NaiveDate,
+    pub template_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_id: Option<i64>,
+    // Why they weren't put on the shift
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// What applying a pattern to a rota did. People are never forced onto a
+// shift: anyone with conflicts is left off and reported.
+#[derive(Debug, Default, Serialize)]
+pub struct ApplyReport {
+    pub shifts_created: usize,
+    pub assigned: Vec<AppliedDay>,
+    pub already_assigned: Vec<AppliedDay>,
+    pub conflicts: Vec<AppliedDay>,
+    pub skipped: Vec<AppliedDay>,
+}
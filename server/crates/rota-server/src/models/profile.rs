@@ -0,0 +1,119 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::profile::{
+    ContractType, Ineligibility, Qualification, ShiftRequirements, StaffProfile,
+};
+
+use crate::models::user::User;
+
+// The employment record kept for a member of staff. Their home team, weekly
+// hours and skills live on the user; together they make a [`StaffProfile`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Employment {
+    pub user_id: i64,
+    pub contract_type: ContractType,
+    pub pay_grade: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    // Ordered by name
+    pub qualifications: Vec<Qualification>,
+    // 0 for a record that hasn't been saved yet
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Employment {
+    // The full profile of `user`, who this record belongs to
+    pub fn profile(&self, user: &User) -> StaffProfile {
+        StaffProfile {
+            user_id: user.id,
+            contract_type: self.contract_type,
+            weekly_hours: user.contracted_hours,
+            pay_grade: self.pay_grade.clone(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            home_team_id: user.team_id,
+            skills: user.skills.clone(),
+            qualifications: self.qualifications.clone(),
+        }
+    }
+}
+
+// Body of `PUT /api/users/:id/profile`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileRequest {
+    pub contract_type: ContractType,
+    pub weekly_hours: Option<f64>,
+    pub pay_grade: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub home_team_id: Option<i64>,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[serde(default)]
+    pub qualifications: Vec<Qualification>,
+}
+
+// A staff profile as the API returns it
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub profile: StaffProfile,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProfileResponse {
+    pub fn new(employment: &Employment, user: &User) -> Self {
+        Self {
+            profile: employment.profile(user),
+            version: employment.version,
+            created_at: employment.created_at,
+            updated_at: employment.updated_at,
+        }
+    }
+}
+
+// Query string for `GET /api/staff/eligibility`. Skills and qualifications
+// are comma-separated.
+#[derive(Debug, Deserialize)]
+pub struct EligibilityQuery {
+    pub date: NaiveDate,
+    // Only staff whose home team this is
+    pub team: Option<i64>,
+    pub skills: Option<String>,
+    pub qualifications: Option<String>,
+}
+
+impl EligibilityQuery {
+    pub fn requirements(&self) -> ShiftRequirements {
+        let list = |text: &Option<String>| -> Vec<String> {
+            text.as_deref()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        ShiftRequirements {
+            date: self.date,
+            skills: list(&self.skills),
+            qualifications: list(&self.qualifications),
+        }
+    }
+}
+
+// Whether one member of staff could work a shift, and if not why not
+#[derive(Debug, Serialize)]
+pub struct Eligibility {
+    pub user_id: i64,
+    pub name: String,
+    pub eligible: bool,
+    pub reasons: Vec<Ineligibility>,
+}
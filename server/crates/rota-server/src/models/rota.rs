@@ -0,0 +1,164 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use rota_core::rota::{NewRota, Rota, RotaAction, RotaLength, RotaStatus};
+
+use crate::models::assignment::Conflict;
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// Body of `POST /api/rotas`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotaRequest {
+    pub team_id: i64,
+    pub period_start: NaiveDate,
+    pub length: RotaLength,
+}
+
+// What staff see of a rota: its shifts and who was on them when it was last
+// published. Each shift is a `ShiftResponse` with an `assigned` list of user
+// ids.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotaSnapshot {
+    pub rota_id: i64,
+    pub revision: i64,
+    pub published_by: Option<String>,
+    pub published_at: DateTime<Utc>,
+    pub shifts: Value,
+}
+
+// Query string filters for `GET /api/rotas`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct RotaFilter {
+    pub team: Option<i64>,
+    pub status: Option<RotaStatus>,
+    // Rotas with any day on or after this date
+    pub from: Option<NaiveDate>,
+    // Rotas with any day on or before this date
+    pub to: Option<NaiveDate>,
+    // Set for staff, who never see drafts
+    #[serde(skip)]
+    pub published_only: bool,
+}
+
+impl RotaFilter {
+    pub fn matches(&self, rota: &Rota) -> bool {
+        self.team.is_none_or(|team| rota.team_id == team)
+            && self.status.is_none_or(|status| rota.status == status)
+            && self.from.is_none_or(|from| rota.period_end >= from)
+            && self.to.is_none_or(|to| rota.period_start <= to)
+            && !(self.published_only && rota.status == RotaStatus::Draft)
+    }
+}
+
+// Fields the rota list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotaSort {
+    PeriodStart,
+}
+
+impl RotaSort {
+    // Most recent period first
+    pub const DEFAULT: &'static [SortKey<RotaSort>] = &[SortKey::desc(RotaSort::PeriodStart)];
+}
+
+impl SortField for RotaSort {
+    const ALL: &'static [Self] = &[Self::PeriodStart];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::PeriodStart => "period_start",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::PeriodStart => "period_start",
+        }
+    }
+}
+
+impl Sortable<RotaSort> for Rota {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: RotaSort) -> SortValue {
+        match field {
+            RotaSort::PeriodStart => SortValue::Date(self.period_start),
+        }
+    }
+}
+
+// Query string of `GET /api/rotas/:id/published`
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotQuery {
+    // An earlier publication; the latest when left out
+    pub revision: Option<i64>,
+}
+
+// Body of `POST /api/rotas/:id/copy`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CopyRequest {
+    // First day of the new rota, which is as long as the one copied
+    pub period_start: NaiveDate,
+    // Put the same people on the copied shifts where they are still free
+    #[serde(default)]
+    pub assignments: bool,
+}
+
+// A shift copied into the new rota
+#[derive(Debug, Clone, Serialize)]
+pub struct CopiedShift {
+    pub from_shift_id: i64,
+    pub shift_id: i64,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+// Someone put on a copied shift
+#[derive(Debug, Clone, Serialize)]
+pub struct CopiedAssignment {
+    pub shift_id: i64,
+    pub user_id: i64,
+}
+
+// Why something wasn't carried over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotCopiedCode {
+    // The shift wouldn't be valid in the new period
+    InvalidShift,
+    // The person has been deleted, or no longer exists
+    UserDeleted,
+    UserDeactivated,
+    // The person can't work the copied shift; see `conflicts`
+    Conflicts,
+}
+
+// A shift, or someone on one, that couldn't be carried over. `user_id` is
+// set for people and `shift_id` once the shift itself was copied.
+#[derive(Debug, Clone, Serialize)]
+pub struct NotCopied {
+    pub from_shift_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    pub code: NotCopiedCode,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+}
+
+// What copying a rota made. Nobody is forced onto a shift: anyone with
+// conflicts in the new period is left off and reported.
+#[derive(Debug, Serialize)]
+pub struct CopyReport {
+    pub rota: Rota,
+    pub shifts: Vec<CopiedShift>,
+    pub assigned: Vec<CopiedAssignment>,
+    pub not_copied: Vec<NotCopied>,
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::shift::{NewShift, Shift};
+use rota_core::{
+    shift::{local_to_utc, parse_time_zone},
+    user::normalise_skills,
+    ValidationError,
+};
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+fn default_time_zone() -> String {
+    "UTC".to_string()
+}
+
+fn default_headcount() -> i32 {
+    1
+}
+
+// Body of `POST /api/shifts` and `PUT /api/shifts/:id`. Start and end are
+// wall-clock times in `time_zone`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShiftRequest {
+    // The rota the shift belongs to; its team is used when `team_id` is left out
+    pub rota_id: Option<i64>,
+    pub team_id: Option<i64>,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub position: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    #[serde(default = "default_time_zone")]
+    pub time_zone: String,
+    #[serde(default)]
+    pub unpaid_break_minutes: i32,
+    #[serde(default = "default_headcount")]
+    pub required_headcount: i32,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub required_skills: Vec<String>,
+    #[serde(default)]
+    pub required_qualifications: Vec<String>,
+}
+
+impl ShiftRequest {
+    // Resolve the local times to instants. The result still needs validating.
+    pub fn into_new_shift(self) -> Result<NewShift, ValidationError> {
+        let tz = parse_time_zone(self.time_zone.trim())?;
+
+        Ok(NewShift {
+            rota_id: self.rota_id,
+            team_id: self.team_id,
+            location: self.location.trim().to_string(),
+            position: self.position.trim().to_string(),
+            starts_at: local_to_utc(self.start, tz)?,
+            ends_at: local_to_utc(self.end, tz)?,
+            time_zone: tz.name().to_string(),
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            notes: self.notes,
+            required_skills: normalise_skills(self.required_skills),
+            required_qualifications: normalise_skills(self.required_qualifications),
+        })
+    }
+}
+
+// A shift as the API returns it, with its times both in UTC and local to
+// the shift
+#[derive(Debug, Serialize)]
+pub struct ShiftResponse {
+    pub id: i64,
+    pub rota_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub location: String,
+    pub position: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub local_start: NaiveDateTime,
+    pub local_end: NaiveDateTime,
+    pub time_zone: String,
+    pub overnight: bool,
+    pub unpaid_break_minutes: i32,
+    pub paid_minutes: i64,
+    pub required_headcount: i32,
+    pub notes: String,
+    pub required_skills: Vec<String>,
+    pub required_qualifications: Vec<String>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Shift> for ShiftResponse {
+    fn from(shift: Shift) -> Self {
+        Self {
+            local_start: shift.local_start(),
+            local_end: shift.local_end(),
+            overnight: shift.is_overnight(),
+            paid_minutes: shift.paid_minutes(),
+            id: shift.id,
+            rota_id: shift.rota_id,
+            team_id: shift.team_id,
+            location: shift.location,
+            position: shift.position,
+            starts_at: shift.starts_at,
+            ends_at: shift.ends_at,
+            time_zone: shift.time_zone,
+            unpaid_break_minutes: shift.unpaid_break_minutes,
+            required_headcount: shift.required_headcount,
+            notes: shift.notes,
+            required_skills: shift.required_skills,
+            required_qualifications: shift.required_qualifications,
+            version: shift.version,
+            created_at: shift.created_at,
+            updated_at: shift.updated_at,
+        }
+    }
+}
+
+// Query string filters for `GET /api/shifts`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct ShiftFilter {
+    pub team: Option<i64>,
+    pub rota: Option<i64>,
+    // Exact location, ignoring case
+    pub location: Option<String>,
+    // Shifts still running at or after this instant
+    pub from: Option<DateTime<Utc>>,
+    // Shifts starting before this instant
+    pub to: Option<DateTime<Utc>>,
+    // Set for staff, who only see rota shifts through published snapshots
+    #[serde(skip)]
+    pub standalone_only: bool,
+}
+
+impl ShiftFilter {
+    pub fn matches(&self, shift: &Shift) -> bool {
+        self.team.is_none_or(|team| shift.team_id == Some(team))
+            && self.rota.is_none_or(|rota| shift.rota_id == Some(rota))
+            && self
+                .location
+                .as_deref()
+                .is_none_or(|location| shift.location.eq_ignore_ascii_case(location.trim()))
+            && self.from.is_none_or(|from| shift.ends_at > from)
+            && self.to.is_none_or(|to| shift.starts_at < to)
+            && !(self.standalone_only && shift.rota_id.is_some())
+    }
+}
+
+// Fields the shift list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShiftSort {
+    StartsAt,
+    Location,
+    Position,
+}
+
+impl ShiftSort {
+    // In the order they happen
+    pub const DEFAULT: &'static [SortKey<ShiftSort>] = &[SortKey::asc(ShiftSort::StartsAt)];
+}
+
+impl SortField for ShiftSort {
+    const ALL: &'static [Self] = &[Self::StartsAt, Self::Location, Self::Position];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::StartsAt => "starts_at",
+            Self::Location => "location",
+            Self::Position => "position",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::StartsAt => "starts_at",
+            Self::Location => "LOWER(location)",
+            Self::Position => "LOWER(position)",
+        }
+    }
+}
+
+impl Sortable<ShiftSort> for Shift {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: ShiftSort) -> SortValue {
+        match field {
+            ShiftSort::StartsAt => SortValue::Time(self.starts_at),
+            ShiftSort::Location => SortValue::Text(self.location.to_lowercase()),
+            ShiftSort::Position => SortValue::Text(self.position.to_lowercase()),
+        }
+    }
+}
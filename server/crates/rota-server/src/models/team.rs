@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// A team groups staff and owns rotas. Teams nest under a parent; leave goes
+// to the manager of the nearest team up the tree that has one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Team {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub manager_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Fields the team list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TeamSort {
+    Name,
+}
+
+impl TeamSort {
+    // By name, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<TeamSort>] = &[SortKey::asc(TeamSort::Name)];
+}
+
+impl SortField for TeamSort {
+    const ALL: &'static [Self] = &[Self::Name];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "LOWER(name)",
+        }
+    }
+}
+
+impl Sortable<TeamSort> for Team {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: TeamSort) -> SortValue {
+        match field {
+            TeamSort::Name => SortValue::Text(self.name.to_lowercase()),
+        }
+    }
+}
+
+// Payload for creating a team
+#[derive(Debug, Deserialize)]
+pub struct CreateTeamRequest {
+    pub name: String,
+}
+
+// Payload for changing a team's name, parent and manager
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateTeamRequest {
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub manager_id: Option<i64>,
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::recurrence::{Recurrence, RecurrenceRule};
+pub use rota_core::template::{NewShiftTemplate, ShiftTemplate};
+use rota_core::{shift::parse_time_zone, ValidationError};
+
+use crate::{
+    models::shift::NewShift,
+    pagination::{SortField, SortKey, SortValue, Sortable},
+};
+
+fn default_time_zone() -> String {
+    "UTC".to_string()
+}
+
+fn default_headcount() -> i32 {
+    1
+}
+
+// Body of `POST /api/shift-templates` and `PUT /api/shift-templates/:id`.
+// Times are wall-clock times in `time_zone`, such as `"20:00"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateRequest {
+    pub team_id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub position: String,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    #[serde(default = "default_time_zone")]
+    pub time_zone: String,
+    #[serde(default)]
+    pub unpaid_break_minutes: i32,
+    #[serde(default = "default_headcount")]
+    pub required_headcount: i32,
+    pub colour: String,
+}
+
+impl TemplateRequest {
+    // Tidy the fields up. The result still needs validating.
+    pub fn into_new_template(self) -> Result<NewShiftTemplate, ValidationError> {
+        let tz = parse_time_zone(self.time_zone.trim())?;
+
+        Ok(NewShiftTemplate {
+            team_id: self.team_id,
+            name: self.name.trim().to_string(),
+            location: self.location.trim().to_string(),
+            position: self.position.trim().to_string(),
+            start_time: self.start_time,
+            end_time: self.end_time,
+            time_zone: tz.name().to_string(),
+            unpaid_break_minutes: self.unpaid_break_minutes,
+            required_headcount: self.required_headcount,
+            colour: self.colour.trim().to_ascii_lowercase(),
+        })
+    }
+}
+
+// Query string filter for `GET /api/shift-templates`
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateFilter {
+    pub team: Option<i64>,
+}
+
+// Fields the template list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateSort {
+    Name,
+    CreatedAt,
+}
+
+impl TemplateSort {
+    // By name, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<TemplateSort>] = &[SortKey::asc(TemplateSort::Name)];
+}
+
+impl SortField for TemplateSort {
+    const ALL: &'static [Self] = &[Self::Name, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "LOWER(name)",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl Sortable<TemplateSort> for ShiftTemplate {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: TemplateSort) -> SortValue {
+        match field {
+            TemplateSort::Name => SortValue::Text(self.name.to_lowercase()),
+            TemplateSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
+
+// When a template repeats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShiftRecurrence {
+    pub id: i64,
+    pub template_id: i64,
+    pub rule: RecurrenceRule,
+    pub starts_on: NaiveDate,
+    pub exceptions: Vec<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ShiftRecurrence {
+    pub fn recurrence(&self) -> Recurrence {
+        Recurrence {
+            rule: self.rule.clone(),
+            starts_on: self.starts_on,
+            exceptions: self.exceptions.clone(),
+        }
+    }
+}
+
+// Body of `POST /api/shift-templates/:id/recurrences`. The rule is an RRULE
+// such as `FREQ=WEEKLY;BYDAY=MO,TU,WE`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecurrenceRequest {
+    pub rule: String,
+    pub starts_on: NaiveDate,
+    #[serde(default)]
+    pub exceptions: Vec<NaiveDate>,
+}
+
+// Everything needed to store a recurrence
+#[derive(Debug, Clone)]
+pub struct NewRecurrence {
+    pub template_id: i64,
+    pub rule: RecurrenceRule,
+    pub starts_on: NaiveDate,
+    pub exceptions: Vec<NaiveDate>,
+}
+
+// Which occurrence of a recurrence a generated shift is
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShiftOccurrence {
+    pub shift_id: i64,
+    pub recurrence_id: i64,
+    pub occurrence_date: NaiveDate,
+}
+
+// A shift to generate for one occurrence of a recurrence
+#[derive(Debug, Clone)]
+pub struct GeneratedShift {
+    pub recurrence_id: i64,
+    pub occurrence_date: NaiveDate,
+    pub shift: NewShift,
+}
+
+// Query string of `POST /api/rotas/:id/generate` and its preview
+#[derive(Debug, Default, Deserialize)]
+pub struct GenerateQuery {
+    // Comma-separated ids of the recurrences to generate from; every
+    // recurrence of the rota team's templates when left out
+    pub recurrences: Option<String>,
+}
+
+impl GenerateQuery {
+    pub fn recurrence_ids(&self) -> Result<Option<Vec<i64>>, ValidationError> {
+        let Some(text) = &self.recurrences else {
+            return Ok(None);
+        };
+        let mut ids = text
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().map_err(|_| ValidationError(format!("Invalid recurrence id: {}", id))))
+            .collect::<Result<Vec<i64>, _>>()?;
+        ids.sort();
+        ids.dedup();
+
+        Ok(Some(ids))
+    }
+}
+
+// One occurrence in a generation report
+#[derive(Debug, Clone, Serialize)]
+pub struct Occurrence {
+    pub recurrence_id: i64,
+    pub template_id: i64,
+    pub date: NaiveDate,
+    // The shift made for the occurrence; absent in a preview
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+    // Why no shift can be made for a skipped occurrence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// What generating shifts into a rota did, or would do in a preview. Existing
+// occurrences already had a shift generated, which may since have been
+// edited or deleted, and are left alone.
+#[derive(Debug, Default, Serialize)]
+pub struct GenerationReport {
+    pub preview: bool,
+    pub new: Vec<Occurrence>,
+    pub existing: Vec<Occurrence>,
+    pub skipped: Vec<Occurrence>,
+}
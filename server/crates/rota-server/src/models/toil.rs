@@ -0,0 +1,107 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+pub use rota_core::toil::{
+    NewOvertime, NewToilEntry, Overtime, OvertimeStatus, ToilBalance, ToilEntry, ToilEntryKind, ToilMultipliers,
+};
+
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// Body of `POST /api/overtime`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OvertimeRequest {
+    pub date: NaiveDate,
+    pub hours: f64,
+    pub shift_id: Option<i64>,
+    pub reason: String,
+}
+
+// Payload for approving or rejecting an overtime claim
+#[derive(Debug, Default, Deserialize)]
+pub struct OvertimeDecision {
+    #[serde(default)]
+    pub note: String,
+}
+
+// Query string filters for `GET /api/overtime`; every field is optional and
+// the dates bound the day worked
+#[derive(Debug, Default, Deserialize)]
+pub struct OvertimeFilter {
+    pub user: Option<i64>,
+    pub status: Option<OvertimeStatus>,
+    pub approver: Option<i64>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl OvertimeFilter {
+    pub fn matches(&self, overtime: &Overtime) -> bool {
+        self.user.is_none_or(|user| overtime.user_id == user)
+            && self.status.is_none_or(|status| overtime.status == status)
+            && self.approver.is_none_or(|approver| overtime.approver_id == Some(approver))
+            && self.from.is_none_or(|from| from <= overtime.date)
+            && self.to.is_none_or(|to| overtime.date <= to)
+    }
+}
+
+// Fields the overtime list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OvertimeSort {
+    Date,
+    CreatedAt,
+}
+
+impl OvertimeSort {
+    // By the day worked, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<OvertimeSort>] = &[SortKey::asc(OvertimeSort::Date)];
+}
+
+impl SortField for OvertimeSort {
+    const ALL: &'static [Self] = &[Self::Date, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Date => "work_date",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl Sortable<OvertimeSort> for Overtime {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: OvertimeSort) -> SortValue {
+        match field {
+            OvertimeSort::Date => SortValue::Date(self.date),
+            OvertimeSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
+
+// Body of `POST /api/users/:id/toil/adjustments`. Positive hours expire like
+// accruals unless `expires_on` is given.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToilAdjustmentRequest {
+    pub hours: f64,
+    pub note: String,
+    pub expires_on: Option<NaiveDate>,
+}
+
+// Response of `GET /api/users/:id/toil`
+#[derive(Debug, Serialize)]
+pub struct ToilLedger {
+    pub balance: ToilBalance,
+    // Oldest first
+    pub entries: Vec<ToilEntry>,
+}
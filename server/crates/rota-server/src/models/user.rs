@@ -0,0 +1,314 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::toil::ToilBalance;
+use crate::pagination::{SortField, SortKey, SortValue, Sortable};
+
+// The user model itself lives in the domain crate; this module adds the
+// HTTP representations. The API calls the username `name`.
+pub use rota_core::user::{EmergencyContact, NewUser, PersonalDetails, User, UserRole};
+
+// Payload for creating a user. Users created without a password cannot log
+// in until one is set.
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub name: String,
+    pub email: String,
+    pub role: Option<UserRole>,
+    pub password: Option<String>,
+}
+
+// Payload for replacing a user's details. The role is left unchanged if omitted.
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    pub name: String,
+    pub email: String,
+    pub role: Option<UserRole>,
+}
+
+// The fields `PATCH /users/:id` may change. Only admins may change the role,
+// team, skills or contracted hours.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EditableUser {
+    pub name: String,
+    pub email: String,
+    pub role: UserRole,
+    pub team_id: Option<i64>,
+    pub skills: Vec<String>,
+    pub contracted_hours: Option<f64>,
+}
+
+impl From<&User> for EditableUser {
+    fn from(user: &User) -> Self {
+        Self {
+            name: user.username.clone(),
+            email: user.email.clone(),
+            role: user.role.clone(),
+            team_id: user.team_id,
+            skills: user.skills.clone(),
+            contracted_hours: user.contracted_hours,
+        }
+    }
+}
+
+// The fields users may change on their own profile through `/api/me`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EditableProfile {
+    pub name: String,
+    pub email: String,
+}
+
+impl From<&User> for EditableProfile {
+    fn from(user: &User) -> Self {
+        Self {
+            name: user.username.clone(),
+            email: user.email.clone(),
+        }
+    }
+}
+
+// Payload for changing your own password
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// For user data to return in responses (excludes sensitive information)
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub role: UserRole,
+    pub team_id: Option<i64>,
+    pub skills: Vec<String>,
+    pub contracted_hours: Option<f64>,
+    pub active: bool,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            active: user.is_active(),
+            name: user.username,
+            email: user.email,
+            role: user.role,
+            team_id: user.team_id,
+            skills: user.skills,
+            contracted_hours: user.contracted_hours,
+            version: user.version,
+            created_at: user.created_at,
+        }
+    }
+}
+
+// Response of `GET /api/me`: your profile and where you stand with TOIL
+#[derive(Debug, Serialize)]
+pub struct MeResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub toil: ToilBalance,
+}
+
+// Extended user details for admin-only endpoints
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub role: UserRole,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name: user.username,
+            email: user.email,
+            role: user.role,
+            version: user.version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+            deactivated_at: user.deactivated_at,
+        }
+    }
+}
+
+// A user as snapshotted into the audit log. The log can't be rewritten when
+// someone's data is erased, so their name and email are left out.
+#[derive(Debug, Serialize)]
+pub struct AuditedUser {
+    pub id: i64,
+    pub role: UserRole,
+    pub team_id: Option<i64>,
+    pub skills: Vec<String>,
+    pub contracted_hours: Option<f64>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub anonymised_at: Option<DateTime<Utc>>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+impl From<&User> for AuditedUser {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            role: user.role.clone(),
+            team_id: user.team_id,
+            skills: user.skills.clone(),
+            contracted_hours: user.contracted_hours,
+            version: user.version,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+            anonymised_at: user.anonymised_at,
+            deactivated_at: user.deactivated_at,
+        }
+    }
+}
+
+// Result of deactivating a user
+#[derive(Debug, Serialize)]
+pub struct DeactivationResponse {
+    #[serde(flatten)]
+    pub user: AdminUserResponse,
+    // Assignments removed from shifts that hadn't started yet
+    pub shifts_unassigned: u64,
+}
+
+// A user as described by one row of a bulk import, matched on email. `None`
+// leaves the stored value alone, which is what a column missing from the file
+// means.
+#[derive(Debug, Clone)]
+pub struct UserUpsert {
+    pub name: String,
+    pub email: String,
+    pub role: Option<UserRole>,
+    pub team_id: Option<Option<i64>>,
+    pub contracted_hours: Option<Option<f64>>,
+    // Already normalised
+    pub skills: Option<Vec<String>>,
+}
+
+impl UserUpsert {
+    // Whether saving this row would change `user`
+    pub fn changes(&self, user: &User) -> bool {
+        let updated = self.apply_to(user);
+        updated.username != user.username
+            || updated.role != user.role
+            || updated.team_id != user.team_id
+            || updated.contracted_hours != user.contracted_hours
+            || updated.skills != user.skills
+    }
+
+    // `user` with this row's details; the email and everything else is kept
+    pub fn apply_to(&self, user: &User) -> User {
+        User {
+            username: self.name.clone(),
+            role: self.role.clone().unwrap_or_else(|| user.role.clone()),
+            team_id: self.team_id.unwrap_or(user.team_id),
+            contracted_hours: self.contracted_hours.unwrap_or(user.contracted_hours),
+            skills: self.skills.clone().unwrap_or_else(|| user.skills.clone()),
+            ..user.clone()
+        }
+    }
+}
+
+// Outcome of one upsert: `before` is `None` for a new user, and equal to
+// `after` when nothing changed
+#[derive(Debug, Clone)]
+pub struct Upserted {
+    pub before: Option<User>,
+    pub after: User,
+}
+
+// Query string filters for `GET /users`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct UserFilter {
+    pub role: Option<UserRole>,
+    pub team: Option<i64>,
+    pub active: Option<bool>,
+    pub skill: Option<String>,
+    // Case-insensitive substring of the name or email
+    pub search: Option<String>,
+}
+
+impl UserFilter {
+    pub fn matches(&self, user: &User) -> bool {
+        let search = self.search.as_deref().map(str::to_lowercase);
+
+        self.role.as_ref().is_none_or(|role| *role == user.role)
+            && self.team.is_none_or(|team| user.team_id == Some(team))
+            && self.active.is_none_or(|active| user.is_active() == active)
+            && self.skill.as_deref().is_none_or(|skill| user.has_skill(skill))
+            && search.is_none_or(|search| {
+                user.username.to_lowercase().contains(&search)
+                    || user.email.to_lowercase().contains(&search)
+            })
+    }
+}
+
+// Fields the user list can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserSort {
+    Name,
+    Email,
+    Role,
+    CreatedAt,
+}
+
+impl UserSort {
+    // Oldest first, as the list has always been ordered
+    pub const DEFAULT: &'static [SortKey<UserSort>] = &[SortKey::asc(UserSort::CreatedAt)];
+}
+
+impl SortField for UserSort {
+    const ALL: &'static [Self] = &[Self::Name, Self::Email, Self::Role, Self::CreatedAt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Email => "email",
+            Self::Role => "role",
+            Self::CreatedAt => "created_at",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Name => "LOWER(username)",
+            Self::Email => "LOWER(email)",
+            Self::Role => "role",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl Sortable<UserSort> for User {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn sort_value(&self, field: UserSort) -> SortValue {
+        match field {
+            UserSort::Name => SortValue::Text(self.username.to_lowercase()),
+            UserSort::Email => SortValue::Text(self.email.to_lowercase()),
+            UserSort::Role => SortValue::Text(self.role.to_string()),
+            UserSort::CreatedAt => SortValue::Time(self.created_at),
+        }
+    }
+}
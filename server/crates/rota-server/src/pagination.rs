@@ -0,0 +1,369 @@
+//! Cursor pagination, sorting and the list envelope shared by list endpoints.
+//!
+//! Lists are ordered by one or more sort keys with the id as a final
+//! tie-breaker. A cursor holds the sort key values of the last item returned,
+//! so the next page starts strictly after it even when rows are added or
+//! removed in between (keyset pagination, never `OFFSET`). Every list responds
+//! with a [`Page`] and, when there is more, a `Link: <...>; rel="next"` header.
+
+use axum::{
+    http::{header::LINK, HeaderValue, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::error::AppError;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 200;
+
+// `limit`, `cursor` and `sort` from the query string. Endpoints take this as a
+// second `Query` next to their own filters.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+    // Comma-separated fields, each optionally prefixed with `-` for descending
+    pub sort: Option<String>,
+}
+
+// A field a list can be sorted on
+pub trait SortField: Copy + PartialEq + Sized + 'static {
+    const ALL: &'static [Self];
+
+    // Name used in `sort=`
+    fn name(self) -> &'static str;
+
+    // SQL expression the field sorts on
+    fn column(self) -> &'static str;
+}
+
+// Something that can appear in a sorted list
+pub trait Sortable<F> {
+    fn id(&self) -> i64;
+
+    // Must order the same way as `F::column` does in SQL
+    fn sort_value(&self, field: F) -> SortValue;
+}
+
+// The value of one sort key
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum SortValue {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+    Time(DateTime<Utc>),
+    Date(NaiveDate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey<F> {
+    pub field: F,
+    pub descending: bool,
+}
+
+impl<F> SortKey<F> {
+    pub const fn asc(field: F) -> Self {
+        Self { field, descending: false }
+    }
+
+    pub const fn desc(field: F) -> Self {
+        Self { field, descending: true }
+    }
+}
+
+// Position after the last item of a page. The sort it was made for is kept so
+// a cursor can't be replayed against a different order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    sort: String,
+    values: Vec<SortValue>,
+    id: i64,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serialises"))
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(text).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+// One page of a list
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Pass back as `cursor` for the next page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+// A validated page request for a list sorted on `F`
+#[derive(Debug, Clone)]
+pub struct PageRequest<F> {
+    pub sort: Vec<SortKey<F>>,
+    pub after: Option<Cursor>,
+    pub limit: usize,
+}
+
+impl<F: SortField> PageRequest<F> {
+    // Check the query string parameters. `default` is used when no sort is given.
+    pub fn new(params: &PageParams, default: &[SortKey<F>]) -> Result<Self, AppError> {
+        let sort = match params.sort.as_deref().filter(|s| !s.trim().is_empty()) {
+            Some(spec) => parse_sort(spec)?,
+            None => default.to_vec(),
+        };
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut request = Self { sort, after: None, limit };
+        if let Some(text) = &params.cursor {
+            let cursor = Cursor::decode(text)
+                .filter(|c| c.sort == request.spec() && c.values.len() == request.sort.len())
+                .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?;
+            request.after = Some(cursor);
+        }
+
+        Ok(request)
+    }
+
+    // A request for the first page, for callers outside HTTP
+    pub fn first(sort: &[SortKey<F>], limit: usize) -> Self {
+        Self { sort: sort.to_vec(), after: None, limit }
+    }
+
+    // The request for the page after `page`, or `None` if it was the last
+    pub fn next<T>(&self, page: &Page<T>) -> Option<Self> {
+        let after = Cursor::decode(page.next_cursor.as_deref()?)?;
+        Some(Self { after: Some(after), ..self.clone() })
+    }
+
+    // Canonical form of the sort, e.g. `name,-created_at`
+    fn spec(&self) -> String {
+        self.sort
+            .iter()
+            .map(|key| format!("{}{}", if key.descending { "-" } else { "" }, key.field.name()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Order of two items under this sort, id last
+    pub fn compare<T: Sortable<F>>(&self, a: &T, b: &T) -> Ordering {
+        self.sort
+            .iter()
+            .map(|key| {
+                let order = a
+                    .sort_value(key.field)
+                    .partial_cmp(&b.sort_value(key.field))
+                    .unwrap_or(Ordering::Equal);
+                if key.descending { order.reverse() } else { order }
+            })
+            .find(|order| order.is_ne())
+            .unwrap_or_else(|| a.id().cmp(&b.id()))
+    }
+
+    // Whether `item` comes after the cursor
+    fn is_after<T: Sortable<F>>(&self, item: &T) -> bool {
+        let Some(cursor) = &self.after else {
+            return true;
+        };
+
+        for (key, value) in self.sort.iter().zip(&cursor.values) {
+            let order = item.sort_value(key.field).partial_cmp(value).unwrap_or(Ordering::Equal);
+            let order = if key.descending { order.reverse() } else { order };
+            if order.is_ne() {
+                return order.is_gt();
+            }
+        }
+        item.id() > cursor.id
+    }
+
+    // Sort and page a complete, already filtered list held in memory
+    pub fn apply<T: Sortable<F>>(&self, mut items: Vec<T>) -> Page<T> {
+        items.retain(|item| self.is_after(item));
+        items.sort_by(|a, b| self.compare(a, b));
+        items.truncate(self.limit + 1);
+
+        self.page(items)
+    }
+
+    // Build the page from up to `limit + 1` sorted rows; the extra row only
+    // tells us there is another page
+    pub fn page<T: Sortable<F>>(&self, mut rows: Vec<T>) -> Page<T> {
+        let more = rows.len() > self.limit;
+        rows.truncate(self.limit);
+
+        let next_cursor = match rows.last() {
+            Some(last) if more => Some(
+                Cursor {
+                    sort: self.spec(),
+                    values: self.sort.iter().map(|key| last.sort_value(key.field)).collect(),
+                    id: last.id(),
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+
+        Page { items: rows, next_cursor }
+    }
+
+    // Append ` AND (...)` restricting rows to those after the cursor, for a
+    // query that already has a `WHERE` clause
+    pub fn push_after<'a, DB>(&self, query: &mut sqlx::QueryBuilder<'a, DB>)
+    where
+        DB: sqlx::Database,
+        bool: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+        i64: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+        String: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+        DateTime<Utc>: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+        NaiveDate: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    {
+        let Some(cursor) = &self.after else {
+            return;
+        };
+
+        // (k1 > v1) OR (k1 = v1 AND k2 > v2) OR ... OR (k1 = v1 AND ... AND id > id0),
+        // with `<` for descending keys
+        query.push(" AND (");
+        for i in 0..=self.sort.len() {
+            if i > 0 {
+                query.push(" OR ");
+            }
+            query.push("(");
+            for (key, value) in self.sort.iter().zip(&cursor.values).take(i) {
+                query.push(format!("{} = ", key.field.column()));
+                push_value(query, value.clone());
+                query.push(" AND ");
+            }
+            match self.sort.get(i) {
+                Some(key) => {
+                    let op = if key.descending { "<" } else { ">" };
+                    query.push(format!("{} {} ", key.field.column(), op));
+                    push_value(query, cursor.values[i].clone());
+                }
+                None => {
+                    query.push("id > ").push_bind(cursor.id);
+                }
+            }
+            query.push(")");
+        }
+        query.push(")");
+    }
+
+    // Append `ORDER BY ... LIMIT n + 1`
+    pub fn push_order<'a, DB>(&self, query: &mut sqlx::QueryBuilder<'a, DB>)
+    where
+        DB: sqlx::Database,
+        i64: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    {
+        query.push(" ORDER BY ");
+        for key in &self.sort {
+            let direction = if key.descending { "DESC" } else { "ASC" };
+            query.push(format!("{} {}, ", key.field.column(), direction));
+        }
+        query.push("id ASC LIMIT ").push_bind(self.limit as i64 + 1);
+    }
+}
+
+fn push_value<'a, DB>(query: &mut sqlx::QueryBuilder<'a, DB>, value: SortValue)
+where
+    DB: sqlx::Database,
+    bool: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    i64: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    String: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    DateTime<Utc>: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    NaiveDate: 'a + sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+{
+    match value {
+        SortValue::Bool(v) => query.push_bind(v),
+        SortValue::Int(v) => query.push_bind(v),
+        SortValue::Text(v) => query.push_bind(v),
+        SortValue::Time(v) => query.push_bind(v),
+        SortValue::Date(v) => query.push_bind(v),
+    };
+}
+
+// Parse `name,-created_at` into sort keys
+fn parse_sort<F: SortField>(spec: &str) -> Result<Vec<SortKey<F>>, AppError> {
+    let mut keys: Vec<SortKey<F>> = Vec::new();
+
+    for part in spec.split(',').map(str::trim) {
+        let (name, descending) = match part.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (part.strip_prefix('+').unwrap_or(part), false),
+        };
+        let field = F::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| {
+                let allowed: Vec<_> = F::ALL.iter().map(|field| field.name()).collect();
+                AppError::BadRequest(format!(
+                    "Can't sort by '{}'; expected one of {}",
+                    name,
+                    allowed.join(", ")
+                ))
+            })?;
+        if keys.iter().any(|key| key.field == field) {
+            return Err(AppError::BadRequest(format!("Sort field '{}' given twice", name)));
+        }
+        keys.push(SortKey { field, descending });
+    }
+
+    Ok(keys)
+}
+
+// A page as an HTTP response, with a `Link` header to the next page
+pub struct Paginated<T> {
+    page: Page<T>,
+    uri: Uri,
+}
+
+impl<T> Paginated<T> {
+    // `uri` is the request URI; the next link keeps its query and swaps the cursor
+    pub fn new(page: Page<T>, uri: Uri) -> Self {
+        Self { page, uri }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        let link = self.page.next_cursor.as_deref().map(|cursor| next_link(&self.uri, cursor));
+        let mut response = Json(self.page).into_response();
+
+        if let Some(value) = link.and_then(|link| HeaderValue::from_str(&link).ok()) {
+            response.headers_mut().insert(LINK, value);
+        }
+
+        response
+    }
+}
+
+// `<path?query&cursor=...>; rel="next"`, replacing any cursor already present
+fn next_link(uri: &Uri, cursor: &str) -> String {
+    let mut params: Vec<&str> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("cursor="))
+        .collect();
+    let cursor = format!("cursor={}", cursor);
+    params.push(&cursor);
+
+    format!("<{}?{}>; rel=\"next\"", uri.path(), params.join("&"))
+}
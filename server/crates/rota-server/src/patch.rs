@@ -0,0 +1,44 @@
+//! JSON merge patch (RFC 7386) for `PATCH` endpoints.
+//!
+//! A patch is an object whose members replace the matching members of the
+//! resource, recursively; `null` removes a member and anything absent is left
+//! alone. Handlers patch the editable view of a resource and deserialise the
+//! result, so removing a required field or naming an unknown one is a 400.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+// Apply `patch` to `target` in place
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let object = target.as_object_mut().expect("target is an object");
+
+    for (key, value) in members {
+        if value.is_null() {
+            object.remove(key);
+        } else {
+            merge(object.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// Apply `patch` to the serialised form of `current` and read the result back
+pub fn apply<T: Serialize + DeserializeOwned>(current: &T, patch: &Value) -> Result<T, AppError> {
+    if !patch.is_object() {
+        return Err(AppError::BadRequest("Patch must be a JSON object".to_string()));
+    }
+
+    let mut value = serde_json::to_value(current).map_err(|_| AppError::InternalServerError)?;
+    merge(&mut value, patch);
+
+    serde_json::from_value(value).map_err(|err| AppError::BadRequest(format!("Invalid patch: {}", err)))
+}
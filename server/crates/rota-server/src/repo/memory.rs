@@ -0,0 +1,1426 @@
+//! In-memory repositories. They enforce the same rules as the SQL backends
+//! so the API behaves identically in tests and demos without a database.
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use std::sync::Mutex;
+
+use rota_core::user::{normalise_phone, normalise_skills, NewUser, PersonalDetails, User, UserRole};
+
+use super::{
+    AuditRepo, AvailabilityRepo, ErasureRepo, LeavePolicyRepo, LeaveRepo, PatternRepo, ProfileRepo, RepoError,
+    RepoResult, RotaRepo, ShiftRepo, TeamRepo, TemplateRepo, ToilRepo, UserRepo, ALREADY_ASSIGNED, ALREADY_GENERATED,
+    AVAILABILITY_EXISTS, ERASURE_DECIDED, ERASURE_PENDING, LEAVE_LIMIT_EXISTS, OVERTIME_CREDITED, PATTERN_SHIFT_EXISTS,
+    ROTA_OVERLAPS,
+};
+use crate::{
+    audit,
+    models::{
+        assignment::{ApprovedLeave, Assignment, NewAssignment},
+        availability::{AvailabilityException, NewException, WeeklyAvailability},
+        audit::{AuditEntry, AuditFilter, AuditSort, NewAuditEntry},
+        erasure::{ErasureRequest, ErasureSort, ErasureStatus, NewErasureRequest},
+        leave::{LeaveEntitlement, LeaveFilter, LeaveRequest, LeaveSort, LeaveStatus, NewLeaveRequest},
+        leave_policy::{Blackout, BlackoutFilter, BlackoutSort, LeaveLimit, NewBlackout, NewLeaveLimit},
+        pattern::{NewPattern, Pattern, PatternMember, PatternOverride, PatternSort, StoredOverride},
+        profile::Employment,
+        rota::{NewRota, Rota, RotaFilter, RotaSnapshot, RotaSort},
+        shift::{NewShift, Shift, ShiftFilter, ShiftSort},
+        team::{Team, TeamSort},
+        template::{
+            GeneratedShift, NewRecurrence, NewShiftTemplate, ShiftOccurrence, ShiftRecurrence, ShiftTemplate, TemplateSort,
+        },
+        toil::{NewOvertime, NewToilEntry, Overtime, OvertimeFilter, OvertimeSort, ToilEntry},
+        user::{Upserted, UserFilter, UserSort, UserUpsert},
+    },
+    pagination::{Page, PageRequest},
+};
+
+#[derive(Default)]
+pub struct InMemoryUserRepo {
+    users: Mutex<Vec<User>>,
+}
+
+impl InMemoryUserRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// A stored user as reads return it: like SQL storage, personal details only
+// come back from `personal`
+fn read(user: &User) -> User {
+    User { personal: PersonalDetails::default(), ..user.clone() }
+}
+
+#[async_trait]
+impl UserRepo for InMemoryUserRepo {
+    async fn create(&self, user: NewUser) -> RepoResult<User> {
+        let mut users = self.users.lock().unwrap();
+
+        if users.iter().any(|u| u.email.eq_ignore_ascii_case(&user.email)) {
+            return Err(RepoError::Conflict("Email already in use".to_string()));
+        }
+
+        // Ids are never reused, matching a database sequence
+        let id = users.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+        let user = user.into_user(id, Utc::now());
+        users.push(user.clone());
+
+        Ok(user)
+    }
+
+    async fn list(&self, filter: &UserFilter, page: &PageRequest<UserSort>) -> RepoResult<Page<User>> {
+        let users = self.users.lock().unwrap();
+        let matching = users
+            .iter()
+            .filter(|u| !u.is_deleted() && filter.matches(u))
+            .map(read)
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.id == id && !u.is_deleted()).map(read))
+    }
+
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.id == id).map(read))
+    }
+
+    async fn deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .iter()
+            .filter(|u| u.anonymised_at.is_none() && u.deleted_at.is_some_and(|at| at < cutoff))
+            .map(read)
+            .collect())
+    }
+
+    async fn update(&self, user: User) -> RepoResult<User> {
+        let mut users = self.users.lock().unwrap();
+
+        if users
+            .iter()
+            .any(|u| u.id != user.id && u.email.eq_ignore_ascii_case(&user.email))
+        {
+            return Err(RepoError::Conflict("Email already in use".to_string()));
+        }
+
+        let stored = users
+            .iter_mut()
+            .find(|u| u.id == user.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != user.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        let personal = match user.anonymised_at {
+            Some(_) => PersonalDetails::default(),
+            None => stored.personal.clone(),
+        };
+        *stored = User {
+            version: user.version + 1,
+            updated_at: Utc::now(),
+            skills: normalise_skills(user.skills.clone()),
+            personal,
+            ..user
+        };
+
+        Ok(read(stored))
+    }
+
+    async fn personal(&self, id: i64) -> RepoResult<PersonalDetails> {
+        let users = self.users.lock().unwrap();
+        users
+            .iter()
+            .find(|u| u.id == id)
+            .map(|u| u.personal.clone())
+            .ok_or(RepoError::NotFound)
+    }
+
+    async fn update_personal(&self, user: User) -> RepoResult<User> {
+        let mut users = self.users.lock().unwrap();
+        let stored = users
+            .iter_mut()
+            .find(|u| u.id == user.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != user.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        stored.personal = user.personal.clone();
+        stored.version += 1;
+        stored.updated_at = Utc::now();
+
+        Ok(User { personal: user.personal, ..read(stored) })
+    }
+
+    async fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .iter()
+            .find(|u| !u.is_deleted() && u.email.eq_ignore_ascii_case(email))
+            .map(read))
+    }
+
+    async fn find_by_email_with_deleted(&self, email: &str) -> RepoResult<Option<User>> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.email.eq_ignore_ascii_case(email)).map(read))
+    }
+
+    async fn find_by_phone(&self, phone: &str) -> RepoResult<Vec<User>> {
+        let phone = normalise_phone(phone);
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .iter()
+            .filter(|u| {
+                !u.is_deleted()
+                    && u.personal.phone.as_deref().map(normalise_phone).as_ref() == Some(&phone)
+            })
+            .map(read)
+            .collect())
+    }
+
+    async fn upsert_many(&self, rows: Vec<UserUpsert>) -> RepoResult<Vec<Upserted>> {
+        let mut users = self.users.lock().unwrap();
+        // Work on a copy so a failure part way leaves nothing changed
+        let mut staged = users.clone();
+        let mut results = Vec::with_capacity(rows.len());
+        let now = Utc::now();
+
+        for row in rows {
+            let existing = staged
+                .iter_mut()
+                .find(|u| u.email.eq_ignore_ascii_case(&row.email));
+
+            match existing {
+                Some(user) if user.is_deleted() => {
+                    return Err(RepoError::Conflict(format!(
+                        "{} belongs to a deleted user",
+                        row.email
+                    )));
+                }
+                Some(user) if !row.changes(user) => results.push(Upserted {
+                    before: Some(user.clone()),
+                    after: user.clone(),
+                }),
+                Some(user) => {
+                    let before = user.clone();
+                    *user = User {
+                        version: user.version + 1,
+                        updated_at: now,
+                        ..row.apply_to(user)
+                    };
+                    results.push(Upserted { before: Some(before), after: user.clone() });
+                }
+                None => {
+                    let id = staged.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+                    let new_user = NewUser {
+                        username: row.name.clone(),
+                        email: row.email.clone(),
+                        password_hash: String::new(),
+                        role: row.role.clone().unwrap_or(UserRole::User),
+                    };
+                    let user = row.apply_to(&new_user.into_user(id, now));
+                    staged.push(user.clone());
+                    results.push(Upserted { before: None, after: user });
+                }
+            }
+        }
+
+        *users = staged;
+        Ok(results)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAuditRepo {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl InMemoryAuditRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AuditRepo for InMemoryAuditRepo {
+    async fn append(&self, entry: NewAuditEntry) -> RepoResult<AuditEntry> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let prev_hash = entries.last().map(|e| e.hash.clone()).unwrap_or_default();
+        let entry = audit::seal(entries.len() as i64 + 1, prev_hash, entry);
+        entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn list(&self, filter: &AuditFilter, page: &PageRequest<AuditSort>) -> RepoResult<Page<AuditEntry>> {
+        let entries = self.entries.lock().unwrap();
+        let matching = entries.iter().filter(|e| filter.matches(e)).cloned().collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn chain(&self) -> RepoResult<Vec<AuditEntry>> {
+        Ok(self.entries.lock().unwrap().clone())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryErasureRepo {
+    requests: Mutex<Vec<ErasureRequest>>,
+}
+
+impl InMemoryErasureRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ErasureRepo for InMemoryErasureRepo {
+    async fn create(&self, request: NewErasureRequest) -> RepoResult<ErasureRequest> {
+        let mut requests = self.requests.lock().unwrap();
+
+        if requests
+            .iter()
+            .any(|r| r.user_id == request.user_id && r.status == ErasureStatus::Pending)
+        {
+            return Err(RepoError::Conflict(ERASURE_PENDING.to_string()));
+        }
+
+        let request = ErasureRequest {
+            id: requests.len() as i64 + 1,
+            user_id: request.user_id,
+            requested_by: request.requested_by,
+            reason: request.reason,
+            status: ErasureStatus::Pending,
+            decided_by: None,
+            decided_at: None,
+            decision_note: String::new(),
+            created_at: Utc::now(),
+        };
+        requests.push(request.clone());
+
+        Ok(request)
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<ErasureRequest>> {
+        let requests = self.requests.lock().unwrap();
+        Ok(requests.iter().find(|r| r.id == id).cloned())
+    }
+
+    async fn list(
+        &self,
+        status: Option<ErasureStatus>,
+        page: &PageRequest<ErasureSort>,
+    ) -> RepoResult<Page<ErasureRequest>> {
+        let requests = self.requests.lock().unwrap();
+        let matching = requests
+            .iter()
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .cloned()
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn decide(
+        &self,
+        id: i64,
+        status: ErasureStatus,
+        decided_by: &str,
+        note: &str,
+    ) -> RepoResult<ErasureRequest> {
+        let mut requests = self.requests.lock().unwrap();
+
+        let request = requests
+            .iter_mut()
+            .find(|r| r.id == id)
+            .ok_or(RepoError::NotFound)?;
+        if request.status != ErasureStatus::Pending {
+            return Err(RepoError::Conflict(ERASURE_DECIDED.to_string()));
+        }
+
+        request.status = status;
+        request.decided_by = Some(decided_by.to_string());
+        request.decided_at = Some(Utc::now());
+        request.decision_note = note.to_string();
+
+        Ok(request.clone())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTeamRepo {
+    teams: Mutex<Vec<Team>>,
+}
+
+impl InMemoryTeamRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TeamRepo for InMemoryTeamRepo {
+    async fn create(&self, name: &str) -> RepoResult<Team> {
+        let mut teams = self.teams.lock().unwrap();
+
+        if teams.iter().any(|t| t.name.eq_ignore_ascii_case(name)) {
+            return Err(RepoError::Conflict("Team name already in use".to_string()));
+        }
+
+        let now = Utc::now();
+        let team = Team {
+            id: teams.iter().map(|t| t.id).max().unwrap_or(0) + 1,
+            name: name.to_string(),
+            parent_id: None,
+            manager_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+        teams.push(team.clone());
+
+        Ok(team)
+    }
+
+    async fn list(&self, page: &PageRequest<TeamSort>) -> RepoResult<Page<Team>> {
+        let teams = self.teams.lock().unwrap().clone();
+        Ok(page.apply(teams))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<Team>> {
+        let teams = self.teams.lock().unwrap();
+        Ok(teams.iter().find(|t| t.id == id).cloned())
+    }
+
+    async fn update(&self, team: Team) -> RepoResult<Team> {
+        let mut teams = self.teams.lock().unwrap();
+
+        if teams.iter().any(|t| t.id != team.id && t.name.eq_ignore_ascii_case(&team.name)) {
+            return Err(RepoError::Conflict("Team name already in use".to_string()));
+        }
+        let stored = teams.iter_mut().find(|t| t.id == team.id).ok_or(RepoError::NotFound)?;
+        *stored = Team { updated_at: Utc::now(), created_at: stored.created_at, ..team };
+
+        Ok(stored.clone())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryProfileRepo {
+    records: Mutex<Vec<Employment>>,
+}
+
+impl InMemoryProfileRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ProfileRepo for InMemoryProfileRepo {
+    async fn get(&self, user_id: i64) -> RepoResult<Option<Employment>> {
+        let records = self.records.lock().unwrap();
+        Ok(records.iter().find(|r| r.user_id == user_id).cloned())
+    }
+
+    async fn get_many(&self, user_ids: &[i64]) -> RepoResult<Vec<Employment>> {
+        let records = self.records.lock().unwrap();
+        Ok(records.iter().filter(|r| user_ids.contains(&r.user_id)).cloned().collect())
+    }
+
+    async fn save(&self, mut employment: Employment) -> RepoResult<Employment> {
+        let mut records = self.records.lock().unwrap();
+        let now = Utc::now();
+        employment.qualifications.sort_by_key(|q| q.name.to_lowercase());
+
+        let existing = records.iter_mut().find(|r| r.user_id == employment.user_id);
+        match existing {
+            None if employment.version == 0 => {
+                employment.version = 1;
+                employment.created_at = now;
+                employment.updated_at = now;
+                records.push(employment.clone());
+            }
+            None => return Err(RepoError::NotFound),
+            Some(_) if employment.version == 0 => {
+                return Err(RepoError::Conflict("Profile already exists".to_string()));
+            }
+            Some(stored) if stored.version != employment.version => {
+                return Err(RepoError::StaleVersion);
+            }
+            Some(stored) => {
+                employment.version += 1;
+                employment.created_at = stored.created_at;
+                employment.updated_at = now;
+                *stored = employment.clone();
+            }
+        }
+
+        Ok(employment)
+    }
+
+    async fn delete(&self, user_id: i64) -> RepoResult<()> {
+        let mut records = self.records.lock().unwrap();
+        let before = records.len();
+        records.retain(|r| r.user_id != user_id);
+
+        if records.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryRotaRepo {
+    rotas: Mutex<Vec<Rota>>,
+    snapshots: Mutex<Vec<RotaSnapshot>>,
+}
+
+impl InMemoryRotaRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RotaRepo for InMemoryRotaRepo {
+    async fn create(&self, rota: NewRota) -> RepoResult<Rota> {
+        let mut rotas = self.rotas.lock().unwrap();
+        if rotas
+            .iter()
+            .any(|r| !r.is_deleted() && r.team_id == rota.team_id && r.overlaps(rota.period_start, rota.period_end))
+        {
+            return Err(RepoError::Conflict(ROTA_OVERLAPS.to_string()));
+        }
+
+        let id = rotas.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        let rota = rota.into_rota(id, Utc::now());
+        rotas.push(rota.clone());
+
+        Ok(rota)
+    }
+
+    async fn list(&self, filter: &RotaFilter, page: &PageRequest<RotaSort>) -> RepoResult<Page<Rota>> {
+        let rotas = self.rotas.lock().unwrap();
+        let matching = rotas
+            .iter()
+            .filter(|r| !r.is_deleted() && filter.matches(r))
+            .cloned()
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<Rota>> {
+        let rotas = self.rotas.lock().unwrap();
+        Ok(rotas.iter().find(|r| r.id == id && !r.is_deleted()).cloned())
+    }
+
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Rota>> {
+        let rotas = self.rotas.lock().unwrap();
+        Ok(rotas.iter().find(|r| r.id == id).cloned())
+    }
+
+    async fn update(&self, rota: Rota) -> RepoResult<Rota> {
+        let mut rotas = self.rotas.lock().unwrap();
+        let stored = rotas
+            .iter_mut()
+            .find(|r| r.id == rota.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != rota.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = Rota {
+            version: rota.version + 1,
+            updated_at: Utc::now(),
+            ..rota
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn publish(&self, rota: Rota, shifts: Value) -> RepoResult<(Rota, RotaSnapshot)> {
+        let snapshot = RotaSnapshot {
+            rota_id: rota.id,
+            revision: rota.revision,
+            published_by: rota.published_by.clone(),
+            published_at: rota.published_at.unwrap_or_else(Utc::now),
+            shifts,
+        };
+        let rota = self.update(rota).await?;
+        self.snapshots.lock().unwrap().push(snapshot.clone());
+
+        Ok((rota, snapshot))
+    }
+
+    async fn snapshot(&self, rota_id: i64, revision: Option<i64>) -> RepoResult<Option<RotaSnapshot>> {
+        let snapshots = self.snapshots.lock().unwrap();
+        Ok(snapshots
+            .iter()
+            .filter(|s| s.rota_id == rota_id && revision.is_none_or(|r| s.revision == r))
+            .max_by_key(|s| s.revision)
+            .cloned())
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+        let mut rotas = self.rotas.lock().unwrap();
+        let purged: Vec<i64> = rotas
+            .iter()
+            .filter(|r| r.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|r| r.id)
+            .collect();
+        rotas.retain(|r| !purged.contains(&r.id));
+        self.snapshots.lock().unwrap().retain(|s| !purged.contains(&s.rota_id));
+
+        Ok(purged)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryShiftRepo {
+    shifts: Mutex<Vec<Shift>>,
+    assignments: Mutex<Vec<Assignment>>,
+    occurrences: Mutex<Vec<ShiftOccurrence>>,
+}
+
+impl InMemoryShiftRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ShiftRepo for InMemoryShiftRepo {
+    async fn create(&self, shift: NewShift) -> RepoResult<Shift> {
+        let mut shifts = self.shifts.lock().unwrap();
+        let id = shifts.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+        let shift = shift.into_shift(id, Utc::now());
+        shifts.push(shift.clone());
+
+        Ok(shift)
+    }
+
+    async fn list(&self, filter: &ShiftFilter, page: &PageRequest<ShiftSort>) -> RepoResult<Page<Shift>> {
+        let shifts = self.shifts.lock().unwrap();
+        let matching = shifts
+            .iter()
+            .filter(|s| !s.is_deleted() && filter.matches(s))
+            .cloned()
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<Shift>> {
+        let shifts = self.shifts.lock().unwrap();
+        Ok(shifts.iter().find(|s| s.id == id && !s.is_deleted()).cloned())
+    }
+
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Shift>> {
+        let shifts = self.shifts.lock().unwrap();
+        Ok(shifts.iter().find(|s| s.id == id).cloned())
+    }
+
+    async fn deleted_with_rota(&self, rota_id: i64, deleted_at: DateTime<Utc>) -> RepoResult<Vec<Shift>> {
+        let shifts = self.shifts.lock().unwrap();
+        Ok(shifts
+            .iter()
+            .filter(|s| s.rota_id == Some(rota_id) && s.deleted_at == Some(deleted_at))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, shift: Shift) -> RepoResult<Shift> {
+        let mut shifts = self.shifts.lock().unwrap();
+        let stored = shifts
+            .iter_mut()
+            .find(|s| s.id == shift.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != shift.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = Shift {
+            version: shift.version + 1,
+            updated_at: Utc::now(),
+            ..shift
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn assign(&self, assignment: NewAssignment) -> RepoResult<Assignment> {
+        let mut assignments = self.assignments.lock().unwrap();
+        if assignments
+            .iter()
+            .any(|a| a.shift_id == assignment.shift_id && a.user_id == assignment.user_id)
+        {
+            return Err(RepoError::Conflict(ALREADY_ASSIGNED.to_string()));
+        }
+
+        let assignment = Assignment {
+            id: assignments.iter().map(|a| a.id).max().unwrap_or(0) + 1,
+            shift_id: assignment.shift_id,
+            user_id: assignment.user_id,
+            assigned_by: assignment.assigned_by,
+            forced: assignment.forced,
+            override_reason: assignment.override_reason,
+            conflicts: assignment.conflicts,
+            created_at: Utc::now(),
+        };
+        assignments.push(assignment.clone());
+
+        Ok(assignment)
+    }
+
+    async fn assignments(&self, shift_id: i64) -> RepoResult<Vec<Assignment>> {
+        let assignments = self.assignments.lock().unwrap();
+        Ok(assignments.iter().filter(|a| a.shift_id == shift_id).cloned().collect())
+    }
+
+    async fn unassign(&self, shift_id: i64, user_id: i64) -> RepoResult<()> {
+        let mut assignments = self.assignments.lock().unwrap();
+        let before = assignments.len();
+        assignments.retain(|a| !(a.shift_id == shift_id && a.user_id == user_id));
+
+        if assignments.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn unassign_future(&self, user_id: i64, from: DateTime<Utc>) -> RepoResult<u64> {
+        let future: Vec<i64> = {
+            let shifts = self.shifts.lock().unwrap();
+            shifts.iter().filter(|s| s.starts_at > from).map(|s| s.id).collect()
+        };
+        let mut assignments = self.assignments.lock().unwrap();
+        let before = assignments.len();
+        assignments.retain(|a| !(a.user_id == user_id && future.contains(&a.shift_id)));
+
+        Ok((before - assignments.len()) as u64)
+    }
+
+    async fn assigned_to(&self, user_id: i64, from: DateTime<Utc>, to: DateTime<Utc>) -> RepoResult<Vec<Shift>> {
+        let shift_ids: Vec<i64> = {
+            let assignments = self.assignments.lock().unwrap();
+            assignments.iter().filter(|a| a.user_id == user_id).map(|a| a.shift_id).collect()
+        };
+        let shifts = self.shifts.lock().unwrap();
+        let mut assigned: Vec<Shift> = shifts
+            .iter()
+            .filter(|s| !s.is_deleted() && shift_ids.contains(&s.id) && s.ends_at > from && s.starts_at < to)
+            .cloned()
+            .collect();
+        assigned.sort_by_key(|s| (s.starts_at, s.id));
+
+        Ok(assigned)
+    }
+
+    async fn all_assigned_to(&self, user_id: i64) -> RepoResult<Vec<Shift>> {
+        let shift_ids: Vec<i64> = {
+            let assignments = self.assignments.lock().unwrap();
+            assignments.iter().filter(|a| a.user_id == user_id).map(|a| a.shift_id).collect()
+        };
+        let shifts = self.shifts.lock().unwrap();
+        let mut assigned: Vec<Shift> = shifts.iter().filter(|s| shift_ids.contains(&s.id)).cloned().collect();
+        assigned.sort_by_key(|s| (s.starts_at, s.id));
+
+        Ok(assigned)
+    }
+
+    async fn generate(&self, generated: Vec<GeneratedShift>) -> RepoResult<Vec<Shift>> {
+        let mut shifts = self.shifts.lock().unwrap();
+        let mut occurrences = self.occurrences.lock().unwrap();
+        let mut taken: Vec<(i64, NaiveDate)> = occurrences.iter().map(|o| (o.recurrence_id, o.occurrence_date)).collect();
+        for g in &generated {
+            let key = (g.recurrence_id, g.occurrence_date);
+            if taken.contains(&key) {
+                return Err(RepoError::Conflict(ALREADY_GENERATED.to_string()));
+            }
+            taken.push(key);
+        }
+
+        let now = Utc::now();
+        let mut created = Vec::new();
+        for g in generated {
+            let id = shifts.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+            let shift = g.shift.into_shift(id, now);
+            shifts.push(shift.clone());
+            occurrences.push(ShiftOccurrence {
+                shift_id: id,
+                recurrence_id: g.recurrence_id,
+                occurrence_date: g.occurrence_date,
+            });
+            created.push(shift);
+        }
+
+        Ok(created)
+    }
+
+    async fn occurrences(&self, recurrence_id: i64) -> RepoResult<Vec<ShiftOccurrence>> {
+        let occurrences = self.occurrences.lock().unwrap();
+        let mut found: Vec<ShiftOccurrence> =
+            occurrences.iter().filter(|o| o.recurrence_id == recurrence_id).cloned().collect();
+        found.sort_by_key(|o| o.occurrence_date);
+
+        Ok(found)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+        let mut shifts = self.shifts.lock().unwrap();
+        let purged: Vec<i64> = shifts
+            .iter()
+            .filter(|s| s.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|s| s.id)
+            .collect();
+        shifts.retain(|s| !purged.contains(&s.id));
+        self.assignments.lock().unwrap().retain(|a| !purged.contains(&a.shift_id));
+        self.occurrences.lock().unwrap().retain(|o| !purged.contains(&o.shift_id));
+
+        Ok(purged)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTemplateRepo {
+    templates: Mutex<Vec<ShiftTemplate>>,
+    recurrences: Mutex<Vec<ShiftRecurrence>>,
+}
+
+impl InMemoryTemplateRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TemplateRepo for InMemoryTemplateRepo {
+    async fn create(&self, template: NewShiftTemplate) -> RepoResult<ShiftTemplate> {
+        let mut templates = self.templates.lock().unwrap();
+        let id = templates.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+        let template = template.into_template(id, Utc::now());
+        templates.push(template.clone());
+
+        Ok(template)
+    }
+
+    async fn list(&self, team: Option<i64>, page: &PageRequest<TemplateSort>) -> RepoResult<Page<ShiftTemplate>> {
+        let templates = self.templates.lock().unwrap();
+        let matching = templates
+            .iter()
+            .filter(|t| !t.is_deleted() && team.is_none_or(|team| t.team_id == Some(team)))
+            .cloned()
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<ShiftTemplate>> {
+        let templates = self.templates.lock().unwrap();
+        Ok(templates.iter().find(|t| t.id == id && !t.is_deleted()).cloned())
+    }
+
+    async fn update(&self, template: ShiftTemplate) -> RepoResult<ShiftTemplate> {
+        let mut templates = self.templates.lock().unwrap();
+        let stored = templates
+            .iter_mut()
+            .find(|t| t.id == template.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != template.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = ShiftTemplate {
+            version: template.version + 1,
+            updated_at: Utc::now(),
+            ..template
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn add_recurrence(&self, recurrence: NewRecurrence) -> RepoResult<ShiftRecurrence> {
+        let mut recurrences = self.recurrences.lock().unwrap();
+        let recurrence = ShiftRecurrence {
+            id: recurrences.iter().map(|r| r.id).max().unwrap_or(0) + 1,
+            template_id: recurrence.template_id,
+            rule: recurrence.rule,
+            starts_on: recurrence.starts_on,
+            exceptions: recurrence.exceptions,
+            created_at: Utc::now(),
+        };
+        recurrences.push(recurrence.clone());
+
+        Ok(recurrence)
+    }
+
+    async fn recurrences(&self, template_id: i64) -> RepoResult<Vec<ShiftRecurrence>> {
+        let recurrences = self.recurrences.lock().unwrap();
+        Ok(recurrences.iter().filter(|r| r.template_id == template_id).cloned().collect())
+    }
+
+    async fn get_recurrence(&self, id: i64) -> RepoResult<Option<ShiftRecurrence>> {
+        let recurrences = self.recurrences.lock().unwrap();
+        Ok(recurrences.iter().find(|r| r.id == id).cloned())
+    }
+
+    async fn remove_recurrence(&self, id: i64) -> RepoResult<()> {
+        let mut recurrences = self.recurrences.lock().unwrap();
+        let before = recurrences.len();
+        recurrences.retain(|r| r.id != id);
+
+        if recurrences.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+// A shift a pattern made: pattern, template, day and the shift's id
+type PatternShift = (i64, i64, NaiveDate, i64);
+
+#[derive(Default)]
+pub struct InMemoryPatternRepo {
+    patterns: Mutex<Vec<Pattern>>,
+    members: Mutex<Vec<(i64, PatternMember)>>,
+    overrides: Mutex<Vec<(i64, StoredOverride)>>,
+    shifts: Mutex<Vec<PatternShift>>,
+}
+
+impl InMemoryPatternRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PatternRepo for InMemoryPatternRepo {
+    async fn create(&self, pattern: NewPattern) -> RepoResult<Pattern> {
+        let mut patterns = self.patterns.lock().unwrap();
+        let id = patterns.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        let pattern = pattern.into_pattern(id, Utc::now());
+        patterns.push(pattern.clone());
+
+        Ok(pattern)
+    }
+
+    async fn list(&self, team: Option<i64>, page: &PageRequest<PatternSort>) -> RepoResult<Page<Pattern>> {
+        let patterns = self.patterns.lock().unwrap();
+        let matching = patterns
+            .iter()
+            .filter(|p| !p.is_deleted() && team.is_none_or(|team| p.team_id == team))
+            .cloned()
+            .collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<Pattern>> {
+        let patterns = self.patterns.lock().unwrap();
+        Ok(patterns.iter().find(|p| p.id == id && !p.is_deleted()).cloned())
+    }
+
+    async fn update(&self, pattern: Pattern) -> RepoResult<Pattern> {
+        let mut patterns = self.patterns.lock().unwrap();
+        let stored = patterns
+            .iter_mut()
+            .find(|p| p.id == pattern.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != pattern.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = Pattern {
+            version: pattern.version + 1,
+            updated_at: Utc::now(),
+            ..pattern
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn members(&self, pattern_id: i64) -> RepoResult<Vec<PatternMember>> {
+        let members = self.members.lock().unwrap();
+        let mut found: Vec<PatternMember> =
+            members.iter().filter(|(id, _)| *id == pattern_id).map(|(_, m)| *m).collect();
+        found.sort_by_key(|m| (m.offset, m.user_id));
+
+        Ok(found)
+    }
+
+    async fn set_members(&self, pattern_id: i64, new_members: &[PatternMember]) -> RepoResult<()> {
+        let mut members = self.members.lock().unwrap();
+        members.retain(|(id, _)| *id != pattern_id);
+        members.extend(new_members.iter().map(|m| (pattern_id, *m)));
+
+        Ok(())
+    }
+
+    async fn overrides(&self, pattern_id: i64, from: NaiveDate, to: NaiveDate) -> RepoResult<Vec<StoredOverride>> {
+        let overrides = self.overrides.lock().unwrap();
+        let mut found: Vec<StoredOverride> = overrides
+            .iter()
+            .filter(|(id, o)| *id == pattern_id && from <= o.change.date && o.change.date <= to)
+            .map(|(_, o)| o.clone())
+            .collect();
+        found.sort_by_key(|o| (o.change.date, o.change.user_id));
+
+        Ok(found)
+    }
+
+    async fn set_override(
+        &self,
+        pattern_id: i64,
+        change: PatternOverride,
+        created_by: Option<String>,
+    ) -> RepoResult<StoredOverride> {
+        let mut overrides = self.overrides.lock().unwrap();
+        overrides.retain(|(id, o)| {
+            !(*id == pattern_id && o.change.user_id == change.user_id && o.change.date == change.date)
+        });
+        let stored = StoredOverride { change, created_by, created_at: Utc::now() };
+        overrides.push((pattern_id, stored.clone()));
+
+        Ok(stored)
+    }
+
+    async fn remove_override(&self, pattern_id: i64, user_id: i64, date: NaiveDate) -> RepoResult<()> {
+        let mut overrides = self.overrides.lock().unwrap();
+        let before = overrides.len();
+        overrides.retain(|(id, o)| !(*id == pattern_id && o.change.user_id == user_id && o.change.date == date));
+
+        if overrides.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn shift_for(&self, pattern_id: i64, template_id: i64, date: NaiveDate) -> RepoResult<Option<i64>> {
+        let shifts = self.shifts.lock().unwrap();
+        Ok(shifts
+            .iter()
+            .find(|(p, t, d, _)| *p == pattern_id && *t == template_id && *d == date)
+            .map(|(_, _, _, shift_id)| *shift_id))
+    }
+
+    async fn link_shift(&self, pattern_id: i64, template_id: i64, date: NaiveDate, shift_id: i64) -> RepoResult<()> {
+        let mut shifts = self.shifts.lock().unwrap();
+        if shifts.iter().any(|(p, t, d, _)| *p == pattern_id && *t == template_id && *d == date) {
+            return Err(RepoError::Conflict(PATTERN_SHIFT_EXISTS.to_string()));
+        }
+        shifts.push((pattern_id, template_id, date, shift_id));
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAvailabilityRepo {
+    weekly: Mutex<Vec<WeeklyAvailability>>,
+    exceptions: Mutex<Vec<AvailabilityException>>,
+}
+
+impl InMemoryAvailabilityRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AvailabilityRepo for InMemoryAvailabilityRepo {
+    async fn weekly(&self, user_id: i64) -> RepoResult<Option<WeeklyAvailability>> {
+        let weekly = self.weekly.lock().unwrap();
+        Ok(weekly.iter().find(|w| w.user_id == user_id).cloned())
+    }
+
+    async fn save_weekly(&self, mut availability: WeeklyAvailability) -> RepoResult<WeeklyAvailability> {
+        let mut weekly = self.weekly.lock().unwrap();
+        let now = Utc::now();
+
+        let existing = weekly.iter_mut().find(|w| w.user_id == availability.user_id);
+        match existing {
+            None if availability.version == 0 => {
+                availability.version = 1;
+                availability.created_at = Some(now);
+                availability.updated_at = Some(now);
+                weekly.push(availability.clone());
+            }
+            None => return Err(RepoError::NotFound),
+            Some(_) if availability.version == 0 => {
+                return Err(RepoError::Conflict(AVAILABILITY_EXISTS.to_string()));
+            }
+            Some(stored) if stored.version != availability.version => {
+                return Err(RepoError::StaleVersion);
+            }
+            Some(stored) => {
+                availability.version += 1;
+                availability.created_at = stored.created_at;
+                availability.updated_at = Some(now);
+                *stored = availability.clone();
+            }
+        }
+
+        Ok(availability)
+    }
+
+    async fn exceptions(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> RepoResult<Vec<AvailabilityException>> {
+        let exceptions = self.exceptions.lock().unwrap();
+        let mut found: Vec<AvailabilityException> = exceptions
+            .iter()
+            .filter(|e| {
+                e.user_id == user_id
+                    && from.is_none_or(|from| from <= e.date)
+                    && to.is_none_or(|to| e.date <= to)
+            })
+            .cloned()
+            .collect();
+        found.sort_by_key(|e| (e.date, e.start_time, e.id));
+
+        Ok(found)
+    }
+
+    async fn add_exception(&self, exception: NewException) -> RepoResult<AvailabilityException> {
+        let mut exceptions = self.exceptions.lock().unwrap();
+        let id = exceptions.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        let stored = exception.into_exception(id, Utc::now());
+        exceptions.push(stored.clone());
+
+        Ok(stored)
+    }
+
+    async fn get_exception(&self, id: i64) -> RepoResult<Option<AvailabilityException>> {
+        let exceptions = self.exceptions.lock().unwrap();
+        Ok(exceptions.iter().find(|e| e.id == id).cloned())
+    }
+
+    async fn remove_exception(&self, id: i64) -> RepoResult<()> {
+        let mut exceptions = self.exceptions.lock().unwrap();
+        let before = exceptions.len();
+        exceptions.retain(|e| e.id != id);
+
+        if exceptions.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+        let mut exceptions = self.exceptions.lock().unwrap();
+        for exception in exceptions.iter_mut().filter(|e| e.user_id == user_id) {
+            exception.note.clear();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryLeaveRepo {
+    requests: Mutex<Vec<LeaveRequest>>,
+    entitlements: Mutex<Vec<LeaveEntitlement>>,
+}
+
+impl InMemoryLeaveRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LeaveRepo for InMemoryLeaveRepo {
+    async fn create(&self, request: NewLeaveRequest) -> RepoResult<LeaveRequest> {
+        let mut requests = self.requests.lock().unwrap();
+        let id = requests.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        let request = request.into_request(id, Utc::now());
+        requests.push(request.clone());
+
+        Ok(request)
+    }
+
+    async fn list(&self, filter: &LeaveFilter, page: &PageRequest<LeaveSort>) -> RepoResult<Page<LeaveRequest>> {
+        let requests = self.requests.lock().unwrap();
+        let matching = requests.iter().filter(|r| filter.matches(r)).cloned().collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get(&self, id: i64) -> RepoResult<Option<LeaveRequest>> {
+        let requests = self.requests.lock().unwrap();
+        Ok(requests.iter().find(|r| r.id == id && !r.is_deleted()).cloned())
+    }
+
+    async fn update(&self, request: LeaveRequest) -> RepoResult<LeaveRequest> {
+        let mut requests = self.requests.lock().unwrap();
+        let stored = requests
+            .iter_mut()
+            .find(|r| r.id == request.id)
+            .ok_or(RepoError::NotFound)?;
+        if stored.version != request.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = LeaveRequest {
+            version: request.version + 1,
+            updated_at: Utc::now(),
+            ..request
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn approved_between(&self, user_id: i64, from: NaiveDate, to: NaiveDate) -> RepoResult<Vec<ApprovedLeave>> {
+        let filter = LeaveFilter {
+            user: Some(user_id),
+            status: Some(LeaveStatus::Approved),
+            from: Some(from),
+            to: Some(to),
+            ..LeaveFilter::default()
+        };
+        let requests = self.requests.lock().unwrap();
+        let mut matching: Vec<&LeaveRequest> = requests.iter().filter(|r| filter.matches(r)).collect();
+        matching.sort_by_key(|r| (r.start_date, r.id));
+
+        Ok(matching.into_iter().map(LeaveRequest::approved_leave).collect())
+    }
+
+    async fn entitlements(&self, user_id: i64, year: i32) -> RepoResult<Vec<LeaveEntitlement>> {
+        let entitlements = self.entitlements.lock().unwrap();
+        let mut found: Vec<LeaveEntitlement> =
+            entitlements.iter().filter(|e| e.user_id == user_id && e.year == year).cloned().collect();
+        found.sort_by_key(|e| e.leave_type.to_string());
+
+        Ok(found)
+    }
+
+    async fn save_entitlement(&self, mut entitlement: LeaveEntitlement) -> RepoResult<LeaveEntitlement> {
+        let mut entitlements = self.entitlements.lock().unwrap();
+        entitlements.retain(|e| {
+            !(e.user_id == entitlement.user_id && e.year == entitlement.year && e.leave_type == entitlement.leave_type)
+        });
+        entitlement.updated_at = Utc::now();
+        entitlements.push(entitlement.clone());
+
+        Ok(entitlement)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+        let mut requests = self.requests.lock().unwrap();
+        let purged: Vec<i64> = requests
+            .iter()
+            .filter(|r| r.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|r| r.id)
+            .collect();
+        requests.retain(|r| !purged.contains(&r.id));
+
+        Ok(purged)
+    }
+
+    // Nothing to hold: the in-memory store has no transactions
+    async fn lock_balances(&self, _user_id: i64) -> RepoResult<()> {
+        Ok(())
+    }
+
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+        let mut requests = self.requests.lock().unwrap();
+        let now = Utc::now();
+        for request in requests.iter_mut().filter(|r| r.user_id == user_id) {
+            if !request.reason.is_empty() || !request.decision_note.is_empty() {
+                request.reason.clear();
+                request.decision_note.clear();
+                request.version += 1;
+                request.updated_at = now;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryLeavePolicyRepo {
+    blackouts: Mutex<Vec<Blackout>>,
+    limits: Mutex<Vec<LeaveLimit>>,
+}
+
+impl InMemoryLeavePolicyRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LeavePolicyRepo for InMemoryLeavePolicyRepo {
+    async fn blackouts(&self, filter: &BlackoutFilter, page: &PageRequest<BlackoutSort>) -> RepoResult<Page<Blackout>> {
+        let blackouts = self.blackouts.lock().unwrap();
+        let matching = blackouts.iter().filter(|b| filter.matches(b)).cloned().collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn create_blackout(&self, blackout: NewBlackout) -> RepoResult<Blackout> {
+        let mut blackouts = self.blackouts.lock().unwrap();
+        let id = blackouts.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        let blackout = blackout.into_blackout(id, Utc::now());
+        blackouts.push(blackout.clone());
+
+        Ok(blackout)
+    }
+
+    async fn get_blackout(&self, id: i64) -> RepoResult<Option<Blackout>> {
+        let blackouts = self.blackouts.lock().unwrap();
+        Ok(blackouts.iter().find(|b| b.id == id).cloned())
+    }
+
+    async fn remove_blackout(&self, id: i64) -> RepoResult<()> {
+        let mut blackouts = self.blackouts.lock().unwrap();
+        let before = blackouts.len();
+        blackouts.retain(|b| b.id != id);
+
+        if blackouts.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn limits(&self, team_id: i64) -> RepoResult<Vec<LeaveLimit>> {
+        let limits = self.limits.lock().unwrap();
+        let mut found: Vec<LeaveLimit> = limits.iter().filter(|l| l.team_id == team_id).cloned().collect();
+        found.sort_by_key(|l| l.skill.as_ref().map(|skill| skill.to_lowercase()));
+
+        Ok(found)
+    }
+
+    async fn create_limit(&self, limit: NewLeaveLimit) -> RepoResult<LeaveLimit> {
+        let mut limits = self.limits.lock().unwrap();
+        let skill = limit.skill.as_ref().map(|skill| skill.to_lowercase());
+        if limits.iter().any(|l| l.team_id == limit.team_id && l.skill.as_ref().map(|s| s.to_lowercase()) == skill) {
+            return Err(RepoError::Conflict(LEAVE_LIMIT_EXISTS.to_string()));
+        }
+        let id = limits.iter().map(|l| l.id).max().unwrap_or(0) + 1;
+        let limit = limit.into_limit(id, Utc::now());
+        limits.push(limit.clone());
+
+        Ok(limit)
+    }
+
+    async fn get_limit(&self, id: i64) -> RepoResult<Option<LeaveLimit>> {
+        let limits = self.limits.lock().unwrap();
+        Ok(limits.iter().find(|l| l.id == id).cloned())
+    }
+
+    async fn remove_limit(&self, id: i64) -> RepoResult<()> {
+        let mut limits = self.limits.lock().unwrap();
+        let before = limits.len();
+        limits.retain(|l| l.id != id);
+
+        if limits.len() == before {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryToilRepo {
+    overtime: Mutex<Vec<Overtime>>,
+    ledger: Mutex<Vec<ToilEntry>>,
+}
+
+impl InMemoryToilRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ToilRepo for InMemoryToilRepo {
+    async fn create_overtime(&self, overtime: NewOvertime) -> RepoResult<Overtime> {
+        let mut claims = self.overtime.lock().unwrap();
+        let id = claims.iter().map(|o| o.id).max().unwrap_or(0) + 1;
+        let overtime = overtime.into_overtime(id, Utc::now());
+        claims.push(overtime.clone());
+
+        Ok(overtime)
+    }
+
+    async fn overtime(&self, filter: &OvertimeFilter, page: &PageRequest<OvertimeSort>) -> RepoResult<Page<Overtime>> {
+        let claims = self.overtime.lock().unwrap();
+        let matching = claims.iter().filter(|o| filter.matches(o)).cloned().collect();
+
+        Ok(page.apply(matching))
+    }
+
+    async fn get_overtime(&self, id: i64) -> RepoResult<Option<Overtime>> {
+        let claims = self.overtime.lock().unwrap();
+        Ok(claims.iter().find(|o| o.id == id).cloned())
+    }
+
+    async fn update_overtime(&self, overtime: Overtime) -> RepoResult<Overtime> {
+        let mut claims = self.overtime.lock().unwrap();
+        let stored = claims.iter_mut().find(|o| o.id == overtime.id).ok_or(RepoError::NotFound)?;
+        if stored.version != overtime.version {
+            return Err(RepoError::StaleVersion);
+        }
+
+        *stored = Overtime {
+            version: overtime.version + 1,
+            updated_at: Utc::now(),
+            ..overtime
+        };
+
+        Ok(stored.clone())
+    }
+
+    async fn ledger(&self, user_id: i64) -> RepoResult<Vec<ToilEntry>> {
+        let ledger = self.ledger.lock().unwrap();
+        let mut found: Vec<ToilEntry> = ledger.iter().filter(|e| e.user_id == user_id).cloned().collect();
+        found.sort_by_key(|e| (e.date, e.id));
+
+        Ok(found)
+    }
+
+    async fn add_entry(&self, entry: NewToilEntry) -> RepoResult<ToilEntry> {
+        let mut ledger = self.ledger.lock().unwrap();
+        if entry.overtime_id.is_some() && ledger.iter().any(|e| e.overtime_id == entry.overtime_id) {
+            return Err(RepoError::Conflict(OVERTIME_CREDITED.to_string()));
+        }
+        let id = ledger.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        let entry = entry.into_entry(id, Utc::now());
+        ledger.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+        let mut claims = self.overtime.lock().unwrap();
+        let now = Utc::now();
+        for claim in claims.iter_mut().filter(|o| o.user_id == user_id) {
+            if !claim.reason.is_empty() || !claim.decision_note.is_empty() {
+                claim.reason.clear();
+                claim.decision_note.clear();
+                claim.version += 1;
+                claim.updated_at = now;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+//! Storage abstraction. Handlers only see the traits defined here; the
+//! concrete backend is picked when the `AppState` is built.
+
+pub mod memory;
+pub mod sql;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use std::fmt;
+
+use rota_core::user::{NewUser, PersonalDetails, User};
+
+use crate::encryption::CryptoError;
+use crate::models::{
+    assignment::{ApprovedLeave, Assignment, NewAssignment},
+    availability::{AvailabilityException, NewException, WeeklyAvailability},
+    audit::{AuditEntry, AuditFilter, AuditSort, NewAuditEntry},
+    erasure::{ErasureRequest, ErasureSort, ErasureStatus, NewErasureRequest},
+    leave::{LeaveEntitlement, LeaveFilter, LeaveRequest, LeaveSort, NewLeaveRequest},
+    leave_policy::{Blackout, BlackoutFilter, BlackoutSort, LeaveLimit, NewBlackout, NewLeaveLimit},
+    pattern::{NewPattern, Pattern, PatternMember, PatternOverride, PatternSort, StoredOverride},
+    profile::Employment,
+    rota::{NewRota, Rota, RotaFilter, RotaSnapshot, RotaSort},
+    shift::{NewShift, Shift, ShiftFilter, ShiftSort},
+    team::{Team, TeamSort},
+    template::{
+        GeneratedShift, NewRecurrence, NewShiftTemplate, ShiftOccurrence, ShiftRecurrence, ShiftTemplate, TemplateSort,
+    },
+    toil::{NewOvertime, NewToilEntry, Overtime, OvertimeFilter, OvertimeSort, ToilEntry},
+    user::{Upserted, UserFilter, UserSort, UserUpsert},
+};
+use crate::pagination::{Page, PageRequest};
+
+// Errors returned by every repository implementation
+#[derive(Debug)]
+pub enum RepoError {
+    NotFound,
+    // A uniqueness or integrity rule would be broken; the message is client-facing
+    Conflict(String),
+    // An update was based on an older version than the one stored
+    StaleVersion,
+    // An encrypted field couldn't be sealed or opened
+    Encryption(CryptoError),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoError::NotFound => write!(f, "Record not found"),
+            RepoError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            RepoError::StaleVersion => write!(f, "Record was modified concurrently"),
+            RepoError::Encryption(err) => write!(f, "Encryption error: {}", err),
+            RepoError::Database(err) => write!(f, "Database error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RepoError {}
+
+impl From<CryptoError> for RepoError {
+    fn from(err: CryptoError) -> Self {
+        RepoError::Encryption(err)
+    }
+}
+
+impl From<sqlx::Error> for RepoError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => RepoError::NotFound,
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                RepoError::Conflict("Record already exists".to_string())
+            }
+            other => RepoError::Database(other),
+        }
+    }
+}
+
+pub type RepoResult<T> = Result<T, RepoError>;
+
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    // Store a new user. Fails with `Conflict` if the email is already taken.
+    async fn create(&self, user: NewUser) -> RepoResult<User>;
+
+    // One page of the users that haven't been deleted and match `filter`
+    async fn list(&self, filter: &UserFilter, page: &PageRequest<UserSort>) -> RepoResult<Page<User>>;
+
+    // Deleted users are treated as missing, as in every lookup below
+    async fn get(&self, id: i64) -> RepoResult<Option<User>>;
+
+    // A user whether or not they have been soft-deleted, for restore and retention
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<User>>;
+
+    // Users soft-deleted before `cutoff` whose data hasn't been anonymised yet
+    async fn deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<User>>;
+
+    // Save changes to an existing user. `user.version` must be the version that
+    // was read; the stored row is only written if it still has that version,
+    // otherwise `StaleVersion` is returned. The saved copy has the next version.
+    // Personal details are left as stored, unless the user has been
+    // anonymised, which removes them.
+    async fn update(&self, user: User) -> RepoResult<User>;
+
+    // The user's decrypted personal details, which no other read fills in.
+    // Fails with `NotFound` if there is no such user, and with `Encryption`
+    // if the details can't be decrypted.
+    async fn personal(&self, id: i64) -> RepoResult<PersonalDetails>;
+
+    // Save `user.personal` under the same version rules as `update`, leaving
+    // the rest of the record alone
+    async fn update_personal(&self, user: User) -> RepoResult<User>;
+
+    // Emails are matched case-insensitively
+    async fn find_by_email(&self, email: &str) -> RepoResult<Option<User>>;
+
+    // The user holding an email whether or not they have been soft-deleted;
+    // a deleted user's email stays taken
+    async fn find_by_email_with_deleted(&self, email: &str) -> RepoResult<Option<User>>;
+
+    // Users whose phone number matches once normalised. Phone numbers are
+    // encrypted at rest, so SQL storage matches on the blind index.
+    async fn find_by_phone(&self, phone: &str) -> RepoResult<Vec<User>>;
+
+    // Create or update users matched by email, all or nothing. Rows that
+    // wouldn't change anything are left alone and come back unchanged.
+    async fn upsert_many(&self, rows: Vec<UserUpsert>) -> RepoResult<Vec<Upserted>>;
+}
+
+#[async_trait]
+pub trait TeamRepo: Send + Sync {
+    // Fails with `Conflict` if the name is taken, ignoring case
+    async fn create(&self, name: &str) -> RepoResult<Team>;
+
+    // Every team, a page at a time
+    async fn list(&self, page: &PageRequest<TeamSort>) -> RepoResult<Page<Team>>;
+
+    async fn get(&self, id: i64) -> RepoResult<Option<Team>>;
+
+    // Save the name, parent and manager. Fails with `Conflict` if another
+    // team has the name.
+    async fn update(&self, team: Team) -> RepoResult<Team>;
+}
+
+#[async_trait]
+pub trait AuditRepo: Send + Sync {
+    // Append an entry, chaining its hash onto the latest one. Inside an
+    // `atomic` block the entry commits or rolls back with the block. Appends
+    // are serialised so two writers can't both chain onto the same predecessor.
+    async fn append(&self, entry: NewAuditEntry) -> RepoResult<AuditEntry>;
+
+    // Entries matching the filter, a page at a time
+    async fn list(&self, filter: &AuditFilter, page: &PageRequest<AuditSort>) -> RepoResult<Page<AuditEntry>>;
+
+    // The whole log, oldest first, for verifying the hash chain
+    async fn chain(&self) -> RepoResult<Vec<AuditEntry>>;
+}
+
+// Conflict messages shared by every erasure repository
+const ERASURE_PENDING: &str = "An erasure request is already pending for this user";
+const ERASURE_DECIDED: &str = "Erasure request has already been decided";
+
+#[async_trait]
+pub trait ErasureRepo: Send + Sync {
+    // Open a request. Fails with `Conflict` if the user already has one pending.
+    async fn create(&self, request: NewErasureRequest) -> RepoResult<ErasureRequest>;
+
+    async fn get(&self, id: i64) -> RepoResult<Option<ErasureRequest>>;
+
+    // Requests in the given status, or all of them, a page at a time
+    async fn list(
+        &self,
+        status: Option<ErasureStatus>,
+        page: &PageRequest<ErasureSort>,
+    ) -> RepoResult<Page<ErasureRequest>>;
+
+    // Move a pending request to `status`. Fails with `Conflict` if it has
+    // already been decided, so two admins can't both act on it.
+    async fn decide(
+        &self,
+        id: i64,
+        status: ErasureStatus,
+        decided_by: &str,
+        note: &str,
+    ) -> RepoResult<ErasureRequest>;
+}
+
+#[async_trait]
+pub trait ProfileRepo: Send + Sync {
+    async fn get(&self, user_id: i64) -> RepoResult<Option<Employment>>;
+
+    // Records for whichever of `user_ids` have one
+    async fn get_many(&self, user_ids: &[i64]) -> RepoResult<Vec<Employment>>;
+
+    // Insert the record when its version is 0, failing with `Conflict` if one
+    // exists; otherwise replace it, failing with `StaleVersion` if the version
+    // has moved on. Qualifications are replaced along with it.
+    async fn save(&self, employment: Employment) -> RepoResult<Employment>;
+
+    async fn delete(&self, user_id: i64) -> RepoResult<()>;
+}
+
+// Conflict message shared by every rota repository
+pub(crate) const ROTA_OVERLAPS: &str = "Team already has a rota covering part of this period";
+
+#[async_trait]
+pub trait RotaRepo: Send + Sync {
+    // Fails with `Conflict` if the team has another rota sharing any day
+    async fn create(&self, rota: NewRota) -> RepoResult<Rota>;
+
+    // Rotas that aren't deleted, a page at a time
+    async fn list(&self, filter: &RotaFilter, page: &PageRequest<RotaSort>) -> RepoResult<Page<Rota>>;
+
+    // `None` for deleted rotas
+    async fn get(&self, id: i64) -> RepoResult<Option<Rota>>;
+
+    // A rota whether or not it has been soft-deleted, for restore
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Rota>>;
+
+    // Save every field. Fails with `StaleVersion` if `rota.version` is no
+    // longer the stored version.
+    async fn update(&self, rota: Rota) -> RepoResult<Rota>;
+
+    // Save a rota that has just been published together with what staff will
+    // now see, as its snapshot for `rota.revision`
+    async fn publish(&self, rota: Rota, shifts: Value) -> RepoResult<(Rota, RotaSnapshot)>;
+
+    // The snapshot of one revision, or of the latest when `revision` is `None`
+    async fn snapshot(&self, rota_id: i64, revision: Option<i64>) -> RepoResult<Option<RotaSnapshot>>;
+
+    // Remove rotas soft-deleted before `cutoff` for good, with their
+    // snapshots, returning their ids. Shifts on them are kept.
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>>;
+}
+
+// Conflict messages shared by every shift repository
+const ALREADY_ASSIGNED: &str = "User is already assigned to this shift";
+const ALREADY_GENERATED: &str = "A shift has already been generated for this occurrence";
+
+#[async_trait]
+pub trait ShiftRepo: Send + Sync {
+    async fn create(&self, shift: NewShift) -> RepoResult<Shift>;
+
+    // Shifts that aren't deleted, a page at a time
+    async fn list(&self, filter: &ShiftFilter, page: &PageRequest<ShiftSort>) -> RepoResult<Page<Shift>>;
+
+    // `None` for deleted shifts
+    async fn get(&self, id: i64) -> RepoResult<Option<Shift>>;
+
+    // A shift whether or not it has been soft-deleted, for restore
+    async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Shift>>;
+
+    // Shifts on the rota soft-deleted at exactly `deleted_at`, which is when
+    // deleting the rota took them with it
+    async fn deleted_with_rota(&self, rota_id: i64, deleted_at: DateTime<Utc>) -> RepoResult<Vec<Shift>>;
+
+    // Save every field. Fails with `StaleVersion` if `shift.version` is no
+    // longer the stored version.
+    async fn update(&self, shift: Shift) -> RepoResult<Shift>;
+
+    // Put someone on a shift. Fails with `Conflict` if they are already on it.
+    async fn assign(&self, assignment: NewAssignment) -> RepoResult<Assignment>;
+
+    // Everyone on the shift, in the order they were assigned
+    async fn assignments(&self, shift_id: i64) -> RepoResult<Vec<Assignment>>;
+
+    // Fails with `NotFound` if the user isn't on the shift
+    async fn unassign(&self, shift_id: i64, user_id: i64) -> RepoResult<()>;
+
+    // Take the user off every shift starting after `from`, returning how many
+    // assignments were removed. Used when a user is deactivated or deleted.
+    async fn unassign_future(&self, user_id: i64, from: DateTime<Utc>) -> RepoResult<u64>;
+
+    // Shifts the user is assigned to that run at any time between `from` and
+    // `to`, by start time
+    async fn assigned_to(&self, user_id: i64, from: DateTime<Utc>, to: DateTime<Utc>) -> RepoResult<Vec<Shift>>;
+
+    // Every shift the user is assigned to, whenever it runs and even if it
+    // has been deleted, by start time
+    async fn all_assigned_to(&self, user_id: i64) -> RepoResult<Vec<Shift>>;
+
+    // Create shifts for occurrences of recurrences, all or nothing, in the
+    // order given. Fails with `Conflict` if any occurrence already has a
+    // shift, even a deleted one.
+    async fn generate(&self, shifts: Vec<GeneratedShift>) -> RepoResult<Vec<Shift>>;
+
+    // Every shift generated from the recurrence, by date
+    async fn occurrences(&self, recurrence_id: i64) -> RepoResult<Vec<ShiftOccurrence>>;
+
+    // Remove shifts soft-deleted before `cutoff` for good, with their
+    // assignments, returning their ids
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>>;
+}
+
+#[async_trait]
+pub trait TemplateRepo: Send + Sync {
+    async fn create(&self, template: NewShiftTemplate) -> RepoResult<ShiftTemplate>;
+
+    // Templates that aren't deleted, a page at a time; only one team's when `team` is set
+    async fn list(&self, team: Option<i64>, page: &PageRequest<TemplateSort>) -> RepoResult<Page<ShiftTemplate>>;
+
+    // `None` for deleted templates
+    async fn get(&self, id: i64) -> RepoResult<Option<ShiftTemplate>>;
+
+    // Save every field. Fails with `StaleVersion` if `template.version` is no
+    // longer the stored version.
+    async fn update(&self, template: ShiftTemplate) -> RepoResult<ShiftTemplate>;
+
+    async fn add_recurrence(&self, recurrence: NewRecurrence) -> RepoResult<ShiftRecurrence>;
+
+    // The template's recurrences, oldest first
+    async fn recurrences(&self, template_id: i64) -> RepoResult<Vec<ShiftRecurrence>>;
+
+    async fn get_recurrence(&self, id: i64) -> RepoResult<Option<ShiftRecurrence>>;
+
+    // Fails with `NotFound` if there is no such recurrence. Shifts already
+    // generated from it are kept.
+    async fn remove_recurrence(&self, id: i64) -> RepoResult<()>;
+}
+
+// Conflict message shared by every pattern repository
+const PATTERN_SHIFT_EXISTS: &str = "Pattern already has a shift for this template and day";
+
+#[async_trait]
+pub trait PatternRepo: Send + Sync {
+    async fn create(&self, pattern: NewPattern) -> RepoResult<Pattern>;
+
+    // Patterns that aren't deleted, a page at a time; only one team's when `team` is set
+    async fn list(&self, team: Option<i64>, page: &PageRequest<PatternSort>) -> RepoResult<Page<Pattern>>;
+
+    // `None` for deleted patterns
+    async fn get(&self, id: i64) -> RepoResult<Option<Pattern>>;
+
+    // Save every field. Fails with `StaleVersion` if `pattern.version` is no
+    // longer the stored version.
+    async fn update(&self, pattern: Pattern) -> RepoResult<Pattern>;
+
+    // Everyone working the pattern, by offset then user
+    async fn members(&self, pattern_id: i64) -> RepoResult<Vec<PatternMember>>;
+
+    // Replace everyone working the pattern, all or nothing
+    async fn set_members(&self, pattern_id: i64, members: &[PatternMember]) -> RepoResult<()>;
+
+    // Overrides from `from` to `to` inclusive, by date then user
+    async fn overrides(&self, pattern_id: i64, from: NaiveDate, to: NaiveDate) -> RepoResult<Vec<StoredOverride>>;
+
+    // Create the override for that person and day, or replace the one there
+    async fn set_override(
+        &self,
+        pattern_id: i64,
+        change: PatternOverride,
+        created_by: Option<String>,
+    ) -> RepoResult<StoredOverride>;
+
+    // Fails with `NotFound` if there is no override for that person and day
+    async fn remove_override(&self, pattern_id: i64, user_id: i64, date: NaiveDate) -> RepoResult<()>;
+
+    // The shift the pattern made for a template on a day, even if it has
+    // since been deleted
+    async fn shift_for(&self, pattern_id: i64, template_id: i64, date: NaiveDate) -> RepoResult<Option<i64>>;
+
+    // Record that the pattern made `shift_id` for a template on a day. Fails
+    // with `Conflict` if it already made one.
+    async fn link_shift(&self, pattern_id: i64, template_id: i64, date: NaiveDate, shift_id: i64) -> RepoResult<()>;
+}
+
+// Conflict message shared by every availability repository
+const AVAILABILITY_EXISTS: &str = "Availability already saved for this user";
+
+#[async_trait]
+pub trait AvailabilityRepo: Send + Sync {
+    // `None` if the user has never saved their weekly availability
+    async fn weekly(&self, user_id: i64) -> RepoResult<Option<WeeklyAvailability>>;
+
+    // Insert the week when its version is 0, failing with `Conflict` if one
+    // exists; otherwise replace it, failing with `StaleVersion` if the version
+    // has moved on
+    async fn save_weekly(&self, weekly: WeeklyAvailability) -> RepoResult<WeeklyAvailability>;
+
+    // The user's exceptions from `from` to `to` inclusive, either end open,
+    // by date then start time
+    async fn exceptions(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> RepoResult<Vec<AvailabilityException>>;
+
+    async fn add_exception(&self, exception: NewException) -> RepoResult<AvailabilityException>;
+
+    async fn get_exception(&self, id: i64) -> RepoResult<Option<AvailabilityException>>;
+
+    // Fails with `NotFound` if there is no such exception
+    async fn remove_exception(&self, id: i64) -> RepoResult<()>;
+
+    // Blank the notes on all the user's exceptions, for an approved erasure
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()>;
+}
+
+#[async_trait]
+pub trait LeaveRepo: Send + Sync {
+    async fn create(&self, request: NewLeaveRequest) -> RepoResult<LeaveRequest>;
+
+    // Requests that aren't deleted matching `filter`, a page at a time
+    async fn list(&self, filter: &LeaveFilter, page: &PageRequest<LeaveSort>) -> RepoResult<Page<LeaveRequest>>;
+
+    // `None` for deleted requests
+    async fn get(&self, id: i64) -> RepoResult<Option<LeaveRequest>>;
+
+    // Save the status, decision, any override and whether it is deleted. Fails
+    // with `StaleVersion` if `request.version` is no longer the stored version.
+    async fn update(&self, request: LeaveRequest) -> RepoResult<LeaveRequest>;
+
+    // Approved leave for the user covering any day from `from` to `to` inclusive
+    async fn approved_between(&self, user_id: i64, from: NaiveDate, to: NaiveDate) -> RepoResult<Vec<ApprovedLeave>>;
+
+    // The user's entitlements for a leave year, by type
+    async fn entitlements(&self, user_id: i64, year: i32) -> RepoResult<Vec<LeaveEntitlement>>;
+
+    // Create the entitlement for that user, year and type, or replace it
+    async fn save_entitlement(&self, entitlement: LeaveEntitlement) -> RepoResult<LeaveEntitlement>;
+
+    // Remove requests soft-deleted before `cutoff` for good, returning their ids
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>>;
+
+    // Hold the user's leave and TOIL balances until the enclosing `atomic`
+    // block ends, so decisions that spend them take turns
+    async fn lock_balances(&self, user_id: i64) -> RepoResult<()>;
+
+    // Blank the reasons, sickness notes and decision notes on all the user's
+    // requests, deleted ones too, for an approved erasure
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()>;
+}
+
+// Conflict message shared by every leave policy repository
+const LEAVE_LIMIT_EXISTS: &str = "That team already has a limit for that skill";
+
+#[async_trait]
+pub trait LeavePolicyRepo: Send + Sync {
+    // Blackouts matching `filter`, a page at a time
+    async fn blackouts(&self, filter: &BlackoutFilter, page: &PageRequest<BlackoutSort>) -> RepoResult<Page<Blackout>>;
+
+    async fn create_blackout(&self, blackout: NewBlackout) -> RepoResult<Blackout>;
+
+    async fn get_blackout(&self, id: i64) -> RepoResult<Option<Blackout>>;
+
+    // Fails with `NotFound` if there is no such blackout
+    async fn remove_blackout(&self, id: i64) -> RepoResult<()>;
+
+    // The team's limits, the whole-team one first, then by skill
+    async fn limits(&self, team_id: i64) -> RepoResult<Vec<LeaveLimit>>;
+
+    // Fails with `Conflict` if the team already has a limit for that skill,
+    // ignoring case
+    async fn create_limit(&self, limit: NewLeaveLimit) -> RepoResult<LeaveLimit>;
+
+    async fn get_limit(&self, id: i64) -> RepoResult<Option<LeaveLimit>>;
+
+    // Fails with `NotFound` if there is no such limit
+    async fn remove_limit(&self, id: i64) -> RepoResult<()>;
+}
+
+// Conflict message shared by every TOIL repository
+const OVERTIME_CREDITED: &str = "That overtime has already been credited";
+
+#[async_trait]
+pub trait ToilRepo: Send + Sync {
+    async fn create_overtime(&self, overtime: NewOvertime) -> RepoResult<Overtime>;
+
+    // Claims matching `filter`, a page at a time
+    async fn overtime(&self, filter: &OvertimeFilter, page: &PageRequest<OvertimeSort>) -> RepoResult<Page<Overtime>>;
+
+    async fn get_overtime(&self, id: i64) -> RepoResult<Option<Overtime>>;
+
+    // Save the decision. Fails with `StaleVersion` if `overtime.version` is no
+    // longer the stored version.
+    async fn update_overtime(&self, overtime: Overtime) -> RepoResult<Overtime>;
+
+    // The user's ledger, by date then id
+    async fn ledger(&self, user_id: i64) -> RepoResult<Vec<ToilEntry>>;
+
+    // Fails with `Conflict` if the entry credits an overtime claim already
+    // credited
+    async fn add_entry(&self, entry: NewToilEntry) -> RepoResult<ToilEntry>;
+
+    // Blank the reasons and decision notes on all the user's overtime claims,
+    // for an approved erasure
+    async fn erase_notes(&self, user_id: i64) -> RepoResult<()>;
+}
@@ -0,0 +1,3214 @@
+//! SQL repositories shared by the PostgreSQL and SQLite backends.
+//!
+//! Queries are written in the common subset of both dialects (`$N`
+//! placeholders, `RETURNING`, `LOWER`), and each repository is implemented
+//! once per engine through a macro so the two can never drift apart.
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use sqlx::{types::Json, Connection, Postgres, QueryBuilder, Row, Sqlite};
+use std::sync::Arc;
+
+use rota_core::user::{normalise_phone, normalise_skills, NewUser, PersonalDetails, User, UserRole};
+
+use super::{
+    AuditRepo, AvailabilityRepo, ErasureRepo, LeavePolicyRepo, LeaveRepo, PatternRepo, ProfileRepo, RepoError,
+    RepoResult, RotaRepo, ShiftRepo, TeamRepo, TemplateRepo, ToilRepo, UserRepo, ALREADY_ASSIGNED, ALREADY_GENERATED,
+    AVAILABILITY_EXISTS, ERASURE_DECIDED, ERASURE_PENDING, LEAVE_LIMIT_EXISTS, OVERTIME_CREDITED, PATTERN_SHIFT_EXISTS,
+    ROTA_OVERLAPS,
+};
+use crate::{
+    audit,
+    database::{self, Pools},
+    encryption::{self, Keyring},
+    models::{
+        assignment::{ApprovedLeave, Assignment, Conflict, NewAssignment},
+        availability::{AvailabilityException, NewException, WeeklyAvailability, WeeklyWindow},
+        audit::{AuditEntry, AuditFilter, AuditSort, NewAuditEntry},
+        erasure::{ErasureRequest, ErasureSort, ErasureStatus, NewErasureRequest},
+        leave::{LeaveEntitlement, LeaveFilter, LeaveRequest, LeaveSort, LeaveType, NewLeaveRequest},
+        leave_policy::{Blackout, BlackoutFilter, BlackoutSort, LeaveLimit, NewBlackout, NewLeaveLimit},
+        pattern::{NewPattern, Pattern, PatternMember, PatternOverride, PatternSort, StoredOverride},
+        profile::{Employment, Qualification},
+        rota::{NewRota, Rota, RotaFilter, RotaSnapshot, RotaSort},
+        shift::{NewShift, Shift, ShiftFilter, ShiftSort},
+        team::{Team, TeamSort},
+        template::{
+            GeneratedShift, NewRecurrence, NewShiftTemplate, ShiftOccurrence, ShiftRecurrence, ShiftTemplate, TemplateSort,
+        },
+        toil::{NewOvertime, NewToilEntry, Overtime, OvertimeFilter, OvertimeSort, ToilEntry},
+        user::{Upserted, UserFilter, UserSort, UserUpsert},
+    },
+    pagination::{Page, PageRequest},
+};
+
+// Escape `%`, `_` and `\` for a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+const USER_COLUMNS: &str = "id, username, email, password_hash, role, team_id, version, created_at, \
+                            updated_at, deleted_at, anonymised_at, deactivated_at, phone, \
+                            emergency_contact, date_of_birth, contracted_hours";
+
+// Users stored in the `users` table of either engine. Everything reads the
+// primary except lists run in a `database::on_replica` block.
+// Personal details are encrypted with the keyring before they are written,
+// and only decrypted by `personal`.
+pub struct SqlUserRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+    keyring: Option<Arc<Keyring>>,
+}
+
+impl<DB: sqlx::Database> SqlUserRepo<DB> {
+    pub fn new(pools: Pools<DB>, keyring: Option<Arc<Keyring>>) -> Self {
+        Self { pools, keyring }
+    }
+}
+
+macro_rules! impl_sql_user_repo {
+    ($db:ty) => {
+        impl SqlUserRepo<$db> {
+            // Map a row of the `users` table onto the model. Personal details
+            // stay encrypted and the field empty, so a key that can't decrypt
+            // them never stops a login or a list.
+            fn read_row(&self, row: &<$db as sqlx::Database>::Row) -> Result<User, sqlx::Error> {
+                let role: String = row.try_get("role")?;
+
+                Ok(User {
+                    id: row.try_get("id")?,
+                    username: row.try_get("username")?,
+                    email: row.try_get("email")?,
+                    password_hash: row.try_get("password_hash")?,
+                    role: role.parse().unwrap_or(UserRole::User),
+                    team_id: row.try_get("team_id")?,
+                    // Filled in by `load_skills`
+                    skills: Vec::new(),
+                    contracted_hours: row.try_get("contracted_hours")?,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                    anonymised_at: row.try_get("anonymised_at")?,
+                    deactivated_at: row.try_get("deactivated_at")?,
+                    personal: PersonalDetails::default(),
+                })
+            }
+
+            // Map rows onto users and attach their skills with one extra query
+            async fn read_rows(
+                &self,
+                conn: &mut <$db as sqlx::Database>::Connection,
+                rows: &[<$db as sqlx::Database>::Row],
+            ) -> RepoResult<Vec<User>> {
+                let mut users = rows.iter().map(|row| self.read_row(row)).collect::<Result<Vec<_>, _>>()?;
+                if users.is_empty() {
+                    return Ok(users);
+                }
+
+                let mut query = QueryBuilder::<$db>::new(
+                    "SELECT user_id, skill FROM user_skills WHERE user_id IN (",
+                );
+                let mut ids = query.separated(", ");
+                for user in &users {
+                    ids.push_bind(user.id);
+                }
+                query.push(") ORDER BY LOWER(skill)");
+
+                for row in query.build().fetch_all(&mut *conn).await? {
+                    let user_id: i64 = row.try_get("user_id")?;
+                    if let Some(user) = users.iter_mut().find(|u| u.id == user_id) {
+                        user.skills.push(row.try_get("skill")?);
+                    }
+                }
+
+                Ok(users)
+            }
+
+            async fn read_optional(
+                &self,
+                conn: &mut <$db as sqlx::Database>::Connection,
+                row: Option<<$db as sqlx::Database>::Row>,
+            ) -> RepoResult<Option<User>> {
+                Ok(self.read_rows(conn, row.as_slice()).await?.pop())
+            }
+
+            // Replace a user's skills, returning them normalised
+            async fn replace_skills(
+                conn: &mut <$db as sqlx::Database>::Connection,
+                user_id: i64,
+                skills: Vec<String>,
+            ) -> RepoResult<Vec<String>> {
+                let skills = normalise_skills(skills);
+                sqlx::query("DELETE FROM user_skills WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *conn)
+                    .await?;
+                for skill in &skills {
+                    sqlx::query("INSERT INTO user_skills (user_id, skill) VALUES ($1, $2)")
+                        .bind(user_id)
+                        .bind(skill)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+
+                Ok(skills)
+            }
+        }
+
+        #[async_trait]
+        impl UserRepo for SqlUserRepo<$db> {
+            async fn create(&self, user: NewUser) -> RepoResult<User> {
+                let mut conn = self.pools.acquire().await?;
+                // Timestamps come from here rather than a column default, so SQLite
+                // stores them in the same format a page cursor binds
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO users (username, email, password_hash, role, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $5) RETURNING {}",
+                    USER_COLUMNS
+                ))
+                .bind(&user.username)
+                .bind(&user.email)
+                .bind(&user.password_hash)
+                .bind(user.role.to_string())
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict("Email already in use".to_string()),
+                    other => other,
+                })?;
+
+                Ok(self.read_row(&row)?)
+            }
+
+            async fn list(&self, filter: &UserFilter, page: &PageRequest<UserSort>) -> RepoResult<Page<User>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM users WHERE deleted_at IS NULL",
+                    USER_COLUMNS
+                ));
+                if let Some(role) = &filter.role {
+                    query.push(" AND role = ").push_bind(role.to_string());
+                }
+                if let Some(team) = filter.team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                match filter.active {
+                    Some(true) => query.push(" AND deactivated_at IS NULL"),
+                    Some(false) => query.push(" AND deactivated_at IS NOT NULL"),
+                    None => &mut query,
+                };
+                if let Some(skill) = &filter.skill {
+                    query
+                        .push(" AND EXISTS (SELECT 1 FROM user_skills s WHERE s.user_id = users.id AND LOWER(s.skill) = LOWER(")
+                        .push_bind(skill.trim().to_string())
+                        .push("))");
+                }
+                if let Some(search) = &filter.search {
+                    let pattern = format!("%{}%", escape_like(&search.to_lowercase()));
+                    query
+                        .push(" AND (LOWER(username) LIKE ")
+                        .push_bind(pattern.clone())
+                        .push(" ESCAPE '\\' OR LOWER(email) LIKE ")
+                        .push_bind(pattern)
+                        .push(" ESCAPE '\\')");
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Ok(page.page(self.read_rows(&mut conn, &rows).await?))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<User>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM users WHERE id = $1 AND deleted_at IS NULL",
+                    USER_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                self.read_optional(&mut conn, row).await
+            }
+
+            async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<User>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                self.read_optional(&mut conn, row).await
+            }
+
+            async fn deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<User>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM users \
+                     WHERE deleted_at IS NOT NULL AND deleted_at < $1 AND anonymised_at IS NULL \
+                     ORDER BY id",
+                    USER_COLUMNS
+                ))
+                .bind(cutoff)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                self.read_rows(&mut conn, &rows).await
+            }
+
+            async fn update(&self, user: User) -> RepoResult<User> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                // Personal details are written by `update_personal`, except
+                // that anonymising a user removes them
+                let scrub = match user.anonymised_at {
+                    Some(_) => ", phone = NULL, phone_bidx = NULL, emergency_contact = NULL, date_of_birth = NULL",
+                    None => "",
+                };
+
+                // The version check and the write happen in one statement, so a
+                // concurrent update can't slip in between them
+                let row = sqlx::query(&format!(
+                    "UPDATE users SET username = $1, email = $2, password_hash = $3, role = $4, \
+                     team_id = $5, deleted_at = $6, anonymised_at = $7, deactivated_at = $8, \
+                     contracted_hours = $9, version = version + 1, updated_at = $10{} \
+                     WHERE id = $11 AND version = $12 RETURNING {}",
+                    scrub, USER_COLUMNS
+                ))
+                .bind(&user.username)
+                .bind(&user.email)
+                .bind(&user.password_hash)
+                .bind(user.role.to_string())
+                .bind(user.team_id)
+                .bind(user.deleted_at)
+                .bind(user.anonymised_at)
+                .bind(user.deactivated_at)
+                .bind(user.contracted_hours)
+                .bind(Utc::now())
+                .bind(user.id)
+                .bind(user.version)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict("Email already in use".to_string()),
+                    other => other,
+                })?;
+
+                let Some(row) = row else {
+                    // Nothing matched: either the user is gone or the version moved on
+                    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE id = $1")
+                        .bind(user.id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    return match exists {
+                        Some(_) => Err(RepoError::StaleVersion),
+                        None => Err(RepoError::NotFound),
+                    };
+                };
+
+                let skills = Self::replace_skills(&mut tx, user.id, user.skills).await?;
+                tx.commit().await?;
+
+                Ok(User { skills, ..self.read_row(&row)? })
+            }
+
+            async fn personal(&self, id: i64) -> RepoResult<PersonalDetails> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query("SELECT phone, emergency_contact, date_of_birth FROM users WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?
+                    .ok_or(RepoError::NotFound)?;
+
+                Ok(encryption::open_personal(
+                    self.keyring.as_deref(),
+                    id,
+                    row.try_get("phone")?,
+                    row.try_get("emergency_contact")?,
+                    row.try_get("date_of_birth")?,
+                )?)
+            }
+
+            async fn update_personal(&self, user: User) -> RepoResult<User> {
+                let mut conn = self.pools.acquire().await?;
+                let sealed = encryption::seal_personal(self.keyring.as_deref(), user.id, &user.personal)?;
+                let row = sqlx::query(&format!(
+                    "UPDATE users SET phone = $1, phone_bidx = $2, emergency_contact = $3, date_of_birth = $4, \
+                     version = version + 1, updated_at = $5 \
+                     WHERE id = $6 AND version = $7 RETURNING {}",
+                    USER_COLUMNS
+                ))
+                .bind(sealed.phone)
+                .bind(sealed.phone_bidx)
+                .bind(sealed.emergency_contact)
+                .bind(sealed.date_of_birth)
+                .bind(Utc::now())
+                .bind(user.id)
+                .bind(user.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                let Some(row) = row else {
+                    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE id = $1")
+                        .bind(user.id)
+                        .fetch_optional(&mut *conn)
+                        .await?;
+                    return match exists {
+                        Some(_) => Err(RepoError::StaleVersion),
+                        None => Err(RepoError::NotFound),
+                    };
+                };
+                let saved = self.read_optional(&mut conn, Some(row)).await?.ok_or(RepoError::NotFound)?;
+
+                Ok(User { personal: user.personal, ..saved })
+            }
+
+            async fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM users WHERE LOWER(email) = LOWER($1) AND deleted_at IS NULL",
+                    USER_COLUMNS
+                ))
+                .bind(email)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                self.read_optional(&mut conn, row).await
+            }
+
+            async fn find_by_email_with_deleted(&self, email: &str) -> RepoResult<Option<User>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM users WHERE LOWER(email) = LOWER($1)", USER_COLUMNS))
+                    .bind(email)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                self.read_optional(&mut conn, row).await
+            }
+
+            async fn find_by_phone(&self, phone: &str) -> RepoResult<Vec<User>> {
+                let mut conn = self.pools.acquire().await?;
+                // Without keys nothing encrypted can have been stored
+                let Some(keyring) = &self.keyring else {
+                    return Ok(Vec::new());
+                };
+
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM users WHERE phone_bidx = $1 AND deleted_at IS NULL ORDER BY id",
+                    USER_COLUMNS
+                ))
+                .bind(keyring.blind_index(&normalise_phone(phone)))
+                .fetch_all(&mut *conn)
+                .await?;
+
+                self.read_rows(&mut conn, &rows).await
+            }
+
+            async fn upsert_many(&self, rows: Vec<UserUpsert>) -> RepoResult<Vec<Upserted>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let mut results = Vec::with_capacity(rows.len());
+
+                for upsert in rows {
+                    // Deleted users are included so their email can't be taken over
+                    let existing = sqlx::query(&format!(
+                        "SELECT {} FROM users WHERE LOWER(email) = LOWER($1)",
+                        USER_COLUMNS
+                    ))
+                    .bind(&upsert.email)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+                    let before = match existing {
+                        Some(row) => {
+                            let mut user = self.read_row(&row)?;
+                            user.skills = sqlx::query_scalar(
+                                "SELECT skill FROM user_skills WHERE user_id = $1 ORDER BY LOWER(skill)",
+                            )
+                            .bind(user.id)
+                            .fetch_all(&mut *tx)
+                            .await?;
+                            Some(user)
+                        }
+                        None => None,
+                    };
+
+                    let (row, skills) = match &before {
+                        Some(user) if user.is_deleted() => {
+                            return Err(RepoError::Conflict(format!(
+                                "{} belongs to a deleted user",
+                                upsert.email
+                            )));
+                        }
+                        Some(user) if !upsert.changes(user) => {
+                            results.push(Upserted { before: before.clone(), after: user.clone() });
+                            continue;
+                        }
+                        Some(user) => {
+                            let changed = upsert.apply_to(user);
+                            let row = sqlx::query(&format!(
+                                "UPDATE users SET username = $1, role = $2, team_id = $3, \
+                                 contracted_hours = $4, version = version + 1, updated_at = $5 \
+                                 WHERE id = $6 RETURNING {}",
+                                USER_COLUMNS
+                            ))
+                            .bind(&changed.username)
+                            .bind(changed.role.to_string())
+                            .bind(changed.team_id)
+                            .bind(changed.contracted_hours)
+                            .bind(Utc::now())
+                            .bind(user.id)
+                            .fetch_one(&mut *tx)
+                            .await?;
+                            (row, changed.skills)
+                        }
+                        // Imported users have no password until they are given one
+                        None => {
+                            let row = sqlx::query(&format!(
+                                "INSERT INTO users (username, email, password_hash, role, team_id, \
+                                 contracted_hours, created_at, updated_at) \
+                                 VALUES ($1, $2, '', $3, $4, $5, $6, $6) RETURNING {}",
+                                USER_COLUMNS
+                            ))
+                            .bind(&upsert.name)
+                            .bind(&upsert.email)
+                            .bind(upsert.role.clone().unwrap_or(UserRole::User).to_string())
+                            .bind(upsert.team_id.flatten())
+                            .bind(upsert.contracted_hours.flatten())
+                            .bind(Utc::now())
+                            .fetch_one(&mut *tx)
+                            .await?;
+                            (row, upsert.skills.clone().unwrap_or_default())
+                        }
+                    };
+
+                    let after = self.read_row(&row)?;
+                    let skills = Self::replace_skills(&mut tx, after.id, skills).await?;
+                    results.push(Upserted { before, after: User { skills, ..after } });
+                }
+
+                tx.commit().await?;
+                Ok(results)
+            }
+        }
+    };
+}
+
+impl_sql_user_repo!(Postgres);
+impl_sql_user_repo!(Sqlite);
+
+const AUDIT_COLUMNS: &str = "id, actor, request_id, entity, entity_id, action, before, after, \
+                             created_at, prev_hash, hash";
+
+// The append-only `audit_log` table of either engine. An entry is written in
+// the transaction of the change it records, so the two commit together.
+// Appends are serialised by that transaction: PostgreSQL takes a table lock
+// held until it ends, and SQLite writers already take turns.
+pub struct SqlAuditRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlAuditRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_audit_repo {
+    ($db:ty, $lock_table:expr) => {
+        impl SqlAuditRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<AuditEntry, sqlx::Error> {
+                let before: Option<Json<Value>> = row.try_get("before")?;
+                let after: Option<Json<Value>> = row.try_get("after")?;
+
+                Ok(AuditEntry {
+                    id: row.try_get("id")?,
+                    actor: row.try_get("actor")?,
+                    request_id: row.try_get("request_id")?,
+                    entity: row.try_get("entity")?,
+                    entity_id: row.try_get("entity_id")?,
+                    action: row.try_get("action")?,
+                    before: before.map(|json| json.0),
+                    after: after.map(|json| json.0),
+                    created_at: row.try_get("created_at")?,
+                    prev_hash: row.try_get("prev_hash")?,
+                    hash: row.try_get("hash")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl AuditRepo for SqlAuditRepo<$db> {
+            async fn append(&self, entry: NewAuditEntry) -> RepoResult<AuditEntry> {
+                // Joins the change's transaction; an append made on its own
+                // gets one of its own
+                database::atomic(async {
+                    let mut conn = self.pools.acquire().await?;
+
+                    let lock_table: Option<&str> = $lock_table;
+                    if let Some(lock_table) = lock_table {
+                        sqlx::query(lock_table).execute(&mut *conn).await?;
+                    }
+
+                    let prev_hash: Option<String> =
+                        sqlx::query_scalar("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1")
+                            .fetch_optional(&mut *conn)
+                            .await?;
+                    let prev_hash = prev_hash.unwrap_or_default();
+                    let created_at = audit::now();
+                    let hash = audit::entry_hash(&prev_hash, &entry, created_at);
+
+                    let row = sqlx::query(&format!(
+                        "INSERT INTO audit_log \
+                         (actor, request_id, entity, entity_id, action, before, after, created_at, prev_hash, hash) \
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING {}",
+                        AUDIT_COLUMNS
+                    ))
+                    .bind(&entry.actor)
+                    .bind(&entry.request_id)
+                    .bind(&entry.entity)
+                    .bind(&entry.entity_id)
+                    .bind(&entry.action)
+                    .bind(entry.before.as_ref().map(Json))
+                    .bind(entry.after.as_ref().map(Json))
+                    .bind(created_at)
+                    .bind(&prev_hash)
+                    .bind(&hash)
+                    .fetch_one(&mut *conn)
+                    .await?;
+
+                    Ok(Self::from_row(&row)?)
+                })
+                .await
+            }
+
+            async fn list(&self, filter: &AuditFilter, page: &PageRequest<AuditSort>) -> RepoResult<Page<AuditEntry>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM audit_log WHERE 1 = 1",
+                    AUDIT_COLUMNS
+                ));
+                for (column, value) in [
+                    ("entity", &filter.entity),
+                    ("entity_id", &filter.entity_id),
+                    ("actor", &filter.actor),
+                    ("action", &filter.action),
+                    ("request_id", &filter.request_id),
+                ] {
+                    if let Some(value) = value {
+                        query.push(format!(" AND {} = ", column)).push_bind(value.clone());
+                    }
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND created_at >= ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND created_at < ").push_bind(to);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let entries = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(entries))
+            }
+
+            async fn chain(&self) -> RepoResult<Vec<AuditEntry>> {
+                let mut conn = self.pools.acquire().await?;
+                // Read from the primary: a lagging replica would look like a broken chain
+                let rows = sqlx::query(&format!("SELECT {} FROM audit_log ORDER BY id", AUDIT_COLUMNS))
+                    .fetch_all(&mut *conn)
+                    .await?;
+
+                Ok(rows.iter().map(Self::from_row).collect::<Result<_, _>>()?)
+            }
+        }
+    };
+}
+
+impl_sql_audit_repo!(Postgres, Some("LOCK TABLE audit_log IN SHARE ROW EXCLUSIVE MODE"));
+impl_sql_audit_repo!(Sqlite, None);
+
+const ERASURE_COLUMNS: &str = "id, user_id, requested_by, reason, status, decided_by, decided_at, \
+                               decision_note, created_at";
+
+// Right-to-erasure requests in the `erasure_requests` table of either engine
+pub struct SqlErasureRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlErasureRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_erasure_repo {
+    ($db:ty) => {
+        impl SqlErasureRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<ErasureRequest, sqlx::Error> {
+                let status: String = row.try_get("status")?;
+
+                Ok(ErasureRequest {
+                    id: row.try_get("id")?,
+                    user_id: row.try_get("user_id")?,
+                    requested_by: row.try_get("requested_by")?,
+                    reason: row.try_get("reason")?,
+                    status: status
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    decided_by: row.try_get("decided_by")?,
+                    decided_at: row.try_get("decided_at")?,
+                    decision_note: row.try_get("decision_note")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl ErasureRepo for SqlErasureRepo<$db> {
+            async fn create(&self, request: NewErasureRequest) -> RepoResult<ErasureRequest> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO erasure_requests (user_id, requested_by, reason, created_at) \
+                     VALUES ($1, $2, $3, $4) RETURNING {}",
+                    ERASURE_COLUMNS
+                ))
+                .bind(request.user_id)
+                .bind(&request.requested_by)
+                .bind(&request.reason)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict(ERASURE_PENDING.to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::from_row(&row)?)
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<ErasureRequest>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM erasure_requests WHERE id = $1",
+                    ERASURE_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn list(
+                &self,
+                status: Option<ErasureStatus>,
+                page: &PageRequest<ErasureSort>,
+            ) -> RepoResult<Page<ErasureRequest>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM erasure_requests WHERE 1 = 1",
+                    ERASURE_COLUMNS
+                ));
+                if let Some(status) = status {
+                    query.push(" AND status = ").push_bind(status.to_string());
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let requests = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(requests))
+            }
+
+            async fn decide(
+                &self,
+                id: i64,
+                status: ErasureStatus,
+                decided_by: &str,
+                note: &str,
+            ) -> RepoResult<ErasureRequest> {
+                let mut conn = self.pools.acquire().await?;
+                // Only a pending request can be decided, checked in the same statement
+                let row = sqlx::query(&format!(
+                    "UPDATE erasure_requests SET status = $1, decided_by = $2, decided_at = $3, \
+                     decision_note = $4 WHERE id = $5 AND status = 'pending' RETURNING {}",
+                    ERASURE_COLUMNS
+                ))
+                .bind(status.to_string())
+                .bind(decided_by)
+                .bind(Utc::now())
+                .bind(note)
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                drop(conn);
+                match row {
+                    Some(row) => Ok(Self::from_row(&row)?),
+                    None => match self.get(id).await? {
+                        Some(_) => Err(RepoError::Conflict(ERASURE_DECIDED.to_string())),
+                        None => Err(RepoError::NotFound),
+                    },
+                }
+            }
+        }
+    };
+}
+
+impl_sql_erasure_repo!(Postgres);
+impl_sql_erasure_repo!(Sqlite);
+
+const TEAM_COLUMNS: &str = "id, name, parent_id, manager_id, created_at, updated_at";
+
+// Teams in the `teams` table of either engine
+pub struct SqlTeamRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlTeamRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_team_repo {
+    ($db:ty) => {
+        impl SqlTeamRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<Team, sqlx::Error> {
+                Ok(Team {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                    parent_id: row.try_get("parent_id")?,
+                    manager_id: row.try_get("manager_id")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl TeamRepo for SqlTeamRepo<$db> {
+            async fn create(&self, name: &str) -> RepoResult<Team> {
+                let mut conn = self.pools.acquire().await?;
+                // The unique index on `name` is case sensitive, so check first
+                let taken: Option<i64> =
+                    sqlx::query_scalar("SELECT id FROM teams WHERE LOWER(name) = LOWER($1)")
+                        .bind(name)
+                        .fetch_optional(&mut *conn)
+                        .await?;
+                if taken.is_some() {
+                    return Err(RepoError::Conflict("Team name already in use".to_string()));
+                }
+
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO teams (name, created_at, updated_at) VALUES ($1, $2, $3) RETURNING {}",
+                    TEAM_COLUMNS
+                ))
+                .bind(name)
+                .bind(now)
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict("Team name already in use".to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::from_row(&row)?)
+            }
+
+            async fn list(&self, page: &PageRequest<TeamSort>) -> RepoResult<Page<Team>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!("SELECT {} FROM teams WHERE 1 = 1", TEAM_COLUMNS));
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Ok(page.page(rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<Team>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM teams WHERE id = $1", TEAM_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn update(&self, team: Team) -> RepoResult<Team> {
+                let mut conn = self.pools.acquire().await?;
+                let taken: Option<i64> =
+                    sqlx::query_scalar("SELECT id FROM teams WHERE LOWER(name) = LOWER($1) AND id <> $2")
+                        .bind(&team.name)
+                        .bind(team.id)
+                        .fetch_optional(&mut *conn)
+                        .await?;
+                if taken.is_some() {
+                    return Err(RepoError::Conflict("Team name already in use".to_string()));
+                }
+
+                let row = sqlx::query(&format!(
+                    "UPDATE teams SET name = $1, parent_id = $2, manager_id = $3, updated_at = $4 \
+                     WHERE id = $5 RETURNING {}",
+                    TEAM_COLUMNS
+                ))
+                .bind(&team.name)
+                .bind(team.parent_id)
+                .bind(team.manager_id)
+                .bind(Utc::now())
+                .bind(team.id)
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict("Team name already in use".to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::from_row(&row.ok_or(RepoError::NotFound)?)?)
+            }
+        }
+    };
+}
+
+impl_sql_team_repo!(Postgres);
+impl_sql_team_repo!(Sqlite);
+
+const EMPLOYMENT_COLUMNS: &str = "user_id, contract_type, pay_grade, start_date, end_date, version, \
+                                  created_at, updated_at";
+
+// Employment records in the `staff_profiles` and `qualifications` tables of
+// either engine
+pub struct SqlProfileRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlProfileRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_profile_repo {
+    ($db:ty) => {
+        impl SqlProfileRepo<$db> {
+            // Qualifications start empty and are filled in by `read_rows`
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<Employment, sqlx::Error> {
+                let contract_type: String = row.try_get("contract_type")?;
+
+                Ok(Employment {
+                    user_id: row.try_get("user_id")?,
+                    contract_type: contract_type
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    pay_grade: row.try_get("pay_grade")?,
+                    start_date: row.try_get("start_date")?,
+                    end_date: row.try_get("end_date")?,
+                    qualifications: Vec::new(),
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                })
+            }
+
+            // Map rows onto records and attach their qualifications
+            async fn read_rows(
+                conn: &mut <$db as sqlx::Database>::Connection,
+                rows: &[<$db as sqlx::Database>::Row],
+            ) -> RepoResult<Vec<Employment>> {
+                let mut records = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+                if records.is_empty() {
+                    return Ok(records);
+                }
+
+                let mut query = QueryBuilder::<$db>::new(
+                    "SELECT user_id, name, issued_on, expires_on FROM qualifications WHERE user_id IN (",
+                );
+                let mut ids = query.separated(", ");
+                for record in &records {
+                    ids.push_bind(record.user_id);
+                }
+                query.push(") ORDER BY LOWER(name)");
+
+                for row in query.build().fetch_all(&mut *conn).await? {
+                    let user_id: i64 = row.try_get("user_id")?;
+                    if let Some(record) = records.iter_mut().find(|r| r.user_id == user_id) {
+                        record.qualifications.push(Qualification {
+                            name: row.try_get("name")?,
+                            issued_on: row.try_get("issued_on")?,
+                            expires_on: row.try_get("expires_on")?,
+                        });
+                    }
+                }
+
+                Ok(records)
+            }
+        }
+
+        #[async_trait]
+        impl ProfileRepo for SqlProfileRepo<$db> {
+            async fn get(&self, user_id: i64) -> RepoResult<Option<Employment>> {
+                Ok(self.get_many(&[user_id]).await?.pop())
+            }
+
+            async fn get_many(&self, user_ids: &[i64]) -> RepoResult<Vec<Employment>> {
+                if user_ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                let mut conn = self.pools.acquire().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM staff_profiles WHERE user_id IN (",
+                    EMPLOYMENT_COLUMNS
+                ));
+                let mut ids = query.separated(", ");
+                for id in user_ids {
+                    ids.push_bind(*id);
+                }
+                query.push(") ORDER BY user_id");
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Self::read_rows(&mut conn, &rows).await
+            }
+
+            async fn save(&self, employment: Employment) -> RepoResult<Employment> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let now = Utc::now();
+
+                let row = if employment.version == 0 {
+                    sqlx::query(&format!(
+                        "INSERT INTO staff_profiles (user_id, contract_type, pay_grade, start_date, \
+                         end_date, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $6) \
+                         RETURNING {}",
+                        EMPLOYMENT_COLUMNS
+                    ))
+                    .bind(employment.user_id)
+                    .bind(employment.contract_type.to_string())
+                    .bind(&employment.pay_grade)
+                    .bind(employment.start_date)
+                    .bind(employment.end_date)
+                    .bind(now)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(|err| match RepoError::from(err) {
+                        RepoError::Conflict(_) => RepoError::Conflict("Profile already exists".to_string()),
+                        other => other,
+                    })?
+                } else {
+                    let row = sqlx::query(&format!(
+                        "UPDATE staff_profiles SET contract_type = $1, pay_grade = $2, start_date = $3, \
+                         end_date = $4, version = version + 1, updated_at = $5 \
+                         WHERE user_id = $6 AND version = $7 RETURNING {}",
+                        EMPLOYMENT_COLUMNS
+                    ))
+                    .bind(employment.contract_type.to_string())
+                    .bind(&employment.pay_grade)
+                    .bind(employment.start_date)
+                    .bind(employment.end_date)
+                    .bind(now)
+                    .bind(employment.user_id)
+                    .bind(employment.version)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+                    match row {
+                        Some(row) => row,
+                        None => {
+                            drop(tx);
+                            drop(conn);
+                            return match self.get(employment.user_id).await? {
+                                Some(_) => Err(RepoError::StaleVersion),
+                                None => Err(RepoError::NotFound),
+                            };
+                        }
+                    }
+                };
+
+                sqlx::query("DELETE FROM qualifications WHERE user_id = $1")
+                    .bind(employment.user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for qualification in &employment.qualifications {
+                    sqlx::query(
+                        "INSERT INTO qualifications (user_id, name, issued_on, expires_on) \
+                         VALUES ($1, $2, $3, $4)",
+                    )
+                    .bind(employment.user_id)
+                    .bind(&qualification.name)
+                    .bind(qualification.issued_on)
+                    .bind(qualification.expires_on)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                let saved = Self::read_rows(&mut tx, &[row]).await?.pop().ok_or(RepoError::NotFound)?;
+                tx.commit().await?;
+
+                Ok(saved)
+            }
+
+            async fn delete(&self, user_id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let result = sqlx::query("DELETE FROM staff_profiles WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if result.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_profile_repo!(Postgres);
+impl_sql_profile_repo!(Sqlite);
+
+const SHIFT_COLUMNS: &str = "id, rota_id, team_id, location, position, starts_at, ends_at, time_zone, \
+                             unpaid_break_minutes, required_headcount, notes, version, created_at, \
+                             updated_at, deleted_at";
+
+const ROTA_COLUMNS: &str = "id, team_id, period_start, period_end, status, revision, published_at, \
+                           published_by, locked_at, locked_by, version, created_at, updated_at, deleted_at";
+
+// Rotas and their published snapshots in the `rotas` and `rota_snapshots`
+// tables of either engine
+pub struct SqlRotaRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlRotaRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_rota_repo {
+    ($db:ty) => {
+        impl SqlRotaRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<Rota, sqlx::Error> {
+                let status: String = row.try_get("status")?;
+
+                Ok(Rota {
+                    id: row.try_get("id")?,
+                    team_id: row.try_get("team_id")?,
+                    period_start: row.try_get("period_start")?,
+                    period_end: row.try_get("period_end")?,
+                    status: status
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    revision: row.try_get("revision")?,
+                    published_at: row.try_get("published_at")?,
+                    published_by: row.try_get("published_by")?,
+                    locked_at: row.try_get("locked_at")?,
+                    locked_by: row.try_get("locked_by")?,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                })
+            }
+
+            fn snapshot_from_row(row: &<$db as sqlx::Database>::Row) -> Result<RotaSnapshot, sqlx::Error> {
+                let shifts: Json<Value> = row.try_get("shifts")?;
+
+                Ok(RotaSnapshot {
+                    rota_id: row.try_get("rota_id")?,
+                    revision: row.try_get("revision")?,
+                    published_by: row.try_get("published_by")?,
+                    published_at: row.try_get("published_at")?,
+                    shifts: shifts.0,
+                })
+            }
+
+            // Write every field of `rota` if its version still matches
+            async fn write(
+                conn: &mut <$db as sqlx::Database>::Connection,
+                rota: &Rota,
+            ) -> RepoResult<Rota> {
+                let row = sqlx::query(&format!(
+                    "UPDATE rotas SET team_id = $1, period_start = $2, period_end = $3, status = $4, \
+                     revision = $5, published_at = $6, published_by = $7, locked_at = $8, \
+                     locked_by = $9, deleted_at = $10, version = version + 1, updated_at = $11 \
+                     WHERE id = $12 AND version = $13 RETURNING {}",
+                    ROTA_COLUMNS
+                ))
+                .bind(rota.team_id)
+                .bind(rota.period_start)
+                .bind(rota.period_end)
+                .bind(rota.status.to_string())
+                .bind(rota.revision)
+                .bind(rota.published_at)
+                .bind(&rota.published_by)
+                .bind(rota.locked_at)
+                .bind(&rota.locked_by)
+                .bind(rota.deleted_at)
+                .bind(Utc::now())
+                .bind(rota.id)
+                .bind(rota.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                match row {
+                    Some(row) => Ok(Self::from_row(&row)?),
+                    None => {
+                        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM rotas WHERE id = $1")
+                            .bind(rota.id)
+                            .fetch_optional(&mut *conn)
+                            .await?;
+                        match exists {
+                            Some(_) => Err(RepoError::StaleVersion),
+                            None => Err(RepoError::NotFound),
+                        }
+                    }
+                }
+            }
+        }
+
+        #[async_trait]
+        impl RotaRepo for SqlRotaRepo<$db> {
+            async fn create(&self, rota: NewRota) -> RepoResult<Rota> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let overlapping: Option<i64> = sqlx::query_scalar(
+                    "SELECT id FROM rotas WHERE team_id = $1 AND deleted_at IS NULL \
+                     AND period_start <= $2 AND period_end >= $3",
+                )
+                .bind(rota.team_id)
+                .bind(rota.period_end)
+                .bind(rota.period_start)
+                .fetch_optional(&mut *tx)
+                .await?;
+                if overlapping.is_some() {
+                    return Err(RepoError::Conflict(ROTA_OVERLAPS.to_string()));
+                }
+
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO rotas (team_id, period_start, period_end, status, created_at, updated_at) \
+                     VALUES ($1, $2, $3, 'draft', $4, $4) RETURNING {}",
+                    ROTA_COLUMNS
+                ))
+                .bind(rota.team_id)
+                .bind(rota.period_start)
+                .bind(rota.period_end)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?;
+                tx.commit().await?;
+
+                Ok(Self::from_row(&row)?)
+            }
+
+            async fn list(&self, filter: &RotaFilter, page: &PageRequest<RotaSort>) -> RepoResult<Page<Rota>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM rotas WHERE deleted_at IS NULL",
+                    ROTA_COLUMNS
+                ));
+                if let Some(team) = filter.team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                if let Some(status) = filter.status {
+                    query.push(" AND status = ").push_bind(status.to_string());
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND period_end >= ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND period_start <= ").push_bind(to);
+                }
+                if filter.published_only {
+                    query.push(" AND status <> 'draft'");
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let rotas = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(rotas))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<Rota>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM rotas WHERE id = $1 AND deleted_at IS NULL",
+                    ROTA_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Rota>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM rotas WHERE id = $1", ROTA_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn update(&self, rota: Rota) -> RepoResult<Rota> {
+                let mut conn = self.pools.acquire().await?;
+                Self::write(&mut conn, &rota).await
+            }
+
+            async fn publish(&self, rota: Rota, shifts: Value) -> RepoResult<(Rota, RotaSnapshot)> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let saved = Self::write(&mut tx, &rota).await?;
+                let row = sqlx::query(
+                    "INSERT INTO rota_snapshots (rota_id, revision, shifts, published_by, published_at) \
+                     VALUES ($1, $2, $3, $4, $5) \
+                     RETURNING rota_id, revision, shifts, published_by, published_at",
+                )
+                .bind(saved.id)
+                .bind(saved.revision)
+                .bind(Json(&shifts))
+                .bind(&saved.published_by)
+                .bind(saved.published_at.unwrap_or_else(Utc::now))
+                .fetch_one(&mut *tx)
+                .await?;
+                let snapshot = Self::snapshot_from_row(&row)?;
+                tx.commit().await?;
+
+                Ok((saved, snapshot))
+            }
+
+            async fn snapshot(&self, rota_id: i64, revision: Option<i64>) -> RepoResult<Option<RotaSnapshot>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut query = QueryBuilder::<$db>::new(
+                    "SELECT rota_id, revision, shifts, published_by, published_at FROM rota_snapshots \
+                     WHERE rota_id = ",
+                );
+                query.push_bind(rota_id);
+                if let Some(revision) = revision {
+                    query.push(" AND revision = ").push_bind(revision);
+                }
+                query.push(" ORDER BY revision DESC LIMIT 1");
+
+                let row = query.build().fetch_optional(&mut *conn).await?;
+                Ok(row.as_ref().map(Self::snapshot_from_row).transpose()?)
+            }
+
+            async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut purged: Vec<i64> = sqlx::query_scalar(
+                    "DELETE FROM rotas WHERE deleted_at IS NOT NULL AND deleted_at < $1 RETURNING id",
+                )
+                .bind(cutoff)
+                .fetch_all(&mut *conn)
+                .await?;
+                purged.sort_unstable();
+
+                Ok(purged)
+            }
+        }
+    };
+}
+
+impl_sql_rota_repo!(Postgres);
+impl_sql_rota_repo!(Sqlite);
+
+const ASSIGNMENT_COLUMNS: &str = "id, shift_id, user_id, assigned_by, forced, override_reason, conflicts, \
+                                  created_at";
+
+// Shifts in the `shifts`, `shift_requirements` and `shift_assignments` tables
+// of either engine
+pub struct SqlShiftRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlShiftRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_shift_repo {
+    ($db:ty) => {
+        impl SqlShiftRepo<$db> {
+            // Requirements start empty and are filled in by `read_rows`
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<Shift, sqlx::Error> {
+                Ok(Shift {
+                    id: row.try_get("id")?,
+                    rota_id: row.try_get("rota_id")?,
+                    team_id: row.try_get("team_id")?,
+                    location: row.try_get("location")?,
+                    position: row.try_get("position")?,
+                    starts_at: row.try_get("starts_at")?,
+                    ends_at: row.try_get("ends_at")?,
+                    time_zone: row.try_get("time_zone")?,
+                    unpaid_break_minutes: row.try_get("unpaid_break_minutes")?,
+                    required_headcount: row.try_get("required_headcount")?,
+                    notes: row.try_get("notes")?,
+                    required_skills: Vec::new(),
+                    required_qualifications: Vec::new(),
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                })
+            }
+
+            // Map rows onto shifts and attach their requirements with one extra query
+            async fn read_rows(
+                conn: &mut <$db as sqlx::Database>::Connection,
+                rows: &[<$db as sqlx::Database>::Row],
+            ) -> RepoResult<Vec<Shift>> {
+                let mut shifts = rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?;
+                if shifts.is_empty() {
+                    return Ok(shifts);
+                }
+
+                let mut query = QueryBuilder::<$db>::new(
+                    "SELECT shift_id, kind, name FROM shift_requirements WHERE shift_id IN (",
+                );
+                let mut ids = query.separated(", ");
+                for shift in &shifts {
+                    ids.push_bind(shift.id);
+                }
+                query.push(") ORDER BY LOWER(name)");
+
+                for row in query.build().fetch_all(&mut *conn).await? {
+                    let shift_id: i64 = row.try_get("shift_id")?;
+                    let kind: String = row.try_get("kind")?;
+                    if let Some(shift) = shifts.iter_mut().find(|s| s.id == shift_id) {
+                        match kind.as_str() {
+                            "skill" => shift.required_skills.push(row.try_get("name")?),
+                            _ => shift.required_qualifications.push(row.try_get("name")?),
+                        }
+                    }
+                }
+
+                Ok(shifts)
+            }
+
+            // Replace a shift's required skills and qualifications
+            async fn replace_requirements(
+                conn: &mut <$db as sqlx::Database>::Connection,
+                shift_id: i64,
+                skills: &[String],
+                qualifications: &[String],
+            ) -> RepoResult<()> {
+                sqlx::query("DELETE FROM shift_requirements WHERE shift_id = $1")
+                    .bind(shift_id)
+                    .execute(&mut *conn)
+                    .await?;
+                let requirements = skills
+                    .iter()
+                    .map(|name| ("skill", name))
+                    .chain(qualifications.iter().map(|name| ("qualification", name)));
+                for (kind, name) in requirements {
+                    sqlx::query("INSERT INTO shift_requirements (shift_id, kind, name) VALUES ($1, $2, $3)")
+                        .bind(shift_id)
+                        .bind(kind)
+                        .bind(name)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+
+                Ok(())
+            }
+
+            fn assignment_from_row(row: &<$db as sqlx::Database>::Row) -> Result<Assignment, sqlx::Error> {
+                let conflicts: Json<Vec<Conflict>> = row.try_get("conflicts")?;
+
+                Ok(Assignment {
+                    id: row.try_get("id")?,
+                    shift_id: row.try_get("shift_id")?,
+                    user_id: row.try_get("user_id")?,
+                    assigned_by: row.try_get("assigned_by")?,
+                    forced: row.try_get("forced")?,
+                    override_reason: row.try_get("override_reason")?,
+                    conflicts: conflicts.0,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+
+            // Insert a shift with its requirements
+            async fn insert(conn: &mut <$db as sqlx::Database>::Connection, shift: &NewShift) -> RepoResult<Shift> {
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO shifts (rota_id, team_id, location, position, starts_at, ends_at, \
+                     time_zone, unpaid_break_minutes, required_headcount, notes, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11) RETURNING {}",
+                    SHIFT_COLUMNS
+                ))
+                .bind(shift.rota_id)
+                .bind(shift.team_id)
+                .bind(&shift.location)
+                .bind(&shift.position)
+                .bind(shift.starts_at)
+                .bind(shift.ends_at)
+                .bind(&shift.time_zone)
+                .bind(shift.unpaid_break_minutes)
+                .bind(shift.required_headcount)
+                .bind(&shift.notes)
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                let id: i64 = row.try_get("id")?;
+                Self::replace_requirements(&mut *conn, id, &shift.required_skills, &shift.required_qualifications)
+                    .await?;
+                Self::read_rows(&mut *conn, &[row]).await?.pop().ok_or(RepoError::NotFound)
+            }
+        }
+
+        #[async_trait]
+        impl ShiftRepo for SqlShiftRepo<$db> {
+            async fn create(&self, shift: NewShift) -> RepoResult<Shift> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let created = Self::insert(&mut tx, &shift).await?;
+                tx.commit().await?;
+
+                Ok(created)
+            }
+
+            async fn list(&self, filter: &ShiftFilter, page: &PageRequest<ShiftSort>) -> RepoResult<Page<Shift>> {
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM shifts WHERE deleted_at IS NULL",
+                    SHIFT_COLUMNS
+                ));
+                if let Some(team) = filter.team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                if let Some(rota) = filter.rota {
+                    query.push(" AND rota_id = ").push_bind(rota);
+                }
+                if let Some(location) = &filter.location {
+                    query.push(" AND LOWER(location) = LOWER(").push_bind(location.trim().to_string()).push(")");
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND ends_at > ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND starts_at < ").push_bind(to);
+                }
+                if filter.standalone_only {
+                    query.push(" AND rota_id IS NULL");
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+
+                let mut conn = self.pools.acquire_list().await?;
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let shifts = Self::read_rows(&mut conn, &rows).await?;
+
+                Ok(page.page(shifts))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM shifts WHERE id = $1 AND deleted_at IS NULL",
+                    SHIFT_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(Self::read_rows(&mut conn, row.as_slice()).await?.pop())
+            }
+
+            async fn get_with_deleted(&self, id: i64) -> RepoResult<Option<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM shifts WHERE id = $1", SHIFT_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(Self::read_rows(&mut conn, row.as_slice()).await?.pop())
+            }
+
+            async fn deleted_with_rota(&self, rota_id: i64, deleted_at: DateTime<Utc>) -> RepoResult<Vec<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM shifts WHERE rota_id = $1 AND deleted_at = $2 ORDER BY starts_at, id",
+                    SHIFT_COLUMNS
+                ))
+                .bind(rota_id)
+                .bind(deleted_at)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Self::read_rows(&mut conn, &rows).await
+            }
+
+            async fn update(&self, shift: Shift) -> RepoResult<Shift> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let row = sqlx::query(&format!(
+                    "UPDATE shifts SET rota_id = $1, team_id = $2, location = $3, position = $4, \
+                     starts_at = $5, ends_at = $6, time_zone = $7, unpaid_break_minutes = $8, \
+                     required_headcount = $9, notes = $10, deleted_at = $11, \
+                     version = version + 1, updated_at = $12 \
+                     WHERE id = $13 AND version = $14 RETURNING {}",
+                    SHIFT_COLUMNS
+                ))
+                .bind(shift.rota_id)
+                .bind(shift.team_id)
+                .bind(&shift.location)
+                .bind(&shift.position)
+                .bind(shift.starts_at)
+                .bind(shift.ends_at)
+                .bind(&shift.time_zone)
+                .bind(shift.unpaid_break_minutes)
+                .bind(shift.required_headcount)
+                .bind(&shift.notes)
+                .bind(shift.deleted_at)
+                .bind(Utc::now())
+                .bind(shift.id)
+                .bind(shift.version)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                match row {
+                    Some(row) => {
+                        Self::replace_requirements(
+                            &mut tx,
+                            shift.id,
+                            &shift.required_skills,
+                            &shift.required_qualifications,
+                        )
+                        .await?;
+                        let updated = Self::read_rows(&mut tx, &[row]).await?.pop().ok_or(RepoError::NotFound)?;
+                        tx.commit().await?;
+                        Ok(updated)
+                    }
+                    None => {
+                        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM shifts WHERE id = $1")
+                            .bind(shift.id)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                        match exists {
+                            Some(_) => Err(RepoError::StaleVersion),
+                            None => Err(RepoError::NotFound),
+                        }
+                    }
+                }
+            }
+
+            async fn assign(&self, assignment: NewAssignment) -> RepoResult<Assignment> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO shift_assignments (shift_id, user_id, assigned_by, forced, \
+                     override_reason, conflicts, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                     RETURNING {}",
+                    ASSIGNMENT_COLUMNS
+                ))
+                .bind(assignment.shift_id)
+                .bind(assignment.user_id)
+                .bind(&assignment.assigned_by)
+                .bind(assignment.forced)
+                .bind(&assignment.override_reason)
+                .bind(Json(&assignment.conflicts))
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict(ALREADY_ASSIGNED.to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::assignment_from_row(&row)?)
+            }
+
+            async fn assignments(&self, shift_id: i64) -> RepoResult<Vec<Assignment>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM shift_assignments WHERE shift_id = $1 ORDER BY id",
+                    ASSIGNMENT_COLUMNS
+                ))
+                .bind(shift_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::assignment_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn unassign(&self, shift_id: i64, user_id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let result = sqlx::query("DELETE FROM shift_assignments WHERE shift_id = $1 AND user_id = $2")
+                    .bind(shift_id)
+                    .bind(user_id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if result.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+
+            async fn unassign_future(&self, user_id: i64, from: DateTime<Utc>) -> RepoResult<u64> {
+                let mut conn = self.pools.acquire().await?;
+                let result = sqlx::query(
+                    "DELETE FROM shift_assignments WHERE user_id = $1 \
+                     AND shift_id IN (SELECT id FROM shifts WHERE starts_at > $2)",
+                )
+                .bind(user_id)
+                .bind(from)
+                .execute(&mut *conn)
+                .await?;
+
+                Ok(result.rows_affected())
+            }
+
+            async fn assigned_to(
+                &self,
+                user_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+            ) -> RepoResult<Vec<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM shifts WHERE deleted_at IS NULL \
+                     AND id IN (SELECT shift_id FROM shift_assignments WHERE user_id = $1) \
+                     AND ends_at > $2 AND starts_at < $3 ORDER BY starts_at, id",
+                    SHIFT_COLUMNS
+                ))
+                .bind(user_id)
+                .bind(from)
+                .bind(to)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Self::read_rows(&mut conn, &rows).await
+            }
+
+            async fn all_assigned_to(&self, user_id: i64) -> RepoResult<Vec<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM shifts \
+                     WHERE id IN (SELECT shift_id FROM shift_assignments WHERE user_id = $1) \
+                     ORDER BY starts_at, id",
+                    SHIFT_COLUMNS
+                ))
+                .bind(user_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Self::read_rows(&mut conn, &rows).await
+            }
+
+            async fn generate(&self, generated: Vec<GeneratedShift>) -> RepoResult<Vec<Shift>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+                let mut created = Vec::new();
+                for g in generated {
+                    let taken: Option<i64> = sqlx::query_scalar(
+                        "SELECT shift_id FROM shift_occurrences WHERE recurrence_id = $1 AND occurrence_date = $2",
+                    )
+                    .bind(g.recurrence_id)
+                    .bind(g.occurrence_date)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                    if taken.is_some() {
+                        return Err(RepoError::Conflict(ALREADY_GENERATED.to_string()));
+                    }
+
+                    let shift = Self::insert(&mut tx, &g.shift).await?;
+                    sqlx::query(
+                        "INSERT INTO shift_occurrences (shift_id, recurrence_id, occurrence_date) VALUES ($1, $2, $3)",
+                    )
+                    .bind(shift.id)
+                    .bind(g.recurrence_id)
+                    .bind(g.occurrence_date)
+                    .execute(&mut *tx)
+                    .await?;
+                    created.push(shift);
+                }
+                tx.commit().await?;
+
+                Ok(created)
+            }
+
+            async fn occurrences(&self, recurrence_id: i64) -> RepoResult<Vec<ShiftOccurrence>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(
+                    "SELECT shift_id, recurrence_id, occurrence_date FROM shift_occurrences \
+                     WHERE recurrence_id = $1 ORDER BY occurrence_date",
+                )
+                .bind(recurrence_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                rows.iter()
+                    .map(|row| {
+                        Ok(ShiftOccurrence {
+                            shift_id: row.try_get("shift_id")?,
+                            recurrence_id: row.try_get("recurrence_id")?,
+                            occurrence_date: row.try_get("occurrence_date")?,
+                        })
+                    })
+                    .collect()
+            }
+
+            async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut purged: Vec<i64> = sqlx::query_scalar(
+                    "DELETE FROM shifts WHERE deleted_at IS NOT NULL AND deleted_at < $1 RETURNING id",
+                )
+                .bind(cutoff)
+                .fetch_all(&mut *conn)
+                .await?;
+                purged.sort_unstable();
+
+                Ok(purged)
+            }
+        }
+    };
+}
+
+impl_sql_shift_repo!(Postgres);
+impl_sql_shift_repo!(Sqlite);
+
+const TEMPLATE_COLUMNS: &str = "id, team_id, name, location, position, start_time, end_time, time_zone, \
+                               unpaid_break_minutes, required_headcount, colour, version, created_at, \
+                               updated_at, deleted_at";
+
+const RECURRENCE_COLUMNS: &str = "id, template_id, rule, starts_on, exceptions, created_at";
+
+// Shift templates and their recurrences in the `shift_templates` and
+// `shift_recurrences` tables of either engine
+pub struct SqlTemplateRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlTemplateRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_template_repo {
+    ($db:ty) => {
+        impl SqlTemplateRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<ShiftTemplate, sqlx::Error> {
+                Ok(ShiftTemplate {
+                    id: row.try_get("id")?,
+                    team_id: row.try_get("team_id")?,
+                    name: row.try_get("name")?,
+                    location: row.try_get("location")?,
+                    position: row.try_get("position")?,
+                    start_time: row.try_get("start_time")?,
+                    end_time: row.try_get("end_time")?,
+                    time_zone: row.try_get("time_zone")?,
+                    unpaid_break_minutes: row.try_get("unpaid_break_minutes")?,
+                    required_headcount: row.try_get("required_headcount")?,
+                    colour: row.try_get("colour")?,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                })
+            }
+
+            fn recurrence_from_row(row: &<$db as sqlx::Database>::Row) -> Result<ShiftRecurrence, sqlx::Error> {
+                let rule: String = row.try_get("rule")?;
+                let exceptions: Json<Vec<NaiveDate>> = row.try_get("exceptions")?;
+
+                Ok(ShiftRecurrence {
+                    id: row.try_get("id")?,
+                    template_id: row.try_get("template_id")?,
+                    rule: rule
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    starts_on: row.try_get("starts_on")?,
+                    exceptions: exceptions.0,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl TemplateRepo for SqlTemplateRepo<$db> {
+            async fn create(&self, template: NewShiftTemplate) -> RepoResult<ShiftTemplate> {
+                let mut conn = self.pools.acquire().await?;
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO shift_templates (team_id, name, location, position, start_time, end_time, \
+                     time_zone, unpaid_break_minutes, required_headcount, colour, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11) RETURNING {}",
+                    TEMPLATE_COLUMNS
+                ))
+                .bind(template.team_id)
+                .bind(&template.name)
+                .bind(&template.location)
+                .bind(&template.position)
+                .bind(template.start_time)
+                .bind(template.end_time)
+                .bind(&template.time_zone)
+                .bind(template.unpaid_break_minutes)
+                .bind(template.required_headcount)
+                .bind(&template.colour)
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::from_row(&row)?)
+            }
+
+            async fn list(&self, team: Option<i64>, page: &PageRequest<TemplateSort>) -> RepoResult<Page<ShiftTemplate>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM shift_templates WHERE deleted_at IS NULL",
+                    TEMPLATE_COLUMNS
+                ));
+                if let Some(team) = team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Ok(page.page(rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<ShiftTemplate>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM shift_templates WHERE id = $1 AND deleted_at IS NULL",
+                    TEMPLATE_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn update(&self, template: ShiftTemplate) -> RepoResult<ShiftTemplate> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "UPDATE shift_templates SET team_id = $1, name = $2, location = $3, position = $4, \
+                     start_time = $5, end_time = $6, time_zone = $7, unpaid_break_minutes = $8, \
+                     required_headcount = $9, colour = $10, deleted_at = $11, version = version + 1, \
+                     updated_at = $12 WHERE id = $13 AND version = $14 RETURNING {}",
+                    TEMPLATE_COLUMNS
+                ))
+                .bind(template.team_id)
+                .bind(&template.name)
+                .bind(&template.location)
+                .bind(&template.position)
+                .bind(template.start_time)
+                .bind(template.end_time)
+                .bind(&template.time_zone)
+                .bind(template.unpaid_break_minutes)
+                .bind(template.required_headcount)
+                .bind(&template.colour)
+                .bind(template.deleted_at)
+                .bind(Utc::now())
+                .bind(template.id)
+                .bind(template.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                match row {
+                    Some(row) => Ok(Self::from_row(&row)?),
+                    None => {
+                        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM shift_templates WHERE id = $1")
+                            .bind(template.id)
+                            .fetch_optional(&mut *conn)
+                            .await?;
+                        match exists {
+                            Some(_) => Err(RepoError::StaleVersion),
+                            None => Err(RepoError::NotFound),
+                        }
+                    }
+                }
+            }
+
+            async fn add_recurrence(&self, recurrence: NewRecurrence) -> RepoResult<ShiftRecurrence> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO shift_recurrences (template_id, rule, starts_on, exceptions, created_at) \
+                     VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+                    RECURRENCE_COLUMNS
+                ))
+                .bind(recurrence.template_id)
+                .bind(recurrence.rule.to_string())
+                .bind(recurrence.starts_on)
+                .bind(Json(&recurrence.exceptions))
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::recurrence_from_row(&row)?)
+            }
+
+            async fn recurrences(&self, template_id: i64) -> RepoResult<Vec<ShiftRecurrence>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM shift_recurrences WHERE template_id = $1 ORDER BY id",
+                    RECURRENCE_COLUMNS
+                ))
+                .bind(template_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::recurrence_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn get_recurrence(&self, id: i64) -> RepoResult<Option<ShiftRecurrence>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM shift_recurrences WHERE id = $1", RECURRENCE_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::recurrence_from_row).transpose()?)
+            }
+
+            async fn remove_recurrence(&self, id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let removed = sqlx::query("DELETE FROM shift_recurrences WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if removed.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_template_repo!(Postgres);
+impl_sql_template_repo!(Sqlite);
+
+const PATTERN_COLUMNS: &str = "id, team_id, name, anchor_date, cycle, version, created_at, updated_at, deleted_at";
+
+const OVERRIDE_COLUMNS: &str = "user_id, override_date, template_id, reason, created_by, created_at";
+
+// Rotating patterns, their members, overrides and the shifts they made, in
+// the `shift_patterns`, `pattern_members`, `pattern_overrides` and
+// `pattern_shifts` tables of either engine
+pub struct SqlPatternRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlPatternRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_pattern_repo {
+    ($db:ty) => {
+        impl SqlPatternRepo<$db> {
+            fn from_row(row: &<$db as sqlx::Database>::Row) -> Result<Pattern, sqlx::Error> {
+                let cycle: Json<Vec<Option<i64>>> = row.try_get("cycle")?;
+
+                Ok(Pattern {
+                    id: row.try_get("id")?,
+                    team_id: row.try_get("team_id")?,
+                    name: row.try_get("name")?,
+                    anchor_date: row.try_get("anchor_date")?,
+                    cycle: cycle.0,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                })
+            }
+
+            fn override_from_row(row: &<$db as sqlx::Database>::Row) -> Result<StoredOverride, sqlx::Error> {
+                Ok(StoredOverride {
+                    change: PatternOverride {
+                        user_id: row.try_get("user_id")?,
+                        date: row.try_get("override_date")?,
+                        template_id: row.try_get("template_id")?,
+                        reason: row.try_get("reason")?,
+                    },
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl PatternRepo for SqlPatternRepo<$db> {
+            async fn create(&self, pattern: NewPattern) -> RepoResult<Pattern> {
+                let mut conn = self.pools.acquire().await?;
+                let now = Utc::now();
+                let row = sqlx::query(&format!(
+                    "INSERT INTO shift_patterns (team_id, name, anchor_date, cycle, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $5) RETURNING {}",
+                    PATTERN_COLUMNS
+                ))
+                .bind(pattern.team_id)
+                .bind(&pattern.name)
+                .bind(pattern.anchor_date)
+                .bind(Json(&pattern.cycle))
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::from_row(&row)?)
+            }
+
+            async fn list(&self, team: Option<i64>, page: &PageRequest<PatternSort>) -> RepoResult<Page<Pattern>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM shift_patterns WHERE deleted_at IS NULL",
+                    PATTERN_COLUMNS
+                ));
+                if let Some(team) = team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Ok(page.page(rows.iter().map(Self::from_row).collect::<Result<Vec<_>, _>>()?))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<Pattern>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM shift_patterns WHERE id = $1 AND deleted_at IS NULL",
+                    PATTERN_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(Self::from_row).transpose()?)
+            }
+
+            async fn update(&self, pattern: Pattern) -> RepoResult<Pattern> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "UPDATE shift_patterns SET team_id = $1, name = $2, anchor_date = $3, cycle = $4, \
+                     deleted_at = $5, version = version + 1, updated_at = $6 \
+                     WHERE id = $7 AND version = $8 RETURNING {}",
+                    PATTERN_COLUMNS
+                ))
+                .bind(pattern.team_id)
+                .bind(&pattern.name)
+                .bind(pattern.anchor_date)
+                .bind(Json(&pattern.cycle))
+                .bind(pattern.deleted_at)
+                .bind(Utc::now())
+                .bind(pattern.id)
+                .bind(pattern.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                match row {
+                    Some(row) => Ok(Self::from_row(&row)?),
+                    None => {
+                        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM shift_patterns WHERE id = $1")
+                            .bind(pattern.id)
+                            .fetch_optional(&mut *conn)
+                            .await?;
+                        match exists {
+                            Some(_) => Err(RepoError::StaleVersion),
+                            None => Err(RepoError::NotFound),
+                        }
+                    }
+                }
+            }
+
+            async fn members(&self, pattern_id: i64) -> RepoResult<Vec<PatternMember>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(
+                    "SELECT user_id, cycle_offset FROM pattern_members WHERE pattern_id = $1 \
+                     ORDER BY cycle_offset, user_id",
+                )
+                .bind(pattern_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows
+                    .iter()
+                    .map(|row| {
+                        Ok(PatternMember {
+                            user_id: row.try_get("user_id")?,
+                            offset: row.try_get("cycle_offset")?,
+                        })
+                    })
+                    .collect::<Result<_, sqlx::Error>>()?)
+            }
+
+            async fn set_members(&self, pattern_id: i64, members: &[PatternMember]) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let mut tx = conn.begin().await?;
+
+                sqlx::query("DELETE FROM pattern_members WHERE pattern_id = $1")
+                    .bind(pattern_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for member in members {
+                    sqlx::query("INSERT INTO pattern_members (pattern_id, user_id, cycle_offset) VALUES ($1, $2, $3)")
+                        .bind(pattern_id)
+                        .bind(member.user_id)
+                        .bind(member.offset)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                tx.commit().await?;
+                Ok(())
+            }
+
+            async fn overrides(
+                &self,
+                pattern_id: i64,
+                from: NaiveDate,
+                to: NaiveDate,
+            ) -> RepoResult<Vec<StoredOverride>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM pattern_overrides WHERE pattern_id = $1 \
+                     AND override_date >= $2 AND override_date <= $3 ORDER BY override_date, user_id",
+                    OVERRIDE_COLUMNS
+                ))
+                .bind(pattern_id)
+                .bind(from)
+                .bind(to)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::override_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn set_override(
+                &self,
+                pattern_id: i64,
+                change: PatternOverride,
+                created_by: Option<String>,
+            ) -> RepoResult<StoredOverride> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO pattern_overrides \
+                     (pattern_id, user_id, override_date, template_id, reason, created_by, created_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                     ON CONFLICT (pattern_id, user_id, override_date) DO UPDATE SET \
+                     template_id = excluded.template_id, reason = excluded.reason, \
+                     created_by = excluded.created_by, created_at = excluded.created_at \
+                     RETURNING {}",
+                    OVERRIDE_COLUMNS
+                ))
+                .bind(pattern_id)
+                .bind(change.user_id)
+                .bind(change.date)
+                .bind(change.template_id)
+                .bind(&change.reason)
+                .bind(&created_by)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::override_from_row(&row)?)
+            }
+
+            async fn remove_override(&self, pattern_id: i64, user_id: i64, date: NaiveDate) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let removed = sqlx::query(
+                    "DELETE FROM pattern_overrides WHERE pattern_id = $1 AND user_id = $2 AND override_date = $3",
+                )
+                .bind(pattern_id)
+                .bind(user_id)
+                .bind(date)
+                .execute(&mut *conn)
+                .await?;
+
+                if removed.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+
+            async fn shift_for(
+                &self,
+                pattern_id: i64,
+                template_id: i64,
+                date: NaiveDate,
+            ) -> RepoResult<Option<i64>> {
+                let mut conn = self.pools.acquire().await?;
+                Ok(sqlx::query_scalar(
+                    "SELECT shift_id FROM pattern_shifts \
+                     WHERE pattern_id = $1 AND template_id = $2 AND shift_date = $3",
+                )
+                .bind(pattern_id)
+                .bind(template_id)
+                .bind(date)
+                .fetch_optional(&mut *conn)
+                .await?)
+            }
+
+            async fn link_shift(
+                &self,
+                pattern_id: i64,
+                template_id: i64,
+                date: NaiveDate,
+                shift_id: i64,
+            ) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                sqlx::query(
+                    "INSERT INTO pattern_shifts (shift_id, pattern_id, template_id, shift_date) \
+                     VALUES ($1, $2, $3, $4)",
+                )
+                .bind(shift_id)
+                .bind(pattern_id)
+                .bind(template_id)
+                .bind(date)
+                .execute(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict(PATTERN_SHIFT_EXISTS.to_string()),
+                    other => other,
+                })?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_pattern_repo!(Postgres);
+impl_sql_pattern_repo!(Sqlite);
+
+const WEEKLY_COLUMNS: &str = "user_id, weekly, version, created_at, updated_at";
+
+const EXCEPTION_COLUMNS: &str = "id, user_id, exception_date, start_time, end_time, kind, note, created_at";
+
+// Weekly availability and date exceptions in the `availability` and
+// `availability_exceptions` tables of either engine
+pub struct SqlAvailabilityRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlAvailabilityRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_availability_repo {
+    ($db:ty) => {
+        impl SqlAvailabilityRepo<$db> {
+            fn weekly_from_row(row: &<$db as sqlx::Database>::Row) -> Result<WeeklyAvailability, sqlx::Error> {
+                let weekly: Json<Vec<WeeklyWindow>> = row.try_get("weekly")?;
+
+                Ok(WeeklyAvailability {
+                    user_id: row.try_get("user_id")?,
+                    weekly: weekly.0,
+                    version: row.try_get("version")?,
+                    created_at: Some(row.try_get("created_at")?),
+                    updated_at: Some(row.try_get("updated_at")?),
+                })
+            }
+
+            fn exception_from_row(row: &<$db as sqlx::Database>::Row) -> Result<AvailabilityException, sqlx::Error> {
+                let kind: String = row.try_get("kind")?;
+
+                Ok(AvailabilityException {
+                    id: row.try_get("id")?,
+                    user_id: row.try_get("user_id")?,
+                    date: row.try_get("exception_date")?,
+                    start_time: row.try_get("start_time")?,
+                    end_time: row.try_get("end_time")?,
+                    kind: kind
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    note: row.try_get("note")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl AvailabilityRepo for SqlAvailabilityRepo<$db> {
+            async fn weekly(&self, user_id: i64) -> RepoResult<Option<WeeklyAvailability>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM availability WHERE user_id = $1", WEEKLY_COLUMNS))
+                    .bind(user_id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::weekly_from_row).transpose()?)
+            }
+
+            async fn save_weekly(&self, availability: WeeklyAvailability) -> RepoResult<WeeklyAvailability> {
+                let mut conn = self.pools.acquire().await?;
+                let now = Utc::now();
+
+                if availability.version == 0 {
+                    let row = sqlx::query(&format!(
+                        "INSERT INTO availability (user_id, weekly, created_at, updated_at)                          VALUES ($1, $2, $3, $3) RETURNING {}",
+                        WEEKLY_COLUMNS
+                    ))
+                    .bind(availability.user_id)
+                    .bind(Json(&availability.weekly))
+                    .bind(now)
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|err| match RepoError::from(err) {
+                        RepoError::Conflict(_) => RepoError::Conflict(AVAILABILITY_EXISTS.to_string()),
+                        other => other,
+                    })?;
+
+                    return Ok(Self::weekly_from_row(&row)?);
+                }
+
+                let row = sqlx::query(&format!(
+                    "UPDATE availability SET weekly = $1, version = version + 1, updated_at = $2                      WHERE user_id = $3 AND version = $4 RETURNING {}",
+                    WEEKLY_COLUMNS
+                ))
+                .bind(Json(&availability.weekly))
+                .bind(now)
+                .bind(availability.user_id)
+                .bind(availability.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                drop(conn);
+                match row {
+                    Some(row) => Ok(Self::weekly_from_row(&row)?),
+                    None => match self.weekly(availability.user_id).await? {
+                        Some(_) => Err(RepoError::StaleVersion),
+                        None => Err(RepoError::NotFound),
+                    },
+                }
+            }
+
+            async fn exceptions(
+                &self,
+                user_id: i64,
+                from: Option<NaiveDate>,
+                to: Option<NaiveDate>,
+            ) -> RepoResult<Vec<AvailabilityException>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM availability_exceptions WHERE user_id = ",
+                    EXCEPTION_COLUMNS
+                ));
+                query.push_bind(user_id);
+                if let Some(from) = from {
+                    query.push(" AND exception_date >= ").push_bind(from);
+                }
+                if let Some(to) = to {
+                    query.push(" AND exception_date <= ").push_bind(to);
+                }
+                query.push(" ORDER BY exception_date, start_time, id");
+                let rows = query.build().fetch_all(&mut *conn).await?;
+
+                Ok(rows.iter().map(Self::exception_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn add_exception(&self, exception: NewException) -> RepoResult<AvailabilityException> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO availability_exceptions \
+                     (user_id, exception_date, start_time, end_time, kind, note, created_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {}",
+                    EXCEPTION_COLUMNS
+                ))
+                .bind(exception.user_id)
+                .bind(exception.date)
+                .bind(exception.start_time)
+                .bind(exception.end_time)
+                .bind(exception.kind.to_string())
+                .bind(&exception.note)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::exception_from_row(&row)?)
+            }
+
+            async fn get_exception(&self, id: i64) -> RepoResult<Option<AvailabilityException>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM availability_exceptions WHERE id = $1",
+                    EXCEPTION_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(Self::exception_from_row).transpose()?)
+            }
+
+            async fn remove_exception(&self, id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let removed = sqlx::query("DELETE FROM availability_exceptions WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if removed.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+
+            async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                sqlx::query("UPDATE availability_exceptions SET note = '' WHERE user_id = $1")
+                    .bind(user_id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_availability_repo!(Postgres);
+impl_sql_availability_repo!(Sqlite);
+
+const LEAVE_COLUMNS: &str = "id, user_id, leave_type, start_date, end_date, half_day_start, half_day_end, days, \
+                            reason, status, approver_id, decided_by, decided_at, decision_note, overridden_by, \
+                            override_reason, overridden, version, created_at, updated_at, deleted_at, sickness_note";
+
+const ENTITLEMENT_COLUMNS: &str = "user_id, leave_year, leave_type, days, carried_over, updated_at";
+
+// Leave requests and entitlements in the `leave_requests` and
+// `leave_entitlements` tables of either engine. The reason given for sickness
+// leave is a sickness note, encrypted with the keyring into `sickness_note`.
+pub struct SqlLeaveRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+    keyring: Option<Arc<Keyring>>,
+}
+
+impl<DB: sqlx::Database> SqlLeaveRepo<DB> {
+    pub fn new(pools: Pools<DB>, keyring: Option<Arc<Keyring>>) -> Self {
+        Self { pools, keyring }
+    }
+}
+
+macro_rules! impl_sql_leave_repo {
+    ($db:ty, $lock_user:expr) => {
+        impl SqlLeaveRepo<$db> {
+            // Decode a `LEAVE_COLUMNS` row, decrypting any sickness note
+            fn read_row(&self, row: &<$db as sqlx::Database>::Row) -> Result<LeaveRequest, sqlx::Error> {
+                let id: i64 = row.try_get("id")?;
+                let leave_type: String = row.try_get("leave_type")?;
+                let status: String = row.try_get("status")?;
+                let overridden: Json<Vec<Conflict>> = row.try_get("overridden")?;
+                let sickness_note: Option<String> = row.try_get("sickness_note")?;
+                let reason = match sickness_note {
+                    Some(stored) => encryption::open_sickness_note(self.keyring.as_deref(), id, &stored)
+                        .map_err(|err| sqlx::Error::Decode(err.into()))?,
+                    None => row.try_get("reason")?,
+                };
+
+                Ok(LeaveRequest {
+                    id,
+                    user_id: row.try_get("user_id")?,
+                    leave_type: leave_type
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    start_date: row.try_get("start_date")?,
+                    end_date: row.try_get("end_date")?,
+                    half_day_start: row.try_get("half_day_start")?,
+                    half_day_end: row.try_get("half_day_end")?,
+                    days: row.try_get("days")?,
+                    reason,
+                    status: status
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    approver_id: row.try_get("approver_id")?,
+                    decided_by: row.try_get("decided_by")?,
+                    decided_at: row.try_get("decided_at")?,
+                    decision_note: row.try_get("decision_note")?,
+                    overridden_by: row.try_get("overridden_by")?,
+                    override_reason: row.try_get("override_reason")?,
+                    overridden: overridden.0,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                })
+            }
+
+            fn entitlement_from_row(row: &<$db as sqlx::Database>::Row) -> Result<LeaveEntitlement, sqlx::Error> {
+                let leave_type: String = row.try_get("leave_type")?;
+
+                Ok(LeaveEntitlement {
+                    user_id: row.try_get("user_id")?,
+                    year: row.try_get("leave_year")?,
+                    leave_type: leave_type
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    days: row.try_get("days")?,
+                    carried_over: row.try_get("carried_over")?,
+                    updated_at: row.try_get("updated_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl LeaveRepo for SqlLeaveRepo<$db> {
+            async fn create(&self, request: NewLeaveRequest) -> RepoResult<LeaveRequest> {
+                let mut conn = self.pools.acquire().await?;
+                let days = request.days();
+                // A sickness note is bound to the request's id, so it is
+                // written once the row exists
+                let sickness_note = (request.leave_type == LeaveType::Sickness && !request.reason.is_empty())
+                    .then_some(request.reason.as_str());
+                let mut tx = conn.begin().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO leave_requests (user_id, leave_type, start_date, end_date, half_day_start, \
+                     half_day_end, days, reason, approver_id, overridden_by, override_reason, overridden, \
+                     created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $13) RETURNING {}",
+                    LEAVE_COLUMNS
+                ))
+                .bind(request.user_id)
+                .bind(request.leave_type.to_string())
+                .bind(request.start_date)
+                .bind(request.end_date)
+                .bind(request.half_day_start)
+                .bind(request.half_day_end)
+                .bind(days)
+                .bind(if sickness_note.is_some() { "" } else { request.reason.as_str() })
+                .bind(request.approver_id)
+                .bind(&request.overridden_by)
+                .bind(&request.override_reason)
+                .bind(Json(&request.overridden))
+                .bind(Utc::now())
+                .fetch_one(&mut *tx)
+                .await?;
+                let row = match sickness_note {
+                    Some(note) => {
+                        let id: i64 = row.try_get("id")?;
+                        let sealed = encryption::seal_sickness_note(self.keyring.as_deref(), id, note)?;
+                        sqlx::query(&format!(
+                            "UPDATE leave_requests SET sickness_note = $1 WHERE id = $2 RETURNING {}",
+                            LEAVE_COLUMNS
+                        ))
+                        .bind(sealed)
+                        .bind(id)
+                        .fetch_one(&mut *tx)
+                        .await?
+                    }
+                    None => row,
+                };
+                let created = self.read_row(&row)?;
+                tx.commit().await?;
+
+                Ok(created)
+            }
+
+            async fn list(&self, filter: &LeaveFilter, page: &PageRequest<LeaveSort>) -> RepoResult<Page<LeaveRequest>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query = QueryBuilder::<$db>::new(format!(
+                    "SELECT {} FROM leave_requests WHERE deleted_at IS NULL",
+                    LEAVE_COLUMNS
+                ));
+                if let Some(user) = filter.user {
+                    query.push(" AND user_id = ").push_bind(user);
+                }
+                if let Some(status) = filter.status {
+                    query.push(" AND status = ").push_bind(status.to_string());
+                }
+                if let Some(leave_type) = filter.leave_type {
+                    query.push(" AND leave_type = ").push_bind(leave_type.to_string());
+                }
+                if let Some(approver) = filter.approver {
+                    query.push(" AND approver_id = ").push_bind(approver);
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND end_date >= ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND start_date <= ").push_bind(to);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let requests = rows.iter().map(|row| self.read_row(row)).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(requests))
+            }
+
+            async fn get(&self, id: i64) -> RepoResult<Option<LeaveRequest>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "SELECT {} FROM leave_requests WHERE id = $1 AND deleted_at IS NULL",
+                    LEAVE_COLUMNS
+                ))
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                Ok(row.as_ref().map(|row| self.read_row(row)).transpose()?)
+            }
+
+            async fn update(&self, request: LeaveRequest) -> RepoResult<LeaveRequest> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "UPDATE leave_requests SET status = $1, decided_by = $2, decided_at = $3, decision_note = $4, \
+                     overridden_by = $5, override_reason = $6, overridden = $7, deleted_at = $8, \
+                     version = version + 1, updated_at = $9 WHERE id = $10 AND version = $11 RETURNING {}",
+                    LEAVE_COLUMNS
+                ))
+                .bind(request.status.to_string())
+                .bind(&request.decided_by)
+                .bind(request.decided_at)
+                .bind(&request.decision_note)
+                .bind(&request.overridden_by)
+                .bind(&request.override_reason)
+                .bind(Json(&request.overridden))
+                .bind(request.deleted_at)
+                .bind(Utc::now())
+                .bind(request.id)
+                .bind(request.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                drop(conn);
+                match row {
+                    Some(row) => Ok(self.read_row(&row)?),
+                    None => match self.get(request.id).await? {
+                        Some(_) => Err(RepoError::StaleVersion),
+                        None => Err(RepoError::NotFound),
+                    },
+                }
+            }
+
+            async fn approved_between(
+                &self,
+                user_id: i64,
+                from: NaiveDate,
+                to: NaiveDate,
+            ) -> RepoResult<Vec<ApprovedLeave>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(
+                    "SELECT id, leave_type, start_date, end_date FROM leave_requests \
+                     WHERE user_id = $1 AND status = 'approved' AND deleted_at IS NULL \
+                     AND start_date <= $2 AND end_date >= $3 ORDER BY start_date, id",
+                )
+                .bind(user_id)
+                .bind(to)
+                .bind(from)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                rows.iter()
+                    .map(|row| {
+                        Ok(ApprovedLeave {
+                            id: row.try_get("id")?,
+                            leave_type: row.try_get("leave_type")?,
+                            start_date: row.try_get("start_date")?,
+                            end_date: row.try_get("end_date")?,
+                        })
+                    })
+                    .collect()
+            }
+
+            async fn entitlements(&self, user_id: i64, year: i32) -> RepoResult<Vec<LeaveEntitlement>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM leave_entitlements WHERE user_id = $1 AND leave_year = $2 ORDER BY leave_type",
+                    ENTITLEMENT_COLUMNS
+                ))
+                .bind(user_id)
+                .bind(year)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::entitlement_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn save_entitlement(&self, entitlement: LeaveEntitlement) -> RepoResult<LeaveEntitlement> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO leave_entitlements (user_id, leave_year, leave_type, days, carried_over, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6) \
+                     ON CONFLICT (user_id, leave_year, leave_type) DO UPDATE SET \
+                     days = excluded.days, carried_over = excluded.carried_over, updated_at = excluded.updated_at \
+                     RETURNING {}",
+                    ENTITLEMENT_COLUMNS
+                ))
+                .bind(entitlement.user_id)
+                .bind(entitlement.year)
+                .bind(entitlement.leave_type.to_string())
+                .bind(entitlement.days)
+                .bind(entitlement.carried_over)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::entitlement_from_row(&row)?)
+            }
+
+            async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> RepoResult<Vec<i64>> {
+                let mut conn = self.pools.acquire().await?;
+                let mut purged: Vec<i64> = sqlx::query_scalar(
+                    "DELETE FROM leave_requests WHERE deleted_at IS NOT NULL AND deleted_at < $1 RETURNING id",
+                )
+                .bind(cutoff)
+                .fetch_all(&mut *conn)
+                .await?;
+                purged.sort_unstable();
+
+                Ok(purged)
+            }
+
+            async fn lock_balances(&self, user_id: i64) -> RepoResult<()> {
+                // Beginning the block's transaction is enough on SQLite, whose
+                // writers already take turns
+                let mut conn = self.pools.acquire().await?;
+                let lock_user: Option<&str> = $lock_user;
+                if let Some(lock_user) = lock_user {
+                    sqlx::query(lock_user).bind(user_id).execute(&mut *conn).await?;
+                }
+
+                Ok(())
+            }
+
+            async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                sqlx::query(
+                    "UPDATE leave_requests SET reason = '', sickness_note = NULL, decision_note = '', \
+                     version = version + 1, updated_at = $2 \
+                     WHERE user_id = $1 AND (reason <> '' OR sickness_note IS NOT NULL OR decision_note <> '')",
+                )
+                .bind(user_id)
+                .bind(Utc::now())
+                .execute(&mut *conn)
+                .await?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_leave_repo!(Postgres, Some("SELECT pg_advisory_xact_lock($1)"));
+impl_sql_leave_repo!(Sqlite, None);
+
+const BLACKOUT_COLUMNS: &str = "id, team_id, location, start_date, end_date, reason, created_by, created_at";
+
+const LIMIT_COLUMNS: &str = "id, team_id, skill, max_off, max_percent, created_at";
+
+// Blackouts and concurrent leave limits in the `leave_blackouts` and
+// `leave_limits` tables of either engine. A limit covering the whole team is
+// stored with an empty skill so the unique index can see it.
+pub struct SqlLeavePolicyRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlLeavePolicyRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_leave_policy_repo {
+    ($db:ty) => {
+        impl SqlLeavePolicyRepo<$db> {
+            fn blackout_from_row(row: &<$db as sqlx::Database>::Row) -> Result<Blackout, sqlx::Error> {
+                Ok(Blackout {
+                    id: row.try_get("id")?,
+                    team_id: row.try_get("team_id")?,
+                    location: row.try_get("location")?,
+                    start_date: row.try_get("start_date")?,
+                    end_date: row.try_get("end_date")?,
+                    reason: row.try_get("reason")?,
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+
+            fn limit_from_row(row: &<$db as sqlx::Database>::Row) -> Result<LeaveLimit, sqlx::Error> {
+                let skill: String = row.try_get("skill")?;
+                let max_off: Option<i32> = row.try_get("max_off")?;
+                let max_percent: Option<i32> = row.try_get("max_percent")?;
+
+                Ok(LeaveLimit {
+                    id: row.try_get("id")?,
+                    team_id: row.try_get("team_id")?,
+                    skill: Some(skill).filter(|skill| !skill.is_empty()),
+                    max_off: max_off.map(|max| max as u32),
+                    max_percent: max_percent.map(|percent| percent as u32),
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl LeavePolicyRepo for SqlLeavePolicyRepo<$db> {
+            async fn blackouts(
+                &self,
+                filter: &BlackoutFilter,
+                page: &PageRequest<BlackoutSort>,
+            ) -> RepoResult<Page<Blackout>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query =
+                    QueryBuilder::<$db>::new(format!("SELECT {} FROM leave_blackouts WHERE 1 = 1", BLACKOUT_COLUMNS));
+                if let Some(team) = filter.team {
+                    query.push(" AND team_id = ").push_bind(team);
+                }
+                if let Some(location) = &filter.location {
+                    query.push(" AND LOWER(location) = LOWER(").push_bind(location.clone()).push(")");
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND end_date >= ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND start_date <= ").push_bind(to);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let blackouts = rows.iter().map(Self::blackout_from_row).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(blackouts))
+            }
+
+            async fn create_blackout(&self, blackout: NewBlackout) -> RepoResult<Blackout> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO leave_blackouts (team_id, location, start_date, end_date, reason, created_by, \
+                     created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {}",
+                    BLACKOUT_COLUMNS
+                ))
+                .bind(blackout.team_id)
+                .bind(&blackout.location)
+                .bind(blackout.start_date)
+                .bind(blackout.end_date)
+                .bind(&blackout.reason)
+                .bind(&blackout.created_by)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::blackout_from_row(&row)?)
+            }
+
+            async fn get_blackout(&self, id: i64) -> RepoResult<Option<Blackout>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM leave_blackouts WHERE id = $1", BLACKOUT_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::blackout_from_row).transpose()?)
+            }
+
+            async fn remove_blackout(&self, id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let removed = sqlx::query("DELETE FROM leave_blackouts WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if removed.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+
+            async fn limits(&self, team_id: i64) -> RepoResult<Vec<LeaveLimit>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM leave_limits WHERE team_id = $1 ORDER BY LOWER(skill)",
+                    LIMIT_COLUMNS
+                ))
+                .bind(team_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::limit_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn create_limit(&self, limit: NewLeaveLimit) -> RepoResult<LeaveLimit> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO leave_limits (team_id, skill, max_off, max_percent, created_at) \
+                     VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+                    LIMIT_COLUMNS
+                ))
+                .bind(limit.team_id)
+                .bind(limit.skill.as_deref().unwrap_or(""))
+                .bind(limit.max_off.map(|max| max as i32))
+                .bind(limit.max_percent.map(|percent| percent as i32))
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict(LEAVE_LIMIT_EXISTS.to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::limit_from_row(&row)?)
+            }
+
+            async fn get_limit(&self, id: i64) -> RepoResult<Option<LeaveLimit>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM leave_limits WHERE id = $1", LIMIT_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::limit_from_row).transpose()?)
+            }
+
+            async fn remove_limit(&self, id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                let removed = sqlx::query("DELETE FROM leave_limits WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+
+                if removed.rows_affected() == 0 {
+                    return Err(RepoError::NotFound);
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_leave_policy_repo!(Postgres);
+impl_sql_leave_policy_repo!(Sqlite);
+
+const OVERTIME_COLUMNS: &str = "id, user_id, work_date, hours, shift_id, reason, status, approver_id, decided_by, \
+                               decided_at, decision_note, toil_hours, version, created_at, updated_at";
+
+const TOIL_ENTRY_COLUMNS: &str = "id, user_id, kind, hours, entry_date, expires_on, overtime_id, leave_request_id, \
+                                 note, created_by, created_at";
+
+// Overtime claims and TOIL ledgers in the `overtime` and `toil_ledger` tables
+// of either engine
+pub struct SqlToilRepo<DB: sqlx::Database> {
+    pools: Pools<DB>,
+}
+
+impl<DB: sqlx::Database> SqlToilRepo<DB> {
+    pub fn new(pools: Pools<DB>) -> Self {
+        Self { pools }
+    }
+}
+
+macro_rules! impl_sql_toil_repo {
+    ($db:ty) => {
+        impl SqlToilRepo<$db> {
+            fn overtime_from_row(row: &<$db as sqlx::Database>::Row) -> Result<Overtime, sqlx::Error> {
+                let status: String = row.try_get("status")?;
+
+                Ok(Overtime {
+                    id: row.try_get("id")?,
+                    user_id: row.try_get("user_id")?,
+                    date: row.try_get("work_date")?,
+                    hours: row.try_get("hours")?,
+                    shift_id: row.try_get("shift_id")?,
+                    reason: row.try_get("reason")?,
+                    status: status
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    approver_id: row.try_get("approver_id")?,
+                    decided_by: row.try_get("decided_by")?,
+                    decided_at: row.try_get("decided_at")?,
+                    decision_note: row.try_get("decision_note")?,
+                    toil_hours: row.try_get("toil_hours")?,
+                    version: row.try_get("version")?,
+                    created_at: row.try_get("created_at")?,
+                    updated_at: row.try_get("updated_at")?,
+                })
+            }
+
+            fn entry_from_row(row: &<$db as sqlx::Database>::Row) -> Result<ToilEntry, sqlx::Error> {
+                let kind: String = row.try_get("kind")?;
+
+                Ok(ToilEntry {
+                    id: row.try_get("id")?,
+                    user_id: row.try_get("user_id")?,
+                    kind: kind
+                        .parse()
+                        .map_err(|err: rota_core::ValidationError| sqlx::Error::Decode(err.into()))?,
+                    hours: row.try_get("hours")?,
+                    date: row.try_get("entry_date")?,
+                    expires_on: row.try_get("expires_on")?,
+                    overtime_id: row.try_get("overtime_id")?,
+                    leave_request_id: row.try_get("leave_request_id")?,
+                    note: row.try_get("note")?,
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl ToilRepo for SqlToilRepo<$db> {
+            async fn create_overtime(&self, overtime: NewOvertime) -> RepoResult<Overtime> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO overtime (user_id, work_date, hours, shift_id, reason, approver_id, created_at, \
+                     updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $7) RETURNING {}",
+                    OVERTIME_COLUMNS
+                ))
+                .bind(overtime.user_id)
+                .bind(overtime.date)
+                .bind(overtime.hours)
+                .bind(overtime.shift_id)
+                .bind(&overtime.reason)
+                .bind(overtime.approver_id)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await?;
+
+                Ok(Self::overtime_from_row(&row)?)
+            }
+
+            async fn overtime(
+                &self,
+                filter: &OvertimeFilter,
+                page: &PageRequest<OvertimeSort>,
+            ) -> RepoResult<Page<Overtime>> {
+                let mut conn = self.pools.acquire_list().await?;
+                let mut query =
+                    QueryBuilder::<$db>::new(format!("SELECT {} FROM overtime WHERE 1 = 1", OVERTIME_COLUMNS));
+                if let Some(user) = filter.user {
+                    query.push(" AND user_id = ").push_bind(user);
+                }
+                if let Some(status) = filter.status {
+                    query.push(" AND status = ").push_bind(status.to_string());
+                }
+                if let Some(approver) = filter.approver {
+                    query.push(" AND approver_id = ").push_bind(approver);
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND work_date >= ").push_bind(from);
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND work_date <= ").push_bind(to);
+                }
+                page.push_after(&mut query);
+                page.push_order(&mut query);
+                let rows = query.build().fetch_all(&mut *conn).await?;
+                let claims = rows.iter().map(Self::overtime_from_row).collect::<Result<Vec<_>, _>>()?;
+
+                Ok(page.page(claims))
+            }
+
+            async fn get_overtime(&self, id: i64) -> RepoResult<Option<Overtime>> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!("SELECT {} FROM overtime WHERE id = $1", OVERTIME_COLUMNS))
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+                Ok(row.as_ref().map(Self::overtime_from_row).transpose()?)
+            }
+
+            async fn update_overtime(&self, overtime: Overtime) -> RepoResult<Overtime> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "UPDATE overtime SET status = $1, decided_by = $2, decided_at = $3, decision_note = $4, \
+                     toil_hours = $5, version = version + 1, updated_at = $6 WHERE id = $7 AND version = $8 \
+                     RETURNING {}",
+                    OVERTIME_COLUMNS
+                ))
+                .bind(overtime.status.to_string())
+                .bind(&overtime.decided_by)
+                .bind(overtime.decided_at)
+                .bind(&overtime.decision_note)
+                .bind(overtime.toil_hours)
+                .bind(Utc::now())
+                .bind(overtime.id)
+                .bind(overtime.version)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                drop(conn);
+                match row {
+                    Some(row) => Ok(Self::overtime_from_row(&row)?),
+                    None => match self.get_overtime(overtime.id).await? {
+                        Some(_) => Err(RepoError::StaleVersion),
+                        None => Err(RepoError::NotFound),
+                    },
+                }
+            }
+
+            async fn ledger(&self, user_id: i64) -> RepoResult<Vec<ToilEntry>> {
+                let mut conn = self.pools.acquire().await?;
+                let rows = sqlx::query(&format!(
+                    "SELECT {} FROM toil_ledger WHERE user_id = $1 ORDER BY entry_date, id",
+                    TOIL_ENTRY_COLUMNS
+                ))
+                .bind(user_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+                Ok(rows.iter().map(Self::entry_from_row).collect::<Result<_, _>>()?)
+            }
+
+            async fn add_entry(&self, entry: NewToilEntry) -> RepoResult<ToilEntry> {
+                let mut conn = self.pools.acquire().await?;
+                let row = sqlx::query(&format!(
+                    "INSERT INTO toil_ledger (user_id, kind, hours, entry_date, expires_on, overtime_id, \
+                     leave_request_id, note, created_by, created_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING {}",
+                    TOIL_ENTRY_COLUMNS
+                ))
+                .bind(entry.user_id)
+                .bind(entry.kind.to_string())
+                .bind(entry.hours)
+                .bind(entry.date)
+                .bind(entry.expires_on)
+                .bind(entry.overtime_id)
+                .bind(entry.leave_request_id)
+                .bind(&entry.note)
+                .bind(&entry.created_by)
+                .bind(Utc::now())
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|err| match RepoError::from(err) {
+                    RepoError::Conflict(_) => RepoError::Conflict(OVERTIME_CREDITED.to_string()),
+                    other => other,
+                })?;
+
+                Ok(Self::entry_from_row(&row)?)
+            }
+
+            async fn erase_notes(&self, user_id: i64) -> RepoResult<()> {
+                let mut conn = self.pools.acquire().await?;
+                sqlx::query(
+                    "UPDATE overtime SET reason = '', decision_note = '', version = version + 1, updated_at = $2 \
+                     WHERE user_id = $1 AND (reason <> '' OR decision_note <> '')",
+                )
+                .bind(user_id)
+                .bind(Utc::now())
+                .execute(&mut *conn)
+                .await?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_toil_repo!(Postgres);
+impl_sql_toil_repo!(Sqlite);
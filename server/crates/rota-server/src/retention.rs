@@ -0,0 +1,104 @@
+//! Data retention.
+//!
+//! Soft-deleted records are kept for a configurable time and then removed for
+//! good: users are anonymised, since historical rotas still refer to them,
+//! while rotas, shifts and leave requests are purged. Every removal is audited.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{future::Future, time::Duration};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    config::RetentionConfig,
+    database,
+    error::AppError,
+    repo::RepoResult,
+};
+
+// Actor recorded in the audit log for changes made by the job
+const ACTOR: &str = "system:retention";
+
+// What one run of the job removed
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct RetentionReport {
+    pub users_anonymised: usize,
+    pub rotas_purged: usize,
+    pub shifts_purged: usize,
+    pub leave_requests_purged: usize,
+}
+
+// Apply every configured policy once. Each removal shares a transaction with
+// its audit entries, so nothing goes without a record of it going.
+pub async fn run(state: &AppState, policy: &RetentionConfig) -> Result<RetentionReport, AppError> {
+    let now = Utc::now();
+    let audit = Audit::system(state.audit.clone(), ACTOR);
+    let mut report = RetentionReport::default();
+
+    if let Some(cutoff) = cutoff(now, policy.users) {
+        for user in state.users.deleted_before(cutoff).await? {
+            database::atomic(async {
+                let mut anonymised = user.clone();
+                anonymised.anonymise(now);
+                let anonymised = state.users.update(anonymised).await?;
+                // User snapshots never identify the person, so the permanent
+                // audit log keeps nothing the job has just removed
+                audit.record_user(user.id, "anonymise", Some(&user), Some(&anonymised)).await
+            })
+            .await?;
+            report.users_anonymised += 1;
+        }
+    }
+
+    // Shifts go before rotas so a purged rota's deleted shifts are counted
+    // as shifts
+    if let Some(cutoff) = cutoff(now, policy.shifts) {
+        report.shifts_purged = purge(&audit, "shift", state.shifts.purge_deleted_before(cutoff)).await?;
+    }
+    if let Some(cutoff) = cutoff(now, policy.rotas) {
+        report.rotas_purged = purge(&audit, "rota", state.rotas.purge_deleted_before(cutoff)).await?;
+    }
+    if let Some(cutoff) = cutoff(now, policy.leave_requests) {
+        report.leave_requests_purged =
+            purge(&audit, "leave_request", state.leave.purge_deleted_before(cutoff)).await?;
+    }
+
+    Ok(report)
+}
+
+// Run a repository purge and audit each id it removed in one transaction,
+// returning how many went
+async fn purge(
+    audit: &Audit,
+    entity: &str,
+    purged: impl Future<Output = RepoResult<Vec<i64>>>,
+) -> Result<usize, AppError> {
+    database::atomic(async {
+        let ids = purged.await?;
+        for id in &ids {
+            audit.record::<()>(entity, id, "purge", None, None).await?;
+        }
+
+        Ok(ids.len())
+    })
+    .await
+}
+
+// Run the job every `interval` for as long as the server is up
+pub async fn schedule(state: AppState, policy: RetentionConfig, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match run(&state, &policy).await {
+            Ok(report) => tracing::info!(?report, "Retention job finished"),
+            Err(err) => tracing::error!("Retention job failed: {}", err),
+        }
+    }
+}
+
+// Records deleted before this moment are due for removal
+fn cutoff(now: DateTime<Utc>, age: Option<Duration>) -> Option<DateTime<Utc>> {
+    let age = chrono::Duration::from_std(age?).ok()?;
+    now.checked_sub_signed(age)
+}
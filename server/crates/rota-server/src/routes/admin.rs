@@ -0,0 +1,27 @@
+use axum::{extract::State, routing::post, Json, Router};
+
+use crate::{
+    app::AppState,
+    auth::jwt::Claims,
+    error::AppError,
+    retention::{self, RetentionReport},
+};
+
+// Operational endpoints for administrators
+pub fn admin_routes() -> Router<AppState> {
+    Router::new().route("/api/admin/retention/run", post(run_retention))
+}
+
+// Handler to apply the retention policies now instead of waiting for the schedule
+async fn run_retention(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<RetentionReport>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let report = retention::run(&state, &state.config.retention).await?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,67 @@
+use axum::{
+    extract::{Query, State},
+    http::Uri,
+    routing::get,
+    Json, Router,
+};
+
+use crate::{
+    app::AppState,
+    audit::{verify_chain, ChainReport},
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    models::audit::{AuditEntry, AuditEntryResponse, AuditFilter, AuditSort},
+    pagination::{PageParams, PageRequest, Paginated, SortKey, MAX_LIMIT},
+};
+
+// Admin-only access to the audit log
+pub fn audit_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/audit", get(list_audit))
+        .route("/api/audit/verify", get(verify_audit))
+}
+
+// Handler to search the audit log, newest first
+async fn list_audit(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(filter): Query<AuditFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<AuditEntryResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    let page = PageRequest::new(&params, AuditSort::DEFAULT)?;
+    let entries = database::on_replica(state.audit.list(&filter, &page)).await?;
+
+    Ok(Paginated::new(entries.map(AuditEntryResponse::from), uri))
+}
+
+// Every entry matching `filter`, oldest first, for callers that need them all
+pub(crate) async fn all_audit(state: &AppState, filter: &AuditFilter) -> Result<Vec<AuditEntry>, AppError> {
+    let mut entries = Vec::new();
+    let mut request = Some(PageRequest::first(&[SortKey::asc(AuditSort::Id)], MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.audit.list(filter, &page_request).await?;
+        request = page_request.next(&page);
+        entries.extend(page.items);
+    }
+
+    Ok(entries)
+}
+
+// Handler to recompute the hash chain and report the first broken link
+async fn verify_audit(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<ChainReport>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let entries = state.audit.chain().await?;
+
+    Ok(Json(verify_chain(&entries)))
+}
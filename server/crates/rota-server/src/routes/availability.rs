@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Response,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{NaiveDate, Utc};
+use rota_core::availability::{validate_exception, validate_weekly};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::{
+        availability::{
+            Availability, AvailabilityDay, AvailabilityException, ExceptionFilter, ExceptionRequest,
+            MemberAvailability, NewException, TeamAvailabilityQuery, WeeklyAvailability, WeeklyRequest,
+        },
+        user::UserFilter,
+    },
+    repo::RepoError,
+    routes::users::all_users,
+};
+
+// Staff availability: recurring weekly windows and one-off date exceptions.
+// Staff keep their own; managers and admins can keep anyone's and see a
+// whole team's at once.
+pub fn availability_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/users/:id/availability", get(get_weekly).put(put_weekly))
+        .route(
+            "/api/users/:id/availability/exceptions",
+            get(list_exceptions).post(add_exception),
+        )
+        .route("/api/users/:id/availability/exceptions/:exception_id", delete(remove_exception))
+        .route("/api/teams/:id/availability", get(team_availability))
+}
+
+// Everything the user has said about when they can work, with only the
+// exceptions from `from` to `to` inclusive
+pub(crate) async fn availability_of(
+    state: &AppState,
+    user_id: i64,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Availability, AppError> {
+    let weekly = state.availability.weekly(user_id).await?.map(|w| w.weekly).unwrap_or_default();
+    let exceptions = state.availability.exceptions(user_id, from, to).await?;
+
+    Ok(Availability { weekly, exceptions })
+}
+
+// The user's weekly availability, empty at version 0 if never saved. 404 for
+// unknown users and 403 for anyone but them or a manager.
+async fn load_weekly(state: &AppState, claims: &Claims, id: i64) -> Result<WeeklyAvailability, AppError> {
+    if !claims.is_self_or_manager(id) {
+        return Err(AppError::Forbidden);
+    }
+    state.users.get(id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(state.availability.weekly(id).await?.unwrap_or_else(|| WeeklyAvailability::empty(id)))
+}
+
+// The 412 response for a weekly availability write that lost a race
+async fn stale_weekly(state: &AppState, id: i64) -> AppError {
+    match state.availability.weekly(id).await {
+        Ok(Some(weekly)) => AppError::precondition_failed(weekly.version, &weekly),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Handler to read someone's weekly availability
+async fn get_weekly(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    let weekly = load_weekly(&state, &claims, id).await?;
+
+    Ok(Versioned::ok(weekly.version, weekly).or_not_modified(&if_none_match))
+}
+
+// Handler to replace someone's weekly availability (honours If-Match)
+async fn put_weekly(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<WeeklyRequest>,
+) -> Result<Versioned<WeeklyAvailability>, AppError> {
+    let current = load_weekly(&state, &claims, id).await?;
+    if_match.check(current.version, &current)?;
+    validate_weekly(&payload.weekly)?;
+
+    let mut weekly = payload.weekly;
+    weekly.sort_by_key(|w| (w.weekday.num_days_from_monday(), w.start_time));
+    let changed = WeeklyAvailability { weekly, ..current.clone() };
+    let saved = match state.availability.save_weekly(changed).await {
+        Ok(saved) => saved,
+        Err(RepoError::StaleVersion) => return Err(stale_weekly(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    let before = (current.version > 0).then_some(&current);
+    audit.record("availability", id, "update", before, Some(&saved)).await?;
+
+    Ok(Versioned::ok(saved.version, saved))
+}
+
+// Handler to list someone's date exceptions, optionally only those from
+// `from` to `to`
+async fn list_exceptions(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(filter): Query<ExceptionFilter>,
+) -> Result<Json<Vec<AvailabilityException>>, AppError> {
+    load_weekly(&state, &claims, id).await?;
+
+    Ok(Json(state.availability.exceptions(id, filter.from, filter.to).await?))
+}
+
+// A date exception as snapshotted into the audit log, without its note, which
+// an erasure removes and the log can't
+fn audited(exception: &AvailabilityException) -> AvailabilityException {
+    AvailabilityException {
+        note: String::new(),
+        ..exception.clone()
+    }
+}
+
+// Handler to add a date exception, which can't overlap another on that date
+async fn add_exception(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<ExceptionRequest>,
+) -> Result<(StatusCode, Json<AvailabilityException>), AppError> {
+    load_weekly(&state, &claims, id).await?;
+
+    let exception = NewException {
+        user_id: id,
+        date: payload.date,
+        start_time: payload.start_time,
+        end_time: payload.end_time,
+        kind: payload.kind,
+        note: payload.note.trim().to_string(),
+    };
+    let existing = state.availability.exceptions(id, Some(exception.date), Some(exception.date)).await?;
+    validate_exception(&exception.clone().into_exception(0, Utc::now()), &existing)?;
+
+    let exception = state.availability.add_exception(exception).await?;
+    audit.record("availability", id, "add_exception", None, Some(&audited(&exception))).await?;
+
+    Ok((StatusCode::CREATED, Json(exception)))
+}
+
+// Handler to remove one of someone's date exceptions
+async fn remove_exception(
+    State(state): State<AppState>,
+    Path((id, exception_id)): Path<(i64, i64)>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    load_weekly(&state, &claims, id).await?;
+
+    let exception = state.availability.get_exception(exception_id).await?
+        .filter(|e| e.user_id == id)
+        .ok_or(AppError::NotFound)?;
+    state.availability.remove_exception(exception_id).await?;
+    audit.record("availability", id, "remove_exception", Some(&audited(&exception)), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Handler to show every active member of a team's availability over a date
+// range, day by day (managers and admins)
+async fn team_availability(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(range): Query<TeamAvailabilityQuery>,
+) -> Result<Json<Vec<MemberAvailability>>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+    range.validate()?;
+    state.teams.get(id).await?.ok_or(AppError::NotFound)?;
+
+    let filter = UserFilter { team: Some(id), active: Some(true), ..UserFilter::default() };
+    let mut members = Vec::new();
+    for user in database::on_replica(all_users(&state, &filter)).await? {
+        let availability = availability_of(&state, user.id, Some(range.from), Some(range.to)).await?;
+        let days = range
+            .from
+            .iter_days()
+            .take_while(|date| *date <= range.to)
+            .map(|date| AvailabilityDay { date, windows: availability.windows_on(date) })
+            .collect();
+        members.push(MemberAvailability { user_id: user.id, username: user.username, days });
+    }
+
+    Ok(Json(members))
+}
+
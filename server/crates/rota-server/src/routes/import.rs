@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    import::{self, ImportReport, RowAction, RowError, RowOutcome},
+    models::user::UserFilter,
+    routes::{teams::all_teams, users::all_users},
+};
+
+// Bulk staff import and export as CSV
+pub fn import_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/users/import", post(import_users))
+        .route("/api/users/export", get(export_users))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ImportQuery {
+    // Check the file and report what would happen without saving anything
+    #[serde(default)]
+    dry_run: bool,
+    // Save nothing unless every row is valid
+    #[serde(default)]
+    atomic: bool,
+}
+
+// Handler to create or update users from a CSV file (admin only). Valid rows
+// are saved together in one transaction; invalid rows are reported and
+// skipped, or stop the whole import when `atomic` is set.
+async fn import_users(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    claims: Claims,
+    audit: Audit,
+    body: String,
+) -> Result<Json<ImportReport>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let teams = all_teams(&state).await?;
+    let mut parsed = import::parse(&body, &teams)?;
+
+    // Plan every row against what is stored. A deleted user's email can't be
+    // reused until they are restored or anonymised, so such rows are errors.
+    let mut rows = Vec::with_capacity(parsed.rows.len());
+    let mut planned = Vec::with_capacity(parsed.rows.len());
+    for row in parsed.rows {
+        let action = match state.users.find_by_email_with_deleted(&row.user.email).await? {
+            Some(user) if user.is_deleted() => {
+                parsed.errors.push(RowError::new(row.line, Some("email"), "Belongs to a deleted user"));
+                continue;
+            }
+            None => RowAction::Create,
+            Some(user) if row.user.changes(&user) => RowAction::Update,
+            Some(_) => RowAction::Unchanged,
+        };
+        planned.push(RowOutcome { line: row.line, email: row.user.email.clone(), action });
+        rows.push(row);
+    }
+    parsed.errors.sort_by_key(|error| error.line);
+
+    if query.dry_run || (query.atomic && !parsed.errors.is_empty()) {
+        return Ok(Json(ImportReport::new(query.dry_run, false, planned, parsed.errors)));
+    }
+
+    let lines: Vec<u64> = rows.iter().map(|row| row.line).collect();
+    let results = state
+        .users
+        .upsert_many(rows.into_iter().map(|row| row.user).collect())
+        .await?;
+
+    let mut outcomes = Vec::with_capacity(results.len());
+    for (line, result) in lines.into_iter().zip(results) {
+        let action = match &result.before {
+            None => RowAction::Create,
+            Some(before) if before.version != result.after.version => RowAction::Update,
+            Some(_) => RowAction::Unchanged,
+        };
+        let verb = match action {
+            RowAction::Create => Some("create"),
+            RowAction::Update => Some("update"),
+            RowAction::Unchanged => None,
+        };
+        if let Some(verb) = verb {
+            audit
+                .record_user(result.after.id, verb, result.before.as_ref(), Some(&result.after))
+                .await?;
+        }
+        outcomes.push(RowOutcome { line, email: result.after.email, action });
+    }
+
+    Ok(Json(ImportReport::new(false, true, outcomes, parsed.errors)))
+}
+
+// Handler to download users as CSV in the import format (admin only), with
+// the same filters as the user list
+async fn export_users(
+    State(state): State<AppState>,
+    Query(filter): Query<UserFilter>,
+    claims: Claims,
+) -> Result<Response, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let users = database::on_replica(all_users(&state, &filter)).await?;
+    let teams = database::on_replica(all_teams(&state)).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"users.csv\""),
+        ],
+        import::write(&users, &teams)?,
+    )
+        .into_response())
+}
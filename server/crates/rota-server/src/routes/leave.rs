@@ -0,0 +1,833 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use rota_core::leave::{pro_rata, validate_entitlement, validate_leave};
+use rota_core::leave_policy::{validate_blackout, validate_limit};
+use rota_core::toil::toil_spent_by;
+use std::collections::HashSet;
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, Versioned},
+    models::{
+        assignment::Conflict,
+        leave::{
+            EntitlementRequest, LeaveAction, LeaveBalance, LeaveDecision, LeaveEntitlement, LeaveFilter,
+            LeaveRequest, LeaveRequestBody, LeaveSort, LeaveStatus, LeaveType, LeaveYearQuery, NewLeaveRequest,
+        },
+        leave_policy::{
+            Blackout, BlackoutFilter, BlackoutRequest, BlackoutSort, LeaveLimit, LeaveLimitRequest, NewBlackout,
+            NewLeaveLimit,
+        },
+        team::Team,
+        toil::{NewToilEntry, ToilEntryKind},
+        user::{User, UserFilter},
+    },
+    pagination::{PageParams, PageRequest, Paginated, MAX_LIMIT},
+    repo::RepoError,
+    routes::{
+        toil::{pending_toil_hours, toil_balance_of, toil_leave_hours},
+        users::all_users,
+    },
+};
+
+// Leave: staff request it, the manager found through their team hierarchy
+// decides, and admins set how much of each type people get per leave year.
+// Blackouts and limits on how many can be off at once hold planned leave
+// back unless a manager overrides them. Approved leave blocks assignments to
+// shifts on those days. TOIL leave is paid for from the TOIL ledger.
+pub fn leave_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/leave", get(list_leave).post(request_leave))
+        .route("/api/leave/blackouts", get(list_blackouts).post(create_blackout))
+        .route("/api/leave/blackouts/:id", delete(remove_blackout))
+        .route("/api/teams/:id/leave-limits", get(list_limits).post(create_limit))
+        .route("/api/teams/:id/leave-limits/:limit_id", delete(remove_limit))
+        .route("/api/leave/:id", get(get_leave).delete(delete_leave))
+        .route("/api/leave/:id/approve", post(approve_leave))
+        .route("/api/leave/:id/reject", post(reject_leave))
+        .route("/api/leave/:id/cancel", post(cancel_leave))
+        .route("/api/users/:id/leave/entitlements", get(list_entitlements))
+        .route("/api/users/:id/leave/entitlements/:year/:leave_type", put(set_entitlement))
+        .route("/api/users/:id/leave/balance", get(get_balance))
+}
+
+// `user`'s home team followed by each team above it
+async fn team_chain(state: &AppState, user: &User) -> Result<Vec<Team>, AppError> {
+    let mut seen = HashSet::new();
+    let mut chain = Vec::new();
+    let mut next = user.team_id;
+    while let Some(team_id) = next.filter(|id| seen.insert(*id)) {
+        let Some(team) = state.teams.get(team_id).await? else {
+            break;
+        };
+        next = team.parent_id;
+        chain.push(team);
+    }
+
+    Ok(chain)
+}
+
+// Who decides `user`'s leave: the manager of their home team, or of the
+// nearest team above it with a manager other than them. `None` leaves it to
+// admins.
+pub(crate) async fn approver_for(state: &AppState, user: &User) -> Result<Option<i64>, AppError> {
+    for team in team_chain(state, user).await? {
+        if let Some(manager) = team.manager_id.filter(|manager| *manager != user.id) {
+            if state.users.get(manager).await?.is_some_and(|m| m.is_active()) {
+                return Ok(Some(manager));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Whether `user` is rostered at `location` on any day from `from` to `to`
+async fn rostered_at(
+    state: &AppState,
+    user: &User,
+    location: &str,
+    (from, to): (NaiveDate, NaiveDate),
+) -> Result<bool, AppError> {
+    // A day either side catches shifts whose local dates differ from UTC
+    let start = (from - Duration::days(1)).and_time(NaiveTime::MIN).and_utc();
+    let end = (to + Duration::days(2)).and_time(NaiveTime::MIN).and_utc();
+    let shifts = state.shifts.assigned_to(user.id, start, end).await?;
+
+    Ok(shifts.iter().any(|shift| {
+        let (first, last) = shift.local_dates();
+        shift.location.eq_ignore_ascii_case(location) && first <= to && from <= last
+    }))
+}
+
+// The blackouts and limits `user` taking `leave_type` from `start` to `end`
+// would break. Only planned leave is held back; the limits are those of the
+// user's home team that cover them, counting other members' approved leave.
+async fn policy_conflicts(
+    state: &AppState,
+    user: &User,
+    leave_type: LeaveType,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<Conflict>, AppError> {
+    if !leave_type.is_planned() {
+        return Ok(Vec::new());
+    }
+
+    let mut conflicts = Vec::new();
+    let teams: Vec<i64> = team_chain(state, user).await?.iter().map(|team| team.id).collect();
+    let filter = BlackoutFilter { from: Some(start), to: Some(end), ..BlackoutFilter::default() };
+    for blackout in all_blackouts(state, &filter).await? {
+        let Some(overlap) = blackout.overlap(start, end) else {
+            continue;
+        };
+        let covered = match (blackout.team_id, &blackout.location) {
+            (Some(team), _) if teams.contains(&team) => true,
+            (_, Some(location)) => rostered_at(state, user, location, overlap).await?,
+            _ => false,
+        };
+        if covered {
+            conflicts.extend(blackout.conflict(start, end));
+        }
+    }
+
+    let Some(team) = user.team_id else {
+        return Ok(conflicts);
+    };
+    for limit in state.leave_policy.limits(team).await? {
+        if !limit.applies_to(&user.skills) {
+            continue;
+        }
+        let filter =
+            UserFilter { team: Some(team), active: Some(true), skill: limit.skill.clone(), ..UserFilter::default() };
+        let members = all_users(state, &filter).await?;
+        let mut others = Vec::new();
+        for member in members.iter().filter(|member| member.id != user.id) {
+            others.extend(state.leave.approved_between(member.id, start, end).await?);
+        }
+        conflicts.extend(limit.conflict(start, end, members.len().max(1), &others));
+    }
+
+    Ok(conflicts)
+}
+
+// Let leave with `conflicts` through only when `force` is set by someone who
+// may override, with a reason. The reason comes back when something was
+// overridden.
+fn check_override(
+    conflicts: &[Conflict],
+    force: bool,
+    reason: Option<String>,
+    may_override: bool,
+) -> Result<Option<String>, AppError> {
+    if conflicts.is_empty() {
+        return Ok(None);
+    }
+    if !force {
+        return Err(AppError::Conflicts(
+            "Leave breaks the team's blackouts or limits; a manager can set force to override".to_string(),
+            conflicts.to_vec(),
+        ));
+    }
+    if !may_override {
+        return Err(AppError::Forbidden);
+    }
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    match reason {
+        Some(reason) => Ok(Some(reason)),
+        None => Err(AppError::BadRequest("A reason is required to override conflicts".to_string())),
+    }
+}
+
+// A leave year from a request, which must be one chrono can represent
+fn checked_year(state: &AppState, year: Option<i32>) -> Result<i32, AppError> {
+    let current = state.config.leave.year().of(Utc::now().date_naive());
+    match year {
+        None => Ok(current),
+        Some(year) if (1970..=9999).contains(&year) => Ok(year),
+        Some(_) => Err(AppError::BadRequest("Leave year must be from 1970 to 9999".to_string())),
+    }
+}
+
+// Where `user` stands with each type of leave they have an entitlement to
+// in leave year `year`
+pub(crate) async fn balances(state: &AppState, user: &User, year: i32) -> Result<Vec<LeaveBalance>, AppError> {
+    let entitlements = state.leave.entitlements(user.id, year).await?;
+    if entitlements.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let bounds = state.config.leave.year().bounds(year);
+    let filter = LeaveFilter {
+        user: Some(user.id),
+        from: Some(bounds.0),
+        to: Some(bounds.1),
+        ..LeaveFilter::default()
+    };
+    let requests = all_leave(state, &filter).await?;
+    let employment = state.profiles.get(user.id).await?.map(|e| (e.start_date, e.end_date));
+
+    Ok(entitlements
+        .iter()
+        .map(|entitlement| {
+            let pro_rated = pro_rata(
+                entitlement.days,
+                user.contracted_hours,
+                state.config.leave.full_time_hours,
+                employment,
+                bounds,
+            );
+            LeaveBalance::new(entitlement, pro_rated, bounds, &requests)
+        })
+        .collect())
+}
+
+// The balance a request of `leave_type` in `year` is taken from, if that
+// type is limited for them
+async fn balance_of(
+    state: &AppState,
+    user: &User,
+    leave_type: LeaveType,
+    year: i32,
+) -> Result<Option<LeaveBalance>, AppError> {
+    Ok(balances(state, user, year).await?.into_iter().find(|b| b.leave_type == leave_type))
+}
+
+// A leave request as snapshotted into the audit log. The log can't be
+// rewritten, so the free text an erasure has to remove is left out: the
+// reason, which for sickness leave is the encrypted note, and the decision note.
+fn audited(request: &LeaveRequest) -> LeaveRequest {
+    LeaveRequest {
+        reason: String::new(),
+        decision_note: String::new(),
+        ..request.clone()
+    }
+}
+
+// The 412 response for a leave write that lost a race
+async fn stale_leave(state: &AppState, id: i64) -> AppError {
+    match state.leave.get(id).await {
+        Ok(Some(request)) => AppError::precondition_failed(request.version, &request),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Handler to list leave requests. Staff see their own, or those waiting on
+// them with `approver` set to their id; managers and admins see everyone's.
+async fn list_leave(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(mut filter): Query<LeaveFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<LeaveRequest>, AppError> {
+    if !claims.is_manager() {
+        let me = claims.user_id().ok_or(AppError::Forbidden)?;
+        if filter.approver != Some(me) {
+            filter.user = Some(me);
+        }
+    }
+    let page = PageRequest::new(&params, LeaveSort::DEFAULT)?;
+    let requests = database::on_replica(state.leave.list(&filter, &page)).await?;
+
+    Ok(Paginated::new(requests, uri))
+}
+
+// Every leave request matching `filter`, fetched a page at a time
+pub(crate) async fn all_leave(state: &AppState, filter: &LeaveFilter) -> Result<Vec<LeaveRequest>, AppError> {
+    let mut requests = Vec::new();
+    let mut request = Some(PageRequest::first(LeaveSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.leave.list(filter, &page_request).await?;
+        request = page_request.next(&page);
+        requests.extend(page.items);
+    }
+
+    Ok(requests)
+}
+
+// Handler to request leave for yourself. It must not overlap your other
+// pending or approved leave, nor take more than is left of a limited type.
+// Breaking blackouts or limits refuses it with 409 unless a manager forces it.
+async fn request_leave(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<LeaveRequestBody>,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let me = claims.user_id().ok_or(AppError::Forbidden)?;
+    let user = state.users.get(me).await?
+        .ok_or(AppError::NotFound)?;
+    if !user.is_active() {
+        return Err(AppError::BadRequest("User is deactivated".to_string()));
+    }
+
+    let request = NewLeaveRequest {
+        user_id: me,
+        leave_type: payload.leave_type,
+        start_date: payload.start_date,
+        end_date: payload.end_date,
+        half_day_start: payload.half_day_start,
+        half_day_end: payload.half_day_end,
+        reason: payload.reason.trim().to_string(),
+        approver_id: approver_for(&state, &user).await?,
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let leave_year = state.config.leave.year();
+    validate_leave(&request, leave_year)?;
+
+    let filter = LeaveFilter {
+        user: Some(me),
+        from: Some(request.start_date),
+        to: Some(request.end_date),
+        ..LeaveFilter::default()
+    };
+    if let Some(clash) = all_leave(&state, &filter).await?.iter().find(|r| r.status.is_active()) {
+        return Err(AppError::Conflict(format!("Overlaps leave request {}", clash.id)));
+    }
+    let year = leave_year.of(request.start_date);
+    if let Some(balance) = balance_of(&state, &user, request.leave_type, year).await? {
+        if request.days() > balance.available() {
+            return Err(AppError::BadRequest(format!(
+                "Only {} days of {} leave are left in {}",
+                balance.available(),
+                request.leave_type,
+                year
+            )));
+        }
+    }
+    if request.leave_type == LeaveType::Toil {
+        let hours = toil_leave_hours(&state, &user, request.days());
+        let available = toil_balance_of(&state, me).await?.available - pending_toil_hours(&state, &user).await?;
+        if hours > available {
+            return Err(AppError::BadRequest(format!(
+                "{} hours of TOIL are needed but only {} are available",
+                hours,
+                available.max(0.0)
+            )));
+        }
+    }
+    let conflicts = policy_conflicts(&state, &user, request.leave_type, request.start_date, request.end_date).await?;
+    let override_reason = check_override(&conflicts, payload.force, payload.override_reason, claims.is_manager())?;
+    let request = match override_reason {
+        Some(reason) => NewLeaveRequest {
+            overridden_by: Some(claims.sub.clone()),
+            override_reason: Some(reason),
+            overridden: conflicts,
+            ..request
+        },
+        None => request,
+    };
+
+    let request = state.leave.create(request).await?;
+    audit.record("leave_request", request.id, "create", None, Some(&audited(&request))).await?;
+
+    Ok(Versioned::created(request.version, request))
+}
+
+// The request, if `claims` may see it: the person who asked, their approver,
+// or a manager or admin
+async fn visible_leave(state: &AppState, id: i64, claims: &Claims) -> Result<LeaveRequest, AppError> {
+    let request = state.leave.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let me = claims.user_id();
+    if !claims.is_manager() && me != Some(request.user_id) && me != request.approver_id {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(request)
+}
+
+// Handler to read one leave request
+async fn get_leave(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let request = visible_leave(&state, id, &claims).await?;
+
+    Ok(Versioned::ok(request.version, request))
+}
+
+// Take `action` on a leave request (honours If-Match). Only the approver or
+// an admin decides, and nobody approves their own leave. Approving checks
+// blackouts and limits again, which the approver can override. The person who
+// asked can cancel it while pending, or once approved until it starts.
+async fn decide(
+    state: AppState,
+    id: i64,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    action: LeaveAction,
+    decision: LeaveDecision,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let request = visible_leave(&state, id, &claims).await?;
+    if_match.check(request.version, &request)?;
+
+    let me = claims.user_id();
+    let is_decider = claims.is_admin() || (request.approver_id.is_some() && me == request.approver_id);
+    let allowed = match action {
+        LeaveAction::Approve | LeaveAction::Reject => is_decider && me != Some(request.user_id),
+        LeaveAction::Cancel => {
+            is_decider
+                || (me == Some(request.user_id)
+                    && (request.status == LeaveStatus::Pending || request.start_date > Utc::now().date_naive()))
+        }
+    };
+    if !allowed {
+        return Err(AppError::Forbidden);
+    }
+
+    let saved = database::atomic(apply_decision(&state, &claims, &audit, &request, action, &decision)).await?;
+
+    Ok(Versioned::ok(saved.version, saved))
+}
+
+// Save `action` on `request` with the TOIL it spends or hands back, all in
+// one transaction. The user's balances stay locked until it ends, so approving
+// checks them against whatever a decision just before it spent.
+async fn apply_decision(
+    state: &AppState,
+    claims: &Claims,
+    audit: &Audit,
+    request: &LeaveRequest,
+    action: LeaveAction,
+    decision: &LeaveDecision,
+) -> Result<LeaveRequest, AppError> {
+    state.leave.lock_balances(request.user_id).await?;
+
+    let mut changed = request.clone();
+    if action == LeaveAction::Approve {
+        let user = state.users.get_with_deleted(request.user_id).await?
+            .ok_or(AppError::NotFound)?;
+        let year = state.config.leave.year().of(request.start_date);
+        if let Some(balance) = balance_of(state, &user, request.leave_type, year).await? {
+            if request.days > balance.remaining {
+                return Err(AppError::Conflict(format!(
+                    "Only {} days of {} leave are left in {}",
+                    balance.remaining, request.leave_type, year
+                )));
+            }
+        }
+        if request.leave_type == LeaveType::Toil {
+            let hours = toil_leave_hours(state, &user, request.days);
+            let available = toil_balance_of(state, user.id).await?.available;
+            if hours > available {
+                return Err(AppError::Conflict(format!(
+                    "{} hours of TOIL are needed but only {} are available",
+                    hours,
+                    available.max(0.0)
+                )));
+            }
+        }
+        let conflicts =
+            policy_conflicts(state, &user, request.leave_type, request.start_date, request.end_date).await?;
+        if let Some(reason) = check_override(&conflicts, decision.force, decision.override_reason.clone(), true)? {
+            changed.overridden_by = Some(claims.sub.clone());
+            changed.override_reason = Some(reason);
+            changed.overridden = conflicts;
+        }
+    }
+
+    changed
+        .transition(action, &claims.sub, &decision.note, Utc::now())
+        .map_err(|err| AppError::Conflict(err.0))?;
+    let saved = match state.leave.update(changed).await {
+        Ok(saved) => saved,
+        Err(RepoError::StaleVersion) => return Err(stale_leave(state, request.id).await),
+        Err(err) => return Err(err.into()),
+    };
+    let verb = match action {
+        LeaveAction::Approve => "approve",
+        LeaveAction::Reject => "reject",
+        LeaveAction::Cancel => "cancel",
+    };
+    audit.record("leave_request", request.id, verb, Some(&audited(request)), Some(&audited(&saved))).await?;
+
+    // Approving TOIL leave spends the hours; cancelling it once approved hands
+    // back the hours it took, each lapsing when it would have anyway
+    if saved.leave_type == LeaveType::Toil {
+        let today = Utc::now().date_naive();
+        let note = format!("{} to {}", saved.start_date, saved.end_date);
+        let postings = match (request.status, saved.status) {
+            (LeaveStatus::Pending, LeaveStatus::Approved) => {
+                let user = state.users.get_with_deleted(saved.user_id).await?
+                    .ok_or(AppError::NotFound)?;
+                vec![(ToilEntryKind::Redemption, -toil_leave_hours(state, &user, saved.days), None)]
+            }
+            (LeaveStatus::Approved, LeaveStatus::Cancelled) => {
+                let ledger = state.toil.ledger(saved.user_id).await?;
+                ledger
+                    .iter()
+                    .filter(|e| e.kind == ToilEntryKind::Redemption && e.leave_request_id == Some(saved.id))
+                    .flat_map(|e| toil_spent_by(&ledger, e.id, today))
+                    .map(|(hours, expires_on)| (ToilEntryKind::Refund, hours, expires_on))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        for (kind, hours, expires_on) in postings {
+            let entry = NewToilEntry {
+                user_id: saved.user_id,
+                kind,
+                hours,
+                date: today,
+                expires_on,
+                overtime_id: None,
+                leave_request_id: Some(saved.id),
+                note: note.clone(),
+                created_by: Some(claims.sub.clone()),
+            };
+            let entry = state.toil.add_entry(entry).await?;
+            audit.record("toil_entry", entry.id, "create", None, Some(&entry)).await?;
+        }
+    }
+
+    Ok(saved)
+}
+
+// Handler to approve a pending leave request
+async fn approve_leave(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    payload: Option<Json<LeaveDecision>>,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let Json(decision) = payload.unwrap_or_default();
+    decide(state, id, claims, if_match, audit, LeaveAction::Approve, decision).await
+}
+
+// Handler to turn down a pending leave request
+async fn reject_leave(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    payload: Option<Json<LeaveDecision>>,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let Json(decision) = payload.unwrap_or_default();
+    decide(state, id, claims, if_match, audit, LeaveAction::Reject, decision).await
+}
+
+// Handler to withdraw a pending request or give back approved leave
+async fn cancel_leave(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    payload: Option<Json<LeaveDecision>>,
+) -> Result<Versioned<LeaveRequest>, AppError> {
+    let Json(decision) = payload.unwrap_or_default();
+    decide(state, id, claims, if_match, audit, LeaveAction::Cancel, decision).await
+}
+
+// Handler to soft-delete a leave request that isn't approved (admin only,
+// honours If-Match). Approved leave is cancelled first, so any TOIL it spent
+// is handed back. Retention removes it for good later.
+async fn delete_leave(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let request = state.leave.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(request.version, &request)?;
+    if request.status == LeaveStatus::Approved {
+        return Err(AppError::Conflict("Approved leave must be cancelled first".to_string()));
+    }
+
+    let deleted = LeaveRequest {
+        deleted_at: Some(Utc::now()),
+        ..request.clone()
+    };
+    match state.leave.update(deleted).await {
+        Ok(deleted) => {
+            audit.record("leave_request", id, "delete", Some(&audited(&request)), Some(&audited(&deleted))).await?;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(RepoError::StaleVersion) => Err(stale_leave(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// The user whose leave is being looked at, for them, a manager or an admin
+async fn leave_owner(state: &AppState, id: i64, claims: &Claims) -> Result<User, AppError> {
+    if !claims.is_self_or_manager(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    state.users.get(id).await?.ok_or(AppError::NotFound)
+}
+
+// Handler to list someone's entitlements for a leave year
+async fn list_entitlements(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(query): Query<LeaveYearQuery>,
+) -> Result<Json<Vec<LeaveEntitlement>>, AppError> {
+    leave_owner(&state, id, &claims).await?;
+    let year = checked_year(&state, query.year)?;
+
+    Ok(Json(state.leave.entitlements(id, year).await?))
+}
+
+// Handler to set how many days of a type of leave someone gets in a leave
+// year (admin only). Unless given, the carry-over is what they had left the
+// year before, up to the configured maximum.
+async fn set_entitlement(
+    State(state): State<AppState>,
+    Path((id, year, leave_type)): Path<(i64, i32, LeaveType)>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<EntitlementRequest>,
+) -> Result<Json<LeaveEntitlement>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    if leave_type == LeaveType::Toil {
+        return Err(AppError::BadRequest("TOIL is earned from overtime, not set as an entitlement".to_string()));
+    }
+    let user = leave_owner(&state, id, &claims).await?;
+    let year = checked_year(&state, Some(year))?;
+
+    let carried_over = match payload.carried_over {
+        Some(days) => days,
+        None => match balance_of(&state, &user, leave_type, year - 1).await? {
+            Some(last_year) => {
+                let unused = (last_year.remaining * 2.0).floor() / 2.0;
+                unused.clamp(0.0, state.config.leave.max_carry_over_days)
+            }
+            None => 0.0,
+        },
+    };
+    let entitlement = LeaveEntitlement {
+        user_id: id,
+        year,
+        leave_type,
+        days: payload.days,
+        carried_over,
+        updated_at: Utc::now(),
+    };
+    validate_entitlement(&entitlement)?;
+
+    let before = state.leave.entitlements(id, year).await?.into_iter().find(|e| e.leave_type == leave_type);
+    let saved = state.leave.save_entitlement(entitlement).await?;
+    audit.record("leave_entitlement", id, "update", before.as_ref(), Some(&saved)).await?;
+
+    Ok(Json(saved))
+}
+
+// Handler to show where someone stands with each type of leave they have an
+// entitlement to in a leave year
+async fn get_balance(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(query): Query<LeaveYearQuery>,
+) -> Result<Json<Vec<LeaveBalance>>, AppError> {
+    let user = leave_owner(&state, id, &claims).await?;
+    let year = checked_year(&state, query.year)?;
+
+    Ok(Json(balances(&state, &user, year).await?))
+}
+
+// Handler to list blackouts, optionally only a team's, a location's or those
+// sharing a day with `from` to `to`
+async fn list_blackouts(
+    State(state): State<AppState>,
+    uri: Uri,
+    _claims: Claims,
+    Query(filter): Query<BlackoutFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<Blackout>, AppError> {
+    let page = PageRequest::new(&params, BlackoutSort::DEFAULT)?;
+    let blackouts = database::on_replica(state.leave_policy.blackouts(&filter, &page)).await?;
+
+    Ok(Paginated::new(blackouts, uri))
+}
+
+// Every blackout matching `filter`, fetched a page at a time
+async fn all_blackouts(state: &AppState, filter: &BlackoutFilter) -> Result<Vec<Blackout>, AppError> {
+    let mut blackouts = Vec::new();
+    let mut request = Some(PageRequest::first(BlackoutSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.leave_policy.blackouts(filter, &page_request).await?;
+        request = page_request.next(&page);
+        blackouts.extend(page.items);
+    }
+
+    Ok(blackouts)
+}
+
+// Handler to black out days for a team or location (managers and admins)
+async fn create_blackout(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<BlackoutRequest>,
+) -> Result<(StatusCode, Json<Blackout>), AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let blackout = NewBlackout {
+        team_id: payload.team_id,
+        location: payload.location.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()),
+        start_date: payload.start_date,
+        end_date: payload.end_date,
+        reason: payload.reason.trim().to_string(),
+        created_by: Some(claims.sub.clone()),
+    };
+    validate_blackout(&blackout)?;
+    if let Some(team) = blackout.team_id {
+        state.teams.get(team).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", team)))?;
+    }
+
+    let blackout = state.leave_policy.create_blackout(blackout).await?;
+    audit.record("leave_blackout", blackout.id, "create", None, Some(&blackout)).await?;
+
+    Ok((StatusCode::CREATED, Json(blackout)))
+}
+
+// Handler to lift a blackout (managers and admins)
+async fn remove_blackout(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let blackout = state.leave_policy.get_blackout(id).await?
+        .ok_or(AppError::NotFound)?;
+    state.leave_policy.remove_blackout(id).await?;
+    audit.record("leave_blackout", id, "delete", Some(&blackout), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Handler to list how many of a team may be off at once
+async fn list_limits(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+) -> Result<Json<Vec<LeaveLimit>>, AppError> {
+    state.teams.get(id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(Json(state.leave_policy.limits(id).await?))
+}
+
+// Handler to cap how many of a team, or of those with a skill, may be off at
+// once (managers and admins)
+async fn create_limit(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<LeaveLimitRequest>,
+) -> Result<(StatusCode, Json<LeaveLimit>), AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+    state.teams.get(id).await?.ok_or(AppError::NotFound)?;
+
+    let limit = NewLeaveLimit {
+        team_id: id,
+        skill: payload.skill.map(|s| s.trim().to_string()),
+        max_off: payload.max_off,
+        max_percent: payload.max_percent,
+    };
+    validate_limit(&limit)?;
+
+    let limit = state.leave_policy.create_limit(limit).await?;
+    audit.record("leave_limit", limit.id, "create", None, Some(&limit)).await?;
+
+    Ok((StatusCode::CREATED, Json(limit)))
+}
+
+// Handler to drop one of a team's limits (managers and admins)
+async fn remove_limit(
+    State(state): State<AppState>,
+    Path((id, limit_id)): Path<(i64, i64)>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let limit = state.leave_policy.get_limit(limit_id).await?
+        .filter(|l| l.team_id == id)
+        .ok_or(AppError::NotFound)?;
+    state.leave_policy.remove_limit(limit_id).await?;
+    audit.record("leave_limit", limit_id, "delete", Some(&limit), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
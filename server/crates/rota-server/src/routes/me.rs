@@ -0,0 +1,107 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use rota_core::user::{validate_new_user, validate_password};
+use serde_json::Value;
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::{jwt::Claims, password},
+    database,
+    error::AppError,
+    etag::{IfMatch, Versioned},
+    models::user::{ChangePasswordRequest, EditableProfile, MeResponse, User, UserResponse},
+    patch,
+    repo::RepoError,
+    routes::{toil::toil_balance_of, users::stale},
+};
+
+// Self-service for the signed-in user
+pub fn me_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/me", get(get_me).patch(patch_me))
+        .route("/api/me/password", post(change_password))
+}
+
+// The user the access token was issued to
+async fn current_user(state: &AppState, claims: &Claims) -> Result<User, AppError> {
+    let id = claims.user_id().ok_or(AppError::Unauthorized)?;
+
+    state.users.get(id).await?.ok_or(AppError::NotFound)
+}
+
+// Handler to read your own profile and TOIL balance
+async fn get_me(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Versioned<MeResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let toil = toil_balance_of(&state, user.id).await?;
+
+    Ok(Versioned::ok(user.version, MeResponse { user: user.into(), toil }))
+}
+
+// Handler to change your own name or email with a JSON merge patch
+async fn patch_me(
+    State(state): State<AppState>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(patch): Json<Value>,
+) -> Result<Versioned<UserResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+
+    let edited: EditableProfile = patch::apply(&EditableProfile::from(&user), &patch)?;
+    validate_new_user(&edited.name, &edited.email)?;
+
+    let changed = User {
+        username: edited.name,
+        email: edited.email,
+        ..user.clone()
+    };
+
+    match state.users.update(changed).await {
+        Ok(updated) => {
+            audit.record_user(user.id, "update", Some(&user), Some(&updated)).await?;
+            Ok(Versioned::ok(updated.version, updated.into()))
+        }
+        Err(RepoError::StaleVersion) => Err(stale(&state, user.id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to change your own password, which needs the current one. The
+// request runs outside the write transaction so neither bcrypt call holds it;
+// only the save and its audit entry share one.
+async fn change_password(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    let user = current_user(&state, &claims).await?;
+    if !password::verify(&user, &payload.current_password).await {
+        return Err(AppError::Forbidden);
+    }
+    validate_password(&payload.new_password)?;
+
+    let mut changed = user.clone();
+    let changed = password::blocking(move || {
+        changed.set_password(&payload.new_password).map(|_| changed)
+    })
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+    database::atomic(async {
+        state.users.update(changed).await?;
+        // Password hashes are never serialised, so the entry only records that it happened
+        audit.record_user(user.id, "change_password", None, None).await
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,75 @@
+pub mod admin;
+pub mod audit;
+pub mod availability;
+pub mod import;
+pub mod leave;
+pub mod me;
+pub mod patterns;
+pub mod privacy;
+pub mod profiles;
+pub mod rotas;
+pub mod shifts;
+pub mod teams;
+pub mod templates;
+pub mod toil;
+pub mod users;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router, response::IntoResponse,
+};
+use serde_json::json;
+use crate::{app::AppState, middleware::request_id::RequestId};
+
+// Root route handler
+async fn root(Extension(request_id): Extension<RequestId>) -> impl IntoResponse {
+    let id = &request_id.0; // Now we're using the field!
+    format!("Welcome to our Axum API! Request ID: {}", id)
+}
+
+// Health check endpoint
+async fn health_check() -> StatusCode {
+    StatusCode::OK
+}
+
+// Database connectivity and pool statistics for monitoring.
+// Returns 503 if the primary or replica can't be reached.
+async fn database_health(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(db) = &state.db else {
+        return (StatusCode::OK, Json(json!({ "healthy": true, "backend": "memory" })));
+    };
+
+    let health = db.health().await;
+    let status = if health.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(json!(health)))
+}
+
+// Combine all routes
+pub fn app_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(root))
+        .route("/health", get(health_check))
+        .route("/health/db", get(database_health))
+        .merge(users::user_routes())
+        .merge(me::me_routes())
+        .merge(audit::audit_routes())
+        .merge(admin::admin_routes())
+        .merge(privacy::privacy_routes())
+        .merge(teams::team_routes())
+        .merge(profiles::profile_routes())
+        .merge(shifts::shift_routes())
+        .merge(rotas::rota_routes())
+        .merge(templates::template_routes())
+        .merge(patterns::pattern_routes())
+        .merge(availability::availability_routes())
+        .merge(leave::leave_routes())
+        .merge(toil::toil_routes())
+        .merge(import::import_routes())
+}
\ No newline at end of file
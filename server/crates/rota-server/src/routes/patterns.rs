@@ -0,0 +1,504 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{NaiveDate, Utc};
+use rota_core::{pattern::validate_pattern, shift::validate_shift};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::{
+        assignment::NewAssignment,
+        pattern::{
+            AppliedDay, ApplyReport, ApplyRequest, DateRange, NewPattern, OverrideRequest, Pattern, PatternFilter,
+            PatternMember, PatternOverride, PatternRequest, PatternSort, ProjectedDay, ProjectedShift,
+            StoredOverride,
+        },
+        shift::{NewShift, Shift},
+        template::ShiftTemplate,
+    },
+    pagination::{PageParams, PageRequest, Paginated},
+    repo::RepoError,
+    routes::shifts::{assignment_conflicts, ensure_editable},
+};
+
+// Rotating team patterns: a cycle of templates that members work through
+// from their own offset, projected onto dates and applied to rotas.
+// Managers and admins make changes; anyone signed in can read.
+pub fn pattern_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/patterns", get(list_patterns).post(create_pattern))
+        .route("/api/patterns/:id", get(get_pattern).put(update_pattern).delete(delete_pattern))
+        .route("/api/patterns/:id/members", get(list_members).put(set_members))
+        .route("/api/patterns/:id/overrides", get(list_overrides).put(set_override))
+        .route("/api/patterns/:id/overrides/:user_id/:date", delete(remove_override))
+        .route("/api/patterns/:id/projection", get(project_pattern))
+        .route("/api/patterns/:id/apply", post(apply_pattern))
+}
+
+// A template a pattern can use: it must exist and be the team's or shared
+async fn usable_template(state: &AppState, team_id: i64, id: i64) -> Result<ShiftTemplate, AppError> {
+    let template = state.templates.get(id).await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown template: {}", id)))?;
+    if template.team_id.is_some_and(|team| team != team_id) {
+        return Err(AppError::BadRequest(format!("Template {} belongs to another team", id)));
+    }
+
+    Ok(template)
+}
+
+// Turn a request into a checked pattern, making sure its team and every
+// template in its cycle exist
+async fn checked_pattern(state: &AppState, payload: PatternRequest) -> Result<NewPattern, AppError> {
+    let pattern = payload.into_new_pattern()?;
+    validate_pattern(&pattern)?;
+
+    state.teams.get(pattern.team_id).await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", pattern.team_id)))?;
+    let templates: HashSet<i64> = pattern.cycle.iter().flatten().copied().collect();
+    for id in templates {
+        usable_template(state, pattern.team_id, id).await?;
+    }
+
+    Ok(pattern)
+}
+
+// The 412 response for a pattern write that lost a race
+async fn stale_pattern(state: &AppState, id: i64) -> AppError {
+    match state.patterns.get(id).await {
+        Ok(Some(pattern)) => AppError::precondition_failed(pattern.version, &pattern),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+async fn find_pattern(state: &AppState, id: i64) -> Result<Pattern, AppError> {
+    state.patterns.get(id).await?
+        .ok_or(AppError::NotFound)
+}
+
+// Handler to list patterns by name, optionally for one team
+async fn list_patterns(
+    State(state): State<AppState>,
+    uri: Uri,
+    _claims: Claims,
+    Query(filter): Query<PatternFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<Pattern>, AppError> {
+    let page = PageRequest::new(&params, PatternSort::DEFAULT)?;
+
+    Ok(Paginated::new(database::on_replica(state.patterns.list(filter.team, &page)).await?, uri))
+}
+
+// Handler to create a pattern (managers and admins)
+async fn create_pattern(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<PatternRequest>,
+) -> Result<Versioned<Pattern>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = checked_pattern(&state, payload).await?;
+    let pattern = state.patterns.create(pattern).await?;
+    audit.record("shift_pattern", pattern.id, "create", None, Some(&pattern)).await?;
+
+    Ok(Versioned::created(pattern.version, pattern))
+}
+
+// Handler to get a pattern by ID
+async fn get_pattern(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    let pattern = find_pattern(&state, id).await?;
+
+    Ok(Versioned::ok(pattern.version, pattern).or_not_modified(&if_none_match))
+}
+
+// Handler to replace a pattern's details (managers and admins, honours
+// If-Match). Members keep their offsets, which must still fit the cycle, and
+// overrides are kept.
+async fn update_pattern(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<PatternRequest>,
+) -> Result<Versioned<Pattern>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = find_pattern(&state, id).await?;
+    if_match.check(pattern.version, &pattern)?;
+
+    let edited = checked_pattern(&state, payload).await?;
+    let changed = Pattern {
+        team_id: edited.team_id,
+        name: edited.name,
+        anchor_date: edited.anchor_date,
+        cycle: edited.cycle,
+        ..pattern.clone()
+    };
+    for member in state.patterns.members(id).await? {
+        changed.validate_member(&member)?;
+    }
+
+    let updated = match state.patterns.update(changed).await {
+        Ok(updated) => updated,
+        Err(RepoError::StaleVersion) => return Err(stale_pattern(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("shift_pattern", id, "update", Some(&pattern), Some(&updated)).await?;
+
+    Ok(Versioned::ok(updated.version, updated))
+}
+
+// Handler to soft-delete a pattern (managers and admins, honours If-Match).
+// Shifts it has already made are kept.
+async fn delete_pattern(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = find_pattern(&state, id).await?;
+    if_match.check(pattern.version, &pattern)?;
+
+    let deleted = match state.patterns.update(Pattern { deleted_at: Some(Utc::now()), ..pattern.clone() }).await {
+        Ok(deleted) => deleted,
+        Err(RepoError::StaleVersion) => return Err(stale_pattern(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("shift_pattern", id, "delete", Some(&pattern), Some(&deleted)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Handler to list who works a pattern and at which offset
+async fn list_members(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+) -> Result<Json<Vec<PatternMember>>, AppError> {
+    find_pattern(&state, id).await?;
+
+    Ok(Json(state.patterns.members(id).await?))
+}
+
+// Handler to replace who works a pattern (managers and admins)
+async fn set_members(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(members): Json<Vec<PatternMember>>,
+) -> Result<Json<Vec<PatternMember>>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = find_pattern(&state, id).await?;
+    let mut seen = HashSet::new();
+    for member in &members {
+        if !seen.insert(member.user_id) {
+            return Err(AppError::BadRequest(format!("User {} is listed more than once", member.user_id)));
+        }
+        pattern.validate_member(member)?;
+        let user = state.users.get(member.user_id).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown user: {}", member.user_id)))?;
+        if !user.is_active() {
+            return Err(AppError::BadRequest(format!("User {} is deactivated", member.user_id)));
+        }
+    }
+
+    let before = state.patterns.members(id).await?;
+    state.patterns.set_members(id, &members).await?;
+    let after = state.patterns.members(id).await?;
+    audit.record("shift_pattern", id, "set_members", Some(&before), Some(&after)).await?;
+
+    Ok(Json(after))
+}
+
+// Handler to list a pattern's overrides between two dates
+async fn list_overrides(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Vec<StoredOverride>>, AppError> {
+    range.validate()?;
+    find_pattern(&state, id).await?;
+
+    Ok(Json(state.patterns.overrides(id, range.from, range.to).await?))
+}
+
+// Handler to change what one member works on one day, replacing any
+// override already there (managers and admins)
+async fn set_override(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<OverrideRequest>,
+) -> Result<Json<StoredOverride>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = find_pattern(&state, id).await?;
+    if !state.patterns.members(id).await?.iter().any(|m| m.user_id == payload.user_id) {
+        return Err(AppError::BadRequest(format!("User {} doesn't work this pattern", payload.user_id)));
+    }
+    if let Some(template) = payload.template_id {
+        usable_template(&state, pattern.team_id, template).await?;
+    }
+
+    let change = PatternOverride {
+        user_id: payload.user_id,
+        date: payload.date,
+        template_id: payload.template_id,
+        reason: payload.reason.trim().to_string(),
+    };
+    let stored = state.patterns.set_override(id, change, Some(claims.sub.clone())).await?;
+    audit.record("shift_pattern", id, "set_override", None, Some(&stored)).await?;
+
+    Ok(Json(stored))
+}
+
+// Handler to drop an override, so the cycle decides that day again
+// (managers and admins)
+async fn remove_override(
+    State(state): State<AppState>,
+    Path((id, user_id, date)): Path<(i64, i64, NaiveDate)>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    find_pattern(&state, id).await?;
+    let removed = state.patterns.overrides(id, date, date).await?
+        .into_iter()
+        .find(|o| o.change.user_id == user_id)
+        .ok_or(AppError::NotFound)?;
+    state.patterns.remove_override(id, user_id, date).await?;
+    audit.record("shift_pattern", id, "remove_override", Some(&removed), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The working days a pattern gives its members over a date range
+async fn projected_days(
+    state: &AppState,
+    pattern: &Pattern,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ProjectedDay>, AppError> {
+    let members = state.patterns.members(pattern.id).await?;
+    let overrides: Vec<PatternOverride> = state
+        .patterns
+        .overrides(pattern.id, from, to)
+        .await?
+        .into_iter()
+        .map(|o| o.change)
+        .collect();
+
+    Ok(pattern.project(&members, &overrides, from, to))
+}
+
+// Every template the days use, or `None` for those since deleted
+async fn templates_for(
+    state: &AppState,
+    days: &[ProjectedDay],
+) -> Result<HashMap<i64, Option<ShiftTemplate>>, AppError> {
+    let ids: HashSet<i64> = days.iter().map(|day| day.template_id).collect();
+    let mut templates = HashMap::new();
+    for id in ids {
+        templates.insert(id, state.templates.get(id).await?);
+    }
+
+    Ok(templates)
+}
+
+// Handler to show the working days a pattern gives its members between two
+// dates, overrides included
+async fn project_pattern(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+    Query(range): Query<DateRange>,
+) -> Result<Json<Vec<ProjectedShift>>, AppError> {
+    range.validate()?;
+    let pattern = find_pattern(&state, id).await?;
+    let days = projected_days(&state, &pattern, range.from, range.to).await?;
+    let templates = templates_for(&state, &days).await?;
+
+    let projected = days
+        .into_iter()
+        .map(|day| {
+            let shift = templates[&day.template_id].as_ref().and_then(|t| t.shift_on(day.date).ok());
+            ProjectedShift {
+                starts_at: shift.as_ref().map(|s| s.starts_at),
+                ends_at: shift.as_ref().map(|s| s.ends_at),
+                day,
+            }
+        })
+        .collect();
+
+    Ok(Json(projected))
+}
+
+// The shift the pattern has for a template on a day, made now if it has
+// never made one. `Err` holds why there is no shift to put people on.
+async fn pattern_shift(
+    state: &AppState,
+    audit: &Audit,
+    pattern: &Pattern,
+    rota_id: i64,
+    template: Option<&ShiftTemplate>,
+    (template_id, date): (i64, NaiveDate),
+    report: &mut ApplyReport,
+) -> Result<Result<Shift, String>, AppError> {
+    let Some(template) = template else {
+        return Ok(Err(format!("Template {} has been deleted", template_id)));
+    };
+    if let Some(shift_id) = state.patterns.shift_for(pattern.id, template_id, date).await? {
+        return Ok(state
+            .shifts
+            .get(shift_id)
+            .await?
+            .ok_or_else(|| "The shift made for this day has been deleted".to_string()));
+    }
+
+    let shift = template.shift_on(date).and_then(|shift| {
+        let shift = NewShift { rota_id: Some(rota_id), team_id: Some(pattern.team_id), ..shift };
+        validate_shift(&shift)?;
+        Ok(shift)
+    });
+    let shift = match shift {
+        Ok(shift) => state.shifts.create(shift).await?,
+        Err(err) => return Ok(Err(err.0)),
+    };
+    state.patterns.link_shift(pattern.id, template_id, date, shift.id).await?;
+    audit.record("shift", shift.id, "generate", None, Some(&shift)).await?;
+    report.shifts_created += 1;
+
+    Ok(Ok(shift))
+}
+
+// Handler to put a pattern's members onto shifts across a rota's period
+// (managers and admins). One shift is made per template and day and reused
+// when applying again. Nobody is forced: members with conflicts are left off
+// and reported.
+async fn apply_pattern(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<ApplyRequest>,
+) -> Result<Json<ApplyReport>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let pattern = find_pattern(&state, id).await?;
+    let rota = state.rotas.get(payload.rota_id).await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown rota: {}", payload.rota_id)))?;
+    if rota.team_id != pattern.team_id {
+        return Err(AppError::BadRequest("The rota is for another team".to_string()));
+    }
+    ensure_editable(&rota)?;
+
+    let days = projected_days(&state, &pattern, rota.period_start, rota.period_end).await?;
+    let templates = templates_for(&state, &days).await?;
+
+    // Days come by date, so grouping keeps each template's shifts in order
+    let mut slots: Vec<((i64, NaiveDate), Vec<ProjectedDay>)> = Vec::new();
+    for day in days {
+        let key = (day.template_id, day.date);
+        match slots.iter_mut().find(|(slot, _)| *slot == key) {
+            Some((_, slot_days)) => slot_days.push(day),
+            None => slots.push((key, vec![day])),
+        }
+    }
+
+    let mut report = ApplyReport::default();
+    for (key, slot_days) in slots {
+        let template = templates[&key.0].as_ref();
+        let shift = pattern_shift(&state, &audit, &pattern, rota.id, template, key, &mut report).await?;
+
+        for day in slot_days {
+            let applied = AppliedDay {
+                user_id: day.user_id,
+                date: day.date,
+                template_id: day.template_id,
+                shift_id: None,
+                conflicts: Vec::new(),
+                reason: None,
+            };
+            let shift = match &shift {
+                Ok(shift) => shift,
+                Err(reason) => {
+                    report.skipped.push(AppliedDay { reason: Some(reason.clone()), ..applied });
+                    continue;
+                }
+            };
+            let applied = AppliedDay { shift_id: Some(shift.id), ..applied };
+
+            let user = match state.users.get(day.user_id).await? {
+                Some(user) if user.is_active() => user,
+                _ => {
+                    report.skipped.push(AppliedDay { reason: Some("User is deactivated".to_string()), ..applied });
+                    continue;
+                }
+            };
+            if state.shifts.assignments(shift.id).await?.iter().any(|a| a.user_id == user.id) {
+                report.already_assigned.push(applied);
+                continue;
+            }
+            let conflicts = assignment_conflicts(&state, shift, &user).await?;
+            if !conflicts.is_empty() {
+                report.conflicts.push(AppliedDay { conflicts, ..applied });
+                continue;
+            }
+
+            let assignment = state
+                .shifts
+                .assign(NewAssignment {
+                    shift_id: shift.id,
+                    user_id: user.id,
+                    assigned_by: Some(claims.sub.clone()),
+                    forced: false,
+                    override_reason: None,
+                    conflicts: Vec::new(),
+                })
+                .await?;
+            audit.record("shift_assignment", assignment.id, "create", None, Some(&assignment)).await?;
+            report.assigned.push(applied);
+        }
+    }
+
+    Ok(Json(report))
+}
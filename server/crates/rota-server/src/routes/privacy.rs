@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    export,
+    models::erasure::{
+        CreateErasureRequest, ErasureDecision, ErasureFilter, ErasureRequest, ErasureSort,
+        ErasureStatus, NewErasureRequest,
+    },
+    pagination::{PageParams, PageRequest, Paginated},
+};
+
+// Subject access exports and the right-to-erasure workflow
+pub fn privacy_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/users/:id/export", get(export_user))
+        .route("/api/users/:id/erasure", post(request_erasure))
+        .route("/api/erasure-requests", get(list_erasure_requests))
+        .route("/api/erasure-requests/:id/approve", post(approve_erasure))
+        .route("/api/erasure-requests/:id/reject", post(reject_erasure))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Zip,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+// Handler to export everything held about a user, for the user or an admin
+async fn export_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<ExportQuery>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<Response, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+    let bundle = export::collect(&state, user).await?;
+    audit.record::<()>("user", id, "export", None, None).await?;
+
+    Ok(match query.format {
+        ExportFormat::Json => Json(bundle).into_response(),
+        ExportFormat::Zip => (
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"user-{}-export.zip\"", id),
+                ),
+            ],
+            export::to_zip(&bundle)?,
+        )
+            .into_response(),
+    })
+}
+
+// Handler to ask for a user's personal data to be erased. Nothing happens
+// until an admin approves the request.
+async fn request_erasure(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    payload: Option<Json<CreateErasureRequest>>,
+) -> Result<(StatusCode, Json<ErasureRequest>), AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+    if user.anonymised_at.is_some() {
+        return Err(AppError::Conflict("User has already been anonymised".to_string()));
+    }
+
+    let Json(payload) = payload.unwrap_or_default();
+    let request = state
+        .erasures
+        .create(NewErasureRequest {
+            user_id: id,
+            requested_by: claims.sub,
+            reason: payload.reason,
+        })
+        .await?;
+    audit.record("erasure_request", request.id, "create", None, Some(&request)).await?;
+
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+// Handler to list erasure requests, optionally by status (admin only)
+async fn list_erasure_requests(
+    State(state): State<AppState>,
+    uri: Uri,
+    Query(filter): Query<ErasureFilter>,
+    Query(params): Query<PageParams>,
+    claims: Claims,
+) -> Result<Paginated<ErasureRequest>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    let page = PageRequest::new(&params, ErasureSort::DEFAULT)?;
+
+    Ok(Paginated::new(database::on_replica(state.erasures.list(filter.status, &page)).await?, uri))
+}
+
+// Handler to approve an erasure request and anonymise the user (admin only).
+// Shifts, leave and assignments keep pointing at the user id, so rota history
+// and totals are unaffected; only what identifies the person is removed.
+async fn approve_erasure(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    payload: Option<Json<ErasureDecision>>,
+) -> Result<Json<ErasureRequest>, AppError> {
+    let request = pending_request(&state, id, &claims).await?;
+    // Nobody signs off the erasure of their own account
+    if claims.user_id() == Some(request.user_id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let Json(decision) = payload.unwrap_or_default();
+    let decided = database::atomic(erase(&state, &claims, &audit, &request, &decision)).await?;
+
+    Ok(Json(decided))
+}
+
+// Anonymise the subject of `request`, blank the free text they wrote or that
+// was written about them, and mark the request approved, all in one
+// transaction so an erasure is never half done
+async fn erase(
+    state: &AppState,
+    claims: &Claims,
+    audit: &Audit,
+    request: &ErasureRequest,
+    decision: &ErasureDecision,
+) -> Result<ErasureRequest, AppError> {
+    let user = state.users.get_with_deleted(request.user_id).await?
+        .ok_or(AppError::NotFound)?;
+    let now = Utc::now();
+    let mut erased = user.clone();
+    erased.anonymise(now);
+    erased.deleted_at = erased.deleted_at.or(Some(now));
+    let erased = state.users.update(erased).await?;
+    // The audit chain can't be rewritten, so its user, leave, overtime and
+    // availability snapshots never hold anything this removes
+    audit.record_user(user.id, "anonymise", Some(&user), Some(&erased)).await?;
+    state.leave.erase_notes(user.id).await?;
+    state.toil.erase_notes(user.id).await?;
+    state.availability.erase_notes(user.id).await?;
+
+    let decided = state
+        .erasures
+        .decide(request.id, ErasureStatus::Approved, &claims.sub, &decision.note)
+        .await?;
+    audit.record("erasure_request", request.id, "approve", Some(request), Some(&decided)).await?;
+
+    Ok(decided)
+}
+
+// Handler to turn down an erasure request (admin only)
+async fn reject_erasure(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    payload: Option<Json<ErasureDecision>>,
+) -> Result<Json<ErasureRequest>, AppError> {
+    let request = pending_request(&state, id, &claims).await?;
+
+    let Json(decision) = payload.unwrap_or_default();
+    let decided = state
+        .erasures
+        .decide(id, ErasureStatus::Rejected, &claims.sub, &decision.note)
+        .await?;
+    audit.record("erasure_request", id, "reject", Some(&request), Some(&decided)).await?;
+
+    Ok(Json(decided))
+}
+
+// The request an admin is about to decide, which must still be pending
+async fn pending_request(
+    state: &AppState,
+    id: i64,
+    claims: &Claims,
+) -> Result<ErasureRequest, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let request = state.erasures.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if request.status != ErasureStatus::Pending {
+        return Err(AppError::Conflict("Erasure request has already been decided".to_string()));
+    }
+
+    Ok(request)
+}
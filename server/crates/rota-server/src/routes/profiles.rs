@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use rota_core::{profile::validate_profile, user::normalise_skills};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    error::AppError,
+    etag::{IfMatch, Versioned},
+    models::{
+        profile::{
+            Eligibility, EligibilityQuery, Employment, Ineligibility, ProfileRequest,
+            ProfileResponse, Qualification, StaffProfile,
+        },
+        user::{User, UserFilter},
+    },
+    repo::RepoError,
+    routes::users::{all_users, stale},
+};
+
+// Staff profiles: employment contract, hours, team, skills and qualifications
+pub fn profile_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/users/:id/profile",
+            get(get_profile).put(put_profile).delete(delete_profile),
+        )
+        .route("/api/staff/eligibility", get(eligibility))
+}
+
+// The user and their employment record, if they have one
+async fn load(state: &AppState, id: i64) -> Result<(User, Option<Employment>), AppError> {
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let employment = state.profiles.get(id).await?;
+
+    Ok((user, employment))
+}
+
+// The 412 response for a profile write that lost a race
+async fn stale_profile(state: &AppState, id: i64) -> AppError {
+    match load(state, id).await {
+        Ok((user, Some(employment))) => AppError::precondition_failed(
+            employment.version,
+            &ProfileResponse::new(&employment, &user),
+        ),
+        Ok((_, None)) => AppError::NotFound,
+        Err(err) => err,
+    }
+}
+
+// Handler to read a staff profile, for the member of staff or an admin
+async fn get_profile(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Versioned<ProfileResponse>, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let (user, employment) = load(&state, id).await?;
+    let employment = employment.ok_or(AppError::NotFound)?;
+
+    Ok(Versioned::ok(employment.version, ProfileResponse::new(&employment, &user)))
+}
+
+// Handler to create or replace a staff profile (admin only, honours If-Match).
+// The home team, weekly hours and skills are saved on the user.
+async fn put_profile(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<ProfileRequest>,
+) -> Result<Versioned<ProfileResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let (user, existing) = load(&state, id).await?;
+    if let Some(existing) = &existing {
+        if_match.check(existing.version, &ProfileResponse::new(existing, &user))?;
+    }
+    if let Some(team) = payload.home_team_id {
+        state.teams.get(team).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", team)))?;
+    }
+
+    let profile = StaffProfile {
+        user_id: id,
+        contract_type: payload.contract_type,
+        weekly_hours: payload.weekly_hours,
+        pay_grade: payload.pay_grade.map(|grade| grade.trim().to_string()),
+        start_date: payload.start_date,
+        end_date: payload.end_date,
+        home_team_id: payload.home_team_id,
+        skills: normalise_skills(payload.skills),
+        qualifications: payload
+            .qualifications
+            .into_iter()
+            .map(|q| Qualification { name: q.name.trim().to_string(), ..q })
+            .collect(),
+    };
+    validate_profile(&profile)?;
+
+    let user = if profile.home_team_id != user.team_id
+        || profile.weekly_hours != user.contracted_hours
+        || profile.skills != user.skills
+    {
+        let changed = User {
+            team_id: profile.home_team_id,
+            contracted_hours: profile.weekly_hours,
+            skills: profile.skills.clone(),
+            ..user.clone()
+        };
+        match state.users.update(changed).await {
+            Ok(updated) => {
+                audit.record_user(id, "update", Some(&user), Some(&updated)).await?;
+                updated
+            }
+            Err(RepoError::StaleVersion) => return Err(stale(&state, id).await),
+            Err(err) => return Err(err.into()),
+        }
+    } else {
+        user
+    };
+
+    let now = Utc::now();
+    let employment = Employment {
+        user_id: id,
+        contract_type: profile.contract_type,
+        pay_grade: profile.pay_grade,
+        start_date: profile.start_date,
+        end_date: profile.end_date,
+        qualifications: profile.qualifications,
+        version: existing.as_ref().map_or(0, |e| e.version),
+        created_at: now,
+        updated_at: now,
+    };
+    let saved = match state.profiles.save(employment).await {
+        Ok(saved) => saved,
+        Err(RepoError::StaleVersion) => return Err(stale_profile(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+
+    let action = if existing.is_some() { "update" } else { "create" };
+    audit.record("profile", id, action, existing.as_ref(), Some(&saved)).await?;
+
+    let body = ProfileResponse::new(&saved, &user);
+    Ok(match existing {
+        Some(_) => Versioned::ok(saved.version, body),
+        None => Versioned::created(saved.version, body),
+    })
+}
+
+// Handler to remove a staff profile (admin only, honours If-Match). The user
+// keeps their team, hours and skills.
+async fn delete_profile(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let (user, employment) = load(&state, id).await?;
+    let employment = employment.ok_or(AppError::NotFound)?;
+    if_match.check(employment.version, &ProfileResponse::new(&employment, &user))?;
+
+    state.profiles.delete(id).await?;
+    audit.record("profile", id, "delete", Some(&employment), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Handler to check which active staff could work a shift on a date with the
+// given skills and qualifications (admin only). Staff without a profile can't
+// be scheduled.
+async fn eligibility(
+    State(state): State<AppState>,
+    Query(query): Query<EligibilityQuery>,
+    claims: Claims,
+) -> Result<Json<Vec<Eligibility>>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let filter = UserFilter { team: query.team, active: Some(true), ..UserFilter::default() };
+    let mut users = all_users(&state, &filter).await?;
+    users.sort_by_key(|u| (u.username.to_lowercase(), u.id));
+    let ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+    let records = state.profiles.get_many(&ids).await?;
+    let requirements = query.requirements();
+
+    let results = users
+        .into_iter()
+        .map(|user| {
+            let reasons = match records.iter().find(|r| r.user_id == user.id) {
+                Some(employment) => employment.profile(&user).eligibility(&requirements),
+                None => vec![Ineligibility::NoProfile],
+            };
+            Eligibility {
+                user_id: user.id,
+                name: user.username,
+                eligible: reasons.is_empty(),
+                reasons,
+            }
+        })
+        .collect();
+
+    Ok(Json(results))
+}
@@ -0,0 +1,448 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use rota_core::shift::validate_shift;
+use serde_json::Value;
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::{
+        assignment::NewAssignment,
+        rota::{
+            CopiedAssignment, CopiedShift, CopyReport, CopyRequest, NewRota, NotCopied, NotCopiedCode, Rota,
+            RotaAction, RotaFilter, RotaRequest, RotaSnapshot, RotaSort, SnapshotQuery,
+        },
+        shift::{NewShift, Shift, ShiftFilter, ShiftResponse},
+    },
+    pagination::{PageParams, PageRequest, Paginated},
+    repo::{RepoError, ROTA_OVERLAPS},
+    routes::shifts::{all_shifts, assignment_conflicts},
+};
+
+// Rotas: managers build and publish them, admins lock them for payroll, and
+// staff only ever see what was last published
+pub fn rota_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/rotas", get(list_rotas).post(create_rota))
+        .route("/api/rotas/:id", get(get_rota).delete(delete_rota))
+        .route("/api/rotas/:id/restore", post(restore_rota))
+        .route("/api/rotas/:id/publish", post(publish_rota))
+        .route("/api/rotas/:id/lock", post(lock_rota))
+        .route("/api/rotas/:id/unlock", post(unlock_rota))
+        .route("/api/rotas/:id/published", get(get_published))
+        .route("/api/rotas/:id/copy", post(copy_rota))
+}
+
+// The 412 response for a rota write that lost a race
+async fn stale_rota(state: &AppState, id: i64) -> AppError {
+    match state.rotas.get(id).await {
+        Ok(Some(rota)) => AppError::precondition_failed(rota.version, &rota),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// A rota the caller may see: staff never see drafts
+async fn visible_rota(state: &AppState, id: i64, claims: &Claims) -> Result<Rota, AppError> {
+    state
+        .rotas
+        .get(id)
+        .await?
+        .filter(|rota| rota.revision > 0 || claims.is_manager())
+        .ok_or(AppError::NotFound)
+}
+
+// The rota's shifts as they stand, each with the ids of the people on it
+async fn snapshot_shifts(state: &AppState, rota_id: i64) -> Result<Value, AppError> {
+    let filter = ShiftFilter { rota: Some(rota_id), ..ShiftFilter::default() };
+    let mut shifts = Vec::new();
+    for shift in all_shifts(state, &filter).await? {
+        let assigned: Vec<i64> = state.shifts.assignments(shift.id).await?.iter().map(|a| a.user_id).collect();
+        let mut shift = serde_json::to_value(ShiftResponse::from(shift)).map_err(|_| AppError::InternalServerError)?;
+        shift["assigned"] = assigned.into();
+        shifts.push(shift);
+    }
+
+    Ok(Value::Array(shifts))
+}
+
+// Handler to list rotas a page at a time, filtered and sorted
+async fn list_rotas(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(mut filter): Query<RotaFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<Rota>, AppError> {
+    filter.published_only = !claims.is_manager();
+    let page = PageRequest::new(&params, RotaSort::DEFAULT)?;
+    let rotas = database::on_replica(state.rotas.list(&filter, &page)).await?;
+
+    Ok(Paginated::new(rotas, uri))
+}
+
+// Handler to start a draft rota for a team (managers and admins)
+async fn create_rota(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<RotaRequest>,
+) -> Result<Versioned<Rota>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    state.teams.get(payload.team_id).await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", payload.team_id)))?;
+    let rota = state
+        .rotas
+        .create(NewRota::new(payload.team_id, payload.period_start, payload.length))
+        .await?;
+    audit.record("rota", rota.id, "create", None, Some(&rota)).await?;
+
+    Ok(Versioned::created(rota.version, rota))
+}
+
+// Handler to get a rota by ID
+async fn get_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    let rota = visible_rota(&state, id, &claims).await?;
+
+    Ok(Versioned::ok(rota.version, rota).or_not_modified(&if_none_match))
+}
+
+// Handler to soft-delete a rota that was never published, along with its
+// shifts (managers and admins, honours If-Match)
+async fn delete_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let rota = state.rotas.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(rota.version, &rota)?;
+    if rota.revision > 0 {
+        return Err(AppError::Conflict("Published rotas can't be deleted".to_string()));
+    }
+
+    soft_delete_rota(&state, &audit, &rota).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Soft-delete a rota and every shift on it at the same moment, which is how
+// restoring the rota knows which shifts to bring back
+async fn soft_delete_rota(state: &AppState, audit: &Audit, rota: &Rota) -> Result<(), AppError> {
+    let now = Utc::now();
+    let deleted = match state.rotas.update(Rota { deleted_at: Some(now), ..rota.clone() }).await {
+        Ok(deleted) => deleted,
+        Err(RepoError::StaleVersion) => return Err(stale_rota(state, rota.id).await),
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("rota", rota.id, "delete", Some(rota), Some(&deleted)).await?;
+
+    let filter = ShiftFilter { rota: Some(rota.id), ..ShiftFilter::default() };
+    for shift in all_shifts(state, &filter).await? {
+        let removed = state.shifts.update(Shift { deleted_at: Some(now), ..shift.clone() }).await?;
+        audit.record("shift", shift.id, "delete", Some(&shift), Some(&removed)).await?;
+    }
+
+    Ok(())
+}
+
+// Handler to undo a soft delete, bringing back the shifts deleted with the
+// rota (managers and admins, honours If-Match)
+async fn restore_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<Rota>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let rota = state.rotas.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(rota.version, &rota)?;
+    let Some(deleted_at) = rota.deleted_at else {
+        return Err(AppError::Conflict("Rota is not deleted".to_string()));
+    };
+    let filter = RotaFilter {
+        team: Some(rota.team_id),
+        from: Some(rota.period_start),
+        to: Some(rota.period_end),
+        ..RotaFilter::default()
+    };
+    let overlapping = state.rotas.list(&filter, &PageRequest::first(RotaSort::DEFAULT, 1)).await?;
+    if !overlapping.items.is_empty() {
+        return Err(AppError::Conflict(ROTA_OVERLAPS.to_string()));
+    }
+
+    let restored = match state.rotas.update(Rota { deleted_at: None, ..rota.clone() }).await {
+        Ok(restored) => restored,
+        Err(RepoError::StaleVersion) => {
+            return Err(match state.rotas.get_with_deleted(id).await? {
+                Some(rota) => AppError::precondition_failed(rota.version, &rota),
+                None => AppError::NotFound,
+            });
+        }
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("rota", id, "restore", Some(&rota), Some(&restored)).await?;
+
+    for shift in state.shifts.deleted_with_rota(id, deleted_at).await? {
+        let back = state.shifts.update(Shift { deleted_at: None, ..shift.clone() }).await?;
+        audit.record("shift", shift.id, "restore", Some(&shift), Some(&back)).await?;
+    }
+
+    Ok(Versioned::ok(restored.version, restored))
+}
+
+// Move a rota through its lifecycle if the caller's role allows it.
+// Publishing also takes the snapshot staff will see.
+async fn transition(
+    state: AppState,
+    id: i64,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    action: RotaAction,
+) -> Result<Versioned<Rota>, AppError> {
+    if !action.is_allowed_for(&claims.role()) {
+        return Err(AppError::Forbidden);
+    }
+
+    let rota = state.rotas.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(rota.version, &rota)?;
+
+    let mut changed = rota.clone();
+    changed
+        .transition(action, &claims.sub, Utc::now())
+        .map_err(|err| AppError::Conflict(err.0))?;
+
+    let saved = match action {
+        RotaAction::Publish => {
+            let shifts = snapshot_shifts(&state, id).await?;
+            state.rotas.publish(changed, shifts).await.map(|(rota, _)| rota)
+        }
+        RotaAction::Lock | RotaAction::Unlock => state.rotas.update(changed).await,
+    };
+    let saved = match saved {
+        Ok(saved) => saved,
+        Err(RepoError::StaleVersion) => return Err(stale_rota(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    let verb = match action {
+        RotaAction::Publish => "publish",
+        RotaAction::Lock => "lock",
+        RotaAction::Unlock => "unlock",
+    };
+    audit.record("rota", id, verb, Some(&rota), Some(&saved)).await?;
+
+    Ok(Versioned::ok(saved.version, saved))
+}
+
+// Handler to publish a rota's current shifts to staff (managers and admins)
+async fn publish_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<Rota>, AppError> {
+    transition(state, id, claims, if_match, audit, RotaAction::Publish).await
+}
+
+// Handler to lock a published rota once it has gone to payroll (admin only)
+async fn lock_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<Rota>, AppError> {
+    transition(state, id, claims, if_match, audit, RotaAction::Lock).await
+}
+
+// Handler to reopen a locked rota for corrections (admin only)
+async fn unlock_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<Rota>, AppError> {
+    transition(state, id, claims, if_match, audit, RotaAction::Unlock).await
+}
+
+// Handler to get what staff see of a rota: the latest published snapshot, or
+// an earlier one by revision
+async fn get_published(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(query): Query<SnapshotQuery>,
+) -> Result<Json<RotaSnapshot>, AppError> {
+    visible_rota(&state, id, &claims).await?;
+    let snapshot = state.rotas.snapshot(id, query.revision).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(snapshot))
+}
+
+// Put the people on `from` onto its copy `to` where they are still free,
+// recording who couldn't be carried over
+async fn copy_assignments(
+    state: &AppState,
+    claims: &Claims,
+    audit: &Audit,
+    from: &Shift,
+    to: &Shift,
+    report: &mut CopyReport,
+) -> Result<(), AppError> {
+    for assignment in state.shifts.assignments(from.id).await? {
+        let not_copied = |code, reason: &str| NotCopied {
+            from_shift_id: from.id,
+            shift_id: Some(to.id),
+            user_id: Some(assignment.user_id),
+            code,
+            reason: reason.to_string(),
+            conflicts: Vec::new(),
+        };
+        let user = match state.users.get(assignment.user_id).await? {
+            Some(user) if user.is_active() => user,
+            Some(_) => {
+                report.not_copied.push(not_copied(NotCopiedCode::UserDeactivated, "User is deactivated"));
+                continue;
+            }
+            None => {
+                report.not_copied.push(not_copied(NotCopiedCode::UserDeleted, "User has been deleted"));
+                continue;
+            }
+        };
+        let conflicts = assignment_conflicts(state, to, &user).await?;
+        if !conflicts.is_empty() {
+            let reason = conflicts.iter().map(|c| c.message.as_str()).collect::<Vec<_>>().join("; ");
+            report.not_copied.push(NotCopied { conflicts, ..not_copied(NotCopiedCode::Conflicts, &reason) });
+            continue;
+        }
+
+        let copied = state
+            .shifts
+            .assign(NewAssignment {
+                shift_id: to.id,
+                user_id: user.id,
+                assigned_by: Some(claims.sub.clone()),
+                forced: false,
+                override_reason: None,
+                conflicts: Vec::new(),
+            })
+            .await?;
+        audit.record("shift_assignment", copied.id, "create", None, Some(&copied)).await?;
+        report.assigned.push(CopiedAssignment { shift_id: to.id, user_id: user.id });
+    }
+
+    Ok(())
+}
+
+// Handler to copy a rota's shifts, and optionally the people on them, into a
+// new draft rota for another period (managers and admins). Shifts keep their
+// local times, whatever the clocks do. The new rota and everything copied
+// into it are saved in one transaction, so a copy that fails part way leaves
+// nothing behind.
+async fn copy_rota(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<CopyRequest>,
+) -> Result<(StatusCode, Json<CopyReport>), AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let source = state.rotas.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let length = source.length()
+        .ok_or_else(|| AppError::BadRequest(format!("Rota {} doesn't have a standard length", id)))?;
+    // The request's transaction makes this all or nothing on the SQL backends
+    let rota = state.rotas.create(NewRota::new(source.team_id, payload.period_start, length)).await?;
+    let report = copy_into(&state, &claims, &audit, &source, rota, payload.assignments).await?;
+
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+// Copy the shifts on `source`, and the people on them if `assignments` is
+// set, into the new `rota`
+async fn copy_into(
+    state: &AppState,
+    claims: &Claims,
+    audit: &Audit,
+    source: &Rota,
+    rota: Rota,
+    assignments: bool,
+) -> Result<CopyReport, AppError> {
+    audit.record("rota", rota.id, "create", None, Some(&rota)).await?;
+
+    let days = (rota.period_start - source.period_start).num_days();
+    let mut report = CopyReport { rota, shifts: Vec::new(), assigned: Vec::new(), not_copied: Vec::new() };
+    let filter = ShiftFilter { rota: Some(source.id), ..ShiftFilter::default() };
+    for shift in all_shifts(state, &filter).await? {
+        let moved = shift.moved_by(days).and_then(|moved| {
+            let moved = NewShift { rota_id: Some(report.rota.id), ..moved };
+            validate_shift(&moved)?;
+            Ok(moved)
+        });
+        let moved = match moved {
+            Ok(moved) => moved,
+            Err(err) => {
+                report.not_copied.push(NotCopied {
+                    from_shift_id: shift.id,
+                    shift_id: None,
+                    user_id: None,
+                    code: NotCopiedCode::InvalidShift,
+                    reason: err.0,
+                    conflicts: Vec::new(),
+                });
+                continue;
+            }
+        };
+
+        let copy = state.shifts.create(moved).await?;
+        audit.record("shift", copy.id, "create", None, Some(&copy)).await?;
+        report.shifts.push(CopiedShift {
+            from_shift_id: shift.id,
+            shift_id: copy.id,
+            starts_at: copy.starts_at,
+            ends_at: copy.ends_at,
+        });
+        if assignments {
+            copy_assignments(state, claims, audit, &shift, &copy, &mut report).await?;
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,423 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use rota_core::{
+    assignment::{check_assignment, week_of, AssignmentCheck},
+    shift::{parse_time_zone, validate_shift},
+};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::{
+        assignment::{Assignment, AssignmentRequest, Conflict, NewAssignment},
+        rota::Rota,
+        shift::{NewShift, Shift, ShiftFilter, ShiftRequest, ShiftResponse, ShiftSort},
+        user::User,
+    },
+    pagination::{PageParams, PageRequest, Paginated, MAX_LIMIT},
+    repo::RepoError,
+    routes::availability::availability_of,
+};
+
+// Shifts: managers and admins build them; staff see shifts outside rotas here
+// and rota shifts only once published, through the rota's snapshot
+pub fn shift_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/shifts", get(list_shifts).post(create_shift))
+        .route(
+            "/api/shifts/:id",
+            get(get_shift).put(update_shift).delete(delete_shift),
+        )
+        .route("/api/shifts/:id/restore", post(restore_shift))
+        .route(
+            "/api/shifts/:id/assignments",
+            get(list_assignments).post(assign_shift),
+        )
+        .route("/api/shifts/:id/assignments/:user_id", delete(unassign_shift))
+}
+
+// Turn a request into a checked shift, making sure its team exists and that
+// it falls inside its rota, which must still be open for changes
+async fn checked_shift(state: &AppState, payload: ShiftRequest) -> Result<NewShift, AppError> {
+    let mut shift = payload.into_new_shift()?;
+    validate_shift(&shift)?;
+
+    if let Some(rota_id) = shift.rota_id {
+        let rota = state.rotas.get(rota_id).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown rota: {}", rota_id)))?;
+        ensure_editable(&rota)?;
+        match shift.team_id {
+            None => shift.team_id = Some(rota.team_id),
+            Some(team) if team != rota.team_id => {
+                return Err(AppError::BadRequest("Shift team must match the rota's team".to_string()));
+            }
+            Some(_) => {}
+        }
+        let date = shift.starts_at.with_timezone(&parse_time_zone(&shift.time_zone)?).date_naive();
+        if !rota.contains(date) {
+            return Err(AppError::BadRequest(format!(
+                "Shift must start between {} and {}",
+                rota.period_start, rota.period_end
+            )));
+        }
+    }
+
+    if let Some(team) = shift.team_id {
+        state.teams.get(team).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", team)))?;
+    }
+
+    Ok(shift)
+}
+
+// Refuse changes to a rota that has gone to payroll
+pub(crate) fn ensure_editable(rota: &Rota) -> Result<(), AppError> {
+    if rota.is_editable() {
+        Ok(())
+    } else {
+        Err(AppError::Conflict(format!("Rota {} is locked", rota.id)))
+    }
+}
+
+// Refuse changes to a shift whose rota is locked
+pub(crate) async fn ensure_shift_editable(state: &AppState, shift: &Shift) -> Result<(), AppError> {
+    if let Some(rota) = shift.rota_id {
+        if let Some(rota) = state.rotas.get(rota).await? {
+            ensure_editable(&rota)?;
+        }
+    }
+    Ok(())
+}
+
+// A shift the caller may see: staff only see rota shifts through the
+// published snapshot
+async fn visible_shift(state: &AppState, id: i64, claims: &Claims) -> Result<Shift, AppError> {
+    state
+        .shifts
+        .get(id)
+        .await?
+        .filter(|shift| shift.rota_id.is_none() || claims.is_manager())
+        .ok_or(AppError::NotFound)
+}
+
+// Every shift matching `filter`, fetched a page at a time
+pub(crate) async fn all_shifts(state: &AppState, filter: &ShiftFilter) -> Result<Vec<Shift>, AppError> {
+    let mut shifts = Vec::new();
+    let mut request = Some(PageRequest::first(ShiftSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.shifts.list(filter, &page_request).await?;
+        request = page_request.next(&page);
+        shifts.extend(page.items);
+    }
+
+    Ok(shifts)
+}
+
+// The 412 response for a shift write that lost a race
+pub(crate) async fn stale_shift(state: &AppState, id: i64) -> AppError {
+    match state.shifts.get(id).await {
+        Ok(Some(shift)) => AppError::precondition_failed(shift.version, &ShiftResponse::from(shift)),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Everything that makes assigning `user` to `shift` a problem: overlapping
+// shifts, approved leave, time they said they're unavailable, missing skills
+// or qualifications, and hours over contract or over the weekly maximum
+pub(crate) async fn assignment_conflicts(
+    state: &AppState,
+    shift: &Shift,
+    user: &User,
+) -> Result<Vec<Conflict>, AppError> {
+    let profile = state.profiles.get(user.id).await?.map(|employment| employment.profile(user));
+
+    // Their shifts over the week the hours are counted in, and any that overlap
+    let (week_start, week_end) = week_of(shift);
+    let assigned = state
+        .shifts
+        .assigned_to(user.id, week_start.min(shift.starts_at), week_end.max(shift.ends_at))
+        .await?;
+    let (first, last) = shift.local_dates();
+    let leave = state.leave.approved_between(user.id, first, last).await?;
+    // The day before can hold a window running past midnight into the shift
+    let availability = availability_of(state, user.id, Some(first - Duration::days(1)), Some(last)).await?;
+
+    Ok(check_assignment(&AssignmentCheck {
+        shift,
+        profile: profile.as_ref(),
+        assigned: &assigned,
+        leave: &leave,
+        availability: Some(&availability),
+        max_weekly_hours: state.config.scheduling.max_weekly_hours,
+    }))
+}
+
+// Handler to list shifts a page at a time, filtered and sorted
+async fn list_shifts(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(mut filter): Query<ShiftFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<ShiftResponse>, AppError> {
+    filter.standalone_only = !claims.is_manager();
+    let page = PageRequest::new(&params, ShiftSort::DEFAULT)?;
+    let shifts = database::on_replica(state.shifts.list(&filter, &page)).await?;
+
+    Ok(Paginated::new(shifts.map(ShiftResponse::from), uri))
+}
+
+// Handler to create a shift (managers and admins)
+async fn create_shift(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<ShiftRequest>,
+) -> Result<Versioned<ShiftResponse>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = checked_shift(&state, payload).await?;
+    let shift = state.shifts.create(shift).await?;
+    audit.record("shift", shift.id, "create", None, Some(&shift)).await?;
+
+    Ok(Versioned::created(shift.version, shift.into()))
+}
+
+// Handler to get a shift by ID
+async fn get_shift(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    let shift = visible_shift(&state, id, &claims).await?;
+
+    Ok(Versioned::ok(shift.version, ShiftResponse::from(shift)).or_not_modified(&if_none_match))
+}
+
+// Handler to replace a shift's details (managers and admins, honours If-Match)
+async fn update_shift(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<ShiftRequest>,
+) -> Result<Versioned<ShiftResponse>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = state.shifts.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(shift.version, &ShiftResponse::from(shift.clone()))?;
+    ensure_shift_editable(&state, &shift).await?;
+
+    let edited = checked_shift(&state, payload).await?;
+    let changed = Shift {
+        rota_id: edited.rota_id,
+        team_id: edited.team_id,
+        location: edited.location,
+        position: edited.position,
+        starts_at: edited.starts_at,
+        ends_at: edited.ends_at,
+        time_zone: edited.time_zone,
+        unpaid_break_minutes: edited.unpaid_break_minutes,
+        required_headcount: edited.required_headcount,
+        notes: edited.notes,
+        required_skills: edited.required_skills,
+        required_qualifications: edited.required_qualifications,
+        ..shift.clone()
+    };
+
+    match state.shifts.update(changed).await {
+        Ok(updated) => {
+            audit.record("shift", id, "update", Some(&shift), Some(&updated)).await?;
+            Ok(Versioned::ok(updated.version, updated.into()))
+        }
+        Err(RepoError::StaleVersion) => Err(stale_shift(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to soft-delete a shift (managers and admins, honours If-Match)
+async fn delete_shift(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = state.shifts.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(shift.version, &ShiftResponse::from(shift.clone()))?;
+    ensure_shift_editable(&state, &shift).await?;
+
+    let deleted = Shift {
+        deleted_at: Some(Utc::now()),
+        ..shift.clone()
+    };
+
+    match state.shifts.update(deleted).await {
+        Ok(deleted) => {
+            audit.record("shift", id, "delete", Some(&shift), Some(&deleted)).await?;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(RepoError::StaleVersion) => Err(stale_shift(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to undo a soft delete (managers and admins, honours If-Match). A
+// shift deleted with its rota comes back when the rota is restored.
+async fn restore_shift(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<ShiftResponse>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = state.shifts.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(shift.version, &ShiftResponse::from(shift.clone()))?;
+    if !shift.is_deleted() {
+        return Err(AppError::Conflict("Shift is not deleted".to_string()));
+    }
+    if let Some(rota_id) = shift.rota_id {
+        let rota = state.rotas.get(rota_id).await?
+            .ok_or_else(|| AppError::Conflict(format!("Rota {} is deleted", rota_id)))?;
+        ensure_editable(&rota)?;
+    }
+
+    let restored = match state.shifts.update(Shift {
+        deleted_at: None,
+        ..shift.clone()
+    }).await {
+        Ok(restored) => restored,
+        Err(RepoError::StaleVersion) => {
+            return Err(match state.shifts.get_with_deleted(id).await? {
+                Some(shift) => AppError::precondition_failed(shift.version, &ShiftResponse::from(shift)),
+                None => AppError::NotFound,
+            });
+        }
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("shift", id, "restore", Some(&shift), Some(&restored)).await?;
+
+    Ok(Versioned::ok(restored.version, restored.into()))
+}
+
+// Handler to list everyone assigned to a shift
+async fn list_assignments(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Json<Vec<Assignment>>, AppError> {
+    visible_shift(&state, id, &claims).await?;
+
+    Ok(Json(state.shifts.assignments(id).await?))
+}
+
+// Handler to assign someone to a shift (managers and admins). Any conflict refuses the
+// assignment with 409 unless `force` is set, in which case it goes ahead and
+// the conflicts are kept with it as warnings.
+async fn assign_shift(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<AssignmentRequest>,
+) -> Result<(StatusCode, Json<Assignment>), AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = state.shifts.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    ensure_shift_editable(&state, &shift).await?;
+    let user = state.users.get(payload.user_id).await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown user: {}", payload.user_id)))?;
+    if !user.is_active() {
+        return Err(AppError::BadRequest("Deactivated users can't be assigned to shifts".to_string()));
+    }
+    if state.shifts.assignments(id).await?.iter().any(|a| a.user_id == user.id) {
+        return Err(AppError::Conflict("User is already assigned to this shift".to_string()));
+    }
+
+    let conflicts = assignment_conflicts(&state, &shift, &user).await?;
+    let reason = payload.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if !conflicts.is_empty() {
+        if !payload.force {
+            return Err(AppError::Conflicts(
+                "Assignment conflicts with the user's schedule; set force to override".to_string(),
+                conflicts,
+            ));
+        }
+        if reason.is_none() {
+            return Err(AppError::BadRequest("A reason is required to override conflicts".to_string()));
+        }
+    }
+
+    let assignment = state
+        .shifts
+        .assign(NewAssignment {
+            shift_id: id,
+            user_id: user.id,
+            assigned_by: Some(claims.sub.clone()),
+            forced: !conflicts.is_empty(),
+            override_reason: reason.filter(|_| !conflicts.is_empty()),
+            conflicts,
+        })
+        .await?;
+    audit.record("shift_assignment", assignment.id, "create", None, Some(&assignment)).await?;
+
+    Ok((StatusCode::CREATED, Json(assignment)))
+}
+
+// Handler to take someone off a shift (managers and admins)
+async fn unassign_shift(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(i64, i64)>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let shift = state.shifts.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    ensure_shift_editable(&state, &shift).await?;
+    let assignment = state
+        .shifts
+        .assignments(id)
+        .await?
+        .into_iter()
+        .find(|a| a.user_id == user_id)
+        .ok_or(AppError::NotFound)?;
+    state.shifts.unassign(id, user_id).await?;
+    audit.record("shift_assignment", assignment.id, "delete", Some(&assignment), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
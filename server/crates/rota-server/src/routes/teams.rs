@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    routing::{get, put},
+    Json, Router,
+};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    models::team::{CreateTeamRequest, Team, TeamSort, UpdateTeamRequest},
+    pagination::{PageParams, PageRequest, Paginated, MAX_LIMIT},
+};
+
+// Teams that staff belong to
+pub fn team_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/teams", get(list_teams).post(create_team))
+        .route("/api/teams/:id", put(update_team))
+}
+
+// Handler to list every team, by name
+async fn list_teams(
+    State(state): State<AppState>,
+    uri: Uri,
+    _claims: Claims,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<Team>, AppError> {
+    let page = PageRequest::new(&params, TeamSort::DEFAULT)?;
+
+    Ok(Paginated::new(database::on_replica(state.teams.list(&page)).await?, uri))
+}
+
+// Every team, by name, for callers that need them all
+pub(crate) async fn all_teams(state: &AppState) -> Result<Vec<Team>, AppError> {
+    let mut teams = Vec::new();
+    let mut request = Some(PageRequest::first(TeamSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.teams.list(&page_request).await?;
+        request = page_request.next(&page);
+        teams.extend(page.items);
+    }
+
+    Ok(teams)
+}
+
+// Handler to create a team (admin only)
+async fn create_team(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<CreateTeamRequest>,
+) -> Result<(StatusCode, Json<Team>), AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Team name cannot be empty".to_string()));
+    }
+
+    let team = state.teams.create(name).await?;
+    audit.record("team", team.id, "create", None, Some(&team)).await?;
+
+    Ok((StatusCode::CREATED, Json(team)))
+}
+
+// Handler to rename a team and set its parent and manager (admin only). The
+// parent chain can't loop back to the team, and the manager must be active.
+async fn update_team(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<UpdateTeamRequest>,
+) -> Result<Json<Team>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    let team = state.teams.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Team name cannot be empty".to_string()));
+    }
+
+    let mut parent = payload.parent_id;
+    while let Some(ancestor) = parent {
+        if ancestor == id {
+            return Err(AppError::BadRequest("A team can't sit under itself".to_string()));
+        }
+        parent = state.teams.get(ancestor).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", ancestor)))?
+            .parent_id;
+    }
+    if let Some(manager) = payload.manager_id {
+        let user = state.users.get(manager).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown user: {}", manager)))?;
+        if !user.is_active() {
+            return Err(AppError::BadRequest("Managers must be active".to_string()));
+        }
+    }
+
+    let changed = Team {
+        name: name.to_string(),
+        parent_id: payload.parent_id,
+        manager_id: payload.manager_id,
+        ..team.clone()
+    };
+    let updated = state.teams.update(changed).await?;
+    audit.record("team", id, "update", Some(&team), Some(&updated)).await?;
+
+    Ok(Json(updated))
+}
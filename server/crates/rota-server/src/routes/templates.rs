@@ -0,0 +1,403 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use rota_core::{shift::validate_shift, template::validate_template};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::{
+        rota::Rota,
+        shift::NewShift,
+        template::{
+            GenerateQuery, GeneratedShift, GenerationReport, NewRecurrence, NewShiftTemplate, Occurrence,
+            RecurrenceRequest, RecurrenceRule, ShiftRecurrence, ShiftTemplate, TemplateFilter, TemplateRequest,
+            TemplateSort,
+        },
+    },
+    pagination::{PageParams, PageRequest, Paginated, MAX_LIMIT},
+    repo::RepoError,
+    routes::shifts::ensure_editable,
+};
+
+// Shift templates, the rules they repeat by, and generating rota shifts from
+// them. Managers and admins make changes; anyone signed in can read.
+pub fn template_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/shift-templates", get(list_templates).post(create_template))
+        .route(
+            "/api/shift-templates/:id",
+            get(get_template).put(update_template).delete(delete_template),
+        )
+        .route(
+            "/api/shift-templates/:id/recurrences",
+            get(list_recurrences).post(add_recurrence),
+        )
+        .route("/api/shift-templates/:id/recurrences/:recurrence_id", delete(remove_recurrence))
+        .route("/api/rotas/:id/generate/preview", post(preview_generation))
+        .route("/api/rotas/:id/generate", post(generate_shifts))
+}
+
+// Turn a request into a checked template, making sure its team exists
+async fn checked_template(state: &AppState, payload: TemplateRequest) -> Result<NewShiftTemplate, AppError> {
+    let template = payload.into_new_template()?;
+    validate_template(&template)?;
+
+    if let Some(team) = template.team_id {
+        state.teams.get(team).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown team: {}", team)))?;
+    }
+
+    Ok(template)
+}
+
+// The 412 response for a template write that lost a race
+async fn stale_template(state: &AppState, id: i64) -> AppError {
+    match state.templates.get(id).await {
+        Ok(Some(template)) => AppError::precondition_failed(template.version, &template),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Handler to list templates by name, optionally for one team
+async fn list_templates(
+    State(state): State<AppState>,
+    uri: Uri,
+    _claims: Claims,
+    Query(filter): Query<TemplateFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<ShiftTemplate>, AppError> {
+    let page = PageRequest::new(&params, TemplateSort::DEFAULT)?;
+
+    Ok(Paginated::new(database::on_replica(state.templates.list(filter.team, &page)).await?, uri))
+}
+
+// Every template for `team`, by name, for callers that need them all
+async fn all_templates(state: &AppState, team: Option<i64>) -> Result<Vec<ShiftTemplate>, AppError> {
+    let mut templates = Vec::new();
+    let mut request = Some(PageRequest::first(TemplateSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.templates.list(team, &page_request).await?;
+        request = page_request.next(&page);
+        templates.extend(page.items);
+    }
+
+    Ok(templates)
+}
+
+// Handler to create a template (managers and admins)
+async fn create_template(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<TemplateRequest>,
+) -> Result<Versioned<ShiftTemplate>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let template = checked_template(&state, payload).await?;
+    let template = state.templates.create(template).await?;
+    audit.record("shift_template", template.id, "create", None, Some(&template)).await?;
+
+    Ok(Versioned::created(template.version, template))
+}
+
+// Handler to get a template by ID
+async fn get_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    let template = state.templates.get(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Versioned::ok(template.version, template).or_not_modified(&if_none_match))
+}
+
+// Handler to replace a template's details (managers and admins, honours
+// If-Match). Shifts already generated from it are left as they are.
+async fn update_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<TemplateRequest>,
+) -> Result<Versioned<ShiftTemplate>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let template = state.templates.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(template.version, &template)?;
+
+    let edited = checked_template(&state, payload).await?;
+    let changed = ShiftTemplate {
+        team_id: edited.team_id,
+        name: edited.name,
+        location: edited.location,
+        position: edited.position,
+        start_time: edited.start_time,
+        end_time: edited.end_time,
+        time_zone: edited.time_zone,
+        unpaid_break_minutes: edited.unpaid_break_minutes,
+        required_headcount: edited.required_headcount,
+        colour: edited.colour,
+        ..template.clone()
+    };
+    let updated = match state.templates.update(changed).await {
+        Ok(updated) => updated,
+        Err(RepoError::StaleVersion) => return Err(stale_template(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("shift_template", id, "update", Some(&template), Some(&updated)).await?;
+
+    Ok(Versioned::ok(updated.version, updated))
+}
+
+// Handler to soft-delete a template (managers and admins, honours If-Match).
+// Its recurrences stop generating shifts.
+async fn delete_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let template = state.templates.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(template.version, &template)?;
+
+    let deleted = match state.templates.update(ShiftTemplate { deleted_at: Some(Utc::now()), ..template.clone() }).await {
+        Ok(deleted) => deleted,
+        Err(RepoError::StaleVersion) => return Err(stale_template(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    audit.record("shift_template", id, "delete", Some(&template), Some(&deleted)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Handler to list a template's recurrences
+async fn list_recurrences(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    _claims: Claims,
+) -> Result<Json<Vec<ShiftRecurrence>>, AppError> {
+    state.templates.get(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(state.templates.recurrences(id).await?))
+}
+
+// Handler to make a template repeat (managers and admins)
+async fn add_recurrence(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<RecurrenceRequest>,
+) -> Result<(StatusCode, Json<ShiftRecurrence>), AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    state.templates.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let rule: RecurrenceRule = payload.rule.parse()?;
+    if rule.until.is_some_and(|until| until < payload.starts_on) {
+        return Err(AppError::BadRequest("UNTIL can't be before the start date".to_string()));
+    }
+    let mut exceptions = payload.exceptions;
+    exceptions.sort();
+    exceptions.dedup();
+
+    let recurrence = state
+        .templates
+        .add_recurrence(NewRecurrence {
+            template_id: id,
+            rule,
+            starts_on: payload.starts_on,
+            exceptions,
+        })
+        .await?;
+    audit.record("shift_recurrence", recurrence.id, "create", None, Some(&recurrence)).await?;
+
+    Ok((StatusCode::CREATED, Json(recurrence)))
+}
+
+// Handler to stop a recurrence (managers and admins). Shifts it has already
+// generated are kept.
+async fn remove_recurrence(
+    State(state): State<AppState>,
+    Path((id, recurrence_id)): Path<(i64, i64)>,
+    claims: Claims,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let recurrence = state
+        .templates
+        .get_recurrence(recurrence_id)
+        .await?
+        .filter(|r| r.template_id == id)
+        .ok_or(AppError::NotFound)?;
+    state.templates.remove_recurrence(recurrence_id).await?;
+    audit.record("shift_recurrence", recurrence_id, "delete", Some(&recurrence), None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The recurrences to generate into `rota`, each with its template: those
+// asked for, or every one of the rota team's templates
+async fn recurrences_for(
+    state: &AppState,
+    rota: &Rota,
+    query: &GenerateQuery,
+) -> Result<Vec<(ShiftTemplate, ShiftRecurrence)>, AppError> {
+    let mut found = Vec::new();
+
+    match query.recurrence_ids()? {
+        Some(ids) => {
+            for id in ids {
+                let recurrence = state.templates.get_recurrence(id).await?
+                    .ok_or_else(|| AppError::BadRequest(format!("Unknown recurrence: {}", id)))?;
+                let template = state.templates.get(recurrence.template_id).await?
+                    .ok_or_else(|| AppError::BadRequest(format!("Template of recurrence {} has been deleted", id)))?;
+                if template.team_id.is_some_and(|team| team != rota.team_id) {
+                    return Err(AppError::BadRequest(format!(
+                        "Recurrence {} is for another team's template",
+                        id
+                    )));
+                }
+                found.push((template, recurrence));
+            }
+        }
+        None => {
+            for template in all_templates(state, Some(rota.team_id)).await? {
+                for recurrence in state.templates.recurrences(template.id).await? {
+                    found.push((template.clone(), recurrence));
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+// Work out which shifts generating into `rota` would create. Occurrences
+// that already have a shift are reported but never made again, which is
+// what makes generating twice safe.
+async fn plan_generation(
+    state: &AppState,
+    rota: &Rota,
+    query: &GenerateQuery,
+) -> Result<(GenerationReport, Vec<GeneratedShift>), AppError> {
+    let mut report = GenerationReport::default();
+    let mut shifts = Vec::new();
+
+    for (template, recurrence) in recurrences_for(state, rota, query).await? {
+        let generated = state.shifts.occurrences(recurrence.id).await?;
+        for date in recurrence.recurrence().occurrences(rota.period_start, rota.period_end) {
+            let occurrence = Occurrence {
+                recurrence_id: recurrence.id,
+                template_id: template.id,
+                date,
+                shift_id: None,
+                starts_at: None,
+                ends_at: None,
+                reason: None,
+            };
+            if let Some(done) = generated.iter().find(|o| o.occurrence_date == date) {
+                report.existing.push(Occurrence { shift_id: Some(done.shift_id), ..occurrence });
+                continue;
+            }
+
+            let shift = template.shift_on(date).and_then(|shift| {
+                let shift = NewShift { rota_id: Some(rota.id), team_id: Some(rota.team_id), ..shift };
+                validate_shift(&shift)?;
+                Ok(shift)
+            });
+            match shift {
+                Ok(shift) => {
+                    report.new.push(Occurrence {
+                        starts_at: Some(shift.starts_at),
+                        ends_at: Some(shift.ends_at),
+                        ..occurrence
+                    });
+                    shifts.push(GeneratedShift { recurrence_id: recurrence.id, occurrence_date: date, shift });
+                }
+                Err(err) => report.skipped.push(Occurrence { reason: Some(err.0), ..occurrence }),
+            }
+        }
+    }
+
+    Ok((report, shifts))
+}
+
+// The rota to generate into, if the caller may change it
+async fn target_rota(state: &AppState, id: i64, claims: &Claims) -> Result<Rota, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let rota = state.rotas.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    ensure_editable(&rota)?;
+
+    Ok(rota)
+}
+
+// Handler to show what generating into a rota would do, without doing it
+// (managers and admins)
+async fn preview_generation(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    Query(query): Query<GenerateQuery>,
+) -> Result<Json<GenerationReport>, AppError> {
+    let rota = target_rota(&state, id, &claims).await?;
+    let (report, _) = plan_generation(&state, &rota, &query).await?;
+
+    Ok(Json(GenerationReport { preview: true, ..report }))
+}
+
+// Handler to generate shifts into a rota from template recurrences
+// (managers and admins). Safe to repeat: only missing occurrences are made.
+async fn generate_shifts(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Query(query): Query<GenerateQuery>,
+) -> Result<Json<GenerationReport>, AppError> {
+    let rota = target_rota(&state, id, &claims).await?;
+    let (mut report, shifts) = plan_generation(&state, &rota, &query).await?;
+
+    let created = state.shifts.generate(shifts).await?;
+    for (occurrence, shift) in report.new.iter_mut().zip(&created) {
+        occurrence.shift_id = Some(shift.id);
+        audit.record("shift", shift.id, "generate", None, Some(shift)).await?;
+    }
+
+    Ok(Json(report))
+}
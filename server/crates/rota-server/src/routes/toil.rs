@@ -0,0 +1,331 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use rota_core::toil::{toil_balance, toil_expiry, validate_adjustment, validate_overtime};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::jwt::Claims,
+    database,
+    error::AppError,
+    etag::{IfMatch, Versioned},
+    models::{
+        leave::{LeaveFilter, LeaveStatus, LeaveType},
+        toil::{
+            NewOvertime, NewToilEntry, Overtime, OvertimeDecision, OvertimeFilter, OvertimeRequest, OvertimeSort,
+            ToilAdjustmentRequest, ToilBalance, ToilEntry, ToilEntryKind, ToilLedger,
+        },
+        user::User,
+    },
+    pagination::{PageParams, PageRequest, Paginated},
+    repo::RepoError,
+    routes::leave::{all_leave, approver_for},
+};
+
+// Time off in lieu: staff claim overtime, the manager who approves their
+// leave decides, and approved hours are credited to a ledger at the
+// configured multiplier. Credits lapse after `TOIL_EXPIRY_DAYS` and are
+// spent through TOIL leave requests.
+pub fn toil_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/overtime", get(list_overtime).post(claim_overtime))
+        .route("/api/overtime/:id", get(get_overtime))
+        .route("/api/overtime/:id/approve", post(approve_overtime))
+        .route("/api/overtime/:id/reject", post(reject_overtime))
+        .route("/api/users/:id/toil", get(get_ledger))
+        .route("/api/users/:id/toil/adjustments", post(adjust_toil))
+}
+
+// Where the user stands with TOIL today
+pub(crate) async fn toil_balance_of(state: &AppState, user_id: i64) -> Result<ToilBalance, AppError> {
+    let ledger = state.toil.ledger(user_id).await?;
+
+    Ok(toil_balance(&ledger, Utc::now().date_naive()))
+}
+
+// Hours of TOIL that `days` of leave takes from `user`: a fifth of their
+// contracted week per day, or of a full-time one, to the nearest minute
+pub(crate) fn toil_leave_hours(state: &AppState, user: &User, days: f64) -> f64 {
+    let week = user.contracted_hours.unwrap_or(state.config.leave.full_time_hours);
+    (days * week / 5.0 * 60.0).round() / 60.0
+}
+
+// TOIL hours `user` has asked for that are still waiting on a decision
+pub(crate) async fn pending_toil_hours(state: &AppState, user: &User) -> Result<f64, AppError> {
+    let filter = LeaveFilter {
+        user: Some(user.id),
+        status: Some(LeaveStatus::Pending),
+        leave_type: Some(LeaveType::Toil),
+        ..LeaveFilter::default()
+    };
+    let requests = all_leave(state, &filter).await?;
+
+    Ok(requests.iter().map(|r| toil_leave_hours(state, user, r.days)).sum())
+}
+
+// An overtime claim as snapshotted into the audit log, without the reason and
+// decision note: the log can't be rewritten, and an erasure removes them
+fn audited(overtime: &Overtime) -> Overtime {
+    Overtime {
+        reason: String::new(),
+        decision_note: String::new(),
+        ..overtime.clone()
+    }
+}
+
+// The 412 response for an overtime write that lost a race
+async fn stale_overtime(state: &AppState, id: i64) -> AppError {
+    match state.toil.get_overtime(id).await {
+        Ok(Some(overtime)) => AppError::precondition_failed(overtime.version, &overtime),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Handler to list overtime claims. Staff see their own, or those waiting on
+// them with `approver` set to their id; managers and admins see everyone's.
+async fn list_overtime(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(mut filter): Query<OvertimeFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<Overtime>, AppError> {
+    if !claims.is_manager() {
+        let me = claims.user_id().ok_or(AppError::Forbidden)?;
+        if filter.approver != Some(me) {
+            filter.user = Some(me);
+        }
+    }
+    let page = PageRequest::new(&params, OvertimeSort::DEFAULT)?;
+    let claims = database::on_replica(state.toil.overtime(&filter, &page)).await?;
+
+    Ok(Paginated::new(claims, uri))
+}
+
+// Handler to claim overtime you've worked. A claim naming a shift must be
+// for a shift you were on, on a day it ran.
+async fn claim_overtime(
+    State(state): State<AppState>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<OvertimeRequest>,
+) -> Result<Versioned<Overtime>, AppError> {
+    let me = claims.user_id().ok_or(AppError::Forbidden)?;
+    let user = state.users.get(me).await?
+        .ok_or(AppError::NotFound)?;
+    if !user.is_active() {
+        return Err(AppError::BadRequest("User is deactivated".to_string()));
+    }
+
+    let overtime = NewOvertime {
+        user_id: me,
+        date: payload.date,
+        hours: payload.hours,
+        shift_id: payload.shift_id,
+        reason: payload.reason.trim().to_string(),
+        approver_id: approver_for(&state, &user).await?,
+    };
+    validate_overtime(&overtime, Utc::now().date_naive())?;
+    if let Some(shift_id) = overtime.shift_id {
+        let shift = state.shifts.get(shift_id).await?
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown shift: {}", shift_id)))?;
+        if !state.shifts.assignments(shift_id).await?.iter().any(|a| a.user_id == me) {
+            return Err(AppError::BadRequest(format!("You weren't on shift {}", shift_id)));
+        }
+        let (first, last) = shift.local_dates();
+        if overtime.date < first || last < overtime.date {
+            return Err(AppError::BadRequest(format!("Shift {} didn't run on {}", shift_id, overtime.date)));
+        }
+    }
+
+    let overtime = state.toil.create_overtime(overtime).await?;
+    audit.record("overtime", overtime.id, "create", None, Some(&audited(&overtime))).await?;
+
+    Ok(Versioned::created(overtime.version, overtime))
+}
+
+// The claim, if `claims` may see it: the person who claimed it, their
+// approver, or a manager or admin
+async fn visible_overtime(state: &AppState, id: i64, claims: &Claims) -> Result<Overtime, AppError> {
+    let overtime = state.toil.get_overtime(id).await?
+        .ok_or(AppError::NotFound)?;
+    let me = claims.user_id();
+    if !claims.is_manager() && me != Some(overtime.user_id) && me != overtime.approver_id {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(overtime)
+}
+
+// Handler to read one overtime claim
+async fn get_overtime(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Versioned<Overtime>, AppError> {
+    let overtime = visible_overtime(&state, id, &claims).await?;
+
+    Ok(Versioned::ok(overtime.version, overtime))
+}
+
+// Approve or reject an overtime claim (honours If-Match). Only the approver
+// or an admin decides, and nobody decides their own. Approving credits the
+// hours worked times the multiplier for that day, expiring
+// `TOIL_EXPIRY_DAYS` after it.
+async fn decide(
+    state: AppState,
+    id: i64,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    approve: bool,
+    decision: OvertimeDecision,
+) -> Result<Versioned<Overtime>, AppError> {
+    let overtime = visible_overtime(&state, id, &claims).await?;
+    if_match.check(overtime.version, &overtime)?;
+
+    let me = claims.user_id();
+    let is_decider = claims.is_admin() || (overtime.approver_id.is_some() && me == overtime.approver_id);
+    if !is_decider || me == Some(overtime.user_id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let toil = &state.config.toil;
+    let earned = approve.then(|| toil.multipliers().earned(overtime.date, overtime.hours));
+    let mut changed = overtime.clone();
+    changed
+        .decide(earned, &claims.sub, &decision.note, Utc::now())
+        .map_err(|err| AppError::Conflict(err.0))?;
+    let saved = database::atomic(save_decision(&state, &claims, &audit, &overtime, changed, earned)).await?;
+
+    Ok(Versioned::ok(saved.version, saved))
+}
+
+// Save the decision on `overtime` and credit any hours `earned`, in one
+// transaction so a claim is never approved without its TOIL
+async fn save_decision(
+    state: &AppState,
+    claims: &Claims,
+    audit: &Audit,
+    overtime: &Overtime,
+    changed: Overtime,
+    earned: Option<f64>,
+) -> Result<Overtime, AppError> {
+    let saved = match state.toil.update_overtime(changed).await {
+        Ok(saved) => saved,
+        Err(RepoError::StaleVersion) => return Err(stale_overtime(state, overtime.id).await),
+        Err(err) => return Err(err.into()),
+    };
+    let verb = if earned.is_some() { "approve" } else { "reject" };
+    audit.record("overtime", overtime.id, verb, Some(&audited(overtime)), Some(&audited(&saved))).await?;
+
+    if let Some(hours) = earned.filter(|hours| *hours > 0.0) {
+        let entry = NewToilEntry {
+            user_id: saved.user_id,
+            kind: ToilEntryKind::Accrual,
+            hours,
+            date: saved.date,
+            expires_on: toil_expiry(saved.date, state.config.toil.expiry_days),
+            overtime_id: Some(saved.id),
+            leave_request_id: None,
+            note: format!("{} hours of overtime", saved.hours),
+            created_by: Some(claims.sub.clone()),
+        };
+        let entry = state.toil.add_entry(entry).await?;
+        audit.record("toil_entry", entry.id, "create", None, Some(&entry)).await?;
+    }
+
+    Ok(saved)
+}
+
+// Handler to approve a pending overtime claim
+async fn approve_overtime(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    payload: Option<Json<OvertimeDecision>>,
+) -> Result<Versioned<Overtime>, AppError> {
+    let Json(decision) = payload.unwrap_or_default();
+    decide(state, id, claims, if_match, audit, true, decision).await
+}
+
+// Handler to turn down a pending overtime claim
+async fn reject_overtime(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    payload: Option<Json<OvertimeDecision>>,
+) -> Result<Versioned<Overtime>, AppError> {
+    let Json(decision) = payload.unwrap_or_default();
+    decide(state, id, claims, if_match, audit, false, decision).await
+}
+
+// Handler to show someone's TOIL ledger and balance, for them, a manager or
+// an admin
+async fn get_ledger(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Json<ToilLedger>, AppError> {
+    if !claims.is_self_or_manager(id) {
+        return Err(AppError::Forbidden);
+    }
+    state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    let entries = state.toil.ledger(id).await?;
+    let balance = toil_balance(&entries, Utc::now().date_naive());
+
+    Ok(Json(ToilLedger { balance, entries }))
+}
+
+// Handler to add or take away TOIL hours by hand, such as an opening
+// balance (admin only). Hours added lapse like accruals unless an expiry is
+// given.
+async fn adjust_toil(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    audit: Audit,
+    Json(payload): Json<ToilAdjustmentRequest>,
+) -> Result<(StatusCode, Json<ToilEntry>), AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    let today = Utc::now().date_naive();
+    let expires_on = match payload.expires_on {
+        Some(date) => Some(date),
+        None if payload.hours > 0.0 => toil_expiry(today, state.config.toil.expiry_days),
+        None => None,
+    };
+    let entry = NewToilEntry {
+        user_id: id,
+        kind: ToilEntryKind::Adjustment,
+        hours: payload.hours,
+        date: today,
+        expires_on,
+        overtime_id: None,
+        leave_request_id: None,
+        note: payload.note.trim().to_string(),
+        created_by: Some(claims.sub.clone()),
+    };
+    validate_adjustment(&entry)?;
+
+    let entry = state.toil.add_entry(entry).await?;
+    audit.record("toil_entry", entry.id, "create", None, Some(&entry)).await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
@@ -0,0 +1,480 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, Uri},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use rota_core::user::{
+    normalise_skills, validate_contracted_hours, validate_new_user, validate_personal,
+    PersonalDetails,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    app::AppState,
+    audit::Audit,
+    auth::{jwt::Claims, password},
+    database,
+    error::AppError,
+    etag::{IfMatch, IfNoneMatch, Versioned},
+    models::user::{
+        AdminUserResponse, CreateUserRequest, DeactivationResponse, EditableUser, NewUser,
+        UpdateUserRequest, User, UserFilter, UserResponse, UserRole, UserSort,
+    },
+    pagination::{PageParams, PageRequest, Paginated, MAX_LIMIT},
+    patch,
+    repo::RepoError,
+};
+
+// Initialize router with user-related routes
+pub fn user_routes() -> Router<AppState> {
+    Router::new()
+        .route("/users", post(create_user).get(list_users))
+        .route(
+            "/users/:id",
+            get(get_user_by_id).put(update_user).patch(patch_user).delete(delete_user),
+        )
+        .route("/users/:id/restore", post(restore_user))
+        .route("/users/:id/deactivate", post(deactivate_user))
+        .route("/users/:id/reactivate", post(reactivate_user))
+        .route("/users/:id/admin", get(admin_user_details))
+        .route("/users/:id/personal", get(get_personal).put(update_personal))
+        .route("/users/lookup", get(lookup_users))
+}
+
+// Handler to create a new user. Only admins may create users with a role
+// other than `user`.
+async fn create_user(
+    State(state): State<AppState>,
+    claims: Option<Claims>,
+    audit: Audit,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<Versioned<UserResponse>, AppError> {
+    // Validate user input
+    validate_new_user(&payload.name, &payload.email)?;
+    let role = payload.role.unwrap_or(UserRole::User);
+    if role != UserRole::User && !is_admin(&claims) {
+        return Err(AppError::Forbidden);
+    }
+
+    let new_user = password::blocking(move || {
+        NewUser::new(payload.name, payload.email, payload.password.as_deref(), role)
+    })
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    let user = state.users.create(new_user).await?;
+    audit.record_user(user.id, "create", None, Some(&user)).await?;
+
+    Ok(Versioned::created(user.version, user.into()))
+}
+
+// Handler to list users a page at a time, filtered and sorted (managers and
+// admins)
+async fn list_users(
+    State(state): State<AppState>,
+    uri: Uri,
+    claims: Claims,
+    Query(filter): Query<UserFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<Paginated<UserResponse>, AppError> {
+    if !claims.is_manager() {
+        return Err(AppError::Forbidden);
+    }
+
+    let page = PageRequest::new(&params, UserSort::DEFAULT)?;
+    let users = database::on_replica(state.users.list(&filter, &page)).await?;
+
+    Ok(Paginated::new(users.map(UserResponse::from), uri))
+}
+
+// Handler to get a user by ID, for the user, a manager or an admin
+async fn get_user_by_id(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_none_match: IfNoneMatch,
+) -> Result<Response, AppError> {
+    if !claims.is_self_or_manager(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Versioned::ok(user.version, UserResponse::from(user)).or_not_modified(&if_none_match))
+}
+
+// Handler to replace a user's details, for the user or an admin, honouring
+// If-Match. Changing the role needs an admin token.
+async fn update_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<UpdateUserRequest>,
+) -> Result<Versioned<UserResponse>, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+    validate_new_user(&payload.name, &payload.email)?;
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+    if payload.role.as_ref().is_some_and(|role| *role != user.role) && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let changed = User {
+        username: payload.name,
+        email: payload.email,
+        role: payload.role.unwrap_or_else(|| user.role.clone()),
+        ..user.clone()
+    };
+
+    match state.users.update(changed).await {
+        Ok(updated) => {
+            audit.record_user(id, "update", Some(&user), Some(&updated)).await?;
+            Ok(Versioned::ok(updated.version, updated.into()))
+        }
+        // Someone else saved between our read and write
+        Err(RepoError::StaleVersion) => Err(stale(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to apply a JSON merge patch to a user, for the user or an admin.
+// Only admins may change the role, team, skills or contracted hours.
+async fn patch_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(patch): Json<Value>,
+) -> Result<Versioned<UserResponse>, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+
+    let edited: EditableUser = patch::apply(&EditableUser::from(&user), &patch)?;
+    validate_new_user(&edited.name, &edited.email)?;
+    if let Some(hours) = edited.contracted_hours {
+        validate_contracted_hours(hours)?;
+    }
+    let skills = normalise_skills(edited.skills);
+    let admin_only_change = edited.role != user.role
+        || edited.team_id != user.team_id
+        || skills != user.skills
+        || edited.contracted_hours != user.contracted_hours;
+    if admin_only_change && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let changed = User {
+        username: edited.name,
+        email: edited.email,
+        role: edited.role,
+        team_id: edited.team_id,
+        skills,
+        contracted_hours: edited.contracted_hours,
+        ..user.clone()
+    };
+
+    match state.users.update(changed).await {
+        Ok(updated) => {
+            audit.record_user(id, "update", Some(&user), Some(&updated)).await?;
+            Ok(Versioned::ok(updated.version, updated.into()))
+        }
+        Err(RepoError::StaleVersion) => Err(stale(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to soft-delete a user (admin only). The record is kept so rotas that
+// mention them still resolve; the retention job anonymises it later.
+async fn delete_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<StatusCode, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+
+    let deleted = User {
+        deleted_at: Some(Utc::now()),
+        ..user.clone()
+    };
+
+    match state.users.update(deleted).await {
+        Ok(deleted) => {
+            state.shifts.unassign_future(id, Utc::now()).await?;
+            audit.record_user(id, "delete", Some(&user), Some(&deleted)).await?;
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(RepoError::StaleVersion) => Err(stale(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to undo a soft delete (admin only)
+async fn restore_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<AdminUserResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &AdminUserResponse::from(user.clone()))?;
+    if !user.is_deleted() {
+        return Err(AppError::Conflict("User is not deleted".to_string()));
+    }
+    if user.anonymised_at.is_some() {
+        return Err(AppError::Conflict(
+            "User has been anonymised and can't be restored".to_string(),
+        ));
+    }
+
+    let restored = match state.users.update(User {
+        deleted_at: None,
+        ..user.clone()
+    }).await {
+        Ok(restored) => restored,
+        Err(RepoError::StaleVersion) => {
+            // Still deleted, so only the admin view can show it
+            return Err(match state.users.get_with_deleted(id).await? {
+                Some(user) => AppError::precondition_failed(user.version, &AdminUserResponse::from(user)),
+                None => AppError::NotFound,
+            });
+        }
+        Err(err) => return Err(err.into()),
+    };
+    audit.record_user(id, "restore", Some(&user), Some(&restored)).await?;
+
+    Ok(Versioned::ok(restored.version, restored.into()))
+}
+
+// Handler to deactivate a user (admin only). They can no longer log in or
+// refresh tokens, and are taken off every shift that hasn't started yet.
+async fn deactivate_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<DeactivationResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+    if user.deactivated_at.is_some() {
+        return Err(AppError::Conflict("User is already deactivated".to_string()));
+    }
+
+    let now = Utc::now();
+    let deactivated = match state.users.update(User {
+        deactivated_at: Some(now),
+        ..user.clone()
+    }).await {
+        Ok(deactivated) => deactivated,
+        Err(RepoError::StaleVersion) => return Err(stale(&state, id).await),
+        Err(err) => return Err(err.into()),
+    };
+    let shifts_unassigned = state.shifts.unassign_future(id, now).await?;
+    audit.record_user(id, "deactivate", Some(&user), Some(&deactivated)).await?;
+
+    Ok(Versioned::ok(
+        deactivated.version,
+        DeactivationResponse {
+            user: deactivated.into(),
+            shifts_unassigned,
+        },
+    ))
+}
+
+// Handler to let a deactivated user log in again (admin only). Shifts they
+// were taken off are not restored.
+async fn reactivate_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+) -> Result<Versioned<AdminUserResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+    if user.deactivated_at.is_none() {
+        return Err(AppError::Conflict("User is not deactivated".to_string()));
+    }
+
+    match state.users.update(User {
+        deactivated_at: None,
+        ..user.clone()
+    }).await {
+        Ok(reactivated) => {
+            audit.record_user(id, "reactivate", Some(&user), Some(&reactivated)).await?;
+            Ok(Versioned::ok(reactivated.version, reactivated.into()))
+        }
+        Err(RepoError::StaleVersion) => Err(stale(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Handler to read a user's decrypted personal details, for the user or an admin
+async fn get_personal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Versioned<PersonalDetails>, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    let personal = state.users.personal(id).await?;
+
+    Ok(Versioned::ok(user.version, personal))
+}
+
+// Handler to replace a user's personal details, honouring If-Match. The audit
+// entry names the fields that changed but never their values.
+async fn update_personal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+    if_match: IfMatch,
+    audit: Audit,
+    Json(payload): Json<PersonalDetails>,
+) -> Result<Versioned<PersonalDetails>, AppError> {
+    if !claims.is_self_or_admin(id) {
+        return Err(AppError::Forbidden);
+    }
+    validate_personal(&payload)?;
+
+    let user = state.users.get(id).await?
+        .ok_or(AppError::NotFound)?;
+    if_match.check(user.version, &UserResponse::from(user.clone()))?;
+    let current = state.users.personal(id).await?;
+
+    let fields: Vec<&str> = [
+        ("phone", current.phone != payload.phone),
+        ("emergency_contact", current.emergency_contact != payload.emergency_contact),
+        ("date_of_birth", current.date_of_birth != payload.date_of_birth),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect();
+
+    let changed = User {
+        personal: payload,
+        ..user
+    };
+
+    match state.users.update_personal(changed).await {
+        Ok(updated) => {
+            audit
+                .record("user", id, "update_personal", None, Some(&json!({ "fields": fields })))
+                .await?;
+            Ok(Versioned::ok(updated.version, updated.personal))
+        }
+        Err(RepoError::StaleVersion) => Err(stale(&state, id).await),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupQuery {
+    phone: String,
+}
+
+// Handler to find users by phone number (admin only). Matching goes through
+// the blind index, so formatting differences don't matter.
+async fn lookup_users(
+    State(state): State<AppState>,
+    Query(query): Query<LookupQuery>,
+    claims: Claims,
+) -> Result<Json<Vec<UserResponse>>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let users = state.users.find_by_phone(&query.phone).await?;
+
+    Ok(Json(users.into_iter().map(UserResponse::from).collect()))
+}
+
+// Every user matching `filter`, read a page at a time, for reports that
+// need the whole list
+pub(crate) async fn all_users(state: &AppState, filter: &UserFilter) -> Result<Vec<User>, AppError> {
+    let mut users = Vec::new();
+    let mut request = Some(PageRequest::first(UserSort::DEFAULT, MAX_LIMIT));
+    while let Some(page_request) = request {
+        let page = state.users.list(filter, &page_request).await?;
+        request = page_request.next(&page);
+        users.extend(page.items);
+    }
+
+    Ok(users)
+}
+
+fn is_admin(claims: &Option<Claims>) -> bool {
+    claims.as_ref().is_some_and(Claims::is_admin)
+}
+
+// The 412 response for a write that lost a race, carrying the stored user
+pub(crate) async fn stale(state: &AppState, id: i64) -> AppError {
+    match state.users.get(id).await {
+        Ok(Some(user)) => AppError::precondition_failed(user.version, &UserResponse::from(user)),
+        Ok(None) => AppError::NotFound,
+        Err(err) => err.into(),
+    }
+}
+
+// Handler to read the admin view of a user, including when they were
+// deleted or deactivated (admin only)
+async fn admin_user_details(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    claims: Claims,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    // Deleted users too, so an admin can see what restore would bring back
+    let user = state.users.get_with_deleted(id).await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(user.into()))
+}
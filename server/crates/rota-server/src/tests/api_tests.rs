@@ -0,0 +1,2721 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use crate::app::{build_app, AppState};
+use crate::auth::jwt::create_tokens;
+use crate::config::{Config, RetentionConfig};
+
+// Helper function to create a test app router backed by in-memory storage
+fn app() -> Router {
+    build_app(AppState::in_memory(Config::default()))
+}
+
+// Helper to send a JSON request and decode the JSON response
+async fn send_json(app: &Router, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+    send_json_as(app, None, method, uri, body).await
+}
+
+// Access token for a user with the given id and role
+fn token(user_id: i64, role: &str) -> String {
+    create_tokens(&user_id.to_string(), role).unwrap().access_token
+}
+
+// Helper to send a JSON request with an optional bearer token
+async fn send_json_as(
+    app: &Router,
+    token: Option<&str>,
+    method: &str,
+    uri: &str,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Content-Type", "application/json");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_health_check() {
+    // Arrange
+    let app = app();
+
+    // Create a request to /health
+    let request = Request::builder()
+        .uri("/health")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_root() {
+    // Arrange
+    let app = app();
+
+    // Create a request to /
+    let request = Request::builder()
+        .uri("/")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_create_user() {
+    // Arrange
+    let app = app();
+
+    // Create a request to create a user
+    let request = Request::builder()
+        .uri("/users")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "name": "Test User",
+            "email": "test@example.com"
+        }).to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Check the response body
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(body["name"], "Test User");
+    assert_eq!(body["email"], "test@example.com");
+    assert!(body["id"].is_number());
+}
+
+#[tokio::test]
+async fn test_get_nonexistent_user() {
+    // Arrange
+    let app = app();
+
+    // Create a request to get a user that doesn't exist
+    let request = Request::builder()
+        .uri("/users/999")
+        .header("Authorization", format!("Bearer {}", token(99, "admin")))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert - should be not found
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_and_get_user() {
+    // Arrange
+    let app = app();
+
+    // Create a user first
+    let create_request = Request::builder()
+        .uri("/users")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "name": "Jane Doe",
+            "email": "jane@example.com"
+        }).to_string()))
+        .unwrap();
+
+    let create_response = app.clone().oneshot(create_request).await.unwrap();
+    assert_eq!(create_response.status(), StatusCode::CREATED);
+    
+    let body = hyper::body::to_bytes(create_response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let user_id = body["id"].as_i64().unwrap();
+
+    // Now get the user
+    let get_request = Request::builder()
+        .uri(&format!("/users/{}", user_id))
+        .header("Authorization", format!("Bearer {}", token(user_id, "user")))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let get_response = app.oneshot(get_request).await.unwrap();
+
+    // Assert
+    assert_eq!(get_response.status(), StatusCode::OK);
+    
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    
+    assert_eq!(body["id"], user_id);
+    assert_eq!(body["name"], "Jane Doe");
+    assert_eq!(body["email"], "jane@example.com");
+}
+
+#[tokio::test]
+async fn test_invalid_email_validation() {
+    // Arrange
+    let app = app();
+
+    // Create a request with an invalid email
+    let request = Request::builder()
+        .uri("/users")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "name": "Invalid User",
+            "email": "not-an-email"
+        }).to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert - should be bad request
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    // Check error message contains info about invalid email
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    
+    assert!(body["error"].as_str().unwrap().contains("email"));
+}
+
+#[tokio::test]
+async fn test_request_id_header() {
+    // Arrange
+    let app = app();
+
+    let request = Request::builder()
+        .uri("/health")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert - the middleware tags every response with its request id
+    assert!(response.headers().contains_key("X-Request-ID"));
+}
+
+#[tokio::test]
+async fn test_protected_requires_token() {
+    // Arrange
+    let app = app();
+
+    let request = Request::builder()
+        .uri("/api/protected")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert - missing credentials are rejected by the Claims extractor
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_duplicate_email_conflict() {
+    // Arrange
+    let app = app();
+    let user = json!({ "name": "Sam", "email": "sam@example.com" });
+
+    // Act
+    let (first, _) = send_json(&app, "POST", "/users", user.clone()).await;
+    let (second, body) = send_json(&app, "POST", "/users", user).await;
+
+    // Assert
+    assert_eq!(first, StatusCode::CREATED);
+    assert_eq!(second, StatusCode::CONFLICT);
+    assert_eq!(body["error"], "Email already in use");
+}
+
+#[tokio::test]
+async fn test_reading_users_needs_a_token_and_staff_only_see_themselves() {
+    // Arrange
+    let app = app();
+    let (_, sam) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let (_, kim) = send_json(&app, "POST", "/users", json!({ "name": "Kim", "email": "kim@example.com" })).await;
+    let sam_uri = format!("/users/{}", sam["id"]);
+    let kim_uri = format!("/users/{}", kim["id"]);
+    let staff = token(sam["id"].as_i64().unwrap(), "user");
+    let manager = token(98, "manager");
+
+    // Act
+    let (anonymous_list, _) = send_json(&app, "GET", "/users", Value::Null).await;
+    let (anonymous_user, _) = send_json(&app, "GET", &sam_uri, Value::Null).await;
+    let (bad_token, _) = send_json_as(&app, Some("not-a-token"), "GET", &sam_uri, Value::Null).await;
+    let (staff_list, _) = send_json_as(&app, Some(&staff), "GET", "/users", Value::Null).await;
+    let (colleague, _) = send_json_as(&app, Some(&staff), "GET", &kim_uri, Value::Null).await;
+    let (own, _) = send_json_as(&app, Some(&staff), "GET", &sam_uri, Value::Null).await;
+    let (manager_list, listed) = send_json_as(&app, Some(&manager), "GET", "/users", Value::Null).await;
+    let (manager_user, _) = send_json_as(&app, Some(&manager), "GET", &kim_uri, Value::Null).await;
+
+    // Assert
+    // A missing token is a malformed request, as everywhere else
+    assert_eq!(anonymous_list, StatusCode::BAD_REQUEST);
+    assert_eq!(anonymous_user, StatusCode::BAD_REQUEST);
+    assert_eq!(bad_token, StatusCode::UNAUTHORIZED);
+    assert_eq!(staff_list, StatusCode::FORBIDDEN);
+    assert_eq!(colleague, StatusCode::FORBIDDEN);
+    assert_eq!(own, StatusCode::OK);
+    assert_eq!(manager_list, StatusCode::OK);
+    assert_eq!(listed["items"].as_array().unwrap().len(), 2);
+    assert_eq!(manager_user, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_list_users() {
+    // Arrange
+    let app = app();
+    let admin = token(99, "admin");
+    send_json(&app, "POST", "/users", json!({ "name": "A", "email": "a@example.com" })).await;
+    send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/users",
+        json!({ "name": "B", "email": "b@example.com", "role": "admin" }),
+    )
+    .await;
+
+    // Act
+    let request = Request::builder()
+        .uri("/users")
+        .header("Authorization", format!("Bearer {}", admin))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let users = body["items"].as_array().unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[1]["role"], "admin");
+    assert_eq!(body["next_cursor"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_register_then_login() {
+    // Arrange
+    let app = app();
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/auth/register",
+        json!({ "username": "kim", "email": "kim@example.com", "password": "hunter22" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Act
+    let (wrong, _) = send_json(
+        &app,
+        "POST",
+        "/api/auth/login",
+        json!({ "email": "kim@example.com", "password": "nope" }),
+    )
+    .await;
+    let (ok, body) = send_json(
+        &app,
+        "POST",
+        "/api/auth/login",
+        json!({ "email": "KIM@example.com", "password": "hunter22" }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(wrong, StatusCode::UNAUTHORIZED);
+    assert_eq!(ok, StatusCode::OK);
+    assert!(body["access_token"].is_string());
+}
+
+#[tokio::test]
+async fn test_user_etag_and_conditional_get() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    assert_eq!(created["version"], 1);
+
+    let sam = format!("Bearer {}", token(created["id"].as_i64().unwrap(), "user"));
+
+    // Act
+    let request = Request::builder()
+        .uri(format!("/users/{}", created["id"]))
+        .header("Authorization", &sam)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let request = Request::builder()
+        .uri(format!("/users/{}", created["id"]))
+        .header("Authorization", &sam)
+        .header("If-None-Match", &etag)
+        .body(Body::empty())
+        .unwrap();
+    let not_modified = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(etag, "\"1\"");
+    assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(not_modified.headers()["etag"], "\"1\"");
+}
+
+#[tokio::test]
+async fn test_update_user_if_match() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let uri = format!("/users/{}", created["id"]);
+    let sam = token(created["id"].as_i64().unwrap(), "user");
+    let put = |if_match: &str, name: &str| {
+        Request::builder()
+            .uri(&uri)
+            .method("PUT")
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", sam))
+            .header("If-Match", if_match)
+            .body(Body::from(
+                json!({ "name": name, "email": "sam@example.com" }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Act: the first writer holds the current version and wins
+    let first = app.clone().oneshot(put("\"1\"", "Samantha")).await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["etag"], "\"2\"");
+
+    // The second writer still holds version 1
+    let second = app.clone().oneshot(put("\"1\"", "Sammy")).await.unwrap();
+    let anonymous = Request::builder()
+        .uri(&uri)
+        .method("PUT")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "name": "Mallory", "email": "mallory@example.com" }).to_string()))
+        .unwrap();
+    let anonymous = app.clone().oneshot(anonymous).await.unwrap();
+    let (someone_else, _) = send_json_as(
+        &app,
+        Some(&token(999, "user")),
+        "PUT",
+        &uri,
+        json!({ "name": "Mallory", "email": "mallory@example.com" }),
+    )
+    .await;
+
+    // Assert: 412 with the stored representation so the client can merge
+    assert_eq!(second.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(second.headers()["etag"], "\"2\"");
+    let body = hyper::body::to_bytes(second.into_body()).await.unwrap();
+    let current: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(current["name"], "Samantha");
+    assert_eq!(current["version"], 2);
+    assert_eq!(anonymous.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(someone_else, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_writes_are_audited() {
+    // Arrange
+    let app = app();
+    let admin = token(42, "admin");
+    let (_, created) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/users/{}", created["id"]),
+        json!({ "name": "Samantha", "email": "sam@example.com" }),
+    )
+    .await;
+
+    // Act
+    let (status, entries) =
+        send_json_as(&app, Some(&admin), "GET", "/api/audit?entity=user", Value::Null).await;
+    let (_, report) = send_json_as(&app, Some(&admin), "GET", "/api/audit/verify", Value::Null).await;
+
+    // Assert: newest first, attributed to the token holder, with a diff that
+    // leaves out who the user is
+    assert_eq!(status, StatusCode::OK);
+    let entries = entries["items"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["action"], "update");
+    assert_eq!(entries[0]["actor"], "42");
+    assert!(entries[0]["request_id"].is_string());
+    assert_eq!(entries[0]["changes"]["version"]["before"], 1);
+    assert_eq!(entries[0]["changes"]["version"]["after"], 2);
+    assert!(entries[0]["changes"].get("username").is_none());
+    assert!(!Value::Array(entries.clone()).to_string().contains("sam@example.com"));
+    assert_eq!(entries[1]["action"], "create");
+    assert_eq!(entries[0]["prev_hash"], entries[1]["hash"]);
+    assert_eq!(report, json!({ "valid": true, "entries": 2 }));
+}
+
+#[tokio::test]
+async fn test_audit_log_is_admin_only() {
+    // Arrange
+    let app = app();
+
+    // Act
+    let (anonymous, _) = send_json(&app, "GET", "/api/audit", Value::Null).await;
+    let (user, _) = send_json_as(&app, Some(&token(1, "user")), "GET", "/api/audit", Value::Null).await;
+
+    // Assert
+    assert_eq!(anonymous, StatusCode::BAD_REQUEST);
+    assert_eq!(user, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_soft_delete_and_restore_user() {
+    // Arrange
+    let app = app();
+    let admin = token(1, "admin");
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let uri = format!("/users/{}", created["id"]);
+
+    // Act
+    let (forbidden, _) = send_json_as(&app, Some(&token(2, "user")), "DELETE", &uri, Value::Null).await;
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+    let (hidden, _) = send_json_as(&app, Some(&admin), "GET", &uri, Value::Null).await;
+    let (_, listed) = send_json_as(&app, Some(&admin), "GET", "/users", Value::Null).await;
+    let (restored, body) =
+        send_json_as(&app, Some(&admin), "POST", &format!("{}/restore", uri), Value::Null).await;
+    let (visible, _) = send_json_as(&app, Some(&admin), "GET", &uri, Value::Null).await;
+
+    // Assert
+    assert_eq!(forbidden, StatusCode::FORBIDDEN);
+    assert_eq!(deleted, StatusCode::NO_CONTENT);
+    assert_eq!(hidden, StatusCode::NOT_FOUND);
+    assert_eq!(listed["items"], json!([]));
+    assert_eq!(restored, StatusCode::OK);
+    assert_eq!(body["deleted_at"], Value::Null);
+    assert_eq!(body["version"], 3);
+    assert_eq!(visible, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_restore_user_if_match() {
+    // Arrange: Sam was deleted after the admin last read them at version 1
+    let app = app();
+    let admin = token(1, "admin");
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let uri = format!("/users/{}", created["id"]);
+    send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+    let restore = |if_match: &str| {
+        Request::builder()
+            .uri(format!("{}/restore", uri))
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", admin))
+            .header("If-Match", if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let stale = app.clone().oneshot(restore("\"1\"")).await.unwrap();
+    let current = app.clone().oneshot(restore("\"2\"")).await.unwrap();
+
+    // Assert: 412 with the deleted user as stored, then the fresh tag wins
+    assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.headers()["etag"], "\"2\"");
+    let body = hyper::body::to_bytes(stale.into_body()).await.unwrap();
+    let stored: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stored["version"], 2);
+    assert!(stored["deleted_at"].is_string());
+    assert_eq!(current.status(), StatusCode::OK);
+    assert_eq!(current.headers()["etag"], "\"3\"");
+}
+
+#[tokio::test]
+async fn test_admin_user_details_need_an_admin_token() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let id = created["id"].as_i64().unwrap();
+    let uri = format!("/users/{}/admin", id);
+
+    // Act
+    let (old_token, _) = send_json_as(&app, Some("admin-token"), "GET", &uri, Value::Null).await;
+    let (own, _) = send_json_as(&app, Some(&token(id, "user")), "GET", &uri, Value::Null).await;
+    let (manager, _) = send_json_as(&app, Some(&token(2, "manager")), "GET", &uri, Value::Null).await;
+    let (status, details) = send_json_as(&app, Some(&token(99, "admin")), "GET", &uri, Value::Null).await;
+    let (missing, _) = send_json_as(&app, Some(&token(99, "admin")), "GET", "/users/999/admin", Value::Null).await;
+
+    // Assert
+    assert_eq!(old_token, StatusCode::UNAUTHORIZED);
+    assert_eq!(own, StatusCode::FORBIDDEN);
+    assert_eq!(manager, StatusCode::FORBIDDEN);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(details["email"], "sam@example.com");
+    assert_eq!(details["deactivated_at"], Value::Null);
+    assert_eq!(missing, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_admin_user_details_include_deleted_users() {
+    // Arrange
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let id = created["id"].as_i64().unwrap();
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &format!("/users/{}", id), Value::Null).await;
+
+    // Act
+    let (public, _) = send_json_as(&app, Some(&admin), "GET", &format!("/users/{}", id), Value::Null).await;
+    let (status, details) = send_json_as(&app, Some(&admin), "GET", &format!("/users/{}/admin", id), Value::Null).await;
+
+    // Assert
+    assert!(deleted.is_success());
+    assert_eq!(public, StatusCode::NOT_FOUND);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(details["email"], "sam@example.com");
+    assert!(details["deleted_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_retention_anonymises_deleted_users() {
+    // Arrange: keep deleted users for no time at all
+    let config = Config {
+        retention: RetentionConfig {
+            users: Some(std::time::Duration::ZERO),
+            ..RetentionConfig::default()
+        },
+        ..Config::default()
+    };
+    let app = build_app(AppState::in_memory(config));
+    let admin = token(1, "admin");
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let uri = format!("/users/{}", created["id"]);
+    send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+
+    // Act
+    let (status, report) =
+        send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+    let (again, _) =
+        send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+    let (restore, _) =
+        send_json_as(&app, Some(&admin), "POST", &format!("{}/restore", uri), Value::Null).await;
+    let (_, entries) = send_json_as(
+        &app,
+        Some(&admin),
+        "GET",
+        "/api/audit?action=anonymise",
+        Value::Null,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["users_anonymised"], 1);
+    assert_eq!(again, StatusCode::OK);
+    assert_eq!(restore, StatusCode::CONFLICT);
+    assert_eq!(entries["items"][0]["actor"], "system:retention");
+    assert!(entries["items"][0]["after"]["anonymised_at"].is_string());
+    assert_eq!(entries["items"][0]["before"]["anonymised_at"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_retention_leaves_no_identity_in_the_audit_log() {
+    // Arrange: anonymise deleted users straight away
+    let config = Config {
+        retention: RetentionConfig {
+            users: Some(std::time::Duration::ZERO),
+            ..RetentionConfig::default()
+        },
+        ..Config::default()
+    };
+    let app = build_app(AppState::in_memory(config));
+    let admin = token(1, "admin");
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam Jones", "email": "sam.jones@example.com" }),
+    )
+    .await;
+    let uri = format!("/users/{}", created["id"]);
+    let body = json!({ "name": "Sam Jones-Smith", "email": "sam.jones@example.com" });
+    send_json_as(&app, Some(&admin), "PUT", &uri, body).await;
+    send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+
+    // Act
+    let (_, report) =
+        send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+    let (_, entries) = send_json_as(&app, Some(&admin), "GET", "/api/audit?entity=user", Value::Null).await;
+
+    // Assert
+    assert_eq!(report["users_anonymised"], 1);
+    assert_eq!(entries["items"].as_array().unwrap().len(), 4);
+    assert!(!entries.to_string().contains("sam.jones@example.com"));
+    assert!(!entries.to_string().contains("Sam Jones"));
+}
+
+#[tokio::test]
+async fn test_retention_purges_deleted_shifts_in_memory() {
+    // Arrange: keep deleted shifts for no time at all
+    let config = Config {
+        retention: RetentionConfig {
+            shifts: Some(std::time::Duration::ZERO),
+            ..RetentionConfig::default()
+        },
+        ..Config::default()
+    };
+    let app = build_app(AppState::in_memory(config));
+    let admin = token(1, "admin");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 1" })).await;
+    let shift = json!({
+        "team_id": team["id"],
+        "location": "St Mary's",
+        "position": "Nurse",
+        "start": "2030-06-03T08:00:00",
+        "end": "2030-06-03T16:00:00",
+        "time_zone": "Europe/London"
+    });
+    let (_, deleted) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift.clone()).await;
+    send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift).await;
+    let uri = format!("/api/shifts/{}", deleted["id"]);
+    send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+
+    // Act
+    let (status, report) =
+        send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+    let (again, second) =
+        send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+    let (_, entries) =
+        send_json_as(&app, Some(&admin), "GET", "/api/audit?action=purge", Value::Null).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["shifts_purged"], 1);
+    assert_eq!(again, StatusCode::OK);
+    assert_eq!(second["shifts_purged"], 0);
+    assert_eq!(entries["items"].as_array().unwrap().len(), 1);
+    assert_eq!(entries["items"][0]["entity_id"], deleted["id"].to_string());
+}
+
+#[tokio::test]
+async fn test_deleted_leave_is_purged_by_retention() {
+    // Arrange: keep deleted leave for no time at all. Sam has one request
+    // pending and one approved.
+    let config = Config {
+        retention: RetentionConfig {
+            leave_requests: Some(std::time::Duration::ZERO),
+            ..RetentionConfig::default()
+        },
+        ..Config::default()
+    };
+    let app = build_app(AppState::in_memory(config));
+    let admin = token(99, "admin");
+    let (_, sam) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let sam = token(sam["id"].as_i64().unwrap(), "user");
+    let mut uris = Vec::new();
+    for day in ["2030-06-03", "2030-06-10"] {
+        let body = json!({ "leave_type": "unpaid", "start_date": day, "end_date": day });
+        let (_, request) = send_json_as(&app, Some(&sam), "POST", "/api/leave", body).await;
+        uris.push(format!("/api/leave/{}", request["id"]));
+    }
+    send_json_as(&app, Some(&admin), "POST", &format!("{}/approve", uris[1]), Value::Null).await;
+
+    // Act
+    let (staff, _) = send_json_as(&app, Some(&sam), "DELETE", &uris[0], Value::Null).await;
+    let (approved, _) = send_json_as(&app, Some(&admin), "DELETE", &uris[1], Value::Null).await;
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &uris[0], Value::Null).await;
+    let (hidden, _) = send_json_as(&app, Some(&admin), "GET", &uris[0], Value::Null).await;
+    let (_, listed) = send_json_as(&app, Some(&sam), "GET", "/api/leave", Value::Null).await;
+    let (_, report) = send_json_as(&app, Some(&admin), "POST", "/api/admin/retention/run", Value::Null).await;
+
+    // Assert
+    assert_eq!(staff, StatusCode::FORBIDDEN);
+    assert_eq!(approved, StatusCode::CONFLICT);
+    assert_eq!(deleted, StatusCode::NO_CONTENT);
+    assert_eq!(hidden, StatusCode::NOT_FOUND);
+    assert_eq!(listed["items"].as_array().unwrap().len(), 1);
+    assert_eq!(report["leave_requests_purged"], 1);
+}
+
+#[tokio::test]
+async fn test_leave_list_pages_with_a_cursor() {
+    // Arrange: three days off, requested out of order
+    let app = app();
+    let (_, sam) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let sam = token(sam["id"].as_i64().unwrap(), "user");
+    for day in ["2030-06-10", "2030-06-03", "2030-06-17"] {
+        let body = json!({ "leave_type": "unpaid", "start_date": day, "end_date": day });
+        send_json_as(&app, Some(&sam), "POST", "/api/leave", body).await;
+    }
+    let request = Request::builder()
+        .uri("/api/leave?status=pending&limit=2")
+        .header("Authorization", format!("Bearer {}", sam))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act: follow the cursor from the first page to the second
+    let response = app.clone().oneshot(request).await.unwrap();
+    let link = response.headers().get("link").map(|v| v.to_str().unwrap().to_string());
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let first: Value = serde_json::from_slice(&body).unwrap();
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let next = format!("/api/leave?status=pending&limit=2&cursor={}", cursor);
+    let (_, second) = send_json_as(&app, Some(&sam), "GET", &next, Value::Null).await;
+    let (bad_sort, _) = send_json_as(&app, Some(&sam), "GET", "/api/leave?sort=reason", Value::Null).await;
+
+    // Assert: earliest first, and the last page has no cursor
+    let starts = |page: &Value| -> Vec<String> {
+        page["items"].as_array().unwrap().iter().map(|l| l["start_date"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(starts(&first), vec!["2030-06-03", "2030-06-10"]);
+    assert_eq!(link.unwrap(), format!("<{}>; rel=\"next\"", next));
+    assert_eq!(starts(&second), vec!["2030-06-17"]);
+    assert_eq!(second["next_cursor"], Value::Null);
+    assert_eq!(bad_sort, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_export_user_data() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let id = created["id"].as_i64().unwrap();
+    let uri = format!("/api/users/{}/export", id);
+
+    // Act
+    let (status, bundle) = send_json_as(&app, Some(&token(id, "user")), "GET", &uri, Value::Null).await;
+    let (other, _) = send_json_as(&app, Some(&token(id + 1, "user")), "GET", &uri, Value::Null).await;
+    let request = Request::builder()
+        .uri(format!("{}?format=zip", uri))
+        .header("Authorization", format!("Bearer {}", token(99, "admin")))
+        .body(Body::empty())
+        .unwrap();
+    let zipped = app.clone().oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bundle["profile"]["username"], "Sam");
+    assert!(bundle["profile"].get("password_hash").is_none());
+    assert_eq!(bundle["audit"][0]["action"], "create");
+    assert!(bundle.get("sessions").is_none());
+    assert_eq!(other, StatusCode::FORBIDDEN);
+    assert_eq!(zipped.status(), StatusCode::OK);
+    assert_eq!(zipped.headers()["content-type"], "application/zip");
+    let body = hyper::body::to_bytes(zipped.into_body()).await.unwrap();
+    assert!(body.starts_with(b"PK"));
+}
+
+#[tokio::test]
+async fn test_export_includes_shifts_and_leave() {
+    // Arrange: Sam is on one shift, was on another that has since been
+    // deleted, and has booked a day off
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let id = created["id"].as_i64().unwrap();
+    let sam = token(id, "user");
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/users/{}/profile", id),
+        json!({ "contract_type": "full_time", "weekly_hours": 37.5, "start_date": "2025-01-06" }),
+    )
+    .await;
+    let body = json!({ "location": "Ward 3", "start": "2025-06-02T08:00:00", "end": "2025-06-02T16:00:00" });
+    let (_, shift) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+    let uri = format!("/api/shifts/{}/assignments", shift["id"]);
+    let (assigned, _) = send_json_as(&app, Some(&admin), "POST", &uri, json!({ "user_id": id })).await;
+    let body = json!({ "location": "Ward 4", "start": "2025-06-03T08:00:00", "end": "2025-06-03T16:00:00" });
+    let (_, cancelled) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+    let uri = format!("/api/shifts/{}", cancelled["id"]);
+    send_json_as(&app, Some(&admin), "POST", &format!("{}/assignments", uri), json!({ "user_id": id })).await;
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &uri, Value::Null).await;
+    let body = json!({ "leave_type": "unpaid", "start_date": "2025-06-09", "end_date": "2025-06-09", "reason": "Moving house" });
+    let (booked, leave) = send_json_as(&app, Some(&sam), "POST", "/api/leave", body).await;
+
+    // Act
+    let (status, bundle) =
+        send_json_as(&app, Some(&sam), "GET", &format!("/api/users/{}/export", id), Value::Null).await;
+
+    // Assert
+    assert_eq!(assigned, StatusCode::CREATED);
+    assert_eq!(booked, StatusCode::CREATED);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deleted, StatusCode::NO_CONTENT);
+    assert_eq!(bundle["shifts"].as_array().unwrap().len(), 2);
+    assert_eq!(bundle["shifts"][0]["id"], shift["id"]);
+    assert_eq!(bundle["shifts"][0]["location"], "Ward 3");
+    assert!(bundle["shifts"][0]["deleted_at"].is_null());
+    assert_eq!(bundle["shifts"][1]["id"], cancelled["id"]);
+    assert!(bundle["shifts"][1]["deleted_at"].is_string());
+    assert_eq!(bundle["leave"].as_array().unwrap().len(), 1);
+    assert_eq!(bundle["leave"][0]["id"], leave["id"]);
+    assert_eq!(bundle["leave"][0]["leave_type"], "unpaid");
+    assert_eq!(bundle["leave"][0]["reason"], "Moving house");
+}
+
+#[tokio::test]
+async fn test_erasure_requires_admin_approval() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let id = created["id"].as_i64().unwrap();
+    let subject = token(id, "user");
+    let admin = token(99, "admin");
+    let uri = format!("/api/users/{}/erasure", id);
+
+    // Act
+    let (status, request) =
+        send_json_as(&app, Some(&subject), "POST", &uri, json!({ "reason": "Leaving" })).await;
+    let (duplicate, _) = send_json_as(&app, Some(&subject), "POST", &uri, Value::Null).await;
+    let approve = format!("/api/erasure-requests/{}/approve", request["id"]);
+    let (by_subject, _) = send_json_as(&app, Some(&subject), "POST", &approve, Value::Null).await;
+    let (still_visible, _) = send_json_as(&app, Some(&admin), "GET", &format!("/users/{}", id), Value::Null).await;
+    let (approved, decided) =
+        send_json_as(&app, Some(&admin), "POST", &approve, json!({ "note": "Verified" })).await;
+    let (twice, _) = send_json_as(&app, Some(&admin), "POST", &approve, Value::Null).await;
+    let (gone, _) = send_json_as(&app, Some(&admin), "GET", &format!("/users/{}", id), Value::Null).await;
+    let (_, entries) = send_json_as(
+        &app,
+        Some(&admin),
+        "GET",
+        &format!("/api/audit?entity=user&entity_id={}&action=anonymise", id),
+        Value::Null,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(request["status"], "pending");
+    assert_eq!(duplicate, StatusCode::CONFLICT);
+    assert_eq!(by_subject, StatusCode::FORBIDDEN);
+    assert_eq!(still_visible, StatusCode::OK);
+    assert_eq!(approved, StatusCode::OK);
+    assert_eq!(decided["status"], "approved");
+    assert_eq!(decided["decided_by"], "99");
+    assert_eq!(twice, StatusCode::CONFLICT);
+    assert_eq!(gone, StatusCode::NOT_FOUND);
+    assert!(entries["items"][0]["after"]["anonymised_at"].is_string());
+    assert_eq!(entries["items"][0]["before"]["anonymised_at"], Value::Null);
+    assert_eq!(entries["items"][0]["actor"], "99");
+}
+
+#[tokio::test]
+async fn test_erasure_leaves_no_identity_in_the_audit_log() {
+    // Arrange: Sam's record has been created, edited, deactivated and exported
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam Jones", "email": "sam.jones@example.com" }),
+    )
+    .await;
+    let id = created["id"].as_i64().unwrap();
+    let uri = format!("/users/{}", id);
+    let body = json!({ "name": "Sam Jones-Smith", "email": "sam.jones@example.com" });
+    send_json_as(&app, Some(&admin), "PUT", &uri, body).await;
+    send_json_as(&app, Some(&admin), "POST", &format!("{}/deactivate", uri), Value::Null).await;
+    let export = format!("/api/users/{}/export", id);
+    send_json_as(&app, Some(&admin), "GET", &export, Value::Null).await;
+    let (_, request) =
+        send_json_as(&app, Some(&admin), "POST", &format!("/api/users/{}/erasure", id), Value::Null).await;
+
+    // Act
+    let approve = format!("/api/erasure-requests/{}/approve", request["id"]);
+    let (approved, _) = send_json_as(&app, Some(&token(98, "admin")), "POST", &approve, Value::Null).await;
+    let (_, entries) = send_json_as(&app, Some(&admin), "GET", "/api/audit?limit=1000", Value::Null).await;
+    let (_, bundle) = send_json_as(&app, Some(&admin), "GET", &export, Value::Null).await;
+
+    // Assert
+    assert_eq!(approved, StatusCode::OK);
+    let actions: Vec<&str> = entries["items"].as_array().unwrap().iter().map(|e| e["action"].as_str().unwrap()).collect();
+    assert!(actions.contains(&"update"));
+    assert!(actions.contains(&"deactivate"));
+    for text in [entries.to_string(), bundle["audit"].to_string()] {
+        assert!(!text.contains("sam.jones@example.com"));
+        assert!(!text.contains("Sam Jones"));
+    }
+}
+
+#[tokio::test]
+async fn test_erasure_leaves_no_free_text_in_the_audit_log() {
+    // Arrange: Sam gave reasons for leave and overtime, noted an exception,
+    // and an admin answered both requests with a note
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let id = created["id"].as_i64().unwrap();
+    let sam = token(id, "user");
+    let body = json!({ "leave_type": "unpaid", "start_date": "2030-06-10", "end_date": "2030-06-10", "reason": "Moving house" });
+    let (_, leave) = send_json_as(&app, Some(&sam), "POST", "/api/leave", body).await;
+    let approve = format!("/api/leave/{}/approve", leave["id"]);
+    send_json_as(&app, Some(&admin), "POST", &approve, json!({ "note": "Good luck with the flat" })).await;
+    let yesterday = Utc::now().date_naive() - Duration::days(1);
+    let body = json!({ "date": yesterday, "hours": 2.0, "reason": "Covered for Jo at the clinic" });
+    let (_, claim) = send_json_as(&app, Some(&sam), "POST", "/api/overtime", body).await;
+    let reject = format!("/api/overtime/{}/reject", claim["id"]);
+    send_json_as(&app, Some(&admin), "POST", &reject, json!({ "note": "Not agreed with the ward" })).await;
+    let exceptions = format!("/api/users/{}/availability/exceptions", id);
+    let body = json!({ "date": "2030-06-11", "kind": "unavailable", "note": "Hospital appointment" });
+    let (_, exception) = send_json_as(&app, Some(&sam), "POST", &exceptions, body).await;
+    send_json_as(&app, Some(&sam), "DELETE", &format!("{}/{}", exceptions, exception["id"]), Value::Null).await;
+    let (_, request) =
+        send_json_as(&app, Some(&admin), "POST", &format!("/api/users/{}/erasure", id), Value::Null).await;
+
+    // Act
+    let approve = format!("/api/erasure-requests/{}/approve", request["id"]);
+    let (approved, _) = send_json_as(&app, Some(&token(98, "admin")), "POST", &approve, Value::Null).await;
+    let (_, entries) = send_json_as(&app, Some(&admin), "GET", "/api/audit?limit=1000", Value::Null).await;
+
+    // Assert: the snapshots are there, the words aren't
+    assert_eq!(approved, StatusCode::OK);
+    let entities: Vec<&str> = entries["items"].as_array().unwrap().iter().map(|e| e["entity"].as_str().unwrap()).collect();
+    for entity in ["leave_request", "overtime", "availability"] {
+        assert!(entities.contains(&entity), "no {} entries", entity);
+    }
+    let text = entries.to_string();
+    for words in ["Moving house", "Good luck", "Covered for Jo", "Not agreed", "Hospital appointment"] {
+        assert!(!text.contains(words), "{} is still in the audit log", words);
+    }
+}
+
+#[tokio::test]
+async fn test_personal_details_are_private_and_not_audited_in_clear() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let id = created["id"].as_i64().unwrap();
+    let uri = format!("/users/{}/personal", id);
+    let (own, other, admin) = (token(id, "user"), token(id + 1, "user"), token(99, "admin"));
+    let details = json!({
+        "phone": "+44 7700 900123",
+        "emergency_contact": { "name": "Alex", "phone": "07700 900456", "relationship": "Partner" },
+        "date_of_birth": "1990-04-01"
+    });
+
+    // Act
+    let (put_status, saved) = send_json_as(&app, Some(&own), "PUT", &uri, details.clone()).await;
+    let (get_status, fetched) = send_json_as(&app, Some(&own), "GET", &uri, Value::Null).await;
+    let (other_status, _) = send_json_as(&app, Some(&other), "GET", &uri, Value::Null).await;
+    let (invalid_status, _) =
+        send_json_as(&app, Some(&own), "PUT", &uri, json!({ "phone": "12" })).await;
+    let (_, found) =
+        send_json_as(&app, Some(&admin), "GET", "/users/lookup?phone=%2B447700900123", Value::Null).await;
+    let (lookup_status, _) =
+        send_json_as(&app, Some(&own), "GET", "/users/lookup?phone=123", Value::Null).await;
+    let (_, public) = send_json_as(&app, Some(&own), "GET", &format!("/users/{}", id), Value::Null).await;
+    let (_, audit) = send_json_as(
+        &app,
+        Some(&admin),
+        "GET",
+        "/api/audit?action=update_personal",
+        Value::Null,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(put_status, StatusCode::OK);
+    assert_eq!(saved, details);
+    assert_eq!(get_status, StatusCode::OK);
+    assert_eq!(fetched, details);
+    assert_eq!(other_status, StatusCode::FORBIDDEN);
+    assert_eq!(invalid_status, StatusCode::BAD_REQUEST);
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(found[0]["id"], id);
+    assert_eq!(lookup_status, StatusCode::FORBIDDEN);
+    assert!(public.get("phone").is_none());
+    assert_eq!(
+        audit["items"][0]["after"],
+        json!({ "fields": ["phone", "emergency_contact", "date_of_birth"] })
+    );
+    assert!(!audit.to_string().contains("7700"));
+}
+
+#[tokio::test]
+async fn test_patch_user_merges_and_guards_role() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Sam", "email": "sam@example.com" }),
+    )
+    .await;
+    let id = created["id"].as_i64().unwrap();
+    let uri = format!("/users/{}", id);
+    let (own, admin) = (token(id, "user"), token(99, "admin"));
+
+    // Act
+    let (renamed_status, renamed) =
+        send_json_as(&app, Some(&own), "PATCH", &uri, json!({ "name": "Samantha" })).await;
+    let (promote_self, _) =
+        send_json_as(&app, Some(&own), "PATCH", &uri, json!({ "role": "admin" })).await;
+    let (removed, _) = send_json_as(&app, Some(&own), "PATCH", &uri, json!({ "email": null })).await;
+    let (unknown, _) =
+        send_json_as(&app, Some(&own), "PATCH", &uri, json!({ "password_hash": "x" })).await;
+    let (anonymous, _) = send_json(&app, "PATCH", &uri, json!({ "name": "Nope" })).await;
+    let (promoted_status, promoted) =
+        send_json_as(&app, Some(&admin), "PATCH", &uri, json!({ "role": "admin" })).await;
+    let (escalate, _) = send_json(
+        &app,
+        "POST",
+        "/users",
+        json!({ "name": "Eve", "email": "eve@example.com", "role": "admin" }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(renamed_status, StatusCode::OK);
+    assert_eq!(renamed["name"], "Samantha");
+    assert_eq!(renamed["email"], "sam@example.com");
+    assert_eq!(renamed["version"], 2);
+    assert_eq!(promote_self, StatusCode::FORBIDDEN);
+    assert_eq!(removed, StatusCode::BAD_REQUEST);
+    assert_eq!(unknown, StatusCode::BAD_REQUEST);
+    assert_eq!(anonymous, StatusCode::BAD_REQUEST);
+    assert_eq!(promoted_status, StatusCode::OK);
+    assert_eq!(promoted["role"], "admin");
+    assert_eq!(promoted["name"], "Samantha");
+    assert_eq!(escalate, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_deactivated_user_cannot_log_in_until_reactivated() {
+    // Arrange
+    let app = app();
+    let (_, tokens) = send_json(
+        &app,
+        "POST",
+        "/api/auth/register",
+        json!({ "username": "kim", "email": "kim@example.com", "password": "hunter22" }),
+    )
+    .await;
+    let admin = token(99, "admin");
+    let login = json!({ "email": "kim@example.com", "password": "hunter22" });
+    let refresh = json!({ "refresh_token": tokens["refresh_token"] });
+
+    // Act
+    let (forbidden, _) = send_json_as(
+        &app,
+        Some(&token(1, "user")),
+        "POST",
+        "/users/1/deactivate",
+        Value::Null,
+    )
+    .await;
+    let (deactivated_status, deactivated) =
+        send_json_as(&app, Some(&admin), "POST", "/users/1/deactivate", Value::Null).await;
+    let (again, _) = send_json_as(&app, Some(&admin), "POST", "/users/1/deactivate", Value::Null).await;
+    let (blocked, blocked_body) = send_json(&app, "POST", "/api/auth/login", login.clone()).await;
+    let (blocked_refresh, _) = send_json(&app, "POST", "/api/auth/refresh", refresh.clone()).await;
+    let (_, listed) = send_json_as(&app, Some(&admin), "GET", "/users/1", Value::Null).await;
+    let (reactivated, _) =
+        send_json_as(&app, Some(&admin), "POST", "/users/1/reactivate", Value::Null).await;
+    let (allowed, _) = send_json(&app, "POST", "/api/auth/login", login).await;
+    let (refreshed, _) = send_json(&app, "POST", "/api/auth/refresh", refresh).await;
+
+    // Assert
+    assert_eq!(forbidden, StatusCode::FORBIDDEN);
+    assert_eq!(deactivated_status, StatusCode::OK);
+    assert!(deactivated["deactivated_at"].is_string());
+    assert_eq!(deactivated["shifts_unassigned"], 0);
+    assert_eq!(again, StatusCode::CONFLICT);
+    assert_eq!(blocked, StatusCode::FORBIDDEN);
+    assert_eq!(blocked_body["error"], "Account is deactivated");
+    assert_eq!(blocked_refresh, StatusCode::FORBIDDEN);
+    assert_eq!(listed["active"], false);
+    assert_eq!(reactivated, StatusCode::OK);
+    assert_eq!(allowed, StatusCode::OK);
+    assert_eq!(refreshed, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_deactivation_takes_the_user_off_future_shifts_only() {
+    // Arrange: Sam, who has no contract yet, is forced onto yesterday's shift
+    // and tomorrow's
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let id = created["id"].as_i64().unwrap();
+    let mut assignments = Vec::new();
+    for days in [-1, 1] {
+        let day = Utc::now().date_naive() + Duration::days(days);
+        let body = json!({ "location": "Ward 1", "start": format!("{}T08:00:00", day), "end": format!("{}T16:00:00", day) });
+        let (_, shift) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+        let uri = format!("/api/shifts/{}/assignments", shift["id"]);
+        let (assigned, _) =
+            send_json_as(&app, Some(&admin), "POST", &uri, json!({ "user_id": id, "force": true, "reason": "Cover" })).await;
+        assert_eq!(assigned, StatusCode::CREATED);
+        assignments.push(uri);
+    }
+
+    // Act
+    let (status, deactivated) =
+        send_json_as(&app, Some(&admin), "POST", &format!("/users/{}/deactivate", id), Value::Null).await;
+    let (_, past) = send_json_as(&app, Some(&admin), "GET", &assignments[0], Value::Null).await;
+    let (_, future) = send_json_as(&app, Some(&admin), "GET", &assignments[1], Value::Null).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deactivated["shifts_unassigned"], 1);
+    assert_eq!(past.as_array().unwrap().len(), 1);
+    assert_eq!(past[0]["user_id"], id);
+    assert_eq!(future, json!([]));
+}
+
+#[tokio::test]
+async fn test_me_profile_and_password_change() {
+    // Arrange
+    let app = app();
+    let (_, tokens) = send_json(
+        &app,
+        "POST",
+        "/api/auth/register",
+        json!({ "username": "kim", "email": "kim@example.com", "password": "hunter22" }),
+    )
+    .await;
+    let me = tokens["access_token"].as_str().unwrap().to_string();
+
+    // Act
+    let (_, profile) = send_json_as(&app, Some(&me), "GET", "/api/me", Value::Null).await;
+    let (_, patched) =
+        send_json_as(&app, Some(&me), "PATCH", "/api/me", json!({ "name": "Kimberley" })).await;
+    let (role_change, _) =
+        send_json_as(&app, Some(&me), "PATCH", "/api/me", json!({ "role": "admin" })).await;
+    let (wrong_current, _) = send_json_as(
+        &app,
+        Some(&me),
+        "POST",
+        "/api/me/password",
+        json!({ "current_password": "nope", "new_password": "correct horse" }),
+    )
+    .await;
+    let (too_short, _) = send_json_as(
+        &app,
+        Some(&me),
+        "POST",
+        "/api/me/password",
+        json!({ "current_password": "hunter22", "new_password": "short" }),
+    )
+    .await;
+    let (changed, _) = send_json_as(
+        &app,
+        Some(&me),
+        "POST",
+        "/api/me/password",
+        json!({ "current_password": "hunter22", "new_password": "correct horse" }),
+    )
+    .await;
+    let (old_login, _) = send_json(
+        &app,
+        "POST",
+        "/api/auth/login",
+        json!({ "email": "kim@example.com", "password": "hunter22" }),
+    )
+    .await;
+    let (new_login, _) = send_json(
+        &app,
+        "POST",
+        "/api/auth/login",
+        json!({ "email": "kim@example.com", "password": "correct horse" }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(profile["name"], "kim");
+    assert_eq!(profile["active"], true);
+    assert_eq!(patched["name"], "Kimberley");
+    assert_eq!(role_change, StatusCode::BAD_REQUEST);
+    assert_eq!(wrong_current, StatusCode::FORBIDDEN);
+    assert_eq!(too_short, StatusCode::BAD_REQUEST);
+    assert_eq!(changed, StatusCode::NO_CONTENT);
+    assert_eq!(old_login, StatusCode::UNAUTHORIZED);
+    assert_eq!(new_login, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_list_users_pages_filters_and_sorts() {
+    // Arrange: five users, two of them with the ICU skill
+    let app = app();
+    let admin = token(99, "admin");
+    for (name, skills) in [
+        ("Dee", json!(["ICU"])),
+        ("ann", json!([])),
+        ("Cal", json!(["icu", "Triage"])),
+        ("Bea", json!([])),
+        ("Eli", json!([])),
+    ] {
+        let (_, user) = send_json(
+            &app,
+            "POST",
+            "/users",
+            json!({ "name": name, "email": format!("{}@example.com", name.to_lowercase()) }),
+        )
+        .await;
+        send_json_as(
+            &app,
+            Some(&admin),
+            "PATCH",
+            &format!("/users/{}", user["id"]),
+            json!({ "skills": skills, "team_id": 7 }),
+        )
+        .await;
+    }
+    send_json_as(&app, Some(&admin), "POST", "/users/5/deactivate", Value::Null).await;
+    let get = |uri: String| {
+        let app = app.clone();
+        let admin = admin.clone();
+        async move {
+            let request = Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", admin))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let link = response.headers().get("link").map(|v| v.to_str().unwrap().to_string());
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            (status, link, serde_json::from_slice::<Value>(&body).unwrap())
+        }
+    };
+    let names = |body: &Value| -> Vec<String> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| u["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Act: walk the list two at a time by name, following the cursor
+    let (_, link, first) = get("/users?sort=name&limit=2".to_string()).await;
+    let cursor = first["next_cursor"].as_str().unwrap().to_string();
+    let (_, _, second) = get(format!("/users?sort=name&limit=2&cursor={}", cursor)).await;
+    let (_, _, third) = get(format!(
+        "/users?sort=name&limit=2&cursor={}",
+        second["next_cursor"].as_str().unwrap()
+    ))
+    .await;
+    let (_, _, skilled) = get("/users?skill=ICU&sort=-name".to_string()).await;
+    let (_, _, inactive) = get("/users?active=false".to_string()).await;
+    let (_, _, searched) = get("/users?search=EA&team=7".to_string()).await;
+    let (bad_sort, _, _) = get("/users?sort=password".to_string()).await;
+    let (bad_cursor, _, _) = get(format!("/users?sort=-name&cursor={}", cursor)).await;
+
+    // Assert
+    assert_eq!(names(&first), vec!["ann", "Bea"]);
+    assert_eq!(
+        link.unwrap(),
+        format!("</users?sort=name&limit=2&cursor={}>; rel=\"next\"", cursor)
+    );
+    assert_eq!(names(&second), vec!["Cal", "Dee"]);
+    assert_eq!(names(&third), vec!["Eli"]);
+    assert_eq!(third["next_cursor"], Value::Null);
+    assert_eq!(names(&skilled), vec!["Dee", "Cal"]);
+    assert_eq!(skilled["items"][1]["skills"], json!(["icu", "Triage"]));
+    assert_eq!(names(&inactive), vec!["Eli"]);
+    assert_eq!(names(&searched), vec!["Bea"]);
+    assert_eq!(bad_sort, StatusCode::BAD_REQUEST);
+    assert_eq!(bad_cursor, StatusCode::BAD_REQUEST);
+}
+
+// Helper to send a CSV body and read the response as text
+async fn send_csv_as(app: &Router, token: &str, method: &str, uri: &str, csv: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Content-Type", "text/csv")
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::from(csv.to_string()))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_import_users_from_csv_and_export() {
+    // Arrange
+    let app = app();
+    let admin = token(1, "admin");
+    let (status, _) =
+        send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward A" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let csv = "Name,Email,Role,Team,Contracted Hours,Skills\n\
+               Ada,ada@example.com,admin,Ward A,37.5,First Aid;Driving\n\
+               Bob,bob@example.com,,ward a,,\n\
+               Cy,not-an-email,user,,,\n\
+               Di,di@example.com,user,Nowhere,200,\n";
+    let import = |uri: &'static str, csv: &'static str| {
+        let (app, admin) = (app.clone(), admin.clone());
+        async move {
+            let (status, body) = send_csv_as(&app, &admin, "POST", uri, csv).await;
+            (status, serde_json::from_str::<Value>(&body).unwrap_or(Value::Null))
+        }
+    };
+
+    // Act
+    let (_, dry_run) = import("/api/users/import?dry_run=true", csv).await;
+    let (_, atomic) = import("/api/users/import?atomic=true", csv).await;
+    let (_, after_atomic) = send_csv_as(&app, &admin, "GET", "/api/users/export", "").await;
+    let (status, applied) = import("/api/users/import", csv).await;
+    // Columns left out are kept, so Ada stays an admin
+    let (_, reimport) = import(
+        "/api/users/import",
+        "name,email,contracted_hours\nAda,ADA@example.com,37.5\nBob,bob@example.com,20\n",
+    )
+    .await;
+    let (export_status, exported) = send_csv_as(&app, &admin, "GET", "/api/users/export", "").await;
+    let (unknown_column, _) = import("/api/users/import", "name,email,shoe_size\n").await;
+    let (forbidden, _) =
+        send_csv_as(&app, &token(2, "user"), "POST", "/api/users/import", csv).await;
+
+    // Assert
+    assert_eq!(dry_run["applied"], false);
+    assert_eq!(dry_run["created"], 2);
+    assert_eq!(dry_run["rows"][1], json!({ "line": 3, "email": "bob@example.com", "action": "create" }));
+    assert_eq!(
+        dry_run["errors"],
+        json!([
+            { "line": 4, "field": "email", "message": "Invalid email format" },
+            { "line": 5, "field": "team", "message": "Unknown team: Nowhere" },
+            { "line": 5, "field": "contracted_hours", "message": "Contracted hours must be between 0 and 168" },
+        ])
+    );
+    assert_eq!(atomic["applied"], false);
+    assert_eq!(after_atomic.lines().count(), 1);
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(applied["applied"], true);
+    assert_eq!(applied["created"], 2);
+    assert_eq!(applied["errors"].as_array().unwrap().len(), 3);
+    assert_eq!(reimport["updated"], 1);
+    assert_eq!(reimport["unchanged"], 1);
+
+    assert_eq!(export_status, StatusCode::OK);
+    assert_eq!(
+        exported,
+        "name,email,role,team,contracted_hours,skills\n\
+         Ada,ada@example.com,admin,Ward A,37.5,Driving;First Aid\n\
+         Bob,bob@example.com,user,Ward A,20,\n"
+    );
+    assert_eq!(unknown_column, StatusCode::BAD_REQUEST);
+    assert_eq!(forbidden, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_import_reports_rows_matching_deleted_users() {
+    // Arrange: Ada has been deleted but her email is still taken
+    let app = app();
+    let admin = token(1, "admin");
+    let (_, ada) = send_json(&app, "POST", "/users", json!({ "name": "Ada", "email": "ada@example.com" })).await;
+    send_json_as(&app, Some(&admin), "DELETE", &format!("/users/{}", ada["id"]), Value::Null).await;
+    let csv = "name,email
+Ada,ADA@example.com
+Bob,bob@example.com
+";
+    let import = |uri: &'static str| {
+        let (app, admin) = (app.clone(), admin.clone());
+        async move {
+            let (status, body) = send_csv_as(&app, &admin, "POST", uri, csv).await;
+            (status, serde_json::from_str::<Value>(&body).unwrap())
+        }
+    };
+    let error = json!([{ "line": 2, "field": "email", "message": "Belongs to a deleted user" }]);
+
+    // Act
+    let (_, dry_run) = import("/api/users/import?dry_run=true").await;
+    let (_, atomic) = import("/api/users/import?atomic=true").await;
+    let (status, applied) = import("/api/users/import").await;
+
+    // Assert
+    assert_eq!(dry_run["errors"], error);
+    assert_eq!(dry_run["rows"], json!([{ "line": 3, "email": "bob@example.com", "action": "create" }]));
+    assert_eq!(atomic["applied"], false);
+    assert_eq!(atomic["errors"], error);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(applied["applied"], true);
+    assert_eq!(applied["created"], 1);
+    assert_eq!(applied["errors"], error);
+}
+
+#[tokio::test]
+async fn test_staff_profile_crud_and_eligibility() {
+    // Arrange
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "ICU" })).await;
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bo", "Cy"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    let profile = json!({
+        "contract_type": "part_time",
+        "weekly_hours": 22.5,
+        "pay_grade": "Band 5",
+        "start_date": "2025-01-06",
+        "home_team_id": team["id"],
+        "skills": ["Ventilation"],
+        "qualifications": [
+            { "name": "ALS", "issued_on": "2025-01-01", "expires_on": "2026-01-01" },
+            { "name": "BLS", "issued_on": "2025-01-01", "expires_on": null }
+        ]
+    });
+    let uri = |id: i64| format!("/api/users/{}/profile", id);
+
+    // Act
+    let (created, body) = send_json_as(&app, Some(&admin), "PUT", &uri(ids[0]), profile.clone()).await;
+    let (not_admin, _) = send_json_as(&app, Some(&token(ids[0], "user")), "PUT", &uri(ids[0]), profile.clone()).await;
+    let (own, _) = send_json_as(&app, Some(&token(ids[0], "user")), "GET", &uri(ids[0]), Value::Null).await;
+    let (other, _) = send_json_as(&app, Some(&token(ids[1], "user")), "GET", &uri(ids[0]), Value::Null).await;
+    let (zero_hours_with_hours, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &uri(ids[1]),
+        json!({ "contract_type": "zero_hours", "weekly_hours": 10, "start_date": "2025-01-06" }),
+    )
+    .await;
+    let (_, _) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &uri(ids[1]),
+        json!({ "contract_type": "zero_hours", "start_date": "2026-01-05", "home_team_id": team["id"] }),
+    )
+    .await;
+    let (_, user) = send_json_as(&app, Some(&admin), "GET", &format!("/users/{}", ids[0]), Value::Null).await;
+    let (_, eligible) = send_json_as(
+        &app,
+        Some(&admin),
+        "GET",
+        "/api/staff/eligibility?date=2025-06-01&skills=ventilation&qualifications=als",
+        Value::Null,
+    )
+    .await;
+    let (_, lapsed) = send_json_as(
+        &app,
+        Some(&admin),
+        "GET",
+        &format!("/api/staff/eligibility?date=2026-02-01&qualifications=ALS&team={}", team["id"]),
+        Value::Null,
+    )
+    .await;
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &uri(ids[0]), Value::Null).await;
+    let (gone, _) = send_json_as(&app, Some(&admin), "GET", &uri(ids[0]), Value::Null).await;
+
+    // Assert
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(body["contract_type"], "part_time");
+    assert_eq!(body["qualifications"][0]["name"], "ALS");
+    assert_eq!(body["version"], 1);
+    assert_eq!(not_admin, StatusCode::FORBIDDEN);
+    assert_eq!(own, StatusCode::OK);
+    assert_eq!(other, StatusCode::FORBIDDEN);
+    assert_eq!(zero_hours_with_hours, StatusCode::BAD_REQUEST);
+    // Team, hours and skills are kept on the user
+    assert_eq!(user["team_id"], team["id"]);
+    assert_eq!(user["contracted_hours"], 22.5);
+    assert_eq!(user["skills"], json!(["Ventilation"]));
+
+    assert_eq!(eligible[0], json!({ "user_id": ids[0], "name": "Ann", "eligible": true, "reasons": [] }));
+    assert_eq!(
+        eligible[1]["reasons"],
+        json!([
+            { "code": "not_started", "detail": "2026-01-05" },
+            { "code": "missing_skill", "detail": "ventilation" },
+            { "code": "missing_qualification", "detail": "als" }
+        ])
+    );
+    assert_eq!(eligible[2]["reasons"], json!([{ "code": "no_profile" }]));
+    assert_eq!(lapsed.as_array().unwrap().len(), 2);
+    assert_eq!(lapsed[0]["reasons"], json!([{ "code": "expired_qualification", "detail": "ALS" }]));
+
+    assert_eq!(deleted, StatusCode::NO_CONTENT);
+    assert_eq!(gone, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_shift_crud_with_time_zones() {
+    // Arrange
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let shift = |start: &str, end: &str| {
+        json!({
+            "team_id": team["id"],
+            "location": "St Mary's",
+            "position": "Nurse",
+            "start": start,
+            "end": end,
+            "time_zone": "Europe/London",
+            "unpaid_break_minutes": 30,
+            "required_headcount": 2
+        })
+    };
+
+    // Act: a night shift over the spring clock change, and a summer day shift
+    let (created, night) =
+        send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift("2025-03-29T20:00:00", "2025-03-30T08:00:00")).await;
+    let (_, day) =
+        send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift("2025-06-02T08:00:00", "2025-06-02T20:00:00")).await;
+    let (backwards, _) =
+        send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift("2025-06-02T20:00:00", "2025-06-02T08:00:00")).await;
+    let (too_long, _) =
+        send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift("2025-06-02T06:00:00", "2025-06-02T23:00:00")).await;
+    let (skipped_hour, _) =
+        send_json_as(&app, Some(&admin), "POST", "/api/shifts", shift("2025-03-30T01:30:00", "2025-03-30T09:00:00")).await;
+    let (not_admin, _) = send_json_as(
+        &app,
+        Some(&token(1, "user")),
+        "POST",
+        "/api/shifts",
+        shift("2025-06-03T08:00:00", "2025-06-03T20:00:00"),
+    )
+    .await;
+    let (_, june) = send_json_as(
+        &app,
+        Some(&token(1, "user")),
+        "GET",
+        "/api/shifts?from=2025-06-01T00:00:00Z&to=2025-07-01T00:00:00Z",
+        Value::Null,
+    )
+    .await;
+    let day_uri = format!("/api/shifts/{}", day["id"]);
+    let (updated, moved) = send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &day_uri,
+        shift("2025-06-02T09:00:00", "2025-06-02T21:00:00"),
+    )
+    .await;
+    let (deleted, _) = send_json_as(&app, Some(&admin), "DELETE", &day_uri, Value::Null).await;
+    let (gone, _) = send_json_as(&app, Some(&admin), "GET", &day_uri, Value::Null).await;
+
+    // Assert
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(night["starts_at"], "2025-03-29T20:00:00Z");
+    assert_eq!(night["ends_at"], "2025-03-30T07:00:00Z");
+    assert_eq!(night["local_end"], "2025-03-30T08:00:00");
+    assert_eq!(night["overnight"], true);
+    assert_eq!(night["paid_minutes"], 11 * 60 - 30);
+    assert_eq!(day["starts_at"], "2025-06-02T07:00:00Z");
+    assert_eq!(day["overnight"], false);
+    assert_eq!(backwards, StatusCode::BAD_REQUEST);
+    assert_eq!(too_long, StatusCode::BAD_REQUEST);
+    assert_eq!(skipped_hour, StatusCode::BAD_REQUEST);
+    assert_eq!(not_admin, StatusCode::FORBIDDEN);
+    assert_eq!(june["items"].as_array().unwrap().len(), 1);
+    assert_eq!(june["items"][0]["id"], day["id"]);
+    assert_eq!(updated, StatusCode::OK);
+    assert_eq!(moved["local_start"], "2025-06-02T09:00:00");
+    assert_eq!(moved["version"], 2);
+    assert_eq!(deleted, StatusCode::NO_CONTENT);
+    assert_eq!(gone, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_shift_assignments_report_conflicts_and_can_be_forced() {
+    // Arrange: Ann works 16 hours a week in ICU; Bo has no employment record
+    let app = app();
+    let admin = token(99, "admin");
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/users/{}/profile", ids[0]),
+        json!({ "contract_type": "part_time", "weekly_hours": 16, "start_date": "2025-01-06", "skills": ["ICU"] }),
+    )
+    .await;
+    let mut shifts = Vec::new();
+    for (start, end, skills, qualifications) in [
+        ("2025-06-02T08:00:00", "2025-06-02T20:00:00", json!(["icu"]), json!([])),
+        ("2025-06-02T14:00:00", "2025-06-02T22:00:00", json!([]), json!(["ALS"])),
+        ("2025-06-03T08:00:00", "2025-06-03T20:00:00", json!([]), json!([])),
+    ] {
+        let body = json!({
+            "location": "Ward 3",
+            "start": start,
+            "end": end,
+            "required_skills": skills,
+            "required_qualifications": qualifications
+        });
+        let (_, shift) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+        shifts.push(format!("/api/shifts/{}/assignments", shift["id"]));
+    }
+    let assign = |uri: &String, body: Value| {
+        let (app, admin, uri) = (app.clone(), admin.clone(), uri.clone());
+        async move { send_json_as(&app, Some(&admin), "POST", &uri, body).await }
+    };
+
+    // Act
+    let (assigned, clear) = assign(&shifts[0], json!({ "user_id": ids[0] })).await;
+    let (again, _) = assign(&shifts[0], json!({ "user_id": ids[0] })).await;
+    let (refused, problems) = assign(&shifts[1], json!({ "user_id": ids[0] })).await;
+    let (no_reason, _) = assign(&shifts[1], json!({ "user_id": ids[0], "force": true })).await;
+    let (forced, warned) =
+        assign(&shifts[1], json!({ "user_id": ids[0], "force": true, "reason": "Agency cover fell through" })).await;
+    let (_, no_profile) = assign(&shifts[2], json!({ "user_id": ids[1] })).await;
+    let (not_admin, _) =
+        send_json_as(&app, Some(&token(ids[1], "user")), "POST", &shifts[2], json!({ "user_id": ids[1] })).await;
+    let (_, listed) = send_json_as(&app, Some(&token(ids[1], "user")), "GET", &shifts[1], Value::Null).await;
+    let unassign_uri = format!("{}/{}", shifts[1], ids[0]);
+    let (removed, _) = send_json_as(&app, Some(&admin), "DELETE", &unassign_uri, Value::Null).await;
+    let (removed_again, _) = send_json_as(&app, Some(&admin), "DELETE", &unassign_uri, Value::Null).await;
+
+    // Assert
+    let codes = |body: &Value| {
+        body["conflicts"].as_array().unwrap().iter().map(|c| c["code"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(assigned, StatusCode::CREATED);
+    assert_eq!(clear["forced"], false);
+    assert!(codes(&clear).is_empty());
+    assert_eq!(again, StatusCode::CONFLICT);
+    assert_eq!(refused, StatusCode::CONFLICT);
+    assert_eq!(
+        codes(&problems),
+        vec!["double_booked", "missing_qualification", "exceeds_contracted_hours"]
+    );
+    assert_eq!(no_reason, StatusCode::BAD_REQUEST);
+    assert_eq!(forced, StatusCode::CREATED);
+    assert_eq!(warned["forced"], true);
+    assert_eq!(warned["override_reason"], "Agency cover fell through");
+    assert_eq!(codes(&warned), codes(&problems));
+    assert_eq!(codes(&no_profile), vec!["no_profile"]);
+    assert_eq!(not_admin, StatusCode::FORBIDDEN);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(removed, StatusCode::NO_CONTENT);
+    assert_eq!(removed_again, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_rota_lifecycle_controls_what_staff_see() {
+    // Arrange: a draft week for a team, with one shift
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let staff = token(1, "user");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let body = json!({ "team_id": team["id"], "period_start": "2025-06-02", "length": "week" });
+    let (created, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body.clone()).await;
+    let rota_uri = format!("/api/rotas/{}", rota["id"]);
+    let shift_body = |start: &str, end: &str| {
+        json!({ "rota_id": rota["id"], "location": "Ward 3", "start": start, "end": end })
+    };
+    let (_, shift) =
+        send_json_as(&app, Some(&manager), "POST", "/api/shifts", shift_body("2025-06-03T08:00:00", "2025-06-03T20:00:00"))
+            .await;
+    let shift_uri = format!("/api/shifts/{}", shift["id"]);
+
+    // Act
+    let (overlapping, _) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let (outside, _) =
+        send_json_as(&app, Some(&manager), "POST", "/api/shifts", shift_body("2025-06-09T08:00:00", "2025-06-09T20:00:00"))
+            .await;
+    let (draft_hidden, _) = send_json_as(&app, Some(&staff), "GET", &rota_uri, Value::Null).await;
+    let (shift_hidden, _) = send_json_as(&app, Some(&staff), "GET", &shift_uri, Value::Null).await;
+    let (staff_publish, _) =
+        send_json_as(&app, Some(&staff), "POST", &format!("{}/publish", rota_uri), Value::Null).await;
+    let (published, rota) =
+        send_json_as(&app, Some(&manager), "POST", &format!("{}/publish", rota_uri), Value::Null).await;
+    // Edits after publishing stay out of what staff see until republished
+    send_json_as(&app, Some(&manager), "POST", "/api/shifts", shift_body("2025-06-04T08:00:00", "2025-06-04T20:00:00"))
+        .await;
+    let (_, seen) = send_json_as(&app, Some(&staff), "GET", &format!("{}/published", rota_uri), Value::Null).await;
+    let (_, listed) = send_json_as(&app, Some(&staff), "GET", "/api/rotas", Value::Null).await;
+    let (manager_lock, _) =
+        send_json_as(&app, Some(&manager), "POST", &format!("{}/lock", rota_uri), Value::Null).await;
+    let (locked, rota_locked) =
+        send_json_as(&app, Some(&admin), "POST", &format!("{}/lock", rota_uri), Value::Null).await;
+    let (edit_locked, _) = send_json_as(&app, Some(&manager), "DELETE", &shift_uri, Value::Null).await;
+    let (publish_locked, _) =
+        send_json_as(&app, Some(&manager), "POST", &format!("{}/publish", rota_uri), Value::Null).await;
+    let (unlocked, _) = send_json_as(&app, Some(&admin), "POST", &format!("{}/unlock", rota_uri), Value::Null).await;
+    let (_, republished) =
+        send_json_as(&app, Some(&manager), "POST", &format!("{}/publish", rota_uri), Value::Null).await;
+    let (_, latest) = send_json_as(&app, Some(&staff), "GET", &format!("{}/published", rota_uri), Value::Null).await;
+    let (delete_published, _) = send_json_as(&app, Some(&manager), "DELETE", &rota_uri, Value::Null).await;
+
+    // Assert
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(rota["status"], "published");
+    assert_eq!(overlapping, StatusCode::CONFLICT);
+    assert_eq!(outside, StatusCode::BAD_REQUEST);
+    assert_eq!(draft_hidden, StatusCode::NOT_FOUND);
+    assert_eq!(shift_hidden, StatusCode::NOT_FOUND);
+    assert_eq!(staff_publish, StatusCode::FORBIDDEN);
+    assert_eq!(published, StatusCode::OK);
+    assert_eq!(seen["revision"], 1);
+    assert_eq!(seen["shifts"].as_array().unwrap().len(), 1);
+    assert_eq!(seen["shifts"][0]["id"], shift["id"]);
+    assert_eq!(listed["items"].as_array().unwrap().len(), 1);
+    assert_eq!(manager_lock, StatusCode::FORBIDDEN);
+    assert_eq!(locked, StatusCode::OK);
+    assert_eq!(rota_locked["status"], "locked");
+    assert_eq!(edit_locked, StatusCode::CONFLICT);
+    assert_eq!(publish_locked, StatusCode::CONFLICT);
+    assert_eq!(unlocked, StatusCode::OK);
+    assert_eq!(republished["revision"], 2);
+    assert_eq!(latest["shifts"].as_array().unwrap().len(), 2);
+    assert_eq!(delete_published, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_soft_deleted_rotas_and_shifts_can_be_restored() {
+    // Arrange: a draft week with two shifts, one of them deleted on its own
+    // before the rota went
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let body = json!({ "team_id": team["id"], "period_start": "2025-06-02", "length": "week" });
+    let (_, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body.clone()).await;
+    let rota_uri = format!("/api/rotas/{}", rota["id"]);
+    let mut shift_uris = Vec::new();
+    for day in ["2025-06-03", "2025-06-04"] {
+        let shift = json!({
+            "rota_id": rota["id"],
+            "location": "Ward 3",
+            "start": format!("{}T08:00:00", day),
+            "end": format!("{}T20:00:00", day)
+        });
+        let (_, shift) = send_json_as(&app, Some(&manager), "POST", "/api/shifts", shift).await;
+        shift_uris.push(format!("/api/shifts/{}", shift["id"]));
+    }
+    send_json_as(&app, Some(&manager), "DELETE", &shift_uris[0], Value::Null).await;
+    send_json_as(&app, Some(&manager), "DELETE", &rota_uri, Value::Null).await;
+
+    // Act
+    let restore = |uri: &str| format!("{}/restore", uri);
+    let (shift_first, _) = send_json_as(&app, Some(&manager), "POST", &restore(&shift_uris[1]), Value::Null).await;
+    let (_, replacement) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let (overlapping, _) = send_json_as(&app, Some(&manager), "POST", &restore(&rota_uri), Value::Null).await;
+    let replacement_uri = format!("/api/rotas/{}", replacement["id"]);
+    send_json_as(&app, Some(&manager), "DELETE", &replacement_uri, Value::Null).await;
+    let (staff, _) = send_json_as(&app, Some(&token(1, "user")), "POST", &restore(&rota_uri), Value::Null).await;
+    let (restored, body) = send_json_as(&app, Some(&manager), "POST", &restore(&rota_uri), Value::Null).await;
+    let (again, _) = send_json_as(&app, Some(&manager), "POST", &restore(&rota_uri), Value::Null).await;
+    let (taken_with_rota, _) = send_json_as(&app, Some(&manager), "GET", &shift_uris[1], Value::Null).await;
+    let (deleted_alone, _) = send_json_as(&app, Some(&manager), "GET", &shift_uris[0], Value::Null).await;
+    let (shift_restored, shift) =
+        send_json_as(&app, Some(&manager), "POST", &restore(&shift_uris[0]), Value::Null).await;
+    let (_, audit) = send_json_as(&app, Some(&admin), "GET", "/api/audit?action=restore", Value::Null).await;
+
+    // Assert
+    assert_eq!(shift_first, StatusCode::CONFLICT);
+    assert_eq!(overlapping, StatusCode::CONFLICT);
+    assert_eq!(staff, StatusCode::FORBIDDEN);
+    assert_eq!(restored, StatusCode::OK);
+    assert_eq!(body["deleted_at"], Value::Null);
+    assert_eq!(again, StatusCode::CONFLICT);
+    assert_eq!(taken_with_rota, StatusCode::OK);
+    // Deleted on its own, so it stays deleted until restored itself
+    assert_eq!(deleted_alone, StatusCode::NOT_FOUND);
+    assert_eq!(shift_restored, StatusCode::OK);
+    assert_eq!(shift["location"], "Ward 3");
+    assert_eq!(audit["items"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_restore_rota_if_match() {
+    // Arrange: a draft week deleted after the manager last read it
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let body = json!({ "team_id": team["id"], "period_start": "2025-06-02", "length": "week" });
+    let (_, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let uri = format!("/api/rotas/{}", rota["id"]);
+    send_json_as(&app, Some(&manager), "DELETE", &uri, Value::Null).await;
+    let restore = |if_match: String| {
+        Request::builder()
+            .uri(format!("{}/restore", uri))
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", manager))
+            .header("If-Match", if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let stale = app.clone().oneshot(restore(format!("\"{}\"", rota["version"]))).await.unwrap();
+    let etag = stale.headers()["etag"].to_str().unwrap().to_string();
+    let status = stale.status();
+    let body = hyper::body::to_bytes(stale.into_body()).await.unwrap();
+    let stored: Value = serde_json::from_slice(&body).unwrap();
+    let current = app.clone().oneshot(restore(etag.clone())).await.unwrap();
+
+    // Assert: 412 with the deleted rota as stored, then its own tag wins
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(etag, format!("\"{}\"", stored["version"]));
+    assert_ne!(stored["version"], rota["version"]);
+    assert!(stored["deleted_at"].is_string());
+    assert_eq!(current.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_restore_shift_if_match() {
+    // Arrange: a shift deleted after the manager last read it
+    let app = app();
+    let manager = token(98, "manager");
+    let shift = json!({ "location": "Ward 3", "start": "2025-06-03T08:00:00", "end": "2025-06-03T20:00:00" });
+    let (_, shift) = send_json_as(&app, Some(&manager), "POST", "/api/shifts", shift).await;
+    let uri = format!("/api/shifts/{}", shift["id"]);
+    send_json_as(&app, Some(&manager), "DELETE", &uri, Value::Null).await;
+    let restore = |if_match: String| {
+        Request::builder()
+            .uri(format!("{}/restore", uri))
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", manager))
+            .header("If-Match", if_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let stale = app.clone().oneshot(restore(format!("\"{}\"", shift["version"]))).await.unwrap();
+    let etag = stale.headers()["etag"].to_str().unwrap().to_string();
+    let status = stale.status();
+    let body = hyper::body::to_bytes(stale.into_body()).await.unwrap();
+    let stored: Value = serde_json::from_slice(&body).unwrap();
+    let current = app.clone().oneshot(restore(etag.clone())).await.unwrap();
+
+    // Assert: 412 with the shift as stored, then its own tag wins
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(etag, format!("\"{}\"", stored["version"]));
+    assert_ne!(stored["version"], shift["version"]);
+    assert_eq!(stored["location"], "Ward 3");
+    assert_eq!(current.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_recurring_templates_generate_into_a_rota_once() {
+    // Arrange: a week with the clocks going forward early on Sunday
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let body = json!({ "team_id": team["id"], "period_start": "2025-03-24", "length": "week" });
+    let (_, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let template = |name: &str, start: &str, end: &str| {
+        json!({
+            "team_id": team["id"],
+            "name": name,
+            "position": "Nurse",
+            "start_time": start,
+            "end_time": end,
+            "time_zone": "Europe/London",
+            "unpaid_break_minutes": 30,
+            "colour": "#3A7BD5"
+        })
+    };
+    let (created, early) =
+        send_json_as(&app, Some(&manager), "POST", "/api/shift-templates", template("Early", "07:00", "15:00")).await;
+    let (_, small_hours) =
+        send_json_as(&app, Some(&manager), "POST", "/api/shift-templates", template("Small hours", "01:30", "08:00"))
+            .await;
+    let recurrences = |id: &Value| format!("/api/shift-templates/{}/recurrences", id);
+    let (added, weekdays) = send_json_as(
+        &app,
+        Some(&manager),
+        "POST",
+        &recurrences(&early["id"]),
+        json!({ "rule": "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR", "starts_on": "2025-03-03", "exceptions": ["2025-03-26"] }),
+    )
+    .await;
+    send_json_as(
+        &app,
+        Some(&manager),
+        "POST",
+        &recurrences(&small_hours["id"]),
+        json!({ "rule": "FREQ=WEEKLY;BYDAY=SU", "starts_on": "2025-03-02" }),
+    )
+    .await;
+    let rota_uri = format!("/api/rotas/{}", rota["id"]);
+    let generate = |path: &str| {
+        let (app, manager, uri) = (app.clone(), manager.clone(), format!("{}/{}", rota_uri, path));
+        async move { send_json_as(&app, Some(&manager), "POST", &uri, Value::Null).await }
+    };
+    let shifts_uri = format!("/api/shifts?rota={}", rota["id"]);
+
+    // Act
+    let (staff_template, _) =
+        send_json_as(&app, Some(&token(1, "user")), "POST", "/api/shift-templates", template("Late", "14:00", "22:00"))
+            .await;
+    let (bad_colour, _) = send_json_as(
+        &app,
+        Some(&manager),
+        "POST",
+        "/api/shift-templates",
+        json!({ "name": "Late", "start_time": "14:00", "end_time": "22:00", "colour": "orange" }),
+    )
+    .await;
+    let (bad_rule, _) = send_json_as(
+        &app,
+        Some(&manager),
+        "POST",
+        &recurrences(&early["id"]),
+        json!({ "rule": "FREQ=MONTHLY;BYMONTHDAY=1", "starts_on": "2025-03-01" }),
+    )
+    .await;
+    let (_, preview) = generate("generate/preview").await;
+    let (_, before) = send_json_as(&app, Some(&manager), "GET", &shifts_uri, Value::Null).await;
+    let (generated, first) = generate("generate").await;
+    let (_, second) = generate("generate").await;
+    let removed = format!("/api/shifts/{}", first["new"][0]["shift_id"]);
+    send_json_as(&app, Some(&manager), "DELETE", &removed, Value::Null).await;
+    let (_, third) = generate("generate").await;
+    let (_, after) = send_json_as(&app, Some(&manager), "GET", &shifts_uri, Value::Null).await;
+    let (_, unknown) = generate("generate?recurrences=999").await;
+
+    // Assert
+    let dates = |list: &Value| {
+        list.as_array().unwrap().iter().map(|o| o["date"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(early["colour"], "#3a7bd5");
+    assert_eq!(added, StatusCode::CREATED);
+    assert_eq!(weekdays["rule"], "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR");
+    assert_eq!(staff_template, StatusCode::FORBIDDEN);
+    assert_eq!(bad_colour, StatusCode::BAD_REQUEST);
+    assert_eq!(bad_rule, StatusCode::BAD_REQUEST);
+    assert_eq!(preview["preview"], true);
+    assert_eq!(dates(&preview["new"]), vec!["2025-03-24", "2025-03-25", "2025-03-27", "2025-03-28"]);
+    assert_eq!(preview["new"][0]["starts_at"], "2025-03-24T07:00:00Z");
+    assert!(preview["new"][0].get("shift_id").is_none());
+    // 01:30 on the 30th is skipped by the clocks going forward
+    assert_eq!(dates(&preview["skipped"]), vec!["2025-03-30"]);
+    assert_eq!(before["items"].as_array().unwrap().len(), 0);
+    assert_eq!(generated, StatusCode::OK);
+    assert_eq!(first["preview"], false);
+    assert!(first["new"][3]["shift_id"].is_i64());
+    assert_eq!(second["new"].as_array().unwrap().len(), 0);
+    assert_eq!(dates(&second["existing"]), dates(&first["new"]));
+    // A generated shift that was deleted stays deleted
+    assert_eq!(third["new"].as_array().unwrap().len(), 0);
+    assert_eq!(after["items"].as_array().unwrap().len(), 3);
+    assert_eq!(unknown["code"], 400);
+}
+
+#[tokio::test]
+async fn test_patterns_project_offsets_keep_overrides_and_apply_once() {
+    // Arrange: Ann and Bo on a 4-on-4-off pattern, half a cycle apart; Bo
+    // has no employment record
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/users/{}/profile", ids[0]),
+        json!({ "contract_type": "full_time", "weekly_hours": 37.5, "start_date": "2025-01-06" }),
+    )
+    .await;
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Control room" })).await;
+    let body = json!({
+        "team_id": team["id"],
+        "name": "Day",
+        "start_time": "08:00",
+        "end_time": "16:00",
+        "unpaid_break_minutes": 30,
+        "colour": "#2e8b57"
+    });
+    let (_, day) = send_json_as(&app, Some(&manager), "POST", "/api/shift-templates", body).await;
+    let pattern = |anchor: &str| {
+        json!({
+            "team_id": team["id"],
+            "name": "Days 4-on-4-off",
+            "anchor_date": anchor,
+            "preset": "four_on_four_off",
+            "templates": [day["id"]]
+        })
+    };
+    let (created, four_on) = send_json_as(&app, Some(&manager), "POST", "/api/patterns", pattern("2025-06-02")).await;
+    let uri = format!("/api/patterns/{}", four_on["id"]);
+    let members = json!([{ "user_id": ids[0], "offset": 0 }, { "user_id": ids[1], "offset": 4 }]);
+    send_json_as(&app, Some(&manager), "PUT", &format!("{}/members", uri), members).await;
+    let projection = format!("{}/projection?from=2025-06-02&to=2025-06-08", uri);
+    let body = json!({ "team_id": team["id"], "period_start": "2025-06-02", "length": "week" });
+    let (_, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let apply = || {
+        let (app, manager, uri) = (app.clone(), manager.clone(), format!("{}/apply", uri));
+        let body = json!({ "rota_id": rota["id"] });
+        async move { send_json_as(&app, Some(&manager), "POST", &uri, body).await }
+    };
+
+    // Act
+    let (staff_create, _) =
+        send_json_as(&app, Some(&token(1, "user")), "POST", "/api/patterns", pattern("2025-06-02")).await;
+    let body = json!({ "team_id": team["id"], "name": "Panama", "anchor_date": "2025-06-02", "preset": "panama" });
+    let (no_templates, _) = send_json_as(&app, Some(&manager), "POST", "/api/patterns", body).await;
+    let (bad_offset, _) = send_json_as(
+        &app,
+        Some(&manager),
+        "PUT",
+        &format!("{}/members", uri),
+        json!([{ "user_id": ids[0], "offset": 8 }]),
+    )
+    .await;
+    let (_, first) = send_json_as(&app, Some(&token(1, "user")), "GET", &projection, Value::Null).await;
+    let body = json!({ "user_id": ids[0], "date": "2025-06-03", "template_id": null, "reason": "Training" });
+    let (overridden, _) = send_json_as(&app, Some(&manager), "PUT", &format!("{}/overrides", uri), body).await;
+    let (moved, _) = send_json_as(&app, Some(&manager), "PUT", &uri, pattern("2025-06-03")).await;
+    let (_, second) = send_json_as(&app, Some(&manager), "GET", &projection, Value::Null).await;
+    let (applied, report) = apply().await;
+    let (_, again) = apply().await;
+
+    // Assert
+    let worked = |days: &Value, user: i64| {
+        days.as_array()
+            .unwrap()
+            .iter()
+            .filter(|d| d["user_id"] == user)
+            .map(|d| d["date"].as_str().unwrap()[8..].to_string())
+            .collect::<Vec<_>>()
+    };
+    let count = |list: &Value| list.as_array().unwrap().len();
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(four_on["cycle"].as_array().unwrap().len(), 8);
+    assert_eq!(staff_create, StatusCode::FORBIDDEN);
+    assert_eq!(no_templates, StatusCode::BAD_REQUEST);
+    assert_eq!(bad_offset, StatusCode::BAD_REQUEST);
+    assert_eq!(worked(&first, ids[0]), vec!["02", "03", "04", "05"]);
+    assert_eq!(worked(&first, ids[1]), vec!["06", "07", "08"]);
+    assert_eq!(first[0]["starts_at"], "2025-06-02T08:00:00Z");
+    assert_eq!(overridden, StatusCode::OK);
+    assert_eq!(moved, StatusCode::OK);
+    // Moving the anchor shifts everyone a day, but the day off stays put
+    assert_eq!(worked(&second, ids[0]), vec!["04", "05", "06"]);
+    assert_eq!(worked(&second, ids[1]), vec!["02", "07", "08"]);
+    assert_eq!(applied, StatusCode::OK);
+    assert_eq!(report["shifts_created"], 6);
+    assert_eq!(worked(&report["assigned"], ids[0]), vec!["04", "05", "06"]);
+    assert_eq!(count(&report["conflicts"]), 3);
+    assert_eq!(report["conflicts"][0]["conflicts"][0]["code"], "no_profile");
+    assert_eq!(again["shifts_created"], 0);
+    assert_eq!(count(&again["already_assigned"]), 3);
+    assert_eq!(count(&again["assigned"]), 0);
+}
+
+#[tokio::test]
+async fn test_copying_a_rota_moves_shifts_and_skips_leavers() {
+    // Arrange: the week before the clocks go back, with Cy leaving on the
+    // Tuesday after and Di deleted since
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let mut ids = Vec::new();
+    for (name, end_date) in [("Ann", Value::Null), ("Cy", json!("2025-10-28")), ("Di", Value::Null)] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        let body = json!({
+            "contract_type": "full_time",
+            "weekly_hours": 37.5,
+            "start_date": "2025-01-06",
+            "end_date": end_date
+        });
+        send_json_as(&app, Some(&admin), "PUT", &format!("/api/users/{}/profile", user["id"]), body).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let body = json!({ "team_id": team["id"], "period_start": "2025-10-20", "length": "week" });
+    let (_, rota) = send_json_as(&app, Some(&manager), "POST", "/api/rotas", body).await;
+    let mut shifts = Vec::new();
+    for (start, end, people) in [
+        ("2025-10-23T09:00:00", "2025-10-23T17:00:00", vec![ids[0], ids[1], ids[2]]),
+        ("2025-10-25T20:00:00", "2025-10-26T08:00:00", vec![ids[0]]),
+    ] {
+        let body = json!({
+            "rota_id": rota["id"],
+            "location": "Ward 3",
+            "start": start,
+            "end": end,
+            "time_zone": "Europe/London",
+            "unpaid_break_minutes": 30
+        });
+        let (_, shift) = send_json_as(&app, Some(&manager), "POST", "/api/shifts", body).await;
+        for user_id in people {
+            let uri = format!("/api/shifts/{}/assignments", shift["id"]);
+            send_json_as(&app, Some(&manager), "POST", &uri, json!({ "user_id": user_id })).await;
+        }
+        shifts.push(shift);
+    }
+    send_json_as(&app, Some(&admin), "DELETE", &format!("/users/{}", ids[2]), Value::Null).await;
+    let copy = |body: Value, who: &String| {
+        let (app, who, uri) = (app.clone(), who.clone(), format!("/api/rotas/{}/copy", rota["id"]));
+        async move { send_json_as(&app, Some(&who), "POST", &uri, body).await }
+    };
+
+    // Act
+    let (staff, _) = copy(json!({ "period_start": "2025-10-27" }), &token(1, "user")).await;
+    let (copied, report) = copy(json!({ "period_start": "2025-10-27", "assignments": true }), &manager).await;
+    let (overlapping, _) = copy(json!({ "period_start": "2025-10-30" }), &manager).await;
+    let (_, bare) = copy(json!({ "period_start": "2025-11-03" }), &manager).await;
+
+    // Assert
+    assert_eq!(staff, StatusCode::FORBIDDEN);
+    assert_eq!(copied, StatusCode::CREATED);
+    assert_eq!(report["rota"]["period_start"], "2025-10-27");
+    assert_eq!(report["rota"]["status"], "draft");
+    assert_eq!(report["shifts"][0]["from_shift_id"], shifts[0]["id"]);
+    // Same local times, now in GMT: an hour later in UTC
+    assert_eq!(report["shifts"][0]["starts_at"], "2025-10-30T09:00:00Z");
+    // The night moves over the month end and is an hour shorter
+    assert_eq!(report["shifts"][1]["starts_at"], "2025-11-01T20:00:00Z");
+    assert_eq!(report["shifts"][1]["ends_at"], "2025-11-02T08:00:00Z");
+    assert_eq!(report["assigned"].as_array().unwrap().len(), 2);
+    assert_eq!(report["not_copied"].as_array().unwrap().len(), 2);
+    assert_eq!(report["not_copied"][0]["user_id"], ids[1]);
+    assert_eq!(report["not_copied"][0]["code"], "conflicts");
+    assert_eq!(report["not_copied"][0]["conflicts"][0]["code"], "left");
+    assert_eq!(report["not_copied"][1]["user_id"], ids[2]);
+    assert_eq!(report["not_copied"][1]["code"], "user_deleted");
+    assert_eq!(overlapping, StatusCode::CONFLICT);
+    assert_eq!(bare["shifts"].as_array().unwrap().len(), 2);
+    assert_eq!(bare["assigned"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_availability_weekly_exceptions_team_view_and_conflicts() {
+    // Arrange: Ann in the ward team can't work Mondays and prefers Tuesday
+    // days, except on Monday 9 June when she can cover the afternoon
+    let app = app();
+    let admin = token(99, "admin");
+    let manager = token(98, "manager");
+    let (_, team) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 3" })).await;
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/users/{}/profile", ids[0]),
+        json!({
+            "contract_type": "full_time",
+            "weekly_hours": 37.5,
+            "start_date": "2025-01-06",
+            "home_team_id": team["id"]
+        }),
+    )
+    .await;
+    let ann = token(ids[0], "user");
+    let uri = format!("/api/users/{}/availability", ids[0]);
+    let weekly = json!({ "weekly": [
+        { "weekday": "Tue", "start_time": "09:00:00", "end_time": "17:00:00", "kind": "preferred" },
+        { "weekday": "Mon", "start_time": "00:00:00", "end_time": "00:00:00", "kind": "unavailable" }
+    ] });
+    let overlapping = json!({ "weekly": [
+        { "weekday": "Sun", "start_time": "20:00:00", "end_time": "08:00:00", "kind": "unavailable" },
+        { "weekday": "Mon", "start_time": "07:00:00", "end_time": "12:00:00", "kind": "available" }
+    ] });
+    let exception = json!({
+        "date": "2025-06-09",
+        "start_time": "12:00:00",
+        "end_time": "20:00:00",
+        "kind": "available",
+        "note": "Swapped with Bo"
+    });
+    let mut shifts = Vec::new();
+    for (start, end) in [("2025-06-02T08:00:00", "2025-06-02T16:00:00"), ("2025-06-09T12:00:00", "2025-06-09T20:00:00")] {
+        let body = json!({ "location": "Ward 3", "start": start, "end": end });
+        let (_, shift) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+        shifts.push(format!("/api/shifts/{}/assignments", shift["id"]));
+    }
+
+    // Act
+    let (_, empty) = send_json_as(&app, Some(&ann), "GET", &uri, Value::Null).await;
+    let (bad_week, _) = send_json_as(&app, Some(&ann), "PUT", &uri, overlapping).await;
+    let (saved, week) = send_json_as(&app, Some(&ann), "PUT", &uri, weekly).await;
+    let (snooping, _) = send_json_as(&app, Some(&token(ids[1], "user")), "GET", &uri, Value::Null).await;
+    let exceptions = format!("{}/exceptions", uri);
+    let (added, added_body) = send_json_as(&app, Some(&ann), "POST", &exceptions, exception).await;
+    let body = json!({ "date": "2025-06-09", "kind": "unavailable" });
+    let (clashing, _) = send_json_as(&app, Some(&ann), "POST", &exceptions, body).await;
+    let body = json!({ "date": "2025-06-10", "kind": "unavailable" });
+    let (_, spare) = send_json_as(&app, Some(&manager), "POST", &exceptions, body).await;
+    let spare_uri = format!("{}/{}", exceptions, spare["id"]);
+    let (removed, _) = send_json_as(&app, Some(&ann), "DELETE", &spare_uri, Value::Null).await;
+    let (_, listed) = send_json_as(&app, Some(&ann), "GET", &exceptions, Value::Null).await;
+    let view = format!("/api/teams/{}/availability?from=2025-06-08&to=2025-06-10", team["id"]);
+    let (staff_view, _) = send_json_as(&app, Some(&ann), "GET", &view, Value::Null).await;
+    let (_, team_view) = send_json_as(&app, Some(&manager), "GET", &view, Value::Null).await;
+    let (refused, problems) = send_json_as(&app, Some(&admin), "POST", &shifts[0], json!({ "user_id": ids[0] })).await;
+    let (covered, _) = send_json_as(&app, Some(&admin), "POST", &shifts[1], json!({ "user_id": ids[0] })).await;
+
+    // Assert
+    assert_eq!(empty["version"], 0);
+    assert_eq!(empty["weekly"], json!([]));
+    assert_eq!(bad_week, StatusCode::BAD_REQUEST);
+    assert_eq!(saved, StatusCode::OK);
+    assert_eq!(week["version"], 1);
+    // Windows come back Monday first
+    assert_eq!(week["weekly"][0]["weekday"], "Mon");
+    assert_eq!(snooping, StatusCode::FORBIDDEN);
+    assert_eq!(added, StatusCode::CREATED);
+    assert_eq!(added_body["note"], "Swapped with Bo");
+    assert_eq!(clashing, StatusCode::BAD_REQUEST);
+    assert_eq!(removed, StatusCode::NO_CONTENT);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(staff_view, StatusCode::FORBIDDEN);
+    assert_eq!(team_view.as_array().unwrap().len(), 1);
+    let days = &team_view[0]["days"];
+    assert_eq!(days.as_array().unwrap().len(), 3);
+    assert_eq!(days[0]["windows"], json!([]));
+    assert_eq!(days[1]["windows"][0]["kind"], "available");
+    assert_eq!(days[1]["windows"][0]["exception"], true);
+    assert_eq!(days[2]["windows"][0]["kind"], "preferred");
+    assert_eq!(refused, StatusCode::CONFLICT);
+    assert_eq!(problems["conflicts"][0]["code"], "unavailable");
+    assert_eq!(covered, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_leave_requests_go_up_the_team_hierarchy_and_count_against_balances() {
+    // Arrange: Ann works half time on a ward with no manager of its own, so
+    // her leave goes to Mo, who manages the directorate the ward sits under
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, trust) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Surgery" })).await;
+    let (_, ward) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 4" })).await;
+    let mut ids = Vec::new();
+    for name in ["Ann", "Mo", "Bo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    let trust_uri = format!("/api/teams/{}", trust["id"]);
+    let ward_uri = format!("/api/teams/{}", ward["id"]);
+    send_json_as(&app, Some(&admin), "PUT", &trust_uri, json!({ "name": "Surgery", "manager_id": ids[1] })).await;
+    let body = json!({ "name": "Ward 4", "parent_id": trust["id"] });
+    let (_, nested) = send_json_as(&app, Some(&admin), "PUT", &ward_uri, body).await;
+    let body = json!({ "name": "Surgery", "parent_id": ward["id"], "manager_id": ids[1] });
+    let (looped, _) = send_json_as(&app, Some(&admin), "PUT", &trust_uri, body).await;
+    send_json_as(
+        &app,
+        Some(&admin),
+        "PUT",
+        &format!("/api/users/{}/profile", ids[0]),
+        json!({
+            "contract_type": "part_time",
+            "weekly_hours": 18.75,
+            "start_date": "2024-01-01",
+            "home_team_id": ward["id"]
+        }),
+    )
+    .await;
+    let entitlements = format!("/api/users/{}/leave/entitlements", ids[0]);
+    let body = json!({ "days": 10, "carried_over": 0 });
+    send_json_as(&app, Some(&admin), "PUT", &format!("{}/2024/annual", entitlements), body).await;
+    let (_, this_year) =
+        send_json_as(&app, Some(&admin), "PUT", &format!("{}/2025/annual", entitlements), json!({ "days": 28 })).await;
+    let ann = token(ids[0], "user");
+    let mo = token(ids[1], "user");
+    let (_, shift) = send_json_as(
+        &app,
+        Some(&admin),
+        "POST",
+        "/api/shifts",
+        json!({ "location": "Ward 4", "start": "2025-06-03T08:00:00", "end": "2025-06-03T16:00:00" }),
+    )
+    .await;
+
+    // Act
+    let body = json!({
+        "leave_type": "annual",
+        "start_date": "2025-06-02",
+        "end_date": "2025-06-06",
+        "half_day_end": true,
+        "reason": "Family visit"
+    });
+    let (submitted, holiday) = send_json_as(&app, Some(&ann), "POST", "/api/leave", body).await;
+    let body = json!({ "leave_type": "annual", "start_date": "2025-06-06", "end_date": "2025-06-09" });
+    let (overlapping, _) = send_json_as(&app, Some(&ann), "POST", "/api/leave", body).await;
+    let body = json!({ "leave_type": "annual", "start_date": "2025-07-01", "end_date": "2025-07-31" });
+    let (too_long, _) = send_json_as(&app, Some(&ann), "POST", "/api/leave", body).await;
+    let body = json!({ "leave_type": "study", "start_date": "2025-08-04", "end_date": "2025-08-04" });
+    let (_, exam) = send_json_as(&app, Some(&ann), "POST", "/api/leave", body).await;
+    let holiday_uri = format!("/api/leave/{}", holiday["id"]);
+    let exam_uri = format!("/api/leave/{}", exam["id"]);
+    let (_, waiting) = send_json_as(&app, Some(&mo), "GET", &format!("/api/leave?approver={}", ids[1]), Value::Null).await;
+    let (_, own) = send_json_as(&app, Some(&ann), "GET", "/api/leave", Value::Null).await;
+    let bo = token(ids[2], "user");
+    let (outsider, _) = send_json_as(&app, Some(&bo), "POST", &format!("{}/approve", holiday_uri), Value::Null).await;
+    let (self_approved, _) =
+        send_json_as(&app, Some(&ann), "POST", &format!("{}/approve", holiday_uri), Value::Null).await;
+    let (approved, decided) =
+        send_json_as(&app, Some(&mo), "POST", &format!("{}/approve", holiday_uri), json!({ "note": "Enjoy" })).await;
+    let body = json!({ "note": "Course is full" });
+    let (_, rejected) = send_json_as(&app, Some(&mo), "POST", &format!("{}/reject", exam_uri), body).await;
+    let (rejected_again, _) = send_json_as(&app, Some(&mo), "POST", &format!("{}/reject", exam_uri), Value::Null).await;
+    let (late_cancel, _) = send_json_as(&app, Some(&ann), "POST", &format!("{}/cancel", holiday_uri), Value::Null).await;
+    let balance_uri = format!("/api/users/{}/leave/balance?year=2025", ids[0]);
+    let (_, balance) = send_json_as(&app, Some(&ann), "GET", &balance_uri, Value::Null).await;
+    let (prying, _) = send_json_as(&app, Some(&bo), "GET", &balance_uri, Value::Null).await;
+    let assignments = format!("/api/shifts/{}/assignments", shift["id"]);
+    let (blocked, problems) = send_json_as(&app, Some(&admin), "POST", &assignments, json!({ "user_id": ids[0] })).await;
+
+    // Assert
+    assert_eq!(nested["parent_id"], trust["id"]);
+    assert_eq!(looped, StatusCode::BAD_REQUEST);
+    // None of last year's five pro-rated days were taken, and five is the most that carries over
+    assert_eq!(this_year["carried_over"], 5.0);
+    assert_eq!(submitted, StatusCode::CREATED);
+    assert_eq!(holiday["days"], 4.5);
+    assert_eq!(holiday["status"], "pending");
+    assert_eq!(holiday["approver_id"], ids[1]);
+    assert_eq!(overlapping, StatusCode::CONFLICT);
+    assert_eq!(too_long, StatusCode::BAD_REQUEST);
+    assert_eq!(waiting["items"].as_array().unwrap().len(), 2);
+    assert_eq!(own["items"].as_array().unwrap().len(), 2);
+    assert_eq!(outsider, StatusCode::FORBIDDEN);
+    assert_eq!(self_approved, StatusCode::FORBIDDEN);
+    assert_eq!(approved, StatusCode::OK);
+    assert_eq!(decided["status"], "approved");
+    assert_eq!(decided["decision_note"], "Enjoy");
+    assert_eq!(rejected["status"], "rejected");
+    assert_eq!(rejected_again, StatusCode::CONFLICT);
+    assert_eq!(late_cancel, StatusCode::FORBIDDEN);
+    // Half of 28 days, plus the five carried over, less the approved 4.5
+    assert_eq!(balance[0]["pro_rated"], 14.0);
+    assert_eq!(balance[0]["booked"], 4.5);
+    assert_eq!(balance[0]["remaining"], 14.5);
+    assert_eq!(prying, StatusCode::FORBIDDEN);
+    assert_eq!(blocked, StatusCode::CONFLICT);
+    assert_eq!(problems["conflicts"][0]["code"], "on_leave");
+}
+
+#[tokio::test]
+async fn test_sickness_notes_are_kept_out_of_the_audit_log() {
+    // Arrange
+    let app = app();
+    let (_, created) = send_json(&app, "POST", "/users", json!({ "name": "Sam", "email": "sam@example.com" })).await;
+    let sam = token(created["id"].as_i64().unwrap(), "user");
+    let body = json!({ "leave_type": "sickness", "start_date": "2025-06-02", "end_date": "2025-06-02", "reason": "Migraine" });
+
+    // Act
+    let (status, leave) = send_json_as(&app, Some(&sam), "POST", "/api/leave", body).await;
+    let cancel = format!("/api/leave/{}/cancel", leave["id"]);
+    send_json_as(&app, Some(&sam), "POST", &cancel, Value::Null).await;
+    let (_, entries) =
+        send_json_as(&app, Some(&token(99, "admin")), "GET", "/api/audit?entity=leave_request", Value::Null).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(leave["reason"], "Migraine");
+    assert_eq!(entries["items"].as_array().unwrap().len(), 2);
+    assert!(!entries.to_string().contains("Migraine"));
+}
+
+#[tokio::test]
+async fn test_blackouts_and_limits_hold_back_leave_unless_overridden() {
+    // Arrange: Ann, Bo and Cy work on a ward under Surgery, which is blacked
+    // out over Christmas; the ward lets one person off at a time, and Ward 9
+    // is being audited in the first week of November
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, surgery) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Surgery" })).await;
+    let (_, ward) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 5" })).await;
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bo", "Cy", "Mo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    let body = json!({ "name": "Ward 5", "parent_id": surgery["id"], "manager_id": ids[3] });
+    send_json_as(&app, Some(&admin), "PUT", &format!("/api/teams/{}", ward["id"]), body).await;
+    for id in &ids[..3] {
+        let body = json!({
+            "contract_type": "full_time",
+            "weekly_hours": 37.5,
+            "start_date": "2025-01-06",
+            "home_team_id": ward["id"]
+        });
+        send_json_as(&app, Some(&admin), "PUT", &format!("/api/users/{}/profile", id), body).await;
+    }
+    let [ann, bo, cy, mo] = [0, 1, 2, 3].map(|i| token(ids[i], "user"));
+    let manager = token(98, "manager");
+    let christmas = json!({
+        "team_id": surgery["id"],
+        "start_date": "2025-12-22",
+        "end_date": "2025-12-26",
+        "reason": "Christmas cover"
+    });
+    let (staff_blackout, _) = send_json_as(&app, Some(&ann), "POST", "/api/leave/blackouts", christmas.clone()).await;
+    let (created, _) = send_json_as(&app, Some(&manager), "POST", "/api/leave/blackouts", christmas).await;
+    let body = json!({ "location": "Ward 9", "start_date": "2025-11-03", "end_date": "2025-11-07", "reason": "Audit" });
+    send_json_as(&app, Some(&manager), "POST", "/api/leave/blackouts", body).await;
+    let limits = format!("/api/teams/{}/leave-limits", ward["id"]);
+    let (limited, _) = send_json_as(&app, Some(&manager), "POST", &limits, json!({ "max_off": 1 })).await;
+    let (duplicate_limit, _) = send_json_as(&app, Some(&manager), "POST", &limits, json!({ "max_percent": 50 })).await;
+    let body = json!({ "location": "Ward 9", "start": "2025-11-04T08:00:00", "end": "2025-11-04T16:00:00" });
+    let (_, shift) = send_json_as(&app, Some(&admin), "POST", "/api/shifts", body).await;
+    let assignments = format!("/api/shifts/{}/assignments", shift["id"]);
+    send_json_as(&app, Some(&admin), "POST", &assignments, json!({ "user_id": ids[2] })).await;
+    let leave = |leave_type: &str, start: &str, end: &str| {
+        json!({ "leave_type": leave_type, "start_date": start, "end_date": end })
+    };
+
+    // Act
+    let (at_christmas, christmas_problems) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", leave("annual", "2025-12-24", "2025-12-24")).await;
+    let (off_sick, _) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", leave("sickness", "2025-12-24", "2025-12-24")).await;
+    let (during_audit, audit_problems) =
+        send_json_as(&app, Some(&cy), "POST", "/api/leave", leave("annual", "2025-11-03", "2025-11-04")).await;
+    let (not_rostered, _) =
+        send_json_as(&app, Some(&bo), "POST", "/api/leave", leave("annual", "2025-11-03", "2025-11-04")).await;
+    let (_, bo_week) =
+        send_json_as(&app, Some(&bo), "POST", "/api/leave", leave("annual", "2025-10-06", "2025-10-10")).await;
+    send_json_as(&app, Some(&mo), "POST", &format!("/api/leave/{}/approve", bo_week["id"]), Value::Null).await;
+    let (too_many, limit_problems) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", leave("annual", "2025-10-08", "2025-10-08")).await;
+    let mut forced = leave("annual", "2025-10-08", "2025-10-08");
+    forced["force"] = json!(true);
+    forced["override_reason"] = json!("Agency cover booked");
+    let (staff_forced, _) = send_json_as(&app, Some(&ann), "POST", "/api/leave", forced).await;
+    let (_, cy_day) =
+        send_json_as(&app, Some(&cy), "POST", "/api/leave", leave("study", "2025-10-20", "2025-10-20")).await;
+    let (_, ann_day) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", leave("annual", "2025-10-20", "2025-10-20")).await;
+    send_json_as(&app, Some(&mo), "POST", &format!("/api/leave/{}/approve", cy_day["id"]), Value::Null).await;
+    let approve_ann = format!("/api/leave/{}/approve", ann_day["id"]);
+    let (refused, _) = send_json_as(&app, Some(&mo), "POST", &approve_ann, Value::Null).await;
+    let (no_reason, _) = send_json_as(&app, Some(&mo), "POST", &approve_ann, json!({ "force": true })).await;
+    let body = json!({ "force": true, "override_reason": "Agency cover booked" });
+    let (overridden, approved) = send_json_as(&app, Some(&mo), "POST", &approve_ann, body).await;
+    let (_, listed) = send_json_as(&app, Some(&ann), "GET", "/api/leave/blackouts?from=2025-12-01", Value::Null).await;
+
+    // Assert
+    assert_eq!(staff_blackout, StatusCode::FORBIDDEN);
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(limited, StatusCode::CREATED);
+    assert_eq!(duplicate_limit, StatusCode::CONFLICT);
+    assert_eq!(at_christmas, StatusCode::CONFLICT);
+    assert_eq!(christmas_problems["conflicts"][0]["code"], "blackout");
+    assert_eq!(off_sick, StatusCode::CREATED);
+    assert_eq!(during_audit, StatusCode::CONFLICT);
+    assert_eq!(audit_problems["conflicts"][0]["message"], "2025-11-03 to 2025-11-04 is blacked out: Audit");
+    assert_eq!(not_rostered, StatusCode::CREATED);
+    assert_eq!(too_many, StatusCode::CONFLICT);
+    assert_eq!(limit_problems["conflicts"][0]["code"], "too_many_off");
+    assert_eq!(staff_forced, StatusCode::FORBIDDEN);
+    // Pending requests don't count until approved
+    assert_eq!(ann_day["status"], "pending");
+    assert_eq!(refused, StatusCode::CONFLICT);
+    assert_eq!(no_reason, StatusCode::BAD_REQUEST);
+    assert_eq!(overridden, StatusCode::OK);
+    assert_eq!(approved["status"], "approved");
+    assert_eq!(approved["override_reason"], "Agency cover booked");
+    assert_eq!(approved["overridden_by"], ids[3].to_string());
+    assert_eq!(approved["overridden"][0]["code"], "too_many_off");
+    assert_eq!(listed["items"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_overtime_earns_toil_that_is_spent_through_leave() {
+    // Arrange: Ann works 30 hours a week on a ward Mo manages, so a day of
+    // TOIL costs her six hours. She worked late last Friday and Saturday.
+    let app = app();
+    let admin = token(99, "admin");
+    let (_, ward) = send_json_as(&app, Some(&admin), "POST", "/api/teams", json!({ "name": "Ward 6" })).await;
+    let mut ids = Vec::new();
+    for name in ["Ann", "Mo"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let (_, user) = send_json(&app, "POST", "/users", json!({ "name": name, "email": email })).await;
+        ids.push(user["id"].as_i64().unwrap());
+    }
+    let body = json!({ "name": "Ward 6", "manager_id": ids[1] });
+    send_json_as(&app, Some(&admin), "PUT", &format!("/api/teams/{}", ward["id"]), body).await;
+    let body = json!({
+        "contract_type": "part_time",
+        "weekly_hours": 30,
+        "start_date": "2024-01-01",
+        "home_team_id": ward["id"]
+    });
+    send_json_as(&app, Some(&admin), "PUT", &format!("/api/users/{}/profile", ids[0]), body).await;
+    let [ann, mo] = [0, 1].map(|i| token(ids[i], "user"));
+    let today = Utc::now().date_naive();
+    let monday = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+    let (friday, saturday) = (monday - Duration::days(3), monday - Duration::days(2));
+    let (next_week, week_after) = (monday + Duration::days(7), monday + Duration::days(14));
+    let claim = |date: NaiveDate, hours: f64| json!({ "date": date, "hours": hours, "reason": "Late handover" });
+    let toil_leave = |date: NaiveDate, end: NaiveDate| {
+        json!({ "leave_type": "toil", "start_date": date, "end_date": end })
+    };
+
+    // Act
+    let (claimed, weekend) = send_json_as(&app, Some(&ann), "POST", "/api/overtime", claim(saturday, 4.0)).await;
+    let (_, weekday) = send_json_as(&app, Some(&ann), "POST", "/api/overtime", claim(friday, 2.0)).await;
+    let (in_future, _) = send_json_as(&app, Some(&ann), "POST", "/api/overtime", claim(next_week, 2.0)).await;
+    let mut unknown_shift = claim(friday, 1.0);
+    unknown_shift["shift_id"] = json!(999);
+    let (not_on_shift, _) = send_json_as(&app, Some(&ann), "POST", "/api/overtime", unknown_shift).await;
+    let approve_weekend = format!("/api/overtime/{}/approve", weekend["id"]);
+    let (self_approved, _) = send_json_as(&app, Some(&ann), "POST", &approve_weekend, Value::Null).await;
+    let (approved, decided) = send_json_as(&app, Some(&mo), "POST", &approve_weekend, Value::Null).await;
+    let (approved_again, _) = send_json_as(&app, Some(&mo), "POST", &approve_weekend, Value::Null).await;
+    let reject_weekday = format!("/api/overtime/{}/reject", weekday["id"]);
+    let (_, rejected) = send_json_as(&app, Some(&mo), "POST", &reject_weekday, json!({ "note": "Not agreed" })).await;
+    let (_, earned) = send_json_as(&app, Some(&ann), "GET", "/api/me", Value::Null).await;
+    let two_days = toil_leave(next_week, next_week + Duration::days(1));
+    let (too_much, _) = send_json_as(&app, Some(&ann), "POST", "/api/leave", two_days).await;
+    let (requested, day_off) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", toil_leave(next_week, next_week)).await;
+    let (promised_twice, _) =
+        send_json_as(&app, Some(&ann), "POST", "/api/leave", toil_leave(week_after, week_after)).await;
+    let day_off_uri = format!("/api/leave/{}", day_off["id"]);
+    send_json_as(&app, Some(&mo), "POST", &format!("{}/approve", day_off_uri), Value::Null).await;
+    let ledger_uri = format!("/api/users/{}/toil", ids[0]);
+    let (_, spent) = send_json_as(&app, Some(&ann), "GET", &ledger_uri, Value::Null).await;
+    send_json_as(&app, Some(&ann), "POST", &format!("{}/cancel", day_off_uri), Value::Null).await;
+    let adjustments = format!("{}/adjustments", ledger_uri);
+    let body = json!({ "hours": 1.5, "note": "Carried over from paper records" });
+    let (staff_adjusted, _) = send_json_as(&app, Some(&ann), "POST", &adjustments, body.clone()).await;
+    let (adjusted, _) = send_json_as(&app, Some(&admin), "POST", &adjustments, body).await;
+    let (_, refunded) = send_json_as(&app, Some(&ann), "GET", &ledger_uri, Value::Null).await;
+    let (_, me) = send_json_as(&app, Some(&ann), "GET", "/api/me", Value::Null).await;
+    let entitlement = format!("/api/users/{}/leave/entitlements/{}/toil", ids[0], today.year());
+    let (toil_entitlement, _) = send_json_as(&app, Some(&admin), "PUT", &entitlement, json!({ "days": 5 })).await;
+
+    // Assert
+    assert_eq!(claimed, StatusCode::CREATED);
+    assert_eq!(weekend["status"], "pending");
+    assert_eq!(weekend["approver_id"], ids[1]);
+    assert_eq!(in_future, StatusCode::BAD_REQUEST);
+    assert_eq!(not_on_shift, StatusCode::BAD_REQUEST);
+    assert_eq!(self_approved, StatusCode::FORBIDDEN);
+    assert_eq!(approved, StatusCode::OK);
+    // Four weekend hours at time and a half
+    assert_eq!(decided["toil_hours"], 6.0);
+    assert_eq!(approved_again, StatusCode::CONFLICT);
+    assert_eq!(rejected["status"], "rejected");
+    assert_eq!(rejected["toil_hours"], Value::Null);
+    assert_eq!(earned["name"], "Ann");
+    assert_eq!(earned["toil"]["available"], 6.0);
+    assert_eq!(earned["toil"]["next_expiry"]["date"], json!(saturday + Duration::days(90)));
+    assert_eq!(too_much, StatusCode::BAD_REQUEST);
+    assert_eq!(requested, StatusCode::CREATED);
+    // The pending day already has all six hours spoken for
+    assert_eq!(promised_twice, StatusCode::BAD_REQUEST);
+    let kinds: Vec<&Value> = spent["entries"].as_array().unwrap().iter().map(|e| &e["kind"]).collect();
+    assert_eq!(kinds, ["accrual", "redemption"]);
+    assert_eq!(spent["entries"][1]["hours"], -6.0);
+    assert_eq!(spent["entries"][1]["leave_request_id"], day_off["id"]);
+    assert_eq!(spent["balance"]["available"], 0.0);
+    assert_eq!(staff_adjusted, StatusCode::FORBIDDEN);
+    assert_eq!(adjusted, StatusCode::CREATED);
+    assert_eq!(refunded["entries"][2]["kind"], "refund");
+    assert_eq!(refunded["balance"]["earned"], 13.5);
+    assert_eq!(me["toil"]["available"], 7.5);
+    assert_eq!(toil_entitlement, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,69 @@
+use serde_json::json;
+
+use crate::audit::{diff, seal, verify_chain};
+use crate::models::audit::{AuditEntry, NewAuditEntry};
+
+fn change(entity_id: &str, after: serde_json::Value) -> NewAuditEntry {
+    NewAuditEntry {
+        actor: Some("1".to_string()),
+        request_id: None,
+        entity: "user".to_string(),
+        entity_id: entity_id.to_string(),
+        action: "update".to_string(),
+        before: None,
+        after: Some(after),
+    }
+}
+
+// Three chained entries, as a repository would store them
+fn chain() -> Vec<AuditEntry> {
+    let mut entries: Vec<AuditEntry> = Vec::new();
+    for (i, name) in ["Ann", "Bob", "Cat"].iter().enumerate() {
+        let prev_hash = entries.last().map(|e| e.hash.clone()).unwrap_or_default();
+        entries.push(seal(i as i64 + 1, prev_hash, change("7", json!({ "name": name }))));
+    }
+    entries
+}
+
+#[test]
+fn test_intact_chain_verifies() {
+    let report = verify_chain(&chain());
+
+    assert!(report.valid);
+    assert_eq!(report.entries, 3);
+    assert_eq!(report.broken_at, None);
+}
+
+#[test]
+fn test_edited_entry_breaks_chain() {
+    let mut entries = chain();
+    entries[1].after = Some(json!({ "name": "Mallory" }));
+
+    assert_eq!(verify_chain(&entries).broken_at, Some(2));
+}
+
+#[test]
+fn test_removed_entry_breaks_chain() {
+    let mut entries = chain();
+    entries.remove(1);
+
+    assert_eq!(verify_chain(&entries).broken_at, Some(3));
+}
+
+#[test]
+fn test_diff_lists_changed_fields_only() {
+    let before = json!({ "name": "Ann", "email": "a@example.com", "version": 1 });
+    let after = json!({ "name": "Ann", "email": "ann@example.com", "version": 2, "team": 3 });
+
+    let changes = diff(Some(&before), Some(&after));
+
+    assert_eq!(
+        changes,
+        json!({
+            "email": { "before": "a@example.com", "after": "ann@example.com" },
+            "version": { "before": 1, "after": 2 },
+            "team": { "before": null, "after": 3 },
+        })
+    );
+    assert_eq!(diff(None, Some(&json!({ "a": 1 })))["a"]["after"], 1);
+}
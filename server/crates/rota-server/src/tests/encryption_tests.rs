@@ -0,0 +1,138 @@
+use rota_core::user::{EmergencyContact, PersonalDetails};
+
+use crate::encryption::{generate_key, open_personal, seal_personal, CryptoError, Field, Keyring};
+
+// Key file text with the given KEK versions and a shared index key
+fn key_file(versions: &[(u32, &str)], index: &str) -> String {
+    let mut text = String::from("# test keys\n");
+    for (version, key) in versions {
+        text.push_str(&format!("kek.{} = {}\n", version, key));
+    }
+    text.push_str(&format!("index = {}\n", index));
+    text
+}
+
+// The phone number column of user 1
+fn phone() -> Field {
+    Field::new("users", "phone", 1)
+}
+
+#[test]
+fn test_encrypt_round_trip() {
+    let keyring = Keyring::generate();
+    let stored = keyring.encrypt("07700 900123", &phone());
+
+    assert!(stored.starts_with("v1.1."));
+    assert!(!stored.contains("07700"));
+    assert_eq!(keyring.decrypt(&stored, &phone()).unwrap(), "07700 900123");
+
+    // A fresh data key every time, so equal values don't look equal
+    assert_ne!(keyring.encrypt("07700 900123", &phone()), stored);
+}
+
+#[test]
+fn test_values_only_decrypt_where_they_were_written() {
+    let keyring = Keyring::generate();
+    let stored = keyring.encrypt("07700 900123", &phone());
+
+    // Copied onto another user, or into another column of the same user
+    assert_eq!(
+        keyring.decrypt(&stored, &Field::new("users", "phone", 2)),
+        Err(CryptoError::Decrypt)
+    );
+    assert_eq!(
+        keyring.decrypt(&stored, &Field::new("users", "emergency_contact", 1)),
+        Err(CryptoError::Decrypt)
+    );
+}
+
+#[test]
+fn test_rotation_rewraps_under_newest_key() {
+    let (kek1, kek2, index) = (generate_key(), generate_key(), generate_key());
+    let old = Keyring::parse(&key_file(&[(1, &kek1)], &index)).unwrap();
+    let new = Keyring::parse(&key_file(&[(1, &kek1), (2, &kek2)], &index)).unwrap();
+    assert_eq!(new.active_version(), 2);
+
+    let stored = old.encrypt("1990-04-01", &phone());
+    let rewrapped = new.rewrap(&stored).unwrap().unwrap();
+    assert!(rewrapped.starts_with("v1.2."));
+    assert_eq!(new.rewrap(&rewrapped).unwrap(), None);
+
+    // Once re-wrapped, the old KEK can be dropped from the file
+    let only_new = Keyring::parse(&key_file(&[(2, &kek2)], &index)).unwrap();
+    assert_eq!(only_new.decrypt(&rewrapped, &phone()).unwrap(), "1990-04-01");
+    assert_eq!(only_new.decrypt(&stored, &phone()), Err(CryptoError::UnknownKey(1)));
+}
+
+#[test]
+fn test_tampered_ciphertext_is_rejected() {
+    let keyring = Keyring::generate();
+    let stored = keyring.encrypt("secret", &phone());
+    // Flip the first character of the ciphertext; only the final character of
+    // unpadded base64 can make the encoding itself invalid
+    let at = stored.rfind('.').unwrap() + 1;
+    let flipped = if stored[at..].starts_with('A') { "B" } else { "A" };
+    let stored = format!("{}{}{}", &stored[..at], flipped, &stored[at + 1..]);
+
+    assert_eq!(keyring.decrypt(&stored, &phone()), Err(CryptoError::Decrypt));
+    assert_eq!(keyring.decrypt("not encrypted", &phone()), Err(CryptoError::Malformed));
+}
+
+#[test]
+fn test_blind_index_is_deterministic_per_key() {
+    let index = generate_key();
+    let a = Keyring::parse(&key_file(&[(1, &generate_key())], &index)).unwrap();
+    let b = Keyring::parse(&key_file(&[(1, &generate_key())], &index)).unwrap();
+
+    assert_eq!(a.blind_index("+447700900123"), b.blind_index("+447700900123"));
+    assert_ne!(a.blind_index("+447700900123"), a.blind_index("+447700900124"));
+    assert_ne!(a.blind_index("x"), Keyring::generate().blind_index("x"));
+}
+
+#[test]
+fn test_key_file_errors() {
+    let key = generate_key();
+
+    assert!(Keyring::parse("").is_err());
+    assert!(Keyring::parse(&format!("kek.1 = {}\n", key)).is_err());
+    assert!(Keyring::parse(&format!("index = {}\n", key)).is_err());
+    assert!(Keyring::parse(&format!("kek.1 = short\nindex = {}\n", key)).is_err());
+    assert!(Keyring::parse(&format!("kek.one = {}\nindex = {}\n", key, key)).is_err());
+}
+
+#[test]
+fn test_personal_details_seal_and_open() {
+    let keyring = Keyring::generate();
+    let personal = PersonalDetails {
+        phone: Some("+44 7700 900123".to_string()),
+        emergency_contact: Some(EmergencyContact {
+            name: "Sam".to_string(),
+            phone: "07700 900456".to_string(),
+            relationship: "Partner".to_string(),
+        }),
+        date_of_birth: "1990-04-01".parse().ok(),
+    };
+
+    let sealed = seal_personal(Some(&keyring), 7, &personal).unwrap();
+    assert_eq!(
+        sealed.phone_bidx.as_deref(),
+        Some(keyring.blind_index("+447700900123").as_str())
+    );
+
+    let opened = open_personal(
+        Some(&keyring),
+        7,
+        sealed.phone,
+        sealed.emergency_contact,
+        sealed.date_of_birth,
+    )
+    .unwrap();
+    assert_eq!(opened, personal);
+
+    // Empty details need no keys; anything else does
+    assert!(seal_personal(None, 7, &PersonalDetails::default()).is_ok());
+    assert_eq!(
+        seal_personal(None, 7, &personal).unwrap_err(),
+        CryptoError::NotConfigured
+    );
+}
@@ -0,0 +1,50 @@
+use sqlx::migrate::Migrator;
+use std::collections::HashSet;
+
+use crate::migrate::{check_compatible, latest_version, SchemaError, PG_MIGRATOR, SQLITE_MIGRATOR};
+
+fn versions(migrator: &Migrator, down: bool) -> HashSet<i64> {
+    migrator
+        .iter()
+        .filter(|m| m.migration_type.is_down_migration() == down)
+        .map(|m| m.version)
+        .collect()
+}
+
+#[test]
+fn test_every_migration_is_reversible() {
+    for migrator in [&PG_MIGRATOR, &SQLITE_MIGRATOR] {
+        let ups = versions(migrator, false);
+        assert!(!ups.is_empty());
+        assert_eq!(ups, versions(migrator, true));
+    }
+}
+
+#[test]
+fn test_engines_share_migration_versions() {
+    assert_eq!(versions(&PG_MIGRATOR, false), versions(&SQLITE_MIGRATOR, false));
+}
+
+#[test]
+fn test_latest_version_matches_newest_migration() {
+    let newest = PG_MIGRATOR.iter().map(|m| m.version).max().unwrap();
+    assert_eq!(latest_version(&PG_MIGRATOR), newest);
+}
+
+#[test]
+fn test_schema_compatibility_check() {
+    let latest = latest_version(&PG_MIGRATOR);
+
+    // Fresh and up-to-date databases are fine
+    assert!(check_compatible(&PG_MIGRATOR, &[]).is_ok());
+    assert!(check_compatible(&PG_MIGRATOR, &[latest]).is_ok());
+
+    // A schema migrated by a newer release is refused
+    match check_compatible(&PG_MIGRATOR, &[latest, latest + 1]) {
+        Err(SchemaError::NewerThanBinary { applied, supported }) => {
+            assert_eq!(applied, latest + 1);
+            assert_eq!(supported, latest);
+        }
+        other => panic!("expected NewerThanBinary, got {:?}", other),
+    }
+}
@@ -0,0 +1,5 @@
+mod api_tests;
+mod audit_tests;
+mod encryption_tests;
+mod migration_tests;
+mod sqlite_tests;
@@ -0,0 +1,2087 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use crate::app::{build_app, AppState};
+use crate::audit::{verify_chain, ChainReport};
+use crate::auth::jwt::create_tokens;
+use crate::config::{Config, DatabaseConfig, EncryptionConfig, RetentionConfig};
+use crate::database::{self, connect, Database};
+use crate::migrate;
+use crate::models::erasure::{ErasureSort, ErasureStatus, NewErasureRequest};
+use crate::models::profile::{ContractType, Employment, Qualification};
+use crate::models::assignment::{Conflict, ConflictCode, NewAssignment};
+use crate::models::availability::{AvailabilityKind, NewException, WeeklyAvailability, WeeklyWindow};
+use crate::models::leave::{LeaveAction, LeaveEntitlement, LeaveFilter, LeaveRequest, LeaveSort, LeaveStatus, LeaveType, NewLeaveRequest};
+use crate::models::leave_policy::{BlackoutFilter, BlackoutSort, NewBlackout, NewLeaveLimit};
+use crate::models::team::{Team, TeamSort};
+use crate::models::toil::{NewOvertime, NewToilEntry, OvertimeFilter, OvertimeSort, OvertimeStatus, ToilEntryKind};
+use crate::models::rota::{NewRota, Rota, RotaAction, RotaFilter, RotaLength, RotaSort, RotaStatus};
+use crate::models::pattern::{NewPattern, Pattern, PatternMember, PatternOverride, PatternPreset, PatternSort};
+use crate::models::template::{GeneratedShift, NewRecurrence, NewShiftTemplate, ShiftTemplate, TemplateSort};
+use crate::models::shift::{NewShift, Shift, ShiftFilter, ShiftSort};
+use crate::encryption::{self, generate_key, Keyring};
+use crate::models::user::{
+    NewUser, PersonalDetails, User, UserFilter, UserRole, UserSort, UserUpsert,
+};
+use crate::pagination::{Page, PageParams, PageRequest};
+use crate::repo::RepoError;
+use crate::{export, retention};
+use crate::models::audit::{AuditFilter, AuditSort, NewAuditEntry};
+
+// Fresh in-memory SQLite database with the full schema applied
+async fn database() -> Database {
+    let db = connect(&DatabaseConfig::with_url("sqlite::memory:")).await.unwrap();
+    migrate::up(&db).await.unwrap();
+    db
+}
+
+async fn app() -> Router {
+    build_app(AppState::from_database(database().await, Config::default()))
+}
+
+#[tokio::test]
+async fn test_sqlite_migrations_up_status_down() {
+    let db = connect(&DatabaseConfig::with_url("sqlite::memory:")).await.unwrap();
+
+    // Nothing is applied on a new database
+    let status = migrate::status(&db).await.unwrap();
+    assert!(status.iter().all(|m| !m.applied));
+
+    // Up applies everything, and running it twice is harmless
+    migrate::up(&db).await.unwrap();
+    migrate::up(&db).await.unwrap();
+    let status = migrate::status(&db).await.unwrap();
+    assert!(status.iter().all(|m| m.applied));
+
+    // Down reverts exactly the newest migration
+    let latest = migrate::latest_version(migrate::migrator_for(&db));
+    assert_eq!(migrate::down(&db).await.unwrap(), Some(latest));
+    let status = migrate::status(&db).await.unwrap();
+    assert!(!status.iter().find(|m| m.version == latest).unwrap().applied);
+}
+
+#[tokio::test]
+async fn test_sqlite_refuses_newer_schema() {
+    let db = database().await;
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+
+    // Pretend a newer release recorded a migration we don't ship
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES (99999, 'from the future', TRUE, X'00', 0)",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+
+    let err = migrate::prepare_schema(&db, true).await.unwrap_err();
+    assert!(matches!(err, migrate::SchemaError::NewerThanBinary { applied: 99999, .. }));
+}
+
+#[tokio::test]
+async fn test_sqlite_user_round_trip() {
+    // Arrange
+    let app = app().await;
+
+    let create_request = Request::builder()
+        .uri("/users")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header(
+            "Authorization",
+            format!("Bearer {}", create_tokens("99", "admin").unwrap().access_token),
+        )
+        .body(Body::from(json!({
+            "name": "Jo Bloggs",
+            "email": "jo@example.com",
+            "role": "admin"
+        }).to_string()))
+        .unwrap();
+
+    // Act
+    let create_response = app.clone().oneshot(create_request).await.unwrap();
+    assert_eq!(create_response.status(), StatusCode::CREATED);
+    let body = hyper::body::to_bytes(create_response.into_body()).await.unwrap();
+    let created: Value = serde_json::from_slice(&body).unwrap();
+
+    let get_request = Request::builder()
+        .uri(format!("/users/{}", created["id"]))
+        .header(
+            "Authorization",
+            format!("Bearer {}", create_tokens("99", "admin").unwrap().access_token),
+        )
+        .body(Body::empty())
+        .unwrap();
+    let get_response = app.clone().oneshot(get_request).await.unwrap();
+
+    // Assert
+    assert_eq!(get_response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(get_response.into_body()).await.unwrap();
+    let fetched: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(fetched["name"], "Jo Bloggs");
+    assert_eq!(fetched["role"], "admin");
+    assert!(fetched["created_at"].is_string());
+
+    // Updates bump the row version, and a stale version is refused
+    let admin = format!("Bearer {}", create_tokens("99", "admin").unwrap().access_token);
+    let (status, updated) = {
+        let request = Request::builder()
+            .uri(format!("/users/{}", created["id"]))
+            .method("PUT")
+            .header("Content-Type", "application/json")
+            .header("Authorization", &admin)
+            .header("If-Match", "\"1\"")
+            .body(Body::from(json!({ "name": "Jo B", "email": "jo@example.com" }).to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice::<Value>(&body).unwrap())
+    };
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["version"], 2);
+    assert_eq!(updated["role"], "admin");
+
+    let stale = Request::builder()
+        .uri(format!("/users/{}", created["id"]))
+        .method("PUT")
+        .header("Content-Type", "application/json")
+        .header("Authorization", &admin)
+        .header("If-Match", "\"1\"")
+        .body(Body::from(json!({ "name": "Jo C", "email": "jo@example.com" }).to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(stale).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    // Duplicate emails are rejected by the unique index, whatever the case
+    let duplicate = Request::builder()
+        .uri("/users")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "name": "Jo", "email": "JO@Example.com" }).to_string()))
+        .unwrap();
+    let response = app.oneshot(duplicate).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_sqlite_database_health() {
+    // Arrange
+    let app = app().await;
+
+    let request = Request::builder()
+        .uri("/health/db")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["healthy"], true);
+    assert_eq!(body["backend"], "sqlite");
+    assert_eq!(body["primary"]["reachable"], true);
+    assert_eq!(body["primary"]["max_connections"], 1);
+    assert!(body["primary"]["in_use"].is_number());
+    assert!(body.get("replica").is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_only_list_views_read_the_lagging_replica() {
+    // Arrange: a replica that has replicated nothing yet
+    let Database::Sqlite(mut pools) = database().await else { unreachable!() };
+    let Database::Sqlite(replica) = database().await else { unreachable!() };
+    pools.replica = Some(replica.primary);
+    let state = AppState::from_database(Database::Sqlite(pools), Config::default());
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap();
+    let sam = state.users.create(user).await.unwrap();
+    let token = create_tokens(&sam.id.to_string(), "user").unwrap().access_token;
+    let app = build_app(state);
+    let send = |method: &str, body: Value| {
+        Request::builder()
+            .uri("/api/leave")
+            .method(method)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let leave = json!({ "leave_type": "unpaid", "start_date": "2030-06-03", "end_date": "2030-06-04" });
+
+    // Act
+    let first = app.clone().oneshot(send("POST", leave.clone())).await.unwrap();
+    let listed = app.clone().oneshot(send("GET", Value::Null)).await.unwrap();
+    let listed: Value = serde_json::from_slice(&hyper::body::to_bytes(listed.into_body()).await.unwrap()).unwrap();
+    let second = app.oneshot(send("POST", leave)).await.unwrap();
+
+    // Assert
+    assert_eq!(first.status(), StatusCode::CREATED);
+    // The list view tolerates the lag and doesn't see the new request yet...
+    assert_eq!(listed["items"], json!([]));
+    // ...but the overlap check reads the primary and still refuses a clash
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_sqlite_audit_chain_is_append_only() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    for name in ["Ann", "Bob"] {
+        let entry = NewAuditEntry {
+            actor: Some("1".to_string()),
+            request_id: Some("req".to_string()),
+            entity: "user".to_string(),
+            entity_id: "7".to_string(),
+            action: "update".to_string(),
+            before: None,
+            after: Some(json!({ "name": name, "score": 1.5 })),
+        };
+        state.audit.append(entry).await.unwrap();
+    }
+
+    // Act: hashes survive the round trip through the database
+    let chain = state.audit.chain().await.unwrap();
+    let filtered = state
+        .audit
+        .list(
+            &AuditFilter { actor: Some("1".to_string()), ..Default::default() },
+            &PageRequest::first(AuditSort::DEFAULT, 1),
+        )
+        .await
+        .unwrap()
+        .items;
+
+    // Stored rows can't be changed or removed
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let update = sqlx::query("UPDATE audit_log SET actor = 'mallory'").execute(&pools.primary).await;
+    let delete = sqlx::query("DELETE FROM audit_log").execute(&pools.primary).await;
+
+    // Assert
+    assert_eq!(verify_chain(&chain), ChainReport { valid: true, entries: 2, broken_at: None });
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].after.as_ref().unwrap()["name"], "Bob");
+    assert!(update.is_err());
+    assert!(delete.is_err());
+}
+
+#[tokio::test]
+async fn test_sqlite_failed_audit_append_rolls_back_the_change() {
+    // Arrange: the audit log refuses every insert
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query(
+        "CREATE TRIGGER audit_log_unavailable BEFORE INSERT ON audit_log \
+         BEGIN SELECT RAISE(ABORT, 'audit log unavailable'); END",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+    let app = build_app(state.clone());
+    let admin = create_tokens("99", "admin").unwrap().access_token;
+
+    // Act
+    let request = Request::builder()
+        .uri("/api/teams")
+        .method("POST")
+        .header("Authorization", format!("Bearer {}", admin))
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "name": "Ward 1" }).to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let teams = state.teams.list(&PageRequest::first(TeamSort::DEFAULT, 10)).await.unwrap();
+    let chain = state.audit.chain().await.unwrap();
+
+    // Assert: the team and its entry share a transaction, so neither is kept
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(teams.items.is_empty());
+    assert!(chain.is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_login_does_not_wait_for_a_writer() {
+    // Arrange: a file database, since an in-memory one has a single
+    // connection, and a block that holds the writer's turn until told to stop
+    let path = std::env::temp_dir().join(format!("rota-login-{}.db", std::process::id()));
+    let db = connect(&DatabaseConfig::with_url(format!("sqlite://{}", path.display()))).await.unwrap();
+    migrate::up(&db).await.unwrap();
+    let state = AppState::from_database(db, Config::default());
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), Some("s3cret"), UserRole::User).unwrap();
+    let sam = state.users.create(user).await.unwrap();
+    let (started, writing) = tokio::sync::oneshot::channel();
+    let (release, released) = tokio::sync::oneshot::channel::<()>();
+    let writer = {
+        let state = state.clone();
+        // The block's first query takes the writer's turn, whatever it is
+        tokio::spawn(database::atomic(async move {
+            state.users.get(sam.id).await?;
+            started.send(()).unwrap();
+            released.await.ok();
+            Ok::<_, RepoError>(())
+        }))
+    };
+    writing.await.unwrap();
+    let app = build_app(state);
+
+    // Act
+    let login = Request::builder()
+        .uri("/api/auth/login")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "email": "sam@example.com", "password": "s3cret" }).to_string()))
+        .unwrap();
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), app.oneshot(login)).await;
+    release.send(()).unwrap();
+    writer.await.unwrap().unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Assert: the login only reads, so it never queued behind the write
+    assert_eq!(response.expect("login waited for the writer").unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_sqlite_retention_purges_old_deleted_rows() {
+    // Arrange: one shift deleted long ago, one deleted just now, one live
+    let db = database().await;
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query("INSERT INTO teams (name) VALUES ('Ward 1')").execute(&pools.primary).await.unwrap();
+    let long_ago = Utc::now() - chrono::Duration::days(400);
+    for deleted_at in [Some(long_ago), Some(Utc::now()), None] {
+        sqlx::query(
+            "INSERT INTO shifts (team_id, starts_at, ends_at, deleted_at) VALUES (1, $1, $2, $3)",
+        )
+        .bind(Utc::now())
+        .bind(Utc::now() + chrono::Duration::hours(8))
+        .bind(deleted_at)
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    }
+    let policy = RetentionConfig {
+        shifts: Some(std::time::Duration::from_secs(365 * 24 * 60 * 60)),
+        ..RetentionConfig::default()
+    };
+    let state = AppState::from_database(db.clone(), Config::default());
+
+    // Act
+    let report = retention::run(&state, &policy).await.unwrap();
+
+    // Assert
+    assert_eq!(report.shifts_purged, 1);
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shifts")
+        .fetch_one(&pools.primary)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 2);
+}
+
+#[tokio::test]
+async fn test_sqlite_retention_keeps_what_it_could_not_audit() {
+    // Arrange: a shift deleted long ago, and an audit log refusing inserts
+    let db = database().await;
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query("INSERT INTO teams (name) VALUES ('Ward 1')").execute(&pools.primary).await.unwrap();
+    sqlx::query("INSERT INTO shifts (team_id, starts_at, ends_at, deleted_at) VALUES (1, $1, $2, $3)")
+        .bind(Utc::now())
+        .bind(Utc::now() + chrono::Duration::hours(8))
+        .bind(Utc::now() - chrono::Duration::days(400))
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    sqlx::query(
+        "CREATE TRIGGER audit_log_unavailable BEFORE INSERT ON audit_log \
+         BEGIN SELECT RAISE(ABORT, 'audit log unavailable'); END",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+    let policy = RetentionConfig {
+        shifts: Some(std::time::Duration::from_secs(365 * 24 * 60 * 60)),
+        ..RetentionConfig::default()
+    };
+    let state = AppState::from_database(db.clone(), Config::default());
+
+    // Act
+    let failed = retention::run(&state, &policy).await;
+    let after_failure: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shifts")
+        .fetch_one(&pools.primary)
+        .await
+        .unwrap();
+    sqlx::query("DROP TRIGGER audit_log_unavailable").execute(&pools.primary).await.unwrap();
+    let report = retention::run(&state, &policy).await.unwrap();
+    let chain = state.audit.chain().await.unwrap();
+
+    // Assert: the purge waited until its entry could be written with it
+    assert!(failed.is_err());
+    assert_eq!(after_failure, 1);
+    assert_eq!(report.shifts_purged, 1);
+    assert_eq!(chain.len(), 1);
+}
+
+#[tokio::test]
+async fn test_sqlite_export_and_erasure_requests() {
+    // Arrange: a user with one assigned shift and one leave request
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let user = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query("INSERT INTO shifts (location, starts_at, ends_at) VALUES ('Ward 1', $1, $2)")
+        .bind(Utc::now())
+        .bind(Utc::now() + chrono::Duration::hours(8))
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO shift_assignments (shift_id, user_id) VALUES (1, $1)")
+        .bind(user.id)
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO leave_requests (user_id, leave_type, start_date, end_date) \
+         VALUES ($1, 'annual', '2026-08-03', '2026-08-07')",
+    )
+    .bind(user.id)
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+
+    // Act
+    let bundle = export::collect(&state, user.clone()).await.unwrap();
+    let request = state
+        .erasures
+        .create(NewErasureRequest { user_id: user.id, requested_by: "1".into(), reason: String::new() })
+        .await
+        .unwrap();
+    let duplicate = state
+        .erasures
+        .create(NewErasureRequest { user_id: user.id, requested_by: "1".into(), reason: String::new() })
+        .await;
+    let decided = state
+        .erasures
+        .decide(request.id, ErasureStatus::Rejected, "2", "Not verified")
+        .await
+        .unwrap();
+    let again = state.erasures.decide(request.id, ErasureStatus::Approved, "2", "").await;
+
+    // Assert
+    assert_eq!(bundle.shifts.len(), 1);
+    assert_eq!(bundle.shifts[0].location, "Ward 1");
+    assert_eq!(bundle.leave.len(), 1);
+    assert_eq!(bundle.leave[0].start_date.to_string(), "2026-08-03");
+    assert!(matches!(duplicate, Err(RepoError::Conflict(_))));
+    assert_eq!(decided.status, ErasureStatus::Rejected);
+    assert_eq!(decided.decision_note, "Not verified");
+    assert!(matches!(again, Err(RepoError::Conflict(_))));
+    assert_eq!(
+        state.erasures.list(Some(ErasureStatus::Pending), &PageRequest::first(ErasureSort::DEFAULT, 10)).await.unwrap().items.len(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_sqlite_approved_erasure_scrubs_free_text_in_one_transaction() {
+    // Arrange: Sam wrote a leave reason, an overtime reason and an availability
+    // note, and the first approval fails as the request is marked decided
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap();
+    let sam = state.users.create(user).await.unwrap();
+    let leave = NewLeaveRequest {
+        user_id: sam.id,
+        leave_type: LeaveType::Annual,
+        start_date: "2026-08-03".parse().unwrap(),
+        end_date: "2026-08-07".parse().unwrap(),
+        half_day_start: false,
+        half_day_end: false,
+        reason: "Sister's wedding".into(),
+        approver_id: None,
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let leave = state.leave.create(leave).await.unwrap();
+    let claim = NewOvertime {
+        user_id: sam.id,
+        date: "2026-06-09".parse().unwrap(),
+        hours: 2.0,
+        shift_id: None,
+        reason: "Covered for a colleague in hospital".into(),
+        approver_id: None,
+    };
+    let claim = state.toil.create_overtime(claim).await.unwrap();
+    let exception = NewException {
+        user_id: sam.id,
+        date: "2026-06-10".parse().unwrap(),
+        start_time: None,
+        end_time: None,
+        kind: AvailabilityKind::Unavailable,
+        note: "Clinic appointment".into(),
+    };
+    state.availability.add_exception(exception).await.unwrap();
+    let request = state
+        .erasures
+        .create(NewErasureRequest { user_id: sam.id, requested_by: sam.id.to_string(), reason: String::new() })
+        .await
+        .unwrap();
+    sqlx::query(
+        "CREATE TRIGGER erasures_down BEFORE UPDATE ON erasure_requests \
+         BEGIN SELECT RAISE(ABORT, 'erasures unavailable'); END",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+    let app = build_app(state.clone());
+    let admin = create_tokens("99", "admin").unwrap().access_token;
+    let approve = || {
+        Request::builder()
+            .uri(format!("/api/erasure-requests/{}/approve", request.id))
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", admin))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let failed = app.clone().oneshot(approve()).await.unwrap();
+    let user_after_failure = state.users.get_with_deleted(sam.id).await.unwrap().unwrap();
+    let leave_after_failure = state.leave.get(leave.id).await.unwrap().unwrap();
+    sqlx::query("DROP TRIGGER erasures_down").execute(&pools.primary).await.unwrap();
+    let approved = app.oneshot(approve()).await.unwrap();
+    let user = state.users.get_with_deleted(sam.id).await.unwrap().unwrap();
+    let leave = state.leave.get(leave.id).await.unwrap().unwrap();
+    let claim = state.toil.get_overtime(claim.id).await.unwrap().unwrap();
+    let exceptions = state.availability.exceptions(sam.id, None, None).await.unwrap();
+
+    // Assert: nothing was erased until the approval could be saved with it
+    assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(user_after_failure.username, "Sam");
+    assert_eq!(leave_after_failure.reason, "Sister's wedding");
+    assert_eq!(approved.status(), StatusCode::OK);
+    assert!(user.anonymised_at.is_some());
+    assert_eq!(leave.reason, "");
+    assert_eq!(claim.reason, "");
+    assert_eq!(exceptions.len(), 1);
+    assert_eq!(exceptions[0].note, "");
+}
+
+#[tokio::test]
+async fn test_sqlite_personal_details_are_encrypted_at_rest() {
+    // Arrange: a user with a phone number, stored under KEK 1
+    let (kek1, kek2, index) = (generate_key(), generate_key(), generate_key());
+    let keys = |versions: &str| {
+        Keyring::parse(&format!("{}index = {}\n", versions, index)).unwrap()
+    };
+    let db = database().await;
+    let config = |keyring| Config {
+        encryption: EncryptionConfig::with_keyring(keyring),
+        ..Config::default()
+    };
+    let state = AppState::from_database(db.clone(), config(keys(&format!("kek.1 = {}\n", kek1))));
+    let mut user = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    user.personal = PersonalDetails {
+        phone: Some("+44 7700 900123".to_string()),
+        date_of_birth: "1990-04-01".parse().ok(),
+        ..PersonalDetails::default()
+    };
+    state.users.update_personal(user.clone()).await.unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let raw_phone = || async {
+        sqlx::query_scalar::<_, String>("SELECT phone FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&pools.primary)
+            .await
+            .unwrap()
+    };
+
+    // Act: rotate to KEK 2 and re-encrypt
+    let stored = raw_phone().await;
+    let found = state.users.find_by_phone("+447700900123").await.unwrap();
+    let rotated = keys(&format!("kek.1 = {}\nkek.2 = {}\n", kek1, kek2));
+    let report = encryption::reencrypt(&db, &rotated).await.unwrap();
+    let after_rotation = AppState::from_database(db.clone(), config(keys(&format!("kek.2 = {}\n", kek2))));
+    let reloaded = after_rotation.users.personal(user.id).await.unwrap();
+
+    // Assert
+    assert!(stored.starts_with("v1.1."));
+    assert!(!stored.contains("7700"));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, user.id);
+    assert_eq!(report.active_key_version, 2);
+    assert_eq!(report.rewrapped, 2);
+    assert_eq!(report.blind_indexes_refreshed, 0);
+    assert!(raw_phone().await.starts_with("v1.2."));
+    assert_eq!(reloaded, user.personal);
+}
+
+#[tokio::test]
+async fn test_sqlite_sickness_notes_are_encrypted_at_rest() {
+    // Arrange: one sickness absence with a note and one holiday with a reason
+    let (kek1, kek2, index) = (generate_key(), generate_key(), generate_key());
+    let keys = |versions: &str| Keyring::parse(&format!("{}index = {}\n", versions, index)).unwrap();
+    let db = database().await;
+    let config = |keyring| Config {
+        encryption: EncryptionConfig::with_keyring(keyring),
+        ..Config::default()
+    };
+    let state = AppState::from_database(db.clone(), config(keys(&format!("kek.1 = {}\n", kek1))));
+    let user = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let request = |leave_type, date: &str, reason: &str| NewLeaveRequest {
+        user_id: user.id,
+        leave_type,
+        start_date: date.parse().unwrap(),
+        end_date: date.parse().unwrap(),
+        half_day_start: false,
+        half_day_end: false,
+        reason: reason.into(),
+        approver_id: None,
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let sick = state.leave.create(request(LeaveType::Sickness, "2025-06-02", "Migraine")).await.unwrap();
+    let holiday = state.leave.create(request(LeaveType::Annual, "2025-06-09", "Wedding")).await.unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let raw = |id: i64| {
+        let pool = pools.primary.clone();
+        async move {
+            sqlx::query_as::<_, (String, Option<String>)>(
+                "SELECT reason, sickness_note FROM leave_requests WHERE id = $1",
+            )
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+
+    // Act: read everything back, then rotate to KEK 2
+    let (sick_reason, sick_note) = raw(sick.id).await;
+    let (holiday_reason, holiday_note) = raw(holiday.id).await;
+    let listed = state.leave.list(&LeaveFilter::default(), &PageRequest::first(LeaveSort::DEFAULT, 10)).await.unwrap().items;
+    let rotated = keys(&format!("kek.1 = {}\nkek.2 = {}\n", kek1, kek2));
+    let report = encryption::reencrypt(&db, &rotated).await.unwrap();
+    let after_rotation = AppState::from_database(db.clone(), config(keys(&format!("kek.2 = {}\n", kek2))));
+    let reloaded = after_rotation.leave.get(sick.id).await.unwrap().unwrap();
+
+    // Assert
+    assert_eq!(sick.reason, "Migraine");
+    assert_eq!(sick_reason, "");
+    assert!(sick_note.as_deref().unwrap().starts_with("v1.1."));
+    assert!(!sick_note.unwrap().contains("Migraine"));
+    assert_eq!((holiday_reason.as_str(), holiday_note), ("Wedding", None));
+    let reasons: Vec<&str> = listed.iter().map(|l| l.reason.as_str()).collect();
+    assert_eq!(reasons, vec!["Migraine", "Wedding"]);
+    assert_eq!(report.rewrapped, 1);
+    assert_eq!(reloaded.reason, "Migraine");
+}
+
+#[tokio::test]
+async fn test_sqlite_reencrypt_keeps_a_value_written_after_it_was_read() {
+    // Arrange: Sam's phone number was read under KEK 1, then changed by the
+    // server, already running with KEK 2, before the re-wrap was stored
+    let (kek1, kek2, index) = (generate_key(), generate_key(), generate_key());
+    let keys = |versions: &str| Keyring::parse(&format!("{}index = {}\n", versions, index)).unwrap();
+    let rotated = keys(&format!("kek.1 = {}\nkek.2 = {}\n", kek1, kek2));
+    let db = database().await;
+    let config = |keyring| Config {
+        encryption: EncryptionConfig::with_keyring(keyring),
+        ..Config::default()
+    };
+    let before = AppState::from_database(db.clone(), config(keys(&format!("kek.1 = {}\n", kek1))));
+    let mut user = before
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    user.personal.phone = Some("+44 7700 900123".to_string());
+    let user = before.users.update_personal(user).await.unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let raw_phone = || async {
+        sqlx::query_scalar::<_, String>("SELECT phone FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&pools.primary)
+            .await
+            .unwrap()
+    };
+    let read = raw_phone().await;
+    let after = AppState::from_database(db.clone(), config(rotated.clone()));
+    let changed = User {
+        personal: PersonalDetails { phone: Some("+44 7700 900456".to_string()), ..user.personal.clone() },
+        ..user.clone()
+    };
+    after.users.update_personal(changed).await.unwrap();
+    let written = raw_phone().await;
+
+    // Act
+    let rewrapped = rotated.rewrap(&read).unwrap().unwrap();
+    let stored = encryption::store_column(&db, "users", "phone", user.id, Some(&read), &rewrapped).await.unwrap();
+    let report = encryption::reencrypt(&db, &rotated).await.unwrap();
+    let reloaded = after.users.personal(user.id).await.unwrap();
+
+    // Assert: the stale re-wrap didn't overwrite the new number
+    assert!(!stored);
+    assert_eq!(raw_phone().await, written);
+    assert_eq!(reloaded.phone.as_deref(), Some("+44 7700 900456"));
+    assert_eq!((report.rewrapped, report.skipped), (0, 0));
+    assert_eq!(after.users.find_by_phone("+447700900456").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_sqlite_sickness_leave_without_a_key_file_is_unavailable() {
+    // Arrange: no ENCRYPTION_KEYS_FILE, so sickness notes can't be stored
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap();
+    let sam = state.users.create(user).await.unwrap();
+    let token = create_tokens(&sam.id.to_string(), "user").unwrap().access_token;
+    let app = build_app(state.clone());
+
+    // Act
+    let request = Request::builder()
+        .uri("/api/leave")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::from(
+            json!({
+                "leave_type": "sickness",
+                "start_date": "2030-06-03",
+                "end_date": "2030-06-03",
+                "reason": "Migraine"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body: Value = serde_json::from_slice(&hyper::body::to_bytes(response.into_body()).await.unwrap()).unwrap();
+    let page = PageRequest::first(LeaveSort::DEFAULT, 10);
+    let stored = state.leave.list(&LeaveFilter::default(), &page).await.unwrap();
+
+    // Assert
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["error"].as_str().unwrap().contains("ENCRYPTION_KEYS_FILE"));
+    assert!(stored.items.is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_personal_details_without_a_key_file_only_fail_where_shown() {
+    // Arrange: Sam's phone number was stored under a key file the server
+    // has since been started without
+    let db = database().await;
+    let keyed = Config {
+        encryption: EncryptionConfig::with_keyring(Keyring::generate()),
+        ..Config::default()
+    };
+    let before = AppState::from_database(db.clone(), keyed);
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), Some("s3cret"), UserRole::User).unwrap();
+    let mut sam = before.users.create(user).await.unwrap();
+    sam.personal.phone = Some("+44 7700 900123".to_string());
+    before.users.update_personal(sam.clone()).await.unwrap();
+    let app = build_app(AppState::from_database(db, Config::default()));
+    let admin = create_tokens("99", "admin").unwrap().access_token;
+    let send = |method: &str, uri: &str, body: Value| {
+        let request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("Authorization", format!("Bearer {}", admin))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.clone().oneshot(request)
+    };
+
+    // Act
+    let login = send("POST", "/api/auth/login", json!({ "email": "sam@example.com", "password": "s3cret" })).await.unwrap();
+    let listed = send("GET", "/users", Value::Null).await.unwrap();
+    let shown = send("GET", &format!("/users/{}", sam.id), Value::Null).await.unwrap();
+    let personal = send("GET", &format!("/users/{}/personal", sam.id), Value::Null).await.unwrap();
+
+    // Assert: only the endpoint that decrypts needs the key
+    assert_eq!(login.status(), StatusCode::OK);
+    assert_eq!(listed.status(), StatusCode::OK);
+    assert_eq!(shown.status(), StatusCode::OK);
+    assert_eq!(personal.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_sqlite_anonymising_a_user_removes_their_personal_details() {
+    // Arrange: a deleted user with a phone number and date of birth
+    let db = database().await;
+    let config = Config {
+        encryption: EncryptionConfig::with_keyring(Keyring::generate()),
+        ..Config::default()
+    };
+    let state = AppState::from_database(db.clone(), config);
+    let user = NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap();
+    let mut sam = state.users.create(user).await.unwrap();
+    sam.personal = PersonalDetails {
+        phone: Some("+44 7700 900123".to_string()),
+        date_of_birth: "1990-04-01".parse().ok(),
+        ..PersonalDetails::default()
+    };
+    let mut sam = state.users.update_personal(sam).await.unwrap();
+    let renamed = state.users.update(User { username: "Samantha".into(), ..sam.clone() }).await.unwrap();
+    let kept = state.users.personal(sam.id).await.unwrap();
+    sam.version = renamed.version;
+
+    // Act
+    sam.anonymise(Utc::now());
+    state.users.update(sam.clone()).await.unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let (phone, date_of_birth): (Option<String>, Option<String>) =
+        sqlx::query_as("SELECT phone, date_of_birth FROM users WHERE id = $1")
+            .bind(sam.id)
+            .fetch_one(&pools.primary)
+            .await
+            .unwrap();
+
+    // Assert: a plain update leaves them, anonymising removes them
+    assert_eq!(kept.phone.as_deref(), Some("+44 7700 900123"));
+    assert_eq!((phone, date_of_birth), (None, None));
+    assert_eq!(state.users.personal(sam.id).await.unwrap(), PersonalDetails::default());
+    assert!(state.users.find_by_phone("+447700900123").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_encrypted_values_are_bound_to_their_row() {
+    // Arrange: two users, one with a phone number
+    let db = database().await;
+    let config = Config {
+        encryption: EncryptionConfig::with_keyring(Keyring::generate()),
+        ..Config::default()
+    };
+    let state = AppState::from_database(db.clone(), config);
+    let mut sam = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let jo = state
+        .users
+        .create(NewUser::new("Jo".into(), "jo@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    sam.personal.phone = Some("+44 7700 900123".to_string());
+    state.users.update_personal(sam.clone()).await.unwrap();
+
+    // Act: copy Sam's ciphertext onto Jo's row
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query("UPDATE users SET phone = (SELECT phone FROM users WHERE id = $1) WHERE id = $2")
+        .bind(sam.id)
+        .bind(jo.id)
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(state.users.personal(sam.id).await.is_ok());
+    assert!(matches!(state.users.personal(jo.id).await, Err(RepoError::Encryption(_))));
+}
+
+#[tokio::test]
+async fn test_sqlite_deactivation_unassigns_future_shifts_only() {
+    // Arrange: one shift that has finished and one still to come
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let user = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    for days in [-2, 2] {
+        let starts_at = Utc::now() + chrono::Duration::days(days);
+        sqlx::query("INSERT INTO shifts (location, starts_at, ends_at) VALUES ('Ward 1', $1, $2)")
+            .bind(starts_at)
+            .bind(starts_at + chrono::Duration::hours(8))
+            .execute(&pools.primary)
+            .await
+            .unwrap();
+    }
+    for shift_id in [1, 2] {
+        sqlx::query("INSERT INTO shift_assignments (shift_id, user_id) VALUES ($1, $2)")
+            .bind(shift_id)
+            .bind(user.id)
+            .execute(&pools.primary)
+            .await
+            .unwrap();
+    }
+    let app = build_app(state);
+    let admin = create_tokens("99", "admin").unwrap().access_token;
+
+    // Act
+    let request = Request::builder()
+        .uri(format!("/users/{}/deactivate", user.id))
+        .method("POST")
+        .header("Authorization", format!("Bearer {}", admin))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let remaining: Vec<i64> =
+        sqlx::query_scalar("SELECT shift_id FROM shift_assignments WHERE user_id = $1")
+            .bind(user.id)
+            .fetch_all(&pools.primary)
+            .await
+            .unwrap();
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["shifts_unassigned"], 1);
+    assert!(body["deactivated_at"].is_string());
+    assert_eq!(remaining, vec![1]);
+}
+
+#[tokio::test]
+async fn test_sqlite_user_list_pages_filters_and_sorts() {
+    // Arrange: users in team 1, some with skills, created in a fixed order
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    sqlx::query("INSERT INTO teams (name) VALUES ('Ward 1')")
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    for (name, skills) in [
+        ("Dee", vec!["ICU"]),
+        ("ann", vec![]),
+        ("Cal", vec!["icu", "Triage", "ICU"]),
+        ("Bea", vec![]),
+        ("Eli_", vec![]),
+    ] {
+        let mut user = state
+            .users
+            .create(
+                NewUser::new(name.into(), format!("{}@example.com", name.to_lowercase()), None, UserRole::User)
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        user.team_id = Some(1);
+        user.skills = skills.into_iter().map(String::from).collect();
+        state.users.update(user).await.unwrap();
+    }
+    let list = |filter: UserFilter, sort: &'static str, cursor: Option<String>| {
+        let state = state.clone();
+        async move {
+            let params = PageParams { limit: Some(2), cursor, sort: Some(sort.to_string()) };
+            let page = PageRequest::new(&params, UserSort::DEFAULT).unwrap();
+            state.users.list(&filter, &page).await.unwrap()
+        }
+    };
+    let names = |page: &Page<User>| page.items.iter().map(|u| u.username.clone()).collect::<Vec<_>>();
+
+    // Act
+    let first = list(UserFilter::default(), "name", None).await;
+    let second = list(UserFilter::default(), "name", first.next_cursor.clone()).await;
+    let third = list(UserFilter::default(), "name", second.next_cursor.clone()).await;
+    let by_role = list(UserFilter::default(), "role,-name", None).await;
+    let by_role_next = list(UserFilter::default(), "role,-name", by_role.next_cursor.clone()).await;
+    let skilled = list(
+        UserFilter { skill: Some("ICU".into()), team: Some(1), ..UserFilter::default() },
+        "-name",
+        None,
+    )
+    .await;
+    let searched = list(UserFilter { search: Some("i_".into()), ..UserFilter::default() }, "name", None).await;
+
+    // Assert
+    assert_eq!(names(&first), vec!["ann", "Bea"]);
+    assert_eq!(names(&second), vec!["Cal", "Dee"]);
+    assert_eq!(names(&third), vec!["Eli_"]);
+    assert_eq!(third.next_cursor, None);
+    assert_eq!(names(&by_role), vec!["Eli_", "Dee"]);
+    assert_eq!(names(&by_role_next), vec!["Cal", "Bea"]);
+    assert_eq!(names(&skilled), vec!["Dee", "Cal"]);
+    assert_eq!(skilled.items[1].skills, vec!["icu", "Triage"]);
+    assert_eq!(names(&searched), vec!["Eli_"]);
+}
+
+#[tokio::test]
+async fn test_sqlite_default_created_at_sort_pages_to_the_end() {
+    // Arrange: five users, each asking for erasure
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let mut ids = Vec::new();
+    for name in ["Ann", "Bea", "Cal", "Dee", "Eli"] {
+        let email = format!("{}@example.com", name.to_lowercase());
+        let user = state.users.create(NewUser::new(name.into(), email, None, UserRole::User).unwrap()).await.unwrap();
+        let request = NewErasureRequest { user_id: user.id, requested_by: user.id.to_string(), reason: String::new() };
+        state.erasures.create(request).await.unwrap();
+        ids.push(user.id);
+    }
+
+    // Act: walk both lists two at a time in their default order
+    let mut users = Vec::new();
+    let mut request = Some(PageRequest::first(UserSort::DEFAULT, 2));
+    while let Some(page_request) = request {
+        let page = state.users.list(&UserFilter::default(), &page_request).await.unwrap();
+        request = page_request.next(&page);
+        users.extend(page.items.into_iter().map(|u| u.id));
+    }
+    let mut erasures = Vec::new();
+    let mut request = Some(PageRequest::first(ErasureSort::DEFAULT, 2));
+    while let Some(page_request) = request {
+        let page = state.erasures.list(None, &page_request).await.unwrap();
+        request = page_request.next(&page);
+        erasures.extend(page.items.into_iter().map(|r| r.user_id));
+    }
+
+    // Assert: every row comes back, oldest first
+    assert_eq!(users, ids);
+    assert_eq!(erasures, ids);
+}
+
+#[tokio::test]
+async fn test_sqlite_upsert_many_creates_updates_and_rolls_back() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let existing = state
+        .users
+        .create(NewUser::new("Ann".into(), "ann@example.com".into(), None, UserRole::Admin).unwrap())
+        .await
+        .unwrap();
+    let row = |name: &str, email: &str, hours: Option<f64>| UserUpsert {
+        name: name.into(),
+        email: email.into(),
+        role: None,
+        team_id: Some(Some(team.id)),
+        contracted_hours: hours.map(Some),
+        skills: Some(vec!["Triage".into()]),
+    };
+
+    // Act
+    let results = state
+        .users
+        .upsert_many(vec![row("Ann", "ANN@example.com", Some(30.0)), row("Bea", "bea@example.com", None)])
+        .await
+        .unwrap();
+    let again = state.users.upsert_many(vec![row("Ann", "ann@example.com", None)]).await.unwrap();
+
+    // A deleted user's email can't be taken over, and the new row before it
+    // is rolled back with it
+    let mut deleted = state.users.get(existing.id).await.unwrap().unwrap();
+    deleted.deleted_at = Some(Utc::now());
+    state.users.update(deleted).await.unwrap();
+    let conflict = state
+        .users
+        .upsert_many(vec![row("Cal", "cal@example.com", None), row("Ann", "ann@example.com", None)])
+        .await;
+
+    // Assert
+    let ann = &results[0].after;
+    assert_eq!(results[0].before.as_ref().unwrap().version, existing.version);
+    assert_eq!(ann.role, UserRole::Admin);
+    assert_eq!(ann.team_id, Some(team.id));
+    assert_eq!(ann.contracted_hours, Some(30.0));
+    assert_eq!(ann.skills, vec!["Triage"]);
+    assert!(results[1].before.is_none());
+    assert_eq!(results[1].after.contracted_hours, None);
+    assert_eq!(again[0].after.version, ann.version);
+    assert!(matches!(conflict, Err(RepoError::Conflict(_))));
+    assert!(state.users.find_by_email("cal@example.com").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_staff_profile_round_trip() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let user = state
+        .users
+        .create(NewUser::new("Ann".into(), "ann@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let employment = Employment {
+        user_id: user.id,
+        contract_type: ContractType::Annualised,
+        pay_grade: Some("Band 6".into()),
+        start_date: "2025-01-06".parse().unwrap(),
+        end_date: None,
+        qualifications: vec![
+            Qualification { name: "bls".into(), issued_on: "2025-01-01".parse().unwrap(), expires_on: None },
+            Qualification {
+                name: "ALS".into(),
+                issued_on: "2025-01-01".parse().unwrap(),
+                expires_on: "2026-01-01".parse().ok(),
+            },
+        ],
+        version: 0,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    // Act
+    let created = state.profiles.save(employment.clone()).await.unwrap();
+    let duplicate = state.profiles.save(employment.clone()).await;
+    let updated = state
+        .profiles
+        .save(Employment { qualifications: vec![], end_date: "2026-06-30".parse().ok(), ..created.clone() })
+        .await
+        .unwrap();
+    let stale = state.profiles.save(created.clone()).await;
+    let fetched = state.profiles.get_many(&[user.id, 999]).await.unwrap();
+    state.profiles.delete(user.id).await.unwrap();
+
+    // Assert
+    assert_eq!(created.version, 1);
+    let names: Vec<_> = created.qualifications.iter().map(|q| q.name.as_str()).collect();
+    assert_eq!(names, vec!["ALS", "bls"]);
+    assert!(matches!(duplicate, Err(RepoError::Conflict(_))));
+    assert_eq!(updated.version, 2);
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(fetched.len(), 1);
+    assert!(fetched[0].qualifications.is_empty());
+    assert_eq!(fetched[0].end_date, "2026-06-30".parse().ok());
+    assert!(state.profiles.get(user.id).await.unwrap().is_none());
+    assert!(matches!(state.profiles.delete(user.id).await, Err(RepoError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_sqlite_shift_round_trip() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let start: DateTime<Utc> = "2025-06-02T07:00:00Z".parse().unwrap();
+    for (day, location) in [(0, "B"), (1, "a"), (2, "C")] {
+        state
+            .shifts
+            .create(NewShift {
+                rota_id: None,
+                team_id: Some(team.id),
+                location: location.into(),
+                position: "Nurse".into(),
+                starts_at: start + Duration::days(day),
+                ends_at: start + Duration::days(day) + Duration::hours(12),
+                time_zone: "Europe/London".into(),
+                unpaid_break_minutes: 30,
+                required_headcount: 1,
+                notes: String::new(),
+                required_skills: vec!["ICU".into()],
+                required_qualifications: Vec::new(),
+            })
+            .await
+            .unwrap();
+    }
+    let list = |filter: ShiftFilter, sort: &'static str| {
+        let state = state.clone();
+        async move {
+            let params = PageParams { limit: Some(2), cursor: None, sort: Some(sort.to_string()) };
+            let page = PageRequest::new(&params, ShiftSort::DEFAULT).unwrap();
+            state.shifts.list(&filter, &page).await.unwrap()
+        }
+    };
+
+    // Act
+    let first = list(ShiftFilter::default(), "starts_at").await;
+    let by_location = list(ShiftFilter { team: Some(team.id), ..ShiftFilter::default() }, "location").await;
+    let window = list(
+        ShiftFilter {
+            from: Some(start + Duration::hours(13)),
+            to: Some(start + Duration::days(2)),
+            ..ShiftFilter::default()
+        },
+        "starts_at",
+    )
+    .await;
+    let shift = state.shifts.get(first.items[0].id).await.unwrap().unwrap();
+    let updated = state
+        .shifts
+        .update(Shift { notes: "Bring a torch".into(), ..shift.clone() })
+        .await
+        .unwrap();
+    let stale = state.shifts.update(shift.clone()).await;
+    state
+        .shifts
+        .update(Shift { deleted_at: Some(Utc::now()), ..updated.clone() })
+        .await
+        .unwrap();
+
+    // Assert
+    let ids = |page: &Page<Shift>| page.items.iter().map(|s| s.location.clone()).collect::<Vec<_>>();
+    assert_eq!(ids(&first), vec!["B", "a"]);
+    assert!(first.next_cursor.is_some());
+    assert_eq!(first.items[0].starts_at, start);
+    assert_eq!(ids(&by_location), vec!["a", "B"]);
+    assert_eq!(ids(&window), vec!["a"]);
+    assert_eq!(updated.version, shift.version + 1);
+    assert_eq!(updated.notes, "Bring a torch");
+    assert_eq!(updated.required_skills, vec!["ICU"]);
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert!(state.shifts.get(shift.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_assignments_and_approved_leave() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let user = state
+        .users
+        .create(NewUser::new("Sam".into(), "sam@example.com".into(), None, UserRole::User).unwrap())
+        .await
+        .unwrap();
+    let start: DateTime<Utc> = "2025-06-02T08:00:00Z".parse().unwrap();
+    let shift = state
+        .shifts
+        .create(NewShift {
+            rota_id: None,
+            team_id: None,
+            location: "Ward 1".into(),
+            position: "Nurse".into(),
+            starts_at: start,
+            ends_at: start + Duration::hours(12),
+            time_zone: "UTC".into(),
+            unpaid_break_minutes: 0,
+            required_headcount: 1,
+            notes: String::new(),
+            required_skills: vec!["ICU".into()],
+            required_qualifications: vec!["ALS".into()],
+        })
+        .await
+        .unwrap();
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    for (status, start_date, end_date) in [
+        ("approved", "2025-06-01", "2025-06-02"),
+        ("pending", "2025-06-02", "2025-06-02"),
+        ("approved", "2025-06-10", "2025-06-12"),
+    ] {
+        sqlx::query(
+            "INSERT INTO leave_requests (user_id, leave_type, start_date, end_date, status) \
+             VALUES ($1, 'annual', $2, $3, $4)",
+        )
+        .bind(user.id)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(status)
+        .execute(&pools.primary)
+        .await
+        .unwrap();
+    }
+    let forced = NewAssignment {
+        shift_id: shift.id,
+        user_id: user.id,
+        assigned_by: Some("99".into()),
+        forced: true,
+        override_reason: Some("Short staffed".into()),
+        conflicts: vec![Conflict::new(ConflictCode::OnLeave, "On annual leave")],
+    };
+
+    // Act
+    let assignment = state.shifts.assign(forced.clone()).await.unwrap();
+    let twice = state.shifts.assign(forced).await;
+    let listed = state.shifts.assignments(shift.id).await.unwrap();
+    let that_day = state.shifts.assigned_to(user.id, start, start + Duration::days(1)).await.unwrap();
+    let next_day = state
+        .shifts
+        .assigned_to(user.id, start + Duration::hours(12), start + Duration::days(1))
+        .await
+        .unwrap();
+    let leave = state
+        .leave
+        .approved_between(user.id, "2025-06-02".parse().unwrap(), "2025-06-03".parse().unwrap())
+        .await
+        .unwrap();
+    state.shifts.unassign(shift.id, user.id).await.unwrap();
+    let unassigned_again = state.shifts.unassign(shift.id, user.id).await;
+
+    // Assert
+    assert_eq!(assignment.conflicts[0].code, ConflictCode::OnLeave);
+    assert!(assignment.forced);
+    assert!(matches!(twice, Err(RepoError::Conflict(_))));
+    assert_eq!(listed, vec![assignment]);
+    assert_eq!(that_day.len(), 1);
+    assert_eq!(that_day[0].required_qualifications, vec!["ALS"]);
+    assert!(next_day.is_empty());
+    assert_eq!(leave.len(), 1);
+    assert_eq!(leave[0].start_date.to_string(), "2025-06-01");
+    assert!(matches!(unassigned_again, Err(RepoError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_sqlite_rota_publish_keeps_snapshots() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let start = "2025-06-02".parse().unwrap();
+    let rota = state.rotas.create(NewRota::new(team.id, start, RotaLength::Fortnight)).await.unwrap();
+
+    // Act
+    let overlapping = state.rotas.create(NewRota::new(team.id, "2025-06-09".parse().unwrap(), RotaLength::Week)).await;
+    let mut first = rota.clone();
+    first.transition(RotaAction::Publish, "98", Utc::now()).unwrap();
+    let (published, snapshot) = state.rotas.publish(first, json!([{ "id": 1, "assigned": [7] }])).await.unwrap();
+    let mut second = published.clone();
+    second.transition(RotaAction::Publish, "98", Utc::now()).unwrap();
+    let (republished, _) = state.rotas.publish(second, json!([])).await.unwrap();
+    let mut locked = republished.clone();
+    locked.transition(RotaAction::Lock, "99", Utc::now()).unwrap();
+    let locked = state.rotas.update(locked).await.unwrap();
+    let stale = state.rotas.update(published.clone()).await;
+    let latest = state.rotas.snapshot(rota.id, None).await.unwrap().unwrap();
+    let earlier = state.rotas.snapshot(rota.id, Some(1)).await.unwrap().unwrap();
+    let page = PageRequest::first(RotaSort::DEFAULT, 10);
+    let filter = RotaFilter { status: Some(RotaStatus::Locked), ..RotaFilter::default() };
+    let listed = state.rotas.list(&filter, &page).await.unwrap();
+
+    // Assert
+    assert_eq!(rota.period_end.to_string(), "2025-06-15");
+    assert!(matches!(overlapping, Err(RepoError::Conflict(_))));
+    assert_eq!((snapshot.revision, snapshot.published_by.as_deref()), (1, Some("98")));
+    assert_eq!(republished.revision, 2);
+    assert_eq!(locked.status, RotaStatus::Locked);
+    assert_eq!(locked.locked_by.as_deref(), Some("99"));
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(latest.revision, 2);
+    assert_eq!(latest.shifts, json!([]));
+    assert_eq!(earlier.shifts[0]["assigned"], json!([7]));
+    assert_eq!(listed.items, vec![locked]);
+}
+
+#[tokio::test]
+async fn test_sqlite_deleted_rotas_and_shifts_can_be_found_for_restore() {
+    // Arrange: a rota with two shifts, deleted together, and one deleted earlier
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let rota = state.rotas.create(NewRota::new(team.id, "2025-06-02".parse().unwrap(), RotaLength::Week)).await.unwrap();
+    let start: DateTime<Utc> = "2025-06-03T07:00:00Z".parse().unwrap();
+    let mut shifts = Vec::new();
+    for day in 0..3 {
+        let shift = NewShift {
+            rota_id: Some(rota.id),
+            team_id: Some(team.id),
+            location: "Ward 1".into(),
+            position: "Nurse".into(),
+            starts_at: start + Duration::days(day),
+            ends_at: start + Duration::days(day) + Duration::hours(12),
+            time_zone: "Europe/London".into(),
+            unpaid_break_minutes: 0,
+            required_headcount: 1,
+            notes: String::new(),
+            required_skills: Vec::new(),
+            required_qualifications: Vec::new(),
+        };
+        shifts.push(state.shifts.create(shift).await.unwrap());
+    }
+    let earlier = Utc::now() - Duration::hours(1);
+    state.shifts.update(Shift { deleted_at: Some(earlier), ..shifts[0].clone() }).await.unwrap();
+    let now = Utc::now();
+    let deleted = state.rotas.update(Rota { deleted_at: Some(now), ..rota.clone() }).await.unwrap();
+    for shift in &shifts[1..] {
+        state.shifts.update(Shift { deleted_at: Some(now), ..shift.clone() }).await.unwrap();
+    }
+
+    // Act
+    let hidden = state.rotas.get(rota.id).await.unwrap();
+    let found = state.rotas.get_with_deleted(rota.id).await.unwrap().unwrap();
+    let shift = state.shifts.get_with_deleted(shifts[0].id).await.unwrap().unwrap();
+    let taken = state.shifts.deleted_with_rota(rota.id, deleted.deleted_at.unwrap()).await.unwrap();
+
+    // Assert
+    assert_eq!(hidden, None);
+    assert_eq!(found, deleted);
+    assert!(shift.is_deleted());
+    let ids: Vec<i64> = taken.iter().map(|s| s.id).collect();
+    assert_eq!(ids, vec![shifts[1].id, shifts[2].id]);
+}
+
+#[tokio::test]
+async fn test_sqlite_failed_rota_copy_is_rolled_back() {
+    // Arrange: a rota with a shift, and assignments that can't be read
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let source = state.rotas.create(NewRota::new(team.id, "2025-06-02".parse().unwrap(), RotaLength::Week)).await.unwrap();
+    let start: DateTime<Utc> = "2025-06-03T07:00:00Z".parse().unwrap();
+    state
+        .shifts
+        .create(NewShift {
+            rota_id: Some(source.id),
+            team_id: Some(team.id),
+            location: "Ward 1".into(),
+            position: "Nurse".into(),
+            starts_at: start,
+            ends_at: start + Duration::hours(12),
+            time_zone: "Europe/London".into(),
+            unpaid_break_minutes: 0,
+            required_headcount: 1,
+            notes: String::new(),
+            required_skills: Vec::new(),
+            required_qualifications: Vec::new(),
+        })
+        .await
+        .unwrap();
+    sqlx::query("DROP TABLE shift_assignments").execute(&pools.primary).await.unwrap();
+    let app = build_app(state.clone());
+    let manager = create_tokens("98", "manager").unwrap().access_token;
+
+    // Act
+    let request = Request::builder()
+        .uri(format!("/api/rotas/{}/copy", source.id))
+        .method("POST")
+        .header("Authorization", format!("Bearer {}", manager))
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "period_start": "2025-06-09", "assignments": true }).to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let page = PageRequest::first(RotaSort::DEFAULT, 10);
+    let team_rotas = RotaFilter { team: Some(team.id), ..RotaFilter::default() };
+    let rotas = state.rotas.list(&team_rotas, &page).await.unwrap();
+    let copy = state.rotas.get_with_deleted(source.id + 1).await.unwrap();
+    let shifts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shifts").fetch_one(&pools.primary).await.unwrap();
+    let entries = state.audit.chain().await.unwrap();
+
+    // Assert: the new rota, its shifts and their audit entries were never kept
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(rotas.items, vec![source]);
+    assert_eq!(copy, None);
+    assert_eq!(shifts, 1);
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_rota_copy_failing_after_some_shifts_keeps_none_of_them() {
+    // Arrange: two shifts, where copying the second is refused by a trigger
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let source = state.rotas.create(NewRota::new(team.id, "2025-06-02".parse().unwrap(), RotaLength::Week)).await.unwrap();
+    for (day, notes) in [(3, ""), (4, "refuse")] {
+        let start: DateTime<Utc> = format!("2025-06-0{}T07:00:00Z", day).parse().unwrap();
+        state
+            .shifts
+            .create(NewShift {
+                rota_id: Some(source.id),
+                team_id: Some(team.id),
+                location: "Ward 1".into(),
+                position: "Nurse".into(),
+                starts_at: start,
+                ends_at: start + Duration::hours(12),
+                time_zone: "Europe/London".into(),
+                unpaid_break_minutes: 0,
+                required_headcount: 1,
+                notes: notes.into(),
+                required_skills: Vec::new(),
+                required_qualifications: Vec::new(),
+            })
+            .await
+            .unwrap();
+    }
+    sqlx::query(
+        "CREATE TRIGGER refuse_copy BEFORE INSERT ON shifts WHEN NEW.notes = 'refuse' \
+         BEGIN SELECT RAISE(ABORT, 'refused'); END",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+    let app = build_app(state.clone());
+    let manager = create_tokens("98", "manager").unwrap().access_token;
+
+    // Act
+    let request = Request::builder()
+        .uri(format!("/api/rotas/{}/copy", source.id))
+        .method("POST")
+        .header("Authorization", format!("Bearer {}", manager))
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "period_start": "2025-06-09", "assignments": false }).to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let copy = state.rotas.get_with_deleted(source.id + 1).await.unwrap();
+    let shifts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shifts").fetch_one(&pools.primary).await.unwrap();
+    let entries = state.audit.chain().await.unwrap();
+
+    // Assert: the first copied shift went with the rest
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(copy, None);
+    assert_eq!(shifts, 2);
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_templates_recurrences_and_generated_shifts() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Ward 1").await.unwrap();
+    let template = state
+        .templates
+        .create(NewShiftTemplate {
+            team_id: Some(team.id),
+            name: "Night".into(),
+            location: "Ward 1".into(),
+            position: "Nurse".into(),
+            start_time: "20:00".parse().unwrap(),
+            end_time: "08:00".parse().unwrap(),
+            time_zone: "Europe/London".into(),
+            unpaid_break_minutes: 60,
+            required_headcount: 2,
+            colour: "#1f3a93".into(),
+        })
+        .await
+        .unwrap();
+    let recurrence = state
+        .templates
+        .add_recurrence(NewRecurrence {
+            template_id: template.id,
+            rule: "FREQ=WEEKLY;BYDAY=FR,SA".parse().unwrap(),
+            starts_on: "2025-06-01".parse().unwrap(),
+            exceptions: vec!["2025-06-07".parse().unwrap()],
+        })
+        .await
+        .unwrap();
+    let generated = |date: &str| {
+        let date = date.parse().unwrap();
+        GeneratedShift { recurrence_id: recurrence.id, occurrence_date: date, shift: template.shift_on(date).unwrap() }
+    };
+
+    // Act
+    let renamed = state
+        .templates
+        .update(ShiftTemplate { name: "Long night".into(), ..template.clone() })
+        .await
+        .unwrap();
+    let stale = state.templates.update(template.clone()).await;
+    let listed = state.templates.list(Some(team.id), &PageRequest::first(TemplateSort::DEFAULT, 10)).await.unwrap().items;
+    let stored = state.templates.get_recurrence(recurrence.id).await.unwrap().unwrap();
+    let shifts = state.shifts.generate(vec![generated("2025-06-06"), generated("2025-06-13")]).await.unwrap();
+    let again = state.shifts.generate(vec![generated("2025-06-14"), generated("2025-06-13")]).await;
+    let occurrences = state.shifts.occurrences(recurrence.id).await.unwrap();
+    let removed = state.templates.remove_recurrence(recurrence.id).await;
+    let removed_again = state.templates.remove_recurrence(recurrence.id).await;
+
+    // Assert
+    assert_eq!(renamed.version, 2);
+    assert_eq!(renamed.start_time, template.start_time);
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(listed, vec![renamed]);
+    assert_eq!(stored, recurrence);
+    assert_eq!(stored.rule.to_string(), "FREQ=WEEKLY;BYDAY=FR,SA");
+    assert_eq!(shifts.len(), 2);
+    assert_eq!(shifts[0].starts_at.to_rfc3339(), "2025-06-06T19:00:00+00:00");
+    // Nothing from a batch is kept if one occurrence already has a shift
+    assert!(matches!(again, Err(RepoError::Conflict(_))));
+    assert_eq!(
+        occurrences.iter().map(|o| (o.shift_id, o.occurrence_date.to_string())).collect::<Vec<_>>(),
+        vec![(shifts[0].id, "2025-06-06".to_string()), (shifts[1].id, "2025-06-13".to_string())]
+    );
+    assert!(removed.is_ok());
+    assert!(matches!(removed_again, Err(RepoError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_sqlite_patterns_members_overrides_and_shift_links() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Control room").await.unwrap();
+    let mut users = Vec::new();
+    for name in ["Ann", "Bo"] {
+        let user = NewUser::new(name.into(), format!("{}@example.com", name.to_lowercase()), None, UserRole::User);
+        users.push(state.users.create(user.unwrap()).await.unwrap());
+    }
+    let template = state
+        .templates
+        .create(NewShiftTemplate {
+            team_id: Some(team.id),
+            name: "Day".into(),
+            location: "Control room".into(),
+            position: "Operator".into(),
+            start_time: "07:00".parse().unwrap(),
+            end_time: "19:00".parse().unwrap(),
+            time_zone: "UTC".into(),
+            unpaid_break_minutes: 60,
+            required_headcount: 1,
+            colour: "#2e8b57".into(),
+        })
+        .await
+        .unwrap();
+    let pattern = state
+        .patterns
+        .create(NewPattern {
+            team_id: team.id,
+            name: "Panama".into(),
+            anchor_date: "2025-06-02".parse().unwrap(),
+            cycle: PatternPreset::Panama.cycle(&[template.id]).unwrap(),
+        })
+        .await
+        .unwrap();
+    let day = "2025-06-03".parse().unwrap();
+    let change = |reason: &str| PatternOverride {
+        user_id: users[0].id,
+        date: day,
+        template_id: None,
+        reason: reason.into(),
+    };
+    let shift = state.shifts.create(template.shift_on(day).unwrap()).await.unwrap();
+
+    // Act
+    let renamed = state.patterns.update(Pattern { name: "Panama 2-2-3".into(), ..pattern.clone() }).await.unwrap();
+    let stale = state.patterns.update(pattern.clone()).await;
+    state
+        .patterns
+        .set_members(
+            pattern.id,
+            &[PatternMember { user_id: users[1].id, offset: 7 }, PatternMember { user_id: users[0].id, offset: 0 }],
+        )
+        .await
+        .unwrap();
+    let members = state.patterns.members(pattern.id).await.unwrap();
+    state.patterns.set_override(pattern.id, change("Training"), Some("98".into())).await.unwrap();
+    let replaced = state.patterns.set_override(pattern.id, change("Course moved"), Some("97".into())).await.unwrap();
+    let overrides = state.patterns.overrides(pattern.id, day, day).await.unwrap();
+    let removed = state.patterns.remove_override(pattern.id, users[0].id, day).await;
+    let removed_again = state.patterns.remove_override(pattern.id, users[0].id, day).await;
+    state.patterns.link_shift(pattern.id, template.id, day, shift.id).await.unwrap();
+    let linked_again = state.patterns.link_shift(pattern.id, template.id, day, shift.id).await;
+    let linked = state.patterns.shift_for(pattern.id, template.id, day).await.unwrap();
+
+    // Assert
+    assert_eq!(renamed.version, 2);
+    assert_eq!(renamed.cycle, pattern.cycle);
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    let listed = state.patterns.list(Some(team.id), &PageRequest::first(PatternSort::DEFAULT, 10)).await.unwrap().items;
+    assert_eq!(listed, vec![renamed]);
+    assert_eq!(members.iter().map(|m| m.user_id).collect::<Vec<_>>(), vec![users[0].id, users[1].id]);
+    assert_eq!(overrides.len(), 1);
+    assert_eq!(overrides[0].change, change("Course moved"));
+    assert_eq!(replaced.created_by.as_deref(), Some("97"));
+    assert!(removed.is_ok());
+    assert!(matches!(removed_again, Err(RepoError::NotFound)));
+    assert!(matches!(linked_again, Err(RepoError::Conflict(_))));
+    assert_eq!(linked, Some(shift.id));
+}
+
+#[tokio::test]
+async fn test_sqlite_availability_weekly_versions_and_exceptions() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let user = NewUser::new("Ann".into(), "ann@example.com".into(), None, UserRole::User).unwrap();
+    let user = state.users.create(user).await.unwrap();
+    let window = |kind| WeeklyWindow {
+        weekday: chrono::Weekday::Sun,
+        start_time: "20:00".parse().unwrap(),
+        end_time: "08:00".parse().unwrap(),
+        kind,
+    };
+    let exception = |date: &str, times: Option<(&str, &str)>| NewException {
+        user_id: user.id,
+        date: date.parse().unwrap(),
+        start_time: times.map(|(start, _)| start.parse().unwrap()),
+        end_time: times.map(|(_, end)| end.parse().unwrap()),
+        kind: AvailabilityKind::Unavailable,
+        note: "Dentist".into(),
+    };
+
+    // Act
+    let none = state.availability.weekly(user.id).await.unwrap();
+    let first =
+        WeeklyAvailability { weekly: vec![window(AvailabilityKind::Preferred)], ..WeeklyAvailability::empty(user.id) };
+    let created = state.availability.save_weekly(first.clone()).await.unwrap();
+    let duplicate = state.availability.save_weekly(first).await;
+    let changed = WeeklyAvailability { weekly: vec![window(AvailabilityKind::Unavailable)], ..created.clone() };
+    let updated = state.availability.save_weekly(changed).await.unwrap();
+    let stale = state.availability.save_weekly(created.clone()).await;
+    let later = state.availability.add_exception(exception("2025-06-10", None)).await.unwrap();
+    let morning = state.availability.add_exception(exception("2025-06-09", Some(("08:00", "12:00")))).await.unwrap();
+    let all = state.availability.exceptions(user.id, None, None).await.unwrap();
+    let from_tenth = state.availability.exceptions(user.id, Some(later.date), None).await.unwrap();
+    let removed = state.availability.remove_exception(morning.id).await;
+    let removed_again = state.availability.remove_exception(morning.id).await;
+
+    // Assert
+    assert!(none.is_none());
+    assert_eq!(created.version, 1);
+    assert!(matches!(duplicate, Err(RepoError::Conflict(_))));
+    assert_eq!(updated.version, 2);
+    assert_eq!(updated.weekly, vec![window(AvailabilityKind::Unavailable)]);
+    assert_eq!(state.availability.weekly(user.id).await.unwrap(), Some(updated));
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(all, vec![morning.clone(), later.clone()]);
+    assert_eq!(morning.start_time, Some("08:00".parse().unwrap()));
+    assert_eq!(from_tenth, vec![later.clone()]);
+    assert_eq!(state.availability.get_exception(later.id).await.unwrap(), Some(later));
+    assert!(removed.is_ok());
+    assert!(matches!(removed_again, Err(RepoError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_sqlite_leave_requests_entitlements_and_team_hierarchy() {
+    // Arrange: Ann's ward sits under a directorate Mo manages
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let mut users = Vec::new();
+    for (name, email) in [("Ann", "ann@example.com"), ("Mo", "mo@example.com")] {
+        let user = NewUser::new(name.into(), email.into(), None, UserRole::User).unwrap();
+        users.push(state.users.create(user).await.unwrap());
+    }
+    let directorate = state.teams.create("Surgery").await.unwrap();
+    let ward = state.teams.create("Ward 4").await.unwrap();
+    let request = |start: &str, end: &str| NewLeaveRequest {
+        user_id: users[0].id,
+        leave_type: LeaveType::Annual,
+        start_date: start.parse().unwrap(),
+        end_date: end.parse().unwrap(),
+        half_day_start: true,
+        half_day_end: false,
+        reason: "Family visit".into(),
+        approver_id: Some(users[1].id),
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let entitlement = |days| LeaveEntitlement {
+        user_id: users[0].id,
+        year: 2025,
+        leave_type: LeaveType::Annual,
+        days,
+        carried_over: 2.5,
+        updated_at: Utc::now(),
+    };
+
+    // Act
+    let managed = Team { manager_id: Some(users[1].id), ..directorate.clone() };
+    let managed = state.teams.update(managed).await.unwrap();
+    let nested = state.teams.update(Team { parent_id: Some(directorate.id), ..ward.clone() }).await.unwrap();
+    let renamed = state.teams.update(Team { name: "surgery".into(), ..ward }).await;
+    let holiday = state.leave.create(request("2025-06-02", "2025-06-06")).await.unwrap();
+    let later = state.leave.create(request("2025-09-01", "2025-09-01")).await.unwrap();
+    let mut approved = holiday.clone();
+    approved.transition(LeaveAction::Approve, "2", "Enjoy", Utc::now()).unwrap();
+    approved.overridden_by = Some("2".into());
+    approved.override_reason = Some("Cover arranged".into());
+    approved.overridden = vec![Conflict::new(ConflictCode::TooManyOff, "2 of the team would be off")];
+    let approved = state.leave.update(approved).await.unwrap();
+    let stale = state.leave.update(holiday.clone()).await;
+    let waiting =
+        LeaveFilter { approver: Some(users[1].id), status: Some(LeaveStatus::Pending), ..LeaveFilter::default() };
+    let page = PageRequest::first(LeaveSort::DEFAULT, 10);
+    let pending = state.leave.list(&waiting, &page).await.unwrap().items;
+    let june = LeaveFilter { from: "2025-06-06".parse().ok(), to: "2025-06-30".parse().ok(), ..LeaveFilter::default() };
+    let in_june = state.leave.list(&june, &page).await.unwrap().items;
+    let blocking = state
+        .leave
+        .approved_between(users[0].id, "2025-06-04".parse().unwrap(), "2025-06-04".parse().unwrap())
+        .await
+        .unwrap();
+    state.leave.save_entitlement(entitlement(25.0)).await.unwrap();
+    let raised = state.leave.save_entitlement(entitlement(28.0)).await.unwrap();
+    let entitlements = state.leave.entitlements(users[0].id, 2025).await.unwrap();
+    let next_year = state.leave.entitlements(users[0].id, 2026).await.unwrap();
+    let withdrawn = LeaveRequest { deleted_at: Some(Utc::now()), ..later.clone() };
+    let withdrawn = state.leave.update(withdrawn).await.unwrap();
+    let hidden = state.leave.get(later.id).await.unwrap();
+    let purged = state.leave.purge_deleted_before(Utc::now() + Duration::seconds(1)).await.unwrap();
+
+    // Assert
+    assert_eq!(managed.manager_id, Some(users[1].id));
+    assert_eq!(state.teams.get(nested.id).await.unwrap().unwrap().parent_id, Some(directorate.id));
+    assert!(matches!(renamed, Err(RepoError::Conflict(_))));
+    assert_eq!(holiday.days, 4.5);
+    assert_eq!(holiday.version, 1);
+    assert_eq!(approved.status, LeaveStatus::Approved);
+    assert_eq!(approved.version, 2);
+    assert_eq!(approved.decision_note, "Enjoy");
+    assert_eq!(approved.overridden[0].code, ConflictCode::TooManyOff);
+    assert_eq!(state.leave.get(holiday.id).await.unwrap(), Some(approved.clone()));
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(pending, vec![later.clone()]);
+    assert_eq!(in_june, vec![approved]);
+    assert_eq!(blocking.len(), 1);
+    assert_eq!(entitlements.len(), 1);
+    assert_eq!(entitlements[0].days, 28.0);
+    assert_eq!(entitlements[0].carried_over, raised.carried_over);
+    assert!(next_year.is_empty());
+    assert!(withdrawn.is_deleted());
+    assert_eq!(hidden, None);
+    assert_eq!(purged, vec![later.id]);
+}
+
+#[tokio::test]
+async fn test_sqlite_leave_blackouts_and_limits() {
+    // Arrange
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let team = state.teams.create("Theatres").await.unwrap();
+    let blackout = |team_id, location: Option<&str>, start: &str, end: &str| NewBlackout {
+        team_id,
+        location: location.map(str::to_string),
+        start_date: start.parse().unwrap(),
+        end_date: end.parse().unwrap(),
+        reason: "Audit".into(),
+        created_by: Some("1".into()),
+    };
+    let limit = |skill: Option<&str>| NewLeaveLimit {
+        team_id: team.id,
+        skill: skill.map(str::to_string),
+        max_off: Some(2),
+        max_percent: None,
+    };
+
+    // Act
+    let christmas = state
+        .leave_policy
+        .create_blackout(blackout(Some(team.id), None, "2025-12-22", "2025-12-26"))
+        .await
+        .unwrap();
+    let audit = blackout(None, Some("Ward 9"), "2025-11-03", "2025-11-07");
+    let audit = state.leave_policy.create_blackout(audit).await.unwrap();
+    let december =
+        BlackoutFilter { from: "2025-12-01".parse().ok(), to: "2025-12-31".parse().ok(), ..Default::default() };
+    let page = PageRequest::first(BlackoutSort::DEFAULT, 10);
+    let in_december = state.leave_policy.blackouts(&december, &page).await.unwrap().items;
+    let at_ward = BlackoutFilter { location: Some("ward 9".into()), ..Default::default() };
+    let at_ward = state.leave_policy.blackouts(&at_ward, &page).await.unwrap().items;
+    let removed = state.leave_policy.remove_blackout(audit.id).await;
+    let whole_team = state.leave_policy.create_limit(limit(None)).await.unwrap();
+    let scrub = state.leave_policy.create_limit(limit(Some("Scrub"))).await.unwrap();
+    let duplicate = state.leave_policy.create_limit(limit(Some("scrub"))).await;
+    let second_whole_team = state.leave_policy.create_limit(limit(None)).await;
+    let limits = state.leave_policy.limits(team.id).await.unwrap();
+
+    // Assert
+    assert_eq!(in_december, vec![christmas.clone()]);
+    assert_eq!(at_ward, vec![audit.clone()]);
+    assert!(removed.is_ok());
+    assert_eq!(state.leave_policy.get_blackout(audit.id).await.unwrap(), None);
+    assert_eq!(state.leave_policy.get_blackout(christmas.id).await.unwrap(), Some(christmas));
+    assert_eq!(whole_team.skill, None);
+    assert!(matches!(duplicate, Err(RepoError::Conflict(_))));
+    assert!(matches!(second_whole_team, Err(RepoError::Conflict(_))));
+    assert_eq!(limits, vec![whole_team, scrub.clone()]);
+    assert_eq!(state.leave_policy.get_limit(scrub.id).await.unwrap(), Some(scrub));
+}
+
+#[tokio::test]
+async fn test_sqlite_overtime_and_toil_ledger() {
+    // Arrange: Ann claims two evenings of overtime, which Mo decides
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let mut users = Vec::new();
+    for (name, email) in [("Ann", "ann@example.com"), ("Mo", "mo@example.com")] {
+        let user = NewUser::new(name.into(), email.into(), None, UserRole::User).unwrap();
+        users.push(state.users.create(user).await.unwrap());
+    }
+    let claim = |date: &str| NewOvertime {
+        user_id: users[0].id,
+        date: date.parse().unwrap(),
+        hours: 2.5,
+        shift_id: None,
+        reason: "Late handover".into(),
+        approver_id: Some(users[1].id),
+    };
+    let entry = |kind, hours, date: &str, overtime_id, leave_request_id| NewToilEntry {
+        user_id: users[0].id,
+        kind,
+        hours,
+        date: date.parse().unwrap(),
+        expires_on: "2025-09-05".parse().ok().filter(|_| hours > 0.0),
+        overtime_id,
+        leave_request_id,
+        note: String::new(),
+        created_by: Some(users[1].id.to_string()),
+    };
+
+    // Act
+    let saturday = state.toil.create_overtime(claim("2025-06-07")).await.unwrap();
+    let monday = state.toil.create_overtime(claim("2025-06-09")).await.unwrap();
+    let mut approved = saturday.clone();
+    approved.decide(Some(3.75), &users[1].id.to_string(), "Thanks", Utc::now()).unwrap();
+    let approved = state.toil.update_overtime(approved).await.unwrap();
+    let mut stale = saturday.clone();
+    stale.decide(None, &users[1].id.to_string(), "", Utc::now()).unwrap();
+    let stale = state.toil.update_overtime(stale).await;
+    let pending = OvertimeFilter { status: Some(OvertimeStatus::Pending), ..Default::default() };
+    let page = PageRequest::first(OvertimeSort::DEFAULT, 10);
+    let pending = state.toil.overtime(&pending, &page).await.unwrap().items;
+    let june = OvertimeFilter { user: Some(users[0].id), to: "2025-06-08".parse().ok(), ..Default::default() };
+    let to_sunday = state.toil.overtime(&june, &page).await.unwrap().items;
+    let leave = NewLeaveRequest {
+        user_id: users[0].id,
+        leave_type: LeaveType::Toil,
+        start_date: "2025-07-04".parse().unwrap(),
+        end_date: "2025-07-04".parse().unwrap(),
+        half_day_start: false,
+        half_day_end: true,
+        reason: String::new(),
+        approver_id: Some(users[1].id),
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let leave = state.leave.create(leave).await.unwrap();
+    let accrual = entry(ToilEntryKind::Accrual, 3.75, "2025-06-07", Some(saturday.id), None);
+    let accrual = state.toil.add_entry(accrual).await.unwrap();
+    let credited_twice = entry(ToilEntryKind::Accrual, 3.75, "2025-06-07", Some(saturday.id), None);
+    let credited_twice = state.toil.add_entry(credited_twice).await;
+    let redemption = entry(ToilEntryKind::Redemption, -3.0, "2025-06-20", None, Some(leave.id));
+    let redemption = state.toil.add_entry(redemption).await.unwrap();
+    let ledger = state.toil.ledger(users[0].id).await.unwrap();
+    let balance = rota_core::toil::toil_balance(&ledger, "2025-07-01".parse().unwrap());
+
+    // Assert
+    assert_eq!(approved.status, OvertimeStatus::Approved);
+    assert_eq!(approved.toil_hours, Some(3.75));
+    assert_eq!(approved.version, saturday.version + 1);
+    assert!(matches!(stale, Err(RepoError::StaleVersion)));
+    assert_eq!(state.toil.get_overtime(saturday.id).await.unwrap(), Some(approved.clone()));
+    assert_eq!(pending, vec![monday]);
+    assert_eq!(to_sunday, vec![approved]);
+    assert_eq!(state.leave.get(leave.id).await.unwrap().unwrap().leave_type, LeaveType::Toil);
+    assert!(matches!(credited_twice, Err(RepoError::Conflict(_))));
+    assert_eq!(ledger, vec![accrual, redemption]);
+    assert_eq!(balance.available, 0.75);
+}
+
+#[tokio::test]
+async fn test_sqlite_overtime_approval_and_its_toil_commit_together() {
+    // Arrange: Ann's claim waits on Mo while the ledger refuses new entries
+    let db = database().await;
+    let state = AppState::from_database(db.clone(), Config::default());
+    let Database::Sqlite(pools) = &db else { unreachable!() };
+    let mut users = Vec::new();
+    for (name, email) in [("Ann", "ann@example.com"), ("Mo", "mo@example.com")] {
+        let user = NewUser::new(name.into(), email.into(), None, UserRole::User).unwrap();
+        users.push(state.users.create(user).await.unwrap());
+    }
+    let claim = NewOvertime {
+        user_id: users[0].id,
+        date: "2025-06-09".parse().unwrap(),
+        hours: 2.0,
+        shift_id: None,
+        reason: "Late handover".into(),
+        approver_id: Some(users[1].id),
+    };
+    let claim = state.toil.create_overtime(claim).await.unwrap();
+    sqlx::query(
+        "CREATE TRIGGER toil_ledger_down BEFORE INSERT ON toil_ledger \
+         BEGIN SELECT RAISE(ABORT, 'ledger unavailable'); END",
+    )
+    .execute(&pools.primary)
+    .await
+    .unwrap();
+    let app = build_app(state.clone());
+    let mo = create_tokens(&users[1].id.to_string(), "user").unwrap().access_token;
+    let approve = || {
+        Request::builder()
+            .uri(format!("/api/overtime/{}/approve", claim.id))
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", mo))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let failed = app.clone().oneshot(approve()).await.unwrap();
+    let after_failure = state.toil.get_overtime(claim.id).await.unwrap().unwrap();
+    sqlx::query("DROP TRIGGER toil_ledger_down").execute(&pools.primary).await.unwrap();
+    let retried = app.oneshot(approve()).await.unwrap();
+    let ledger = state.toil.ledger(users[0].id).await.unwrap();
+
+    // Assert: the failed approval left the claim pending, so it could be retried
+    assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(after_failure, claim);
+    assert_eq!(retried.status(), StatusCode::OK);
+    assert_eq!(ledger.len(), 1);
+    assert_eq!(ledger[0].overtime_id, Some(claim.id));
+}
+
+#[tokio::test]
+async fn test_sqlite_cancelled_toil_leave_refunds_hours_that_already_lapsed() {
+    // Arrange: Ann booked next week off with six hours of TOIL that lapsed
+    // yesterday, and the leave was approved before they did
+    let db = database().await;
+    let state = AppState::from_database(db, Config::default());
+    let user = NewUser::new("Ann".into(), "ann@example.com".into(), None, UserRole::User).unwrap();
+    let ann = state.users.create(user).await.unwrap();
+    let today = Utc::now().date_naive();
+    let next_week = today + Duration::days(7);
+    let leave = NewLeaveRequest {
+        user_id: ann.id,
+        leave_type: LeaveType::Toil,
+        start_date: next_week,
+        end_date: next_week,
+        half_day_start: false,
+        half_day_end: false,
+        reason: String::new(),
+        approver_id: None,
+        overridden_by: None,
+        override_reason: None,
+        overridden: Vec::new(),
+    };
+    let mut leave = state.leave.create(leave).await.unwrap();
+    leave.transition(LeaveAction::Approve, "99", "", Utc::now()).unwrap();
+    let leave = state.leave.update(leave).await.unwrap();
+    let entry = |kind, hours, days_ago, expires_on, leave_request_id| NewToilEntry {
+        user_id: ann.id,
+        kind,
+        hours,
+        date: today - Duration::days(days_ago),
+        expires_on,
+        overtime_id: None,
+        leave_request_id,
+        note: String::new(),
+        created_by: None,
+    };
+    let lapsed = today - Duration::days(1);
+    let accrual = entry(ToilEntryKind::Accrual, 6.0, 40, Some(lapsed), None);
+    state.toil.add_entry(accrual).await.unwrap();
+    let redemption = entry(ToilEntryKind::Redemption, -6.0, 20, None, Some(leave.id));
+    state.toil.add_entry(redemption).await.unwrap();
+    let app = build_app(state.clone());
+    let ann_token = create_tokens(&ann.id.to_string(), "user").unwrap().access_token;
+
+    // Act
+    let request = Request::builder()
+        .uri(format!("/api/leave/{}/cancel", leave.id))
+        .method("POST")
+        .header("Authorization", format!("Bearer {}", ann_token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let ledger = state.toil.ledger(ann.id).await.unwrap();
+    let balance = rota_core::toil::toil_balance(&ledger, today);
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(ledger.len(), 3);
+    assert_eq!(ledger[2].kind, ToilEntryKind::Refund);
+    assert_eq!(ledger[2].hours, 6.0);
+    // The hours come back already lapsed rather than with a fresh expiry
+    assert_eq!(ledger[2].expires_on, Some(lapsed));
+    assert_eq!(balance.available, 0.0);
+    assert_eq!(balance.expired, 6.0);
+}
@@ -1,5 +0,0 @@
-pub mod handlers;
-pub mod jwt;
-
-// Re-export commonly used functions
-pub use handlers::{login, register, refresh_token, protected, logout};
\ No newline at end of file
@@ -1,61 +0,0 @@
-
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    Json,
-};
-use serde::{Deserialize, Serialize};
-use std::fmt;
-
-#[derive(Debug)]
-pub enum AppError {
-    NotFound,
-    InternalServerError,
-    BadRequest(String),
-    Unauthorized,
-    Forbidden,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub code: u16,
-}
-
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            AppError::NotFound => write!(f, "Resource not found"),
-            AppError::InternalServerError => write!(f, "Internal server error"),
-            AppError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
-            AppError::Unauthorized => write!(f, "Unauthorized"),
-            AppError::Forbidden => write!(f, "Forbidden"),
-        }
-    }
-}
-
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
-            AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
-        };
-
-        let body = Json(ErrorResponse {
-            error: error_message,
-            code: status.as_u16(),
-        });
-
-        (status, body).into_response()
-    }
-}
-
-// Implement From<std::io::Error> for AppError
-impl From<std::io::Error> for AppError {
-    fn from(_: std::io::Error) -> Self {
-        AppError::InternalServerError
-    }
-}
\ No newline at end of file
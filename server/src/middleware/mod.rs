@@ -1 +0,0 @@
-pub mod request_id;
\ No newline at end of file